@@ -15,14 +15,15 @@ use derin::geometry::rect::DimsBox;
 enum GalleryEvent {
     NewButton,
     Checked,
-    SliderMove(f32)
+    SliderMove(f32),
+    TabSelect(usize)
 }
 
 #[derive(WidgetContainer)]
 struct BasicContainer {
     button: Button<Option<GalleryEvent>>,
     nested: ScrollBox<Group<NestedContainer, LayoutVertical>>,
-    tabs: TabList<Button<Option<GalleryEvent>>>
+    tabs: TabList<Button<Option<GalleryEvent>>, TabsH>
 }
 
 #[derive(WidgetContainer)]
@@ -45,6 +46,14 @@ impl SliderHandler for SliderH {
     }
 }
 
+struct TabsH;
+impl TabListHandler for TabsH {
+    type Action = GalleryEvent;
+    fn on_select(&mut self, index: usize) -> Option<GalleryEvent> {
+        Some(GalleryEvent::TabSelect(index))
+    }
+}
+
 fn main() {
     let group = Group::new(
         BasicContainer {
@@ -68,9 +77,9 @@ fn main() {
                 LayoutVertical::new(Margins::new(8, 8, 8, 8), Default::default())
             )),
             tabs: TabList::new(vec![
-                TabPage::new("Tab 1".to_string(), Button::new(Contents::Text("Tab 1".to_string()), None)),
-                TabPage::new("Tab No.2".to_string(), Button::new(Contents::Text("Tab 2".to_string()), None)),
-            ])
+                TabPage::new(Contents::Text("Tab 1".to_string()), Button::new(Contents::Text("Tab 1".to_string()), None)),
+                TabPage::new(Contents::Text("Tab No.2".to_string()), Button::new(Contents::Text("Tab 2".to_string()), None)),
+            ], TabsH)
         },
         LayoutHorizontal::new(Margins::new(8, 8, 8, 8), Default::default())
     );
@@ -83,5 +92,5 @@ fn main() {
     };
 
     let mut window = unsafe{ Window::new(window_config, group, theme).unwrap() };
-    window.run_forever();
+    window.run_forever().unwrap();
 }
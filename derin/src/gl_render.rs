@@ -6,14 +6,13 @@
 //!
 //! Unless you're creating your own widgets, you generally shouldn't have to look at this module.
 
-mod atlas;
-mod font_cache;
-// mod translate;
+mod translate;
 
 use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use derin_common_types::cursor::CursorIcon;
 use derin_common_types::layout::SizeBounds;
-use core::widget::WidgetId;
 
 use crate::cgmath::{Array, Bounded, Point2, Vector2, EuclideanSpace};
 
@@ -26,35 +25,56 @@ use gullery::buffer::{Buffer, BufferUsage};
 use gullery::vertex::VertexArrayObject;
 use gullery::image_format::Rgba;
 
-use glyphydog::DPI;
-
-use cgmath_geometry::{D2, rect::{BoundBox, OffsetBox, DimsBox, GeoBox}};
+use cgmath_geometry::{D2, rect::{BoundBox, DimsBox, GeoBox}};
 
 use glutin::*;
 
-use crate::theme::Theme;
+use crate::core::{
+    render::{WidgetRenderer, SubFrame},
+    widget::WidgetId,
+};
+use crate::theme::{Theme, ThemePath, ThemeWidget, Image};
 pub use crate::core::render::Renderer;
 
-use self::atlas::Atlas;
-use self::font_cache::FontCache;
-// use self::translate::Translator;
-// pub use self::translate::{Prim, ThemedPrim, RelPoint};
+use crate::render_common::{
+    atlas::Atlas,
+    content::{self, Content, ContentLayout, WidgetContent},
+    shape::ShapeVertex,
+    text::{TextCache, ShapedString},
+};
 
 pub struct GLRenderer {
     window: GlWindow,
     client_size_bounds: SizeBounds,
+    widget_content: HashMap<WidgetId, WidgetContent>,
     frame: GLFrame,
+    layout: ContentLayout,
+}
+
+/// Image and glyph atlas shared between the renderers of every window in an application.
+pub struct RenderCache {
+    atlas: Atlas,
 }
 
 pub struct GLFrame {
-    // poly_translator: Translator,
-    draw: FrameDraw
+    draw: FrameDraw,
+    widget: WidgetContent,
+    theme_widget: ThemeWidget,
+    /// The images for the widget's icons, along with the colors they get multiplied by.
+    icons: Vec<Option<(Rc<Image>, Rgba<u8>)>>,
+    /// The color the widget's image gets multiplied by. Used to fade out disabled widgets.
+    image_color: Rgba<u8>,
+    /// The widget's rectangle, in logical pixels.
+    transform: BoundBox<D2, i32>,
+    /// The clipping rectangle, in logical pixels.
+    clip: BoundBox<D2, i32>,
 }
 
 struct FrameDraw {
     vertices: Vec<GLVertex>,
-    atlas: Atlas,
-    font_cache: FontCache,
+    cache: Rc<RefCell<RenderCache>>,
+    text_cache: Rc<RefCell<TextCache>>,
+    shape_vertices: Vec<ShapeVertex>,
 
     // OpenGL structs
     context_state: Rc<ContextState>,
@@ -63,6 +83,7 @@ struct FrameDraw {
     fb: FramebufferDefault,
     program: Program<GLVertex, GLUniforms<'static>>,
     vao: VertexArrayObject<GLVertex, !>,
+    /// The size of the window, in logical pixels.
     window_dims: DimsBox<D2, u32>,
     scale_factor: f32
 }
@@ -83,8 +104,21 @@ struct GLUniforms<'a> {
 }
 
 
+impl RenderCache {
+    pub fn new() -> RenderCache {
+        RenderCache {
+            atlas: Atlas::new(),
+        }
+    }
+}
+
 impl GLRenderer {
-    pub unsafe fn new(events_loop: &EventsLoop, window_builder: WindowBuilder, gen_context_builder: impl Fn() -> ContextBuilder<'static>) -> Result<GLRenderer, CreationError> {
+    pub unsafe fn new(
+        events_loop: &EventsLoop,
+        window_builder: WindowBuilder,
+        gen_context_builder: impl Fn() -> ContextBuilder<'static>,
+        cache: Rc<RefCell<RenderCache>>
+    ) -> Result<GLRenderer, CreationError> {
         let show_window = window_builder.window.visible;
         let window = {
             let window_builder_no_show = window_builder.with_visibility(false);
@@ -146,13 +180,21 @@ impl GLRenderer {
         let vao = VertexArrayObject::new(Buffer::with_data(BufferUsage::StreamDraw, &vertices, context_state.clone()), None);
         vertices.clear();
 
+        // Text gets rasterized at the display's physical resolution.
+        let scale_factor = window.hidpi_factor();
+        let text_cache = Rc::new(RefCell::new(TextCache::new()));
+        text_cache.borrow_mut().set_scale_factor(scale_factor);
+        let mut layout = ContentLayout::new(text_cache.clone());
+        layout.set_scale_factor(scale_factor);
+
         Ok(GLRenderer {
+            widget_content: HashMap::new(),
             frame: GLFrame {
-                // poly_translator: Translator::new(),
                 draw: FrameDraw {
                     vertices,
-                    atlas: Atlas::new(),
-                    font_cache: FontCache::new(),
+                    cache,
+                    text_cache: text_cache.clone(),
+                    shape_vertices: Vec::new(),
                     fb: FramebufferDefault::new(context_state.clone()).expect("Could not access default framebuffer"),
                     vao,
                     render_state: RenderState {
@@ -168,9 +210,16 @@ impl GLRenderer {
                     gl_tex_atlas,
                     context_state,
                     window_dims: DimsBox::new2(0, 0),
-                    scale_factor: 1.0
-                }
+                    scale_factor
+                },
+                widget: WidgetContent::default(),
+                theme_widget: ThemeWidget::default(),
+                icons: Vec::new(),
+                image_color: Rgba::new(255, 255, 255, 255),
+                transform: BoundBox::new2(0, 0, 0, 0),
+                clip: BoundBox::new2(0, 0, 0, 0),
             },
+            layout,
             client_size_bounds: SizeBounds::default(),
             window,
         })
@@ -181,6 +230,15 @@ impl GLRenderer {
         &self.window
     }
 
+    #[inline]
+    pub(crate) fn window_id(&self) -> WindowId {
+        self.window.id()
+    }
+
+    pub(crate) fn hide(&mut self) {
+        self.window.hide();
+    }
+
     pub fn context_state(&self) -> Rc<ContextState> {
         self.frame.draw.context_state.clone()
     }
@@ -234,56 +292,176 @@ impl GLRenderer {
 }
 
 impl Renderer for GLRenderer {
+    type SubFrame = GLFrame;
+    type Theme = Theme;
+    type Layout = ContentLayout;
+
     fn resized(&mut self, new_size: DimsBox<D2, u32>) {
-        self.window.context().resize(new_size.width(), new_size.height());
+        let scale_factor = self.frame.draw.scale_factor;
+        self.window.context().resize(
+            (new_size.width() as f32 * scale_factor) as u32,
+            (new_size.height() as f32 * scale_factor) as u32
+        );
     }
 
     fn dims(&self) -> DimsBox<D2, u32> {
         let (width, height) = self.window.get_inner_size().unwrap();
-        DimsBox::new2(width, height)
+        let scale_factor = self.frame.draw.scale_factor;
+        DimsBox::new2((width as f32 / scale_factor) as u32, (height as f32 / scale_factor) as u32)
+    }
+
+    fn scale_factor(&self) -> f32 {
+        self.frame.draw.scale_factor
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f32) {
+        self.frame.draw.scale_factor = scale_factor;
+        self.frame.draw.text_cache.borrow_mut().set_scale_factor(scale_factor);
+        self.layout.set_scale_factor(scale_factor);
     }
 
     fn widget_removed(&mut self, widget_id: WidgetId) {
-        unimplemented!()
+        self.widget_content.remove(&widget_id);
     }
 
-    fn start_frame(&mut self, _: &Self::Theme) {
+    fn layout(
+        &mut self,
+        widget_id: WidgetId,
+        layout: impl FnOnce(&mut ContentLayout)
+    ) {
+        let widget = self.widget_content.remove(&widget_id).unwrap_or_default();
+        let widget = self.layout.layout(widget, layout);
+        self.widget_content.insert(widget_id, widget);
+    }
+
+    fn start_frame(&mut self, _: &Theme) {
+        // Every window has its own context, so make sure we're drawing to this one.
+        unsafe{ self.window.context().make_current().unwrap() };
+        // Vertices are positioned in logical pixels, and get scaled up to the physical viewport by
+        // the vertex shader.
         let (width, height) = self.window.get_inner_size().unwrap();
-        let scale_factor = self.window.hidpi_factor();
-        self.frame.draw.window_dims = DimsBox::new2(width, height);
-        self.frame.draw.scale_factor = scale_factor;
-        let width_scaled = (width as f32 * scale_factor) as u32;
-        let height_scaled = (height as f32 * scale_factor) as u32;
-        self.frame.draw.render_state.viewport = DimsBox::new2(width_scaled, height_scaled).into();
+        self.frame.draw.window_dims = self.dims();
+        self.frame.draw.render_state.viewport = DimsBox::new2(width, height).into();
         self.frame.draw.fb.clear_color_all(Rgba::new(1., 1., 1., 1.));
         self.frame.draw.fb.clear_depth(1.0);
         self.frame.draw.fb.clear_stencil(0);
     }
 
-    fn render_subframe(
+    fn render_focus_ring(&mut self, theme: &Theme, rect: BoundBox<D2, i32>, clip: BoundBox<D2, i32>) {
+        if let Some(focus_ring) = theme.focus_ring() {
+            let offset = focus_ring.offset as i32;
+            let ring_rect = BoundBox::new2(
+                rect.min.x - offset,
+                rect.min.y - offset,
+                rect.max.x + offset,
+                rect.max.y + offset,
+            );
+            self.frame.draw.draw_shape(&focus_ring.shape(), ring_rect, clip);
+        }
+    }
+
+    fn finish_frame(&mut self, _: &Theme) {
+        self.frame.draw.draw_contents();
+        self.window.swap_buffers().unwrap();
+        self.frame.draw.cache.borrow_mut().atlas.bump_frame_count();
+    }
+}
+
+impl<T> WidgetRenderer<T> for GLRenderer
+    where T: ThemePath
+{
+    fn render_widget(
         &mut self,
-        _: &Self::Theme,
+        widget_id: WidgetId,
+        theme: &Theme,
         transform: BoundBox<D2, i32>,
         clip: BoundBox<D2, i32>,
-        draw_to_frame: impl FnOnce(&mut Self::SubFrame)
+        enabled: bool,
+        widget_theme: T,
+        render_widget: impl FnOnce(&mut GLFrame),
     ) {
-        draw_to_frame(&mut self.frame);
+        let (theme_widget, image_color) = content::resolve_theme(theme, widget_theme.theme_path(), enabled);
+        let mut widget = self.widget_content.remove(&widget_id).unwrap_or_default();
+        widget.theme_widget = Some(theme_widget.clone());
+
+        let frame = &mut self.frame;
+        frame.icons.clear();
+        frame.icons.extend(widget.content.iter().filter_map(|c| match *c {
+            Content::Icon(ref icon) => {
+                let (icon_theme, color) = content::resolve_theme(theme, icon, enabled);
+                Some(icon_theme.image.map(|image| (image, color)))
+            },
+            _ => None
+        }));
+        frame.widget = widget;
+        frame.theme_widget = theme_widget;
+        frame.image_color = image_color;
+        frame.transform = transform;
+        frame.clip = clip;
+
+        render_widget(frame);
+
+        let widget = std::mem::replace(&mut frame.widget, WidgetContent::default());
+        self.widget_content.insert(widget_id, widget);
     }
+}
 
-    fn finish_frame(&mut self, _: &Self::Theme) {
-        self.frame.draw.draw_contents();
-        self.window.swap_buffers().unwrap();
-        self.frame.draw.atlas.bump_frame_count();
+impl SubFrame for GLFrame {
+    fn render_laid_out_content(&mut self) {
+        let GLFrame {
+            ref mut draw,
+            ref mut widget,
+            ref theme_widget,
+            ref icons,
+            image_color,
+            transform,
+            clip,
+        } = *self;
+
+        if let Some(ref shape) = theme_widget.shape {
+            draw.draw_shape(shape, transform, clip);
+        }
+        if let Some(ref image) = theme_widget.image {
+            draw.draw_image(image, transform, clip, image_color);
+        }
+
+        // Text gets shaped and rasterized at the display's physical resolution.
+        let physical_transform = content::to_physical(transform, draw.scale_factor);
+        let physical_clip = content::to_physical(clip, draw.scale_factor);
+        let mut icons = icons.iter();
+        for content in &widget.content {
+            match *content {
+                Content::String(ref string) => {
+                    if let Some(ref text_style) = theme_widget.text {
+                        let mut shaped = ShapedString::default();
+                        draw.text_cache.borrow_mut().shape_in_widget(string, physical_transform, text_style, &mut shaped);
+                        draw.draw_text(&shaped, physical_transform, physical_clip, text_style, None);
+                    }
+                },
+                Content::EditString{ref string, ref cursor_data} => {
+                    if let Some(ref text_style) = theme_widget.text {
+                        draw.text_cache.borrow_mut().shape_in_widget(string, physical_transform, text_style, &mut widget.edit_shaped);
+                        draw.draw_text(&widget.edit_shaped, physical_transform, physical_clip, text_style, Some(cursor_data));
+                    }
+                },
+                Content::Icon(_) => {
+                    if let Some(Some((ref image, color))) = icons.next() {
+                        draw.draw_image(image, transform, clip, color);
+                    }
+                }
+            }
+        }
     }
 }
 
 impl FrameDraw {
     fn draw_contents(&mut self) {
-        let atlas_dims = self.atlas.dims();
+        let cache = self.cache.borrow();
+        let atlas_dims = cache.atlas.dims();
         if atlas_dims != self.gl_tex_atlas.dims() {
             self.gl_tex_atlas = Texture::new(atlas_dims, 1, self.context_state.clone()).unwrap();
         }
-        self.gl_tex_atlas.sub_image(0, Vector2::new(0, 0), atlas_dims, self.atlas.pixels());
+        self.gl_tex_atlas.sub_image(0, Vector2::new(0, 0), atlas_dims, cache.atlas.pixels());
 
         let uniform = GLUniforms {
             atlas_size: self.gl_tex_atlas.dims().dims,
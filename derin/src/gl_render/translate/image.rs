@@ -35,7 +35,9 @@ enum TranslateVerts {
 }
 
 impl ImageToVertices {
-    pub fn new(rect: BoundBox<D2, i32>, clip: BoundBox<D2, i32>, atlas_rect: OffsetBox<D2, u16>, color: Rgba<u8>, rescale: RescaleRules) -> ImageToVertices {
+    /// `image_scale` is the number of the image's pixels per logical pixel, for images drawn from
+    /// one of their HiDPI variants.
+    pub fn new(rect: BoundBox<D2, i32>, clip: BoundBox<D2, i32>, atlas_rect: OffsetBox<D2, u16>, color: Rgba<u8>, rescale: RescaleRules, image_scale: u32) -> ImageToVertices {
         let clipped_rect = match clip.intersect_rect(rect) {
             Some(clipped_rect) => clipped_rect,
             None => return ImageToVertices {
@@ -149,10 +151,10 @@ impl ImageToVertices {
                 }
 
                 let atlas_margins = Margins::new(
-                    margins.left as f32 - atlas_clip_margins.left,
-                    margins.top as f32 - atlas_clip_margins.top,
-                    margins.right as f32 - atlas_clip_margins.right,
-                    margins.bottom as f32 - atlas_clip_margins.bottom,
+                    (margins.left as u32 * image_scale) as f32 - atlas_clip_margins.left,
+                    (margins.top as u32 * image_scale) as f32 - atlas_clip_margins.top,
+                    (margins.right as u32 * image_scale) as f32 - atlas_clip_margins.right,
+                    (margins.bottom as u32 * image_scale) as f32 - atlas_clip_margins.bottom,
                 );
                 let loc_margins = Margins::new(
                     (margins.left as i32 - clip_margins.left).max(0) as f32,
@@ -180,8 +182,8 @@ impl ImageToVertices {
                     (min, max)
                 };
 
-                let (min_x, max_x) = get_dims(alignment.x, atlas_rect.width() as i32, rect.width());
-                let (min_y, max_y) = get_dims(alignment.y, atlas_rect.height() as i32, rect.height());
+                let (min_x, max_x) = get_dims(alignment.x, (atlas_rect.width() as u32 / image_scale) as i32, rect.width());
+                let (min_y, max_y) = get_dims(alignment.y, (atlas_rect.height() as u32 / image_scale) as i32, rect.height());
 
                 let bound_x = |i: i32| i.min(clipped_rect.max().x).max(clipped_rect.min().x);
                 let bound_y = |i: i32| i.min(clipped_rect.max().y).max(clipped_rect.min().y);
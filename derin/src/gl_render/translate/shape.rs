@@ -0,0 +1,35 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::gl_render::GLVertex;
+
+use crate::cgmath::Point2;
+use cgmath_geometry::{D2, rect::{OffsetBox, BoundBox, GeoBox}};
+
+use crate::render_common::shape::{self, ShapeVertex};
+use crate::theme::Shape;
+
+/// Tessellate `shape` into vertices that sample the atlas's white texel, so they get drawn with
+/// just the vertex color.
+///
+/// Vertices are positioned in logical pixels, which the vertex shader scales to the viewport.
+pub(in crate::gl_render) fn shape_vertices(
+    shape: &Shape,
+    rect: BoundBox<D2, i32>,
+    clip: BoundBox<D2, i32>,
+    white: OffsetBox<D2, u32>,
+    shape_vertices: &mut Vec<ShapeVertex>,
+    vertices: &mut Vec<GLVertex>
+) {
+    let white = BoundBox::from(white).cast::<f32>().unwrap();
+    let tex_coord = Point2::new(white.center().x, white.center().y);
+
+    shape_vertices.clear();
+    shape::tessellate_shape(shape, rect, clip, 1.0, shape_vertices);
+    vertices.extend(shape_vertices.iter().map(|v| GLVertex {
+        loc: v.pos,
+        color: v.color,
+        tex_coord
+    }));
+}
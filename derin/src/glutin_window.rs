@@ -4,25 +4,38 @@
 
 use glutin::*;
 use glutin::{MouseButton as GMouseButton, WindowEvent as GWindowEvent, MouseScrollDelta};
-use crate::gl_render::{GLRenderer, GLFrame};
+use crate::gl_render::{GLRenderer, RenderCache};
 use derin_common_types::buttons::{MouseButton, Key, ModifierKeys};
 use crate::core::{
-    Root, EventLoopResult, WindowEvent,
+    Root, EventLoopResult, WindowEvent, FrameEventProcessor,
     widget::Widget,
     render::Renderer,
 };
 use crate::theme::Theme;
+#[cfg(feature = "derin-serde")]
+use crate::theme::{ThemeLoadError, ThemeWatcher};
 use gullery::ContextState;
 
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
 use std::thread::{self, JoinHandle};
 use std::sync::Arc;
 use std::time::Instant;
+#[cfg(feature = "derin-serde")]
+use std::{path::PathBuf, time::Duration};
 use std::rc::Rc;
 use crate::cgmath::{Point2, Vector2};
 use cgmath_geometry::{D2, rect::{DimsBox, GeoBox}};
 
 use parking_lot::Mutex;
 
+/// How often the theme files get checked for changes, when watching a theme.
+#[cfg(feature = "derin-serde")]
+const THEME_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct WindowConfig {
     pub dimensions: Option<DimsBox<D2, u32>>,
@@ -45,13 +58,41 @@ impl Default for WindowConfig {
     }
 }
 
-/// A window displayed on the desktop, which contains a set of drawable widgets.
-pub struct GlutinWindow<W: Widget> {
-    primary_renderer: GLRenderer,
+/// A message that opens or closes windows.
+///
+/// Widgets open and close windows by broadcasting this message with
+/// `WidgetTag::broadcast_message`. It gets picked up by the `Application` once the current frame
+/// finishes, rather than being delivered to any widgets.
+#[derive(Debug)]
+pub enum WindowMessage {
+    /// Open a new window. Create this with `WindowMessage::open`.
+    Open(OpenWindow),
+    /// Close the window containing the widget that sent the message.
+    CloseThis,
+    /// Close the window with the given ID.
+    Close(WindowId)
+}
+
+/// The contents of a window waiting to be opened by a `WindowMessage`.
+pub struct OpenWindow(Box<FnMut(&mut Application) -> Result<WindowId, CreationError>>);
+
+/// A set of windows sharing a single event loop, timer thread, and glyph and image cache.
+pub struct Application {
     events_loop: EventsLoop,
     timer_sync: Arc<Mutex<TimerPark>>,
     timer_thread_handle: JoinHandle<()>,
-    root: Root<W, GLFrame>
+    render_cache: Rc<RefCell<RenderCache>>,
+    windows: HashMap<WindowId, Box<AnyWindow>>
+}
+
+/// A window displayed on the desktop, which contains a set of drawable widgets.
+///
+/// This is a thin wrapper around an `Application` containing a single window. Use
+/// `application_mut` to open more windows on the same event loop.
+pub struct GlutinWindow<W: Widget> {
+    app: Application,
+    window_id: WindowId,
+    _marker: PhantomData<W>
 }
 
 #[derive(PartialEq, Eq, Clone, Copy)]
@@ -61,32 +102,64 @@ enum TimerPark {
     Abort
 }
 
-impl<W: Widget> GlutinWindow<W> {
-    /// Creates a new window, with the given window configuration, root widget, and theme.
-    ///
-    /// This is unsafe, because it creates at least one OpenGL context. By calling this function,
-    /// you hand all control over the thread's OpenGL context management to this window. In most
-    /// cases this shouldn't be an issue, though.
-    pub unsafe fn new(config: WindowConfig, root: W, theme: Theme) -> Result<GlutinWindow<W>, CreationError> {
-        let mut window_builder = WindowBuilder::new();
-        window_builder.window.dimensions = config.dimensions.map(|d| (d.width(), d.height()));
-        window_builder.window.title = config.title.clone();
-        let gen_context_builder = || {
-            let mut context_builder = ContextBuilder::new();
+struct WindowState<W: Widget> {
+    root: Root<W, GLRenderer>,
+    #[cfg(feature = "derin-serde")]
+    theme_watcher: Option<WatchedTheme>,
+    open: bool
+}
 
-            context_builder = context_builder.with_multisampling(config.multisampling);
-            if let Some(depth_bits) = config.depth_bits {
-                context_builder = context_builder.with_depth_buffer(depth_bits);
-            }
-            if let Some(stencil_bits) = config.stencil_bits {
-                context_builder = context_builder.with_stencil_buffer(stencil_bits);
-            }
+/// A theme file being watched for changes, along with the handler for errors encountered while
+/// reloading it.
+#[cfg(feature = "derin-serde")]
+struct WatchedTheme {
+    watcher: ThemeWatcher,
+    on_reload_error: Box<FnMut(ThemeLoadError)>,
+}
 
-            context_builder
-        };
+struct FrameResult {
+    next_wakeup: Option<Instant>,
+    messages: Vec<WindowMessage>
+}
+
+/// Lets `Application` store windows with different root widget types in the same map.
+trait AnyWindow {
+    fn is_open(&self) -> bool;
+    fn close(&mut self);
+    fn run_frame(&mut self, events: Vec<GWindowEvent>, timer_triggered: bool) -> FrameResult;
+    #[cfg(feature = "derin-serde")]
+    fn set_theme(&mut self, theme_watcher: Option<WatchedTheme>, theme: Option<Theme>);
+    fn root_widget(&self) -> &Any;
+    fn root_widget_mut(&mut self) -> &mut Any;
+    fn context_state(&self) -> Rc<ContextState>;
+}
+
+impl WindowMessage {
+    /// Create a message that opens a new window, with the given window configuration, root
+    /// widget, and theme.
+    pub fn open<W: Widget>(config: WindowConfig, root: W, theme: Theme) -> WindowMessage {
+        let mut contents = Some((config, root, theme));
+        WindowMessage::Open(OpenWindow(Box::new(move |app| {
+            let (config, root, theme) = contents.take().expect("window already opened");
+            app.open_window(config, root, theme)
+        })))
+    }
+}
 
+impl fmt::Debug for OpenWindow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("OpenWindow").finish()
+    }
+}
+
+impl Application {
+    /// Creates a new application, with no open windows.
+    ///
+    /// This is unsafe, because the windows it opens create OpenGL contexts. By calling this
+    /// function, you hand all control over the thread's OpenGL context management to the
+    /// application. In most cases this shouldn't be an issue, though.
+    pub unsafe fn new() -> Application {
         let events_loop = EventsLoop::new();
-        let renderer = GLRenderer::new(&events_loop, window_builder, gen_context_builder)?;
 
         let timer_sync = Arc::new(Mutex::new(TimerPark::Indefinite));
         let timer_sync_timer_thread = timer_sync.clone();
@@ -115,161 +188,449 @@ impl<W: Widget> GlutinWindow<W> {
             }
         });
 
-        Ok(GlutinWindow {
-            root: Root::new(root, theme, renderer.dims()),
-            primary_renderer: renderer,
+        Application {
             events_loop,
             timer_sync,
             timer_thread_handle,
+            render_cache: Rc::new(RefCell::new(RenderCache::new())),
+            windows: HashMap::new()
+        }
+    }
+
+    /// Opens a new window, with the given window configuration, root widget, and theme.
+    pub fn open_window<W: Widget>(&mut self, config: WindowConfig, root: W, theme: Theme) -> Result<WindowId, CreationError> {
+        let mut window_builder = WindowBuilder::new();
+        window_builder.window.dimensions = config.dimensions.map(|d| (d.width(), d.height()));
+        window_builder.window.title = config.title.clone();
+        let gen_context_builder = || {
+            let mut context_builder = ContextBuilder::new();
+
+            context_builder = context_builder.with_multisampling(config.multisampling);
+            if let Some(depth_bits) = config.depth_bits {
+                context_builder = context_builder.with_depth_buffer(depth_bits);
+            }
+            if let Some(stencil_bits) = config.stencil_bits {
+                context_builder = context_builder.with_stencil_buffer(stencil_bits);
+            }
+
+            context_builder
+        };
+
+        // `Application::new` is unsafe for the sake of this call.
+        let renderer = unsafe{ GLRenderer::new(&self.events_loop, window_builder, gen_context_builder, self.render_cache.clone())? };
+        let window_id = renderer.window_id();
+        let dims = renderer.dims();
+
+        let mut root = Root::new(root, theme, renderer, dims);
+        root.register_root_message_type::<WindowMessage>();
+        self.windows.insert(window_id, Box::new(WindowState {
+            root,
+            #[cfg(feature = "derin-serde")]
+            theme_watcher: None,
+            open: true
+        }));
+
+        Ok(window_id)
+    }
+
+    /// Closes the window with the given ID. Returns `false` if no open window has that ID.
+    ///
+    /// Closed windows get hidden, but their root widgets stay accessible through `root` and
+    /// `root_mut` until `remove_window` is called.
+    pub fn close_window(&mut self, window_id: WindowId) -> bool {
+        match self.windows.get_mut(&window_id) {
+            Some(window) => {
+                let was_open = window.is_open();
+                if was_open {
+                    window.close();
+                }
+                was_open
+            },
+            None => false
+        }
+    }
+
+    /// Destroys the window with the given ID. Returns `false` if no window has that ID.
+    pub fn remove_window(&mut self, window_id: WindowId) -> bool {
+        self.windows.remove(&window_id).is_some()
+    }
+
+    /// Retrieves a reference to a window's root widget.
+    ///
+    /// Returns `None` if no window has the given ID, or if the window's root widget isn't a `W`.
+    pub fn root<W: Widget>(&self, window_id: WindowId) -> Option<&W> {
+        self.windows.get(&window_id)?.root_widget().downcast_ref()
+    }
+
+    /// Retrieves a mutable reference to a window's root widget.
+    ///
+    /// Returns `None` if no window has the given ID, or if the window's root widget isn't a `W`.
+    pub fn root_mut<W: Widget>(&mut self, window_id: WindowId) -> Option<&mut W> {
+        self.windows.get_mut(&window_id)?.root_widget_mut().downcast_mut()
+    }
+
+    /// Load the theme file at `path` and use it to draw the given window's widgets.
+    ///
+    /// The theme file, along with any images and fonts it references, gets watched for changes
+    /// while the event loop is running. Whenever any of them change, the theme gets reloaded and
+    /// the window gets redrawn. If reloading the theme fails, the window keeps using the previous
+    /// theme and `on_reload_error` gets called with the error.
+    ///
+    /// Panics if no window has the given ID.
+    #[cfg(feature = "derin-serde")]
+    pub fn watch_theme<P, E>(&mut self, window_id: WindowId, path: P, on_reload_error: E) -> Result<(), ThemeLoadError>
+        where P: Into<PathBuf>,
+              E: 'static + FnMut(ThemeLoadError)
+    {
+        let (watcher, theme) = ThemeWatcher::new(path)?;
+        let theme_watcher = WatchedTheme {
+            watcher,
+            on_reload_error: Box::new(on_reload_error),
+        };
+        self.window_mut(window_id).set_theme(Some(theme_watcher), Some(theme));
+        Ok(())
+    }
+
+    /// Stop watching the given window's theme file for changes. The current theme remains in use.
+    ///
+    /// Panics if no window has the given ID.
+    #[cfg(feature = "derin-serde")]
+    pub fn unwatch_theme(&mut self, window_id: WindowId) {
+        self.window_mut(window_id).set_theme(None, None);
+    }
+
+    /// Retrieves the `gullery` context state of the given window.
+    pub fn context_state(&self, window_id: WindowId) -> Option<Rc<ContextState>> {
+        self.windows.get(&window_id).map(|w| w.context_state())
+    }
+
+    fn window_mut(&mut self, window_id: WindowId) -> &mut AnyWindow {
+        &mut **self.windows.get_mut(&window_id).expect("no window with the given ID")
+    }
+
+    /// Starts the `derin` event loop, running until every window has been closed.
+    ///
+    /// Windows opened or closed with a `WindowMessage` get opened or closed after the frame that
+    /// sent the message finishes. If a window opened that way can't be created, the event loop
+    /// stops and returns the error, once the rest of the frame's messages have been handled.
+    /// Calling `run_forever` again resumes the event loop.
+    ///
+    /// TODO: DOCUMENT HOW EVENT BUBBLING WORKS
+    pub fn run_forever(&mut self) -> Result<(), CreationError> {
+        while self.windows.values().any(|w| w.is_open()) {
+            let mut window_events: HashMap<WindowId, Vec<GWindowEvent>> = HashMap::new();
+            let mut timer_triggered = false;
+            {
+                let mut process_glutin_event = |glutin_event| match glutin_event {
+                    Event::WindowEvent{window_id, event} => window_events.entry(window_id).or_insert(Vec::new()).push(event),
+                    Event::Awakened => timer_triggered = true,
+                    Event::Suspended(..) |
+                    Event::DeviceEvent{..} => ()
+                };
+
+                self.events_loop.run_forever(|e| {process_glutin_event(e); ControlFlow::Break});
+                self.events_loop.poll_events(process_glutin_event);
+            }
+
+            let mut next_wakeup: Option<Instant> = None;
+            let mut messages = Vec::new();
+            for (window_id, window) in &mut self.windows {
+                if !window.is_open() {
+                    continue;
+                }
+
+                let events = window_events.remove(window_id).unwrap_or(Vec::new());
+                let frame_result = window.run_frame(events, timer_triggered);
+                next_wakeup = match (next_wakeup, frame_result.next_wakeup) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b)
+                };
+                messages.extend(frame_result.messages.into_iter().map(|m| (*window_id, m)));
+            }
+
+            match next_wakeup {
+                None => *self.timer_sync.lock() = TimerPark::Indefinite,
+                Some(park_until) => *self.timer_sync.lock() = TimerPark::Timeout(park_until)
+            }
+            self.timer_thread_handle.thread().unpark();
+
+            let mut open_error = None;
+            for (sender_id, message) in messages {
+                match message {
+                    WindowMessage::Open(OpenWindow(mut open)) => if let Err(e) = open(self) {
+                        open_error = open_error.or(Some(e));
+                    },
+                    WindowMessage::CloseThis => {self.close_window(sender_id);},
+                    WindowMessage::Close(window_id) => {self.close_window(window_id);}
+                }
+            }
+            if let Some(e) = open_error {
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Application {
+    fn drop(&mut self) {
+        *self.timer_sync.lock() = TimerPark::Abort;
+        self.timer_thread_handle.thread().unpark();
+    }
+}
+
+impl<W: Widget> GlutinWindow<W> {
+    /// Creates a new window, with the given window configuration, root widget, and theme.
+    ///
+    /// This is unsafe, because it creates at least one OpenGL context. By calling this function,
+    /// you hand all control over the thread's OpenGL context management to this window. In most
+    /// cases this shouldn't be an issue, though.
+    pub unsafe fn new(config: WindowConfig, root: W, theme: Theme) -> Result<GlutinWindow<W>, CreationError> {
+        let mut app = Application::new();
+        let window_id = app.open_window(config, root, theme)?;
+        Ok(GlutinWindow {
+            app,
+            window_id,
+            _marker: PhantomData
         })
     }
 
+    /// Retrieves the ID of the window.
+    pub fn window_id(&self) -> WindowId {
+        self.window_id
+    }
+
+    /// Retrieves a reference to the application running the window.
+    pub fn application(&self) -> &Application {
+        &self.app
+    }
+
+    /// Retrieves a mutable reference to the application running the window.
+    pub fn application_mut(&mut self) -> &mut Application {
+        &mut self.app
+    }
+
     /// Retrieves a reference to the root widget.
     pub fn root(&self) -> &W {
-        &self.root.root_widget
+        self.app.root(self.window_id).expect("window removed from application")
     }
 
     /// Retrieves a mutable reference to the root widget.
     pub fn root_mut(&mut self) -> &mut W {
-        &mut self.root.root_widget
+        self.app.root_mut(self.window_id).expect("window removed from application")
     }
 
-    /// Starts the `derin` event loop, calling `on_action` whenever an action is triggered by a
-    /// child widget. Aborts when `LoopFlow::Break` is returned by `on_action`.
+    /// Load the theme file at `path` and use it to draw the window's widgets.
     ///
-    /// `on_fallthrough` is called whenever a raw event bubbles through the root widget.
+    /// The theme file, along with any images and fonts it references, gets watched for changes
+    /// while the event loop is running. Whenever any of them change, the theme gets reloaded and
+    /// the window gets redrawn. If reloading the theme fails, the window keeps using the previous
+    /// theme and `on_reload_error` gets called with the error.
+    #[cfg(feature = "derin-serde")]
+    pub fn watch_theme<P, E>(&mut self, path: P, on_reload_error: E) -> Result<(), ThemeLoadError>
+        where P: Into<PathBuf>,
+              E: 'static + FnMut(ThemeLoadError)
+    {
+        self.app.watch_theme(self.window_id, path, on_reload_error)
+    }
+
+    /// Stop watching the theme file for changes. The current theme remains in use.
+    #[cfg(feature = "derin-serde")]
+    pub fn unwatch_theme(&mut self) {
+        self.app.unwatch_theme(self.window_id)
+    }
+
+    /// Starts the `derin` event loop. Runs until every window opened by the application has been
+    /// closed, or until a window opened with a `WindowMessage` can't be created.
     ///
     /// TODO: DOCUMENT HOW EVENT BUBBLING WORKS
-    pub fn run_forever(&mut self)
-    {
-        let GlutinWindow {
-            ref mut primary_renderer,
-            ref mut events_loop,
-            ref mut timer_sync,
-            ref mut timer_thread_handle,
-            ref mut root,
-        } = *self;
-
-        let map_modifiers = |g_modifiers: ModifiersState| {
-            let mut modifiers = ModifierKeys::empty();
-            modifiers.set(ModifierKeys::SHIFT, g_modifiers.shift);
-            modifiers.set(ModifierKeys::CTRL, g_modifiers.ctrl);
-            modifiers.set(ModifierKeys::ALT, g_modifiers.alt);
-            modifiers.set(ModifierKeys::LOGO, g_modifiers.logo);
-            modifiers
-        };
+    pub fn run_forever(&mut self) -> Result<(), CreationError> {
+        self.app.run_forever()
+    }
 
-        loop {
-            let mut break_loop = false;
+    /// Retrieves the `gullery` context state.
+    pub fn context_state(&self) -> Rc<ContextState> {
+        self.app.context_state(self.window_id).expect("window removed from application")
+    }
+}
 
-            let mut frame = root.start_frame();
-            let mut process_glutin_event = |glutin_event| {
-                let derin_event: WindowEvent = match glutin_event {
-                    Event::WindowEvent{event, ..} => {
-                        let scale_factor = primary_renderer.window().hidpi_factor();
-                        macro_rules! scale {
-                            ($val:expr) => {{($val as f32 / scale_factor) as _}}
-                        }
-                        match event {
-                            GWindowEvent::CursorMoved{position, modifiers, ..} => {
-                                frame.set_modifiers(map_modifiers(modifiers));
-                                WindowEvent::MouseMove(Point2::new(scale!(position.0), scale!(position.1)))
-                            },
-                            GWindowEvent::CursorEntered{..} => WindowEvent::MouseEnter,
-                            GWindowEvent::CursorLeft{..} => WindowEvent::MouseExit,
-                            GWindowEvent::MouseInput{state, button: g_button, modifiers, ..} => {
-                                frame.set_modifiers(map_modifiers(modifiers));
-                                let button = match g_button {
-                                    GMouseButton::Left => MouseButton::Left,
-                                    GMouseButton::Right => MouseButton::Right,
-                                    GMouseButton::Middle => MouseButton::Middle,
-                                    GMouseButton::Other(1) => MouseButton::X1,
-                                    GMouseButton::Other(2) => MouseButton::X2,
-                                    GMouseButton::Other(_) => return
-                                };
-                                match state {
-                                    ElementState::Pressed => WindowEvent::MouseDown(button),
-                                    ElementState::Released => WindowEvent::MouseUp(button)
-                                }
-                            }
-                            GWindowEvent::MouseWheel{delta, modifiers, ..} => {
-                                frame.set_modifiers(map_modifiers(modifiers));
-                                match delta {
-                                    MouseScrollDelta::LineDelta(x, y) => WindowEvent::MouseScrollLines(Vector2::new(x as i32, y as i32)),
-                                    MouseScrollDelta::PixelDelta(x, y) => WindowEvent::MouseScrollPx(Vector2::new(x as i32, y as i32)),
-                                }
-                            }
-                            GWindowEvent::Resized(width, height) => WindowEvent::WindowResize(DimsBox::new2(scale!(width), scale!(height))),
-                            GWindowEvent::ReceivedCharacter(c) => WindowEvent::Char(c),
-                            GWindowEvent::KeyboardInput{ input, .. } => {
-                                if let Some(key) = input.virtual_keycode.and_then(map_key) {
-                                    frame.set_modifiers(map_modifiers(input.modifiers));
-                                    match input.state {
-                                        ElementState::Pressed => WindowEvent::KeyDown(key),
-                                        ElementState::Released => WindowEvent::KeyUp(key)
-                                    }
-                                } else {
-                                    return;
-                                }
-                            }
-                            GWindowEvent::Closed => {
-                                break_loop = true;
-                                return
-                            },
-                            GWindowEvent::Refresh => WindowEvent::Redraw,
-                            _ => return
-                        }
-                    },
-                    Event::Awakened => WindowEvent::Timer,
-                    Event::Suspended(..) |
-                    Event::DeviceEvent{..} => return
-                };
+impl<W: Widget> AnyWindow for WindowState<W> {
+    fn is_open(&self) -> bool {
+        self.open
+    }
 
-                frame.process_event(derin_event);
-            };
+    fn close(&mut self) {
+        self.root.renderer.hide();
+        self.open = false;
+    }
 
-            events_loop.run_forever(|e| {process_glutin_event(e); ControlFlow::Break});
-            events_loop.poll_events(process_glutin_event);
+    fn run_frame(&mut self, events: Vec<GWindowEvent>, timer_triggered: bool) -> FrameResult {
+        let mut close_requested = false;
 
-            let EventLoopResult {
-                next_timer,
-                set_cursor_pos,
-                set_cursor_icon,
-            } = frame.finish();
+        // The window moved to a monitor with a different DPI, so everything needs to get
+        // re-laid-out and re-rasterized at the new resolution. This gets applied before any other
+        // events, so that the resize accompanying the DPI change gets converted to logical pixels
+        // with the new scale factor.
+        let new_scale_factor = events.iter().filter_map(|event| match *event {
+            GWindowEvent::HiDPIFactorChanged(scale_factor) => Some(scale_factor),
+            _ => None
+        }).last();
+        if let Some(scale_factor) = new_scale_factor {
+            self.root.set_scale_factor(scale_factor);
+        }
 
-            match next_timer {
-                None => *timer_sync.lock() = TimerPark::Indefinite,
-                Some(park_until) => *timer_sync.lock() = TimerPark::Timeout(park_until)
-            }
-            if let Some(cursor_pos) = set_cursor_pos {
-                primary_renderer.set_cursor_pos(cursor_pos);
+        let EventLoopResult {
+            next_timer,
+            set_cursor_pos,
+            set_cursor_icon,
+        } = {
+            let scale_factor = self.root.scale_factor();
+            let mut frame = self.root.start_frame();
+            for event in events {
+                let derin_event = match event {
+                    GWindowEvent::Closed => {
+                        close_requested = true;
+                        continue
+                    },
+                    GWindowEvent::HiDPIFactorChanged(_) => continue,
+                    event => match map_window_event(event, scale_factor, &mut frame) {
+                        Some(derin_event) => derin_event,
+                        None => continue
+                    }
+                };
+                frame.process_event(derin_event);
             }
-            if let Some(cursor_icon) = set_cursor_icon {
-                primary_renderer.set_cursor_icon(cursor_icon);
+            if timer_triggered {
+                frame.process_event(WindowEvent::Timer);
             }
-            timer_thread_handle.thread().unpark();
 
-            if break_loop {
-                break;
+            frame.finish()
+        };
+
+        if let Some(cursor_pos) = set_cursor_pos {
+            self.root.renderer.set_cursor_pos(cursor_pos);
+        }
+        if let Some(cursor_icon) = set_cursor_icon {
+            self.root.renderer.set_cursor_icon(cursor_icon);
+        }
+
+        let messages = self.root.take_root_messages::<WindowMessage>();
+        if close_requested {
+            self.close();
+            return FrameResult{ next_wakeup: None, messages };
+        }
+
+        #[cfg(feature = "derin-serde")]
+        {
+            if let Some(ref mut theme_watcher) = self.theme_watcher {
+                match theme_watcher.watcher.poll() {
+                    Some(Ok(theme)) => {
+                        self.root.theme = theme;
+                        self.root.queue_global_update();
+                    },
+                    Some(Err(e)) => (theme_watcher.on_reload_error)(e),
+                    None => ()
+                }
             }
+        }
+
+        let size_bounds = self.root.relayout();
+        self.root.renderer.set_size_bounds(size_bounds);
+        self.root.redraw();
 
-            let size_bounds = root.relayout();
-            primary_renderer.set_size_bounds(size_bounds);
-            root.redraw(primary_renderer);
+        // Wake up periodically to check the theme for changes.
+        #[cfg(feature = "derin-serde")]
+        let next_theme_poll = self.theme_watcher.as_ref().map(|_| Instant::now() + THEME_POLL_INTERVAL);
+        #[cfg(not(feature = "derin-serde"))]
+        let next_theme_poll = None;
+        let next_wakeup = match (next_timer, next_theme_poll) {
+            (Some(timer), Some(poll)) => Some(timer.min(poll)),
+            (timer, poll) => timer.or(poll)
+        };
+        FrameResult{ next_wakeup, messages }
+    }
+
+    #[cfg(feature = "derin-serde")]
+    fn set_theme(&mut self, theme_watcher: Option<WatchedTheme>, theme: Option<Theme>) {
+        self.theme_watcher = theme_watcher;
+        if let Some(theme) = theme {
+            self.root.theme = theme;
+            self.root.queue_global_update();
         }
     }
 
-    /// Retrieves the `gullery` context state.
-    pub fn context_state(&self) -> Rc<ContextState> {
-        self.primary_renderer.context_state()
+    fn root_widget(&self) -> &Any {
+        &self.root.root_widget
+    }
+
+    fn root_widget_mut(&mut self) -> &mut Any {
+        &mut self.root.root_widget
+    }
+
+    fn context_state(&self) -> Rc<ContextState> {
+        self.root.renderer.context_state()
     }
 }
 
-impl<N: Widget> Drop for GlutinWindow<N> {
-    fn drop(&mut self) {
-        *self.timer_sync.lock() = TimerPark::Abort;
-        self.timer_thread_handle.thread().unpark();
+fn map_modifiers(g_modifiers: ModifiersState) -> ModifierKeys {
+    let mut modifiers = ModifierKeys::empty();
+    modifiers.set(ModifierKeys::SHIFT, g_modifiers.shift);
+    modifiers.set(ModifierKeys::CTRL, g_modifiers.ctrl);
+    modifiers.set(ModifierKeys::ALT, g_modifiers.alt);
+    modifiers.set(ModifierKeys::LOGO, g_modifiers.logo);
+    modifiers
+}
+
+/// Convert a glutin window event into a derin window event, scaling positions from physical to
+/// logical pixels.
+fn map_window_event(event: GWindowEvent, scale_factor: f32, frame: &mut FrameEventProcessor<'_, GLRenderer>) -> Option<WindowEvent> {
+    macro_rules! scale {
+        ($val:expr) => {{($val as f32 / scale_factor) as _}}
     }
+    let derin_event = match event {
+        GWindowEvent::CursorMoved{position, modifiers, ..} => {
+            frame.set_modifiers(map_modifiers(modifiers));
+            WindowEvent::MouseMove(Point2::new(scale!(position.0), scale!(position.1)))
+        },
+        GWindowEvent::CursorEntered{..} => WindowEvent::MouseEnter,
+        GWindowEvent::CursorLeft{..} => WindowEvent::MouseExit,
+        GWindowEvent::MouseInput{state, button: g_button, modifiers, ..} => {
+            frame.set_modifiers(map_modifiers(modifiers));
+            let button = match g_button {
+                GMouseButton::Left => MouseButton::Left,
+                GMouseButton::Right => MouseButton::Right,
+                GMouseButton::Middle => MouseButton::Middle,
+                GMouseButton::Other(1) => MouseButton::X1,
+                GMouseButton::Other(2) => MouseButton::X2,
+                GMouseButton::Other(_) => return None
+            };
+            match state {
+                ElementState::Pressed => WindowEvent::MouseDown(button),
+                ElementState::Released => WindowEvent::MouseUp(button)
+            }
+        }
+        GWindowEvent::MouseWheel{delta, modifiers, ..} => {
+            frame.set_modifiers(map_modifiers(modifiers));
+            match delta {
+                MouseScrollDelta::LineDelta(x, y) => WindowEvent::MouseScrollLines(Vector2::new(x as i32, y as i32)),
+                MouseScrollDelta::PixelDelta(x, y) => WindowEvent::MouseScrollPx(Vector2::new(x as i32, y as i32)),
+            }
+        }
+        GWindowEvent::Resized(width, height) => WindowEvent::WindowResize(DimsBox::new2(scale!(width), scale!(height))),
+        GWindowEvent::ReceivedCharacter(c) => WindowEvent::Char(c),
+        GWindowEvent::KeyboardInput{ input, .. } => {
+            let key = input.virtual_keycode.and_then(map_key)?;
+            frame.set_modifiers(map_modifiers(input.modifiers));
+            match input.state {
+                ElementState::Pressed => WindowEvent::KeyDown(key),
+                ElementState::Released => WindowEvent::KeyUp(key)
+            }
+        }
+        GWindowEvent::Refresh => WindowEvent::Redraw,
+        _ => return None
+    };
+    Some(derin_event)
 }
 
 fn map_key(k: VirtualKeyCode) -> Option<Key> {
@@ -8,6 +8,7 @@
 //! containers that makes it easy to design and compose your own complex desktop GUI applications.
 //! Included are the aforementioned [widgets], a [desktop window builder][window], an [OpenGL-powered
 //! renderer][renderer], and utilities for [creating your own widgets and renderers][custom].
+//! A [software renderer][soft-renderer] is also provided, for drawing UIs on machines without a GPU.
 //!
 //! ## Installing External Libraries
 //! Derin currently relies on two external libraries to handle text rendering: Freetype and Harfbuzz.
@@ -24,6 +25,7 @@
 //! [widgets]: widgets/index.html
 //! [window]: struct.Window.html
 //! [renderer]: gl_render/struct.GLRenderer.html
+//! [soft-renderer]: soft_render/struct.SoftRenderer.html
 //! [custom]: widgets/custom/index.html
 //! [freetype-build]: https://github.com/PistonDevelopers/freetype-sys/blob/master/README.md
 //! [container]: container/trait.WidgetContainer.html
@@ -44,19 +46,25 @@ extern crate itertools;
 extern crate unicode_segmentation;
 extern crate clipboard;
 extern crate png;
+#[cfg(feature = "derin-serde")]
+extern crate ron;
+#[cfg(feature = "derin-serde")]
+extern crate serde;
 extern crate parking_lot;
 #[macro_use]
 extern crate lazy_static;
 
 pub mod container;
-// pub mod gl_render;
-// mod glutin_window;
+pub mod gl_render;
+mod glutin_window;
 pub mod layout;
+mod render_common;
+pub mod soft_render;
 pub mod theme;
 pub mod widgets;
 
-// pub use crate::glutin_window::{GlutinWindow as Window, WindowConfig};
-pub use glutin::WindowAttributes;
+pub use crate::glutin_window::{Application, GlutinWindow as Window, OpenWindow, WindowConfig, WindowMessage};
+pub use glutin::{WindowAttributes, WindowId};
 pub use crate::core::LoopFlow;
 
 /// `WidgetEvent` type and associated helpers.
@@ -72,3 +80,13 @@ pub mod geometry {
     pub use crate::cgmath::{Point2, Vector2};
     pub use cgmath_geometry::{D2, rect, line};
 }
+
+/// Types used to show widgets above the rest of the widget tree, like menus and dialogs.
+pub mod overlay {
+    pub use crate::core::overlay::{Overlay, OverlayDismissed, OverlayPlacement};
+}
+
+/// Types used to drag data from one widget and drop it onto another.
+pub mod drag {
+    pub use crate::core::drag::{DragFinished, DragImage};
+}
@@ -0,0 +1,13 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Image atlasing, font loading, text shaping, shape tessellation and widget content layout shared
+//! between the renderers.
+
+pub mod atlas;
+pub mod content;
+pub mod font_cache;
+pub mod shape;
+pub mod shape_glyphs;
+pub mod text;
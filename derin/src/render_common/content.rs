@@ -0,0 +1,168 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use gullery::image_format::Rgba;
+use cgmath_geometry::{D2, rect::{BoundBox, GeoBox}};
+use derin_common_types::layout::SizeBounds;
+use crate::cgmath::Point2;
+
+use crate::core::render::{RendererLayout, LayoutResult, CursorData, CursorOp};
+use crate::render_common::text::{self, TextCache, ShapedString};
+use crate::theme::{Theme, ThemeWidget, LineWrap};
+
+/// Content a widget prepared during layout, along with data cached from the last time it was drawn.
+#[derive(Default)]
+pub struct WidgetContent {
+    pub content: Vec<Content>,
+    /// The theme the widget was last drawn with. Layout doesn't get access to the theme, so this is
+    /// used to compute the widget's size bounds.
+    pub theme_widget: Option<ThemeWidget>,
+    /// The shaped glyphs of the widget's edit string, used to resolve cursor operations.
+    pub edit_shaped: ShapedString,
+}
+
+pub enum Content {
+    String(String),
+    EditString {
+        string: String,
+        cursor_data: CursorData,
+    },
+    Icon(String),
+}
+
+/// Records the content widgets prepare during layout.
+pub struct ContentLayout {
+    text_cache: Rc<RefCell<TextCache>>,
+    widget: WidgetContent,
+    scale_factor: f32,
+    finished: bool,
+}
+
+impl ContentLayout {
+    pub fn new(text_cache: Rc<RefCell<TextCache>>) -> ContentLayout {
+        ContentLayout {
+            text_cache,
+            widget: WidgetContent::default(),
+            scale_factor: 1.0,
+            finished: false,
+        }
+    }
+
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        self.scale_factor = scale_factor;
+    }
+
+    /// Lay out `widget`, handing it to `layout` to have its content prepared. The content from the
+    /// last layout gets cleared beforehand.
+    pub fn layout(&mut self, mut widget: WidgetContent, layout: impl FnOnce(&mut ContentLayout)) -> WidgetContent {
+        widget.content.clear();
+
+        self.widget = widget;
+        self.finished = false;
+        layout(self);
+
+        std::mem::replace(&mut self.widget, WidgetContent::default())
+    }
+}
+
+impl RendererLayout for ContentLayout {
+    fn prepare_string(&mut self, string: &str) {
+        self.widget.content.push(Content::String(string.to_string()));
+    }
+
+    fn prepare_edit_string(
+        &mut self,
+        string: &mut String,
+        cursor_data: &mut CursorData,
+        cursor_ops: impl Iterator<Item=CursorOp>,
+    ) {
+        let scale_factor = self.scale_factor;
+        let to_physical = |p: Point2<i32>| Point2::new(
+            (p.x as f32 * scale_factor).round() as i32,
+            (p.y as f32 * scale_factor).round() as i32,
+        );
+        for mut op in cursor_ops {
+            // The edit string's glyphs are positioned in physical pixels.
+            if let CursorOp::SelectOnSegment(ref mut segment) = op {
+                segment.start = to_physical(segment.start);
+                segment.end = to_physical(segment.end);
+            }
+            text::apply_cursor_op(string, cursor_data, op, &self.widget.edit_shaped.glyphs);
+        }
+        self.widget.content.push(Content::EditString {
+            string: string.clone(),
+            cursor_data: cursor_data.clone(),
+        });
+    }
+
+    fn prepare_icon(&mut self, icon_name: &str) {
+        self.widget.content.push(Content::Icon(icon_name.to_string()));
+    }
+
+    fn finish(&mut self) -> LayoutResult {
+        assert!(!self.finished, "`finish` called more than once");
+        self.finished = true;
+
+        let theme_widget = match self.widget.theme_widget {
+            Some(ref theme_widget) => theme_widget,
+            None => return LayoutResult {
+                size_bounds: SizeBounds::default(),
+                content_rect: BoundBox::new2(0, 0, 0, 0),
+            }
+        };
+
+        let mut size_bounds = theme_widget.image.as_ref().map(|i| i.size_bounds).unwrap_or_default();
+        if let Some(ref text_style) = theme_widget.text {
+            if text_style.line_wrap == LineWrap::None {
+                let mut text_cache = self.text_cache.borrow_mut();
+                for content in &self.widget.content {
+                    let string = match *content {
+                        Content::String(ref string) |
+                        Content::EditString{ref string, ..} => string,
+                        Content::Icon(_) => continue
+                    };
+                    let text_min = text_cache.min_size(string, text_style);
+                    size_bounds.min.dims.x = size_bounds.min.width().max(text_min.width());
+                    size_bounds.min.dims.y = size_bounds.min.height().max(text_min.height());
+                }
+            }
+        }
+
+        let margins = theme_widget.content_margins;
+        LayoutResult {
+            size_bounds,
+            // The content rect is relative to the widget's origin, at the widget's minimum size.
+            content_rect: BoundBox::new2(
+                margins.left as i32,
+                margins.top as i32,
+                size_bounds.min.width() - margins.right as i32,
+                size_bounds.min.height() - margins.bottom as i32,
+            ),
+        }
+    }
+}
+
+/// Look up the theme used to draw `path`, along with the color its images get multiplied by.
+///
+/// Disabled widgets get drawn with their `::Disabled` theme. If the theme doesn't have one, they
+/// get drawn faded out instead.
+pub fn resolve_theme(theme: &Theme, path: &str, enabled: bool) -> (ThemeWidget, Rgba<u8>) {
+    let opaque = Rgba::new(255, 255, 255, 255);
+    match enabled {
+        true => (theme.widget_theme(path), opaque),
+        false => match theme.disabled_widget_theme(path) {
+            Some(theme_widget) => (theme_widget, opaque),
+            None => (theme.widget_theme(path).dimmed(), Rgba::new(255, 255, 255, 128))
+        }
+    }
+}
+
+/// Convert a rectangle from logical pixels to physical pixels.
+pub fn to_physical(rect: BoundBox<D2, i32>, scale_factor: f32) -> BoundBox<D2, i32> {
+    let scale = |px: i32| (px as f32 * scale_factor).round() as i32;
+    BoundBox::new2(scale(rect.min.x), scale(rect.min.y), scale(rect.max.x), scale(rect.max.y))
+}
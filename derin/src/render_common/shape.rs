@@ -0,0 +1,273 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use gullery::image_format::Rgba;
+
+use crate::cgmath::{Point2, Vector2, InnerSpace};
+use cgmath_geometry::{D2, rect::{BoundBox, GeoBox}};
+
+use derin_common_types::layout::Margins;
+
+use crate::theme::{Fill, Shape};
+
+use arrayvec::ArrayVec;
+use std::f32::consts::PI;
+
+/// A vertex of a tessellated shape. Every three vertices form a triangle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapeVertex {
+    pub pos: Point2<f32>,
+    pub color: Rgba<u8>,
+}
+
+/// The radii of a rounded rectangle's corners, in clockwise order starting with the top-left
+/// corner.
+type CornerRadii = [Vector2<f32>; 4];
+
+/// Tessellate `shape` into triangles filling `rect`, clipped to `clip`, and push the triangles onto
+/// `vertices`.
+///
+/// The shape's measurements are in logical pixels, and get multiplied by `scale_factor` to match
+/// `rect` and `clip`.
+pub fn tessellate_shape(
+    shape: &Shape,
+    rect: BoundBox<D2, i32>,
+    clip: BoundBox<D2, i32>,
+    scale_factor: f32,
+    vertices: &mut Vec<ShapeVertex>
+) {
+    let rect = rect.cast::<f32>().unwrap();
+    let clip = clip.cast::<f32>().unwrap();
+    let radius = (shape.corner_radius as f32 * scale_factor).min(rect.width() / 2.).min(rect.height() / 2.).max(0.);
+    let radii = [Vector2::new(radius, radius); 4];
+    let segments = corner_segments(radius);
+
+    let mut push_triangle = |a, b, c| clip_triangle([a, b, c], clip, vertices);
+
+    if let Some(shadow) = shape.shadow {
+        let offset = Vector2::new(shadow.offset_x as f32, shadow.offset_y as f32) * scale_factor;
+        let spread = shadow.spread as f32 * scale_factor;
+        let half_blur = shadow.blur_radius as f32 * scale_factor / 2.;
+        let shadow_rect = BoundBox::new2(
+            rect.min.x - spread + offset.x,
+            rect.min.y - spread + offset.y,
+            rect.max.x + spread + offset.x,
+            rect.max.y + spread + offset.y,
+        );
+        let transparent = Rgba::new(shadow.color.r, shadow.color.g, shadow.color.b, 0);
+
+        // The shadow's solid center. Fade out from the center over the blur radius.
+        let inner_rect = shrink(shadow_rect, Margins::new(half_blur, half_blur, half_blur, half_blur));
+        let inner_radii = adjust_radii(radii, spread - half_blur);
+        let inner = rounded_rect(inner_rect, inner_radii, segments);
+        fill_contour(&inner, |_| shadow.color, &mut push_triangle);
+
+        if half_blur > 0. {
+            let outer_rect = shrink(shadow_rect, Margins::new(-half_blur, -half_blur, -half_blur, -half_blur));
+            let outer = rounded_rect(outer_rect, adjust_radii(radii, spread + half_blur), segments);
+            stroke_contours(&outer, transparent, &inner, shadow.color, &mut push_triangle);
+        }
+    }
+
+    let outer = rounded_rect(rect, radii, segments);
+    match shape.fill {
+        Some(Fill::Solid(color)) => fill_contour(&outer, |_| color, &mut push_triangle),
+        Some(Fill::LinearGradient{start, end, angle}) => {
+            // Project each point onto the gradient line, in the same way CSS does.
+            let angle = (angle as f32).to_radians();
+            let dir = Vector2::new(angle.sin(), -angle.cos());
+            let length = (rect.width() * angle.sin()).abs() + (rect.height() * angle.cos()).abs();
+            let center = rect.center();
+            fill_contour(&outer, |pos| {
+                let t = match length {
+                    l if l > 0. => ((pos - center).dot(dir) / l + 0.5).max(0.).min(1.),
+                    _ => 0.
+                };
+                lerp_color(start, end, t)
+            }, &mut push_triangle);
+        },
+        None => ()
+    }
+
+    if let Some(border) = shape.border {
+        let widths = Margins::new(
+            border.widths.left as f32 * scale_factor,
+            border.widths.top as f32 * scale_factor,
+            border.widths.right as f32 * scale_factor,
+            border.widths.bottom as f32 * scale_factor,
+        );
+        let inner_rect = shrink(rect, widths);
+        let inner_radii = [
+            Vector2::new(radius - widths.left, radius - widths.top),
+            Vector2::new(radius - widths.right, radius - widths.top),
+            Vector2::new(radius - widths.right, radius - widths.bottom),
+            Vector2::new(radius - widths.left, radius - widths.bottom),
+        ];
+        let inner = rounded_rect(inner_rect, clamp_radii(inner_radii, inner_rect), segments);
+        stroke_contours(&outer, border.color, &inner, border.color, &mut push_triangle);
+    }
+}
+
+/// The number of line segments used to approximate a corner with the given radius.
+fn corner_segments(radius: f32) -> usize {
+    match radius {
+        r if r <= 0. => 1,
+        r => ((r / 2.).ceil() as usize).max(2).min(16)
+    }
+}
+
+/// Shrink `rect` by `margins`, without letting it invert.
+fn shrink(rect: BoundBox<D2, f32>, margins: Margins<f32>) -> BoundBox<D2, f32> {
+    let mut min_x = rect.min.x + margins.left;
+    let mut max_x = rect.max.x - margins.right;
+    let mut min_y = rect.min.y + margins.top;
+    let mut max_y = rect.max.y - margins.bottom;
+    if min_x > max_x {
+        min_x = (min_x + max_x) / 2.;
+        max_x = min_x;
+    }
+    if min_y > max_y {
+        min_y = (min_y + max_y) / 2.;
+        max_y = min_y;
+    }
+    BoundBox::new2(min_x, min_y, max_x, max_y)
+}
+
+fn adjust_radii(radii: CornerRadii, delta: f32) -> CornerRadii {
+    let adjust = |r: Vector2<f32>| Vector2::new((r.x + delta).max(0.), (r.y + delta).max(0.));
+    [adjust(radii[0]), adjust(radii[1]), adjust(radii[2]), adjust(radii[3])]
+}
+
+fn clamp_radii(radii: CornerRadii, rect: BoundBox<D2, f32>) -> CornerRadii {
+    let clamp = |r: Vector2<f32>| Vector2::new(
+        r.x.max(0.).min(rect.width() / 2.),
+        r.y.max(0.).min(rect.height() / 2.),
+    );
+    [clamp(radii[0]), clamp(radii[1]), clamp(radii[2]), clamp(radii[3])]
+}
+
+/// Generate the outline of a rounded rectangle, going clockwise from the top-left corner.
+///
+/// Every contour generated with the same number of segments has the same number of points, so
+/// contours can be stitched together with `stroke_contours`.
+fn rounded_rect(rect: BoundBox<D2, f32>, radii: CornerRadii, segments: usize) -> Vec<Point2<f32>> {
+    let radii = clamp_radii(radii, rect);
+    let centers = [
+        Point2::new(rect.min.x + radii[0].x, rect.min.y + radii[0].y),
+        Point2::new(rect.max.x - radii[1].x, rect.min.y + radii[1].y),
+        Point2::new(rect.max.x - radii[2].x, rect.max.y - radii[2].y),
+        Point2::new(rect.min.x + radii[3].x, rect.max.y - radii[3].y),
+    ];
+
+    let mut points = Vec::with_capacity(4 * (segments + 1));
+    for corner in 0..4 {
+        // The top-left corner's arc starts pointing left, and each subsequent corner is rotated
+        // another quarter-turn clockwise.
+        let start_angle = PI + corner as f32 * PI / 2.;
+        for i in 0..=segments {
+            let angle = start_angle + (i as f32 / segments as f32) * PI / 2.;
+            points.push(Point2::new(
+                centers[corner].x + angle.cos() * radii[corner].x,
+                centers[corner].y + angle.sin() * radii[corner].y,
+            ));
+        }
+    }
+    points
+}
+
+/// Fill a convex contour with a triangle fan.
+fn fill_contour(
+    contour: &[Point2<f32>],
+    color: impl Fn(Point2<f32>) -> Rgba<u8>,
+    push_triangle: &mut impl FnMut(ShapeVertex, ShapeVertex, ShapeVertex)
+) {
+    if contour.len() < 3 {
+        return;
+    }
+    let center = contour.iter().fold(Vector2::new(0., 0.), |sum, p| sum + Vector2::new(p.x, p.y)) / contour.len() as f32;
+    let center = Point2::new(center.x, center.y);
+    let vertex = |pos| ShapeVertex{ pos, color: color(pos) };
+
+    for (i, &point) in contour.iter().enumerate() {
+        let next = contour[(i + 1) % contour.len()];
+        push_triangle(vertex(center), vertex(point), vertex(next));
+    }
+}
+
+/// Fill the space between two contours with the same number of points.
+fn stroke_contours(
+    outer: &[Point2<f32>],
+    outer_color: Rgba<u8>,
+    inner: &[Point2<f32>],
+    inner_color: Rgba<u8>,
+    push_triangle: &mut impl FnMut(ShapeVertex, ShapeVertex, ShapeVertex)
+) {
+    debug_assert_eq!(outer.len(), inner.len());
+    let len = outer.len();
+    for i in 0..len {
+        let j = (i + 1) % len;
+        let outer_i = ShapeVertex{ pos: outer[i], color: outer_color };
+        let outer_j = ShapeVertex{ pos: outer[j], color: outer_color };
+        let inner_i = ShapeVertex{ pos: inner[i], color: inner_color };
+        let inner_j = ShapeVertex{ pos: inner[j], color: inner_color };
+        push_triangle(outer_i, outer_j, inner_j);
+        push_triangle(inner_j, inner_i, outer_i);
+    }
+}
+
+fn lerp_color(a: Rgba<u8>, b: Rgba<u8>, t: f32) -> Rgba<u8> {
+    let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    Rgba::new(lerp(a.r, b.r), lerp(a.g, b.g), lerp(a.b, b.b), lerp(a.a, b.a))
+}
+
+fn lerp_vertex(a: ShapeVertex, b: ShapeVertex, t: f32) -> ShapeVertex {
+    ShapeVertex {
+        pos: a.pos + (b.pos - a.pos) * t,
+        color: lerp_color(a.color, b.color, t),
+    }
+}
+
+/// Clip a triangle to `clip`, pushing the resulting triangles onto `vertices`.
+fn clip_triangle(triangle: [ShapeVertex; 3], clip: BoundBox<D2, f32>, vertices: &mut Vec<ShapeVertex>) {
+    let inside = |v: &ShapeVertex| clip.min.x <= v.pos.x && v.pos.x <= clip.max.x && clip.min.y <= v.pos.y && v.pos.y <= clip.max.y;
+    if triangle.iter().all(inside) {
+        vertices.extend_from_slice(&triangle);
+        return;
+    }
+
+    // Sutherland-Hodgman clipping against each edge of the clip rectangle. Clipping a triangle
+    // against four edges yields at most seven points.
+    let mut polygon: ArrayVec<[ShapeVertex; 8]> = triangle.iter().cloned().collect();
+    let edges: [(fn(Point2<f32>) -> f32, f32, bool); 4] = [
+        (|p| p.x, clip.min.x, true),
+        (|p| p.x, clip.max.x, false),
+        (|p| p.y, clip.min.y, true),
+        (|p| p.y, clip.max.y, false),
+    ];
+    for &(axis, bound, is_min) in &edges {
+        let keep = |v: &ShapeVertex| match is_min {
+            true => axis(v.pos) >= bound,
+            false => axis(v.pos) <= bound
+        };
+        let input = polygon.clone();
+        polygon.clear();
+        for (i, &current) in input.iter().enumerate() {
+            let prev = input[(i + input.len() - 1) % input.len()];
+            if keep(&current) != keep(&prev) {
+                let t = (bound - axis(prev.pos)) / (axis(current.pos) - axis(prev.pos));
+                polygon.push(lerp_vertex(prev, current, t));
+            }
+            if keep(&current) {
+                polygon.push(current);
+            }
+        }
+        if polygon.is_empty() {
+            return;
+        }
+    }
+
+    for i in 1..polygon.len().saturating_sub(1) {
+        vertices.extend_from_slice(&[polygon[0], polygon[i], polygon[i + 1]]);
+    }
+}
@@ -0,0 +1,505 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::render_common::{
+    atlas::Atlas,
+    font_cache::FontCache,
+    shape_glyphs::{self, RenderGlyph},
+};
+use crate::theme::ThemeText;
+use crate::core::render::{CursorData, CursorOp};
+
+use crate::cgmath::{EuclideanSpace, ElementWise, Point2, Vector2};
+use cgmath_geometry::{D2, rect::{BoundBox, DimsBox, OffsetBox, GeoBox}, line::Segment};
+
+use gullery::image_format::Rgba;
+
+use glyphydog::{ShapedBuffer, Shaper, FaceSize, DPI, LoadFlags, RenderMode};
+use derin_common_types::layout::Align;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use std::cmp::{self, Ordering};
+
+/// Font faces and shaping buffers shared between a renderer's layout and draw passes.
+pub struct TextCache {
+    font_cache: FontCache,
+    shaper: Shaper,
+    shaped_text: ShapedBuffer,
+    /// The resolution glyphs get rasterized at. This is scaled with the scale factor, so glyphs get
+    /// rendered at the display's physical resolution.
+    dpi: DPI,
+    scale_factor: f32,
+}
+
+/// The position of a string's glyphs within a widget, in physical pixels.
+#[derive(Debug, Clone)]
+pub struct ShapedString {
+    /// The glyphs, relative to the top-left corner of the widget.
+    pub glyphs: Vec<RenderGlyph>,
+    /// The rectangle text can be drawn in, relative to the top-left corner of the widget.
+    pub text_box: BoundBox<D2, i32>,
+}
+
+/// A piece of drawn text, in physical pixels. Renderers draw these clipped to the rectangle passed
+/// alongside them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextDraw {
+    /// A solid rectangle, used to draw highlights and the cursor.
+    Rect(BoundBox<D2, i32>, Rgba<u8>),
+    /// A glyph, stored in the atlas at `atlas_rect`. Glyphs are stored as white, and get tinted
+    /// with `color`.
+    Glyph {
+        rect: BoundBox<D2, i32>,
+        atlas_rect: OffsetBox<D2, u32>,
+        color: Rgba<u8>,
+    },
+}
+
+impl TextCache {
+    pub fn new() -> TextCache {
+        TextCache {
+            font_cache: FontCache::new(),
+            shaper: Shaper::new(),
+            shaped_text: ShapedBuffer::new(),
+            dpi: DPI::new(72, 72),
+            scale_factor: 1.0,
+        }
+    }
+
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        let dpi = (72. * scale_factor).round() as u32;
+        self.dpi = DPI::new(dpi, dpi);
+        self.scale_factor = scale_factor;
+    }
+
+    /// Shape `string` into `rect`, placing the glyphs into `glyphs_out`. Returns the rectangle
+    /// enclosing the shaped text, or `None` if the font face couldn't be loaded.
+    pub fn shape(
+        &mut self,
+        string: &str,
+        rect: BoundBox<D2, i32>,
+        text_style: &ThemeText,
+        glyphs_out: &mut Vec<RenderGlyph>,
+    ) -> Option<BoundBox<D2, i32>> {
+        let TextCache {
+            ref mut font_cache,
+            ref mut shaper,
+            ref mut shaped_text,
+            dpi,
+            ..
+        } = *self;
+
+        let face = font_cache.face(text_style.face.clone()).ok()?;
+        shaper.shape_text(
+            string,
+            face,
+            FaceSize::new(text_style.face_size, text_style.face_size),
+            dpi,
+            shaped_text
+        ).ok()?;
+
+        let shaped_data = shape_glyphs::shape_glyphs(rect, shaped_text, text_style, face, dpi, glyphs_out);
+        shaped_text.clear();
+        Some(shaped_data.text_rect)
+    }
+
+    /// Compute the minimum size of a string that doesn't get line-wrapped, including the text
+    /// style's margins. The size is in logical pixels.
+    pub fn min_size(&mut self, string: &str, text_style: &ThemeText) -> DimsBox<D2, i32> {
+        let mut glyphs = Vec::new();
+        let text_rect = self.shape(string, BoundBox::new2(0, 0, 0, 0), text_style, &mut glyphs)
+            .unwrap_or(BoundBox::new2(0, 0, 0, 0));
+
+        let to_logical = |px: i32| (px as f32 / self.scale_factor).ceil() as i32;
+        DimsBox::new2(
+            to_logical(text_rect.width()) + text_style.margins.width() as i32,
+            to_logical(text_rect.height()) + text_style.margins.height() as i32,
+        )
+    }
+
+    /// Shape the string into the widget rectangle, after applying the text style's margins.
+    /// `widget_rect` is in physical pixels.
+    pub fn shape_in_widget(
+        &mut self,
+        string: &str,
+        widget_rect: BoundBox<D2, i32>,
+        text_style: &ThemeText,
+        shaped_out: &mut ShapedString,
+    ) {
+        let margins = text_style.margins;
+        let scale = |m: u16| (m as f32 * self.scale_factor).round() as i32;
+        let text_box = BoundBox::new2(
+            scale(margins.left),
+            scale(margins.top),
+            widget_rect.width() - scale(margins.right),
+            widget_rect.height() - scale(margins.bottom),
+        );
+
+        shaped_out.glyphs.clear();
+        shaped_out.text_box = text_box;
+        self.shape(string, text_box, text_style, &mut shaped_out.glyphs);
+        for glyph in &mut shaped_out.glyphs {
+            glyph.pos += text_box.min.to_vec();
+            glyph.highlight_rect = glyph.highlight_rect + text_box.min.to_vec();
+        }
+    }
+
+    /// Draw shaped glyphs, along with the highlight and cursor in `cursor_data`. Glyphs get added to
+    /// `atlas`, and every piece of the text gets handed to `draw` along with the atlas and the
+    /// clipping rectangle.
+    pub fn draw_glyphs(
+        &mut self,
+        atlas: &mut Atlas,
+        shaped: &ShapedString,
+        widget_rect: BoundBox<D2, i32>,
+        clip: BoundBox<D2, i32>,
+        text_style: &ThemeText,
+        cursor_data: Option<&CursorData>,
+        mut draw: impl FnMut(&Atlas, TextDraw, BoundBox<D2, i32>),
+    ) {
+        let TextCache {
+            ref mut font_cache,
+            dpi,
+            scale_factor,
+            ..
+        } = *self;
+        let face = match font_cache.face(text_style.face.clone()) {
+            Ok(face) => face,
+            Err(_) => return //TODO: log
+        };
+        let face_size = FaceSize::new(text_style.face_size, text_style.face_size);
+        let (ascender, descender) = match face.metrics_sized(face_size, dpi) {
+            Ok(metrics) => ((metrics.ascender / 64) as i32, (metrics.descender / 64) as i32),
+            Err(_) => return
+        };
+
+        let offset = widget_rect.min.to_vec();
+        let text_clip = match clip.intersect_rect(shaped.text_box + offset) {
+            Some(text_clip) => text_clip,
+            None => return
+        };
+        let (highlight_range, cursor_pos) = match cursor_data {
+            Some(cursor_data) => (
+                cursor_data.highlight_range.clone(),
+                match cursor_data.draw_cursor && cursor_data.highlight_range.len() == 0 {
+                    true => Some(cursor_data.cursor_pos),
+                    false => None
+                }
+            ),
+            None => (0..0, None)
+        };
+
+        for glyph in &shaped.glyphs {
+            let is_highlighted = highlight_range.contains(&glyph.str_index);
+            if is_highlighted {
+                draw(atlas, TextDraw::Rect(glyph.highlight_rect + offset, text_style.highlight_bg_color), text_clip);
+            }
+
+            let glyph_index = match glyph.glyph_index {
+                Some(glyph_index) => glyph_index,
+                None => continue
+            };
+            let render_mode = RenderMode::Normal;
+            let (atlas_rect, glyph_bearing) = atlas.glyph_rect(
+                text_style.face.clone(),
+                text_style.face_size,
+                glyph_index,
+                || {
+                    let glyph_res = face.load_glyph(
+                        glyph_index,
+                        face_size,
+                        dpi,
+                        LoadFlags::empty(),
+                        render_mode
+                    ).and_then(|mut glyph_slot| Ok((
+                        glyph_slot.render_glyph(render_mode)?,
+                        glyph_slot.metrics()
+                    )));
+
+                    let (bytes, pitch, dims, bearing) = match glyph_res {
+                        Ok((bitmap, glyph_metrics)) => match bitmap.pitch {
+                            0 => (&[][..], 1, DimsBox::new2(0, 0), glyph_metrics.hori_bearing / 64),
+                            _ => (bitmap.buffer, bitmap.pitch.abs() as usize, bitmap.dims, glyph_metrics.hori_bearing / 64)
+                        },
+                        // TODO: LOG
+                        Err(_) => (&[][..], 1, DimsBox::new2(0, 0), Vector2::new(0, 0))
+                    };
+                    (
+                        bytes.chunks(pitch)
+                            .map(move |b|
+                                b[..dims.width() as usize]
+                                    // Glyphs are stored as white in the atlas and tinted when they
+                                    // get drawn.
+                                    .into_iter().map(|t| Rgba::new(255, 255, 255, *t))
+                            ),
+                        dims,
+                        bearing
+                    )
+                }
+            );
+
+            let glyph_pos = glyph.pos + offset + Vector2::new(1, -1).mul_element_wise(glyph_bearing);
+            let glyph_rect = BoundBox::new2(
+                glyph_pos.x,
+                glyph_pos.y,
+                glyph_pos.x + atlas_rect.width() as i32,
+                glyph_pos.y + atlas_rect.height() as i32
+            );
+            let glyph_draw = TextDraw::Glyph {
+                rect: glyph_rect,
+                atlas_rect,
+                color: match is_highlighted {
+                    false => text_style.color,
+                    true => text_style.highlight_text_color
+                },
+            };
+            draw(atlas, glyph_draw, text_clip);
+        }
+
+        if let Some(cursor_pos) = cursor_pos {
+            let cursor_top_left = shaped.glyphs.iter()
+                .filter_map(|g| match () {
+                    _ if cursor_pos == g.str_index => Some(g.highlight_rect.min),
+                    _ if cursor_pos == g.str_index + g.grapheme_len => Some(Point2::new(g.highlight_rect.max.x, g.highlight_rect.min.y)),
+                    _ => None
+                })
+                .next()
+                .unwrap_or_else(|| {
+                    let text_box = shaped.text_box;
+                    Point2 {
+                        x: match text_style.justify.x {
+                            Align::Start |
+                            Align::Stretch => 0,
+                            Align::Center => text_box.width() / 2,
+                            Align::End => text_box.width()
+                        },
+                        y: match text_style.justify.y {
+                            Align::Start => -descender,
+                            Align::Stretch => text_box.height() / 2 - ascender,
+                            Align::Center => (text_box.height() - ascender - descender) / 2,
+                            Align::End => text_box.height() - ascender,
+                        }
+                    } + text_box.min.to_vec()
+                });
+
+            let cursor_top_left = cursor_top_left + offset;
+            let cursor_rect = BoundBox::new(cursor_top_left, cursor_top_left + Vector2::new(scale_factor.round().max(1.) as i32, ascender - descender));
+            draw(atlas, TextDraw::Rect(cursor_rect, text_style.color), text_clip);
+        }
+    }
+}
+
+impl Default for ShapedString {
+    fn default() -> ShapedString {
+        ShapedString {
+            glyphs: Vec::new(),
+            text_box: BoundBox::new2(0, 0, 0, 0),
+        }
+    }
+}
+
+/// Perform a cursor operation on an edit string. `glyphs` are the string's glyphs from the last
+/// time it was drawn, and are used to resolve operations that depend on the text's layout.
+pub fn apply_cursor_op(
+    string: &mut String,
+    cursor_data: &mut CursorData,
+    op: CursorOp,
+    glyphs: &[RenderGlyph],
+) {
+    match op {
+        CursorOp::MoveVertical{delta, expand_selection} => {
+            let start_pos = cursor_data.cursor_pos;
+            cursor_data.cursor_pos = move_vertical(cursor_data.cursor_pos, delta, glyphs);
+            finish_move(cursor_data, start_pos, expand_selection);
+        },
+        CursorOp::MoveHorizontal{delta, expand_selection, jump_to_word_boundaries} => {
+            let start_pos = cursor_data.cursor_pos;
+            cursor_data.cursor_pos = match (cursor_data.highlight_range.len() != 0 && !expand_selection, delta.signum()) {
+                (_, 0) => return,
+                (true, 1) => cursor_data.highlight_range.end,
+                (true, _) => cursor_data.highlight_range.start,
+                (false, _) => move_horizontal(string, cursor_data.cursor_pos, delta, jump_to_word_boundaries),
+            };
+            finish_move(cursor_data, start_pos, expand_selection);
+        },
+        CursorOp::SelectOnSegment(segment) => select_on_segment(cursor_data, segment, glyphs),
+        CursorOp::SelectAll => {
+            cursor_data.highlight_range = 0..string.len();
+            cursor_data.cursor_pos = string.len();
+        },
+        CursorOp::UnselectAll => cursor_data.highlight_range = 0..0,
+        CursorOp::InsertChar(c) => {
+            delete_selection(string, cursor_data);
+            string.insert(cursor_data.cursor_pos, c);
+            cursor_data.cursor_pos += c.len_utf8();
+        },
+        CursorOp::InsertString(s) => {
+            delete_selection(string, cursor_data);
+            string.insert_str(cursor_data.cursor_pos, &s);
+            cursor_data.cursor_pos += s.len();
+        },
+        CursorOp::DeleteChars{dist, jump_to_word_boundaries} => {
+            if cursor_data.highlight_range.len() != 0 {
+                delete_selection(string, cursor_data);
+            } else {
+                let old_pos = cursor_data.cursor_pos;
+                let new_pos = move_horizontal(string, old_pos, dist, jump_to_word_boundaries);
+                let drain_range = cmp::min(old_pos, new_pos)..cmp::max(old_pos, new_pos);
+                string.drain(drain_range.clone());
+                cursor_data.cursor_pos = drain_range.start;
+            }
+        },
+        CursorOp::DeleteSelection => delete_selection(string, cursor_data),
+    }
+
+    // Guard against stale cursor data from a string that was modified outside of the layout pass.
+    cursor_data.cursor_pos = cursor_data.cursor_pos.min(string.len());
+    cursor_data.highlight_range.end = cursor_data.highlight_range.end.min(string.len());
+    cursor_data.highlight_range.start = cursor_data.highlight_range.start.min(cursor_data.highlight_range.end);
+}
+
+fn delete_selection(string: &mut String, cursor_data: &mut CursorData) {
+    if cursor_data.highlight_range.len() != 0 {
+        string.drain(cursor_data.highlight_range.clone());
+        cursor_data.cursor_pos = cursor_data.highlight_range.start;
+        cursor_data.highlight_range = 0..0;
+    }
+}
+
+fn move_horizontal(string: &str, cursor_pos: usize, dist: isize, jump_to_word_boundaries: bool) -> usize {
+    match (dist.signum(), jump_to_word_boundaries) {
+        (0, _) => cursor_pos,
+        (1, false) =>
+            string[cursor_pos..].grapheme_indices(true)
+                .skip(dist as usize).map(|(i, _)| i + cursor_pos)
+                .next().unwrap_or(string.len()),
+        (_, false) =>
+            string[..cursor_pos].grapheme_indices(true)
+                .rev().skip(dist.abs() as usize - 1).map(|(i, _)| i)
+                .next().unwrap_or(0),
+        (1, true) =>
+            string[cursor_pos..].unicode_words()
+                .skip(dist as usize).next()
+                .map(|word| word.as_ptr() as usize - string.as_ptr() as usize)
+                .unwrap_or(string.len()),
+        (_, true) =>
+            string[..cursor_pos].unicode_words()
+                .rev().skip(dist.abs() as usize - 1).next()
+                .map(|word| word.as_ptr() as usize - string.as_ptr() as usize)
+                .unwrap_or(0),
+    }
+}
+
+fn move_vertical(cursor_pos: usize, dist: isize, glyphs: &[RenderGlyph]) -> usize {
+    let cursor_glyph = match glyphs.iter().find(|g| g.str_index == cursor_pos) {
+        Some(glyph) => *glyph,
+        None => return cursor_pos
+    };
+    let target_x = cursor_glyph.highlight_rect.min.x;
+
+    let mut new_pos = cursor_pos;
+    let mut search = |glyph_iter: &mut Iterator<Item=&RenderGlyph>| {
+        let mut cur_line_y = cursor_glyph.highlight_rect.min.y;
+        let mut line_delta = 0;
+        let mut min_dist_x = i32::max_value();
+
+        for glyph in glyph_iter.skip_while(|g| g.str_index != cursor_pos) {
+            let glyph_dist_x = (target_x - glyph.highlight_rect.min.x).abs();
+            if glyph.highlight_rect.min.y != cur_line_y {
+                line_delta += 1;
+                cur_line_y = glyph.highlight_rect.min.y;
+                if line_delta > dist.abs() {
+                    break;
+                }
+
+                min_dist_x = glyph_dist_x;
+                new_pos = glyph.str_index;
+                continue;
+            }
+            if line_delta == 0 {
+                continue;
+            }
+
+            if glyph_dist_x < min_dist_x {
+                min_dist_x = glyph_dist_x;
+                new_pos = glyph.str_index;
+            }
+        }
+    };
+
+    match dist.signum() {
+        1 => search(&mut glyphs.iter()),
+        -1 => search(&mut glyphs.iter().rev()),
+        _ => ()
+    }
+    new_pos
+}
+
+fn finish_move(cursor_data: &mut CursorData, start_pos: usize, expand_selection: bool) {
+    if !expand_selection {
+        cursor_data.highlight_range = 0..0;
+        return;
+    }
+
+    if cursor_data.highlight_range.len() == 0 {
+        cursor_data.highlight_range = start_pos..start_pos;
+    }
+
+    let cursor_pos = cursor_data.cursor_pos;
+    let range = &mut cursor_data.highlight_range;
+    match (start_pos == range.start, cursor_pos < range.end) {
+        (false, true) if cursor_pos < range.start => {
+            range.end = range.start;
+            range.start = cursor_pos;
+        }
+        (false, _) => range.end = cursor_pos,
+        (true, true) => range.start = cursor_pos,
+        (true, false) => {
+            range.start = range.end;
+            range.end = cursor_pos;
+        }
+    }
+}
+
+fn select_on_segment(cursor_data: &mut CursorData, segment: Segment<D2, i32>, glyphs: &[RenderGlyph]) {
+    if glyphs.is_empty() {
+        cursor_data.highlight_range = 0..0;
+        return;
+    }
+
+    let dist = |min: i32, max: i32, point: i32| match (min.cmp(&point), max.cmp(&point)) {
+        (Ordering::Equal, _) |
+        (_, Ordering::Equal) |
+        (Ordering::Less, Ordering::Greater) => 0,
+        (Ordering::Greater, _) => min - point,
+        (_, Ordering::Less) => point - max
+    };
+
+    let (mut min_start_x_dist, mut min_start_y_dist) = (i32::max_value(), i32::max_value());
+    let (mut min_end_x_dist, mut min_end_y_dist) = (i32::max_value(), i32::max_value());
+    let (mut start_index, mut end_index) = (0, 0);
+
+    for glyph in glyphs {
+        let x_dist = |point: Point2<i32>| dist(glyph.highlight_rect.min.x, glyph.highlight_rect.max.x, point.x);
+        let y_dist = |point: Point2<i32>| dist(glyph.highlight_rect.min.y, glyph.highlight_rect.max.y, point.y);
+        let (start_x_dist, start_y_dist) = (x_dist(segment.start), y_dist(segment.start));
+        let (end_x_dist, end_y_dist) = (x_dist(segment.end), y_dist(segment.end));
+        let highlight_center = glyph.highlight_rect.center();
+
+        if start_y_dist < min_start_y_dist || (start_x_dist < min_start_x_dist && start_y_dist <= min_start_y_dist) {
+            min_start_y_dist = start_y_dist;
+            min_start_x_dist = start_x_dist;
+            start_index = glyph.str_index + (highlight_center.x <= segment.start.x) as usize * glyph.grapheme_len;
+        }
+        if end_y_dist < min_end_y_dist || (end_x_dist < min_end_x_dist && end_y_dist <= min_end_y_dist) {
+            min_end_y_dist = end_y_dist;
+            min_end_x_dist = end_x_dist;
+            end_index = glyph.str_index + (highlight_center.x <= segment.end.x) as usize * glyph.grapheme_len;
+        }
+    }
+
+    cursor_data.highlight_range = cmp::min(start_index, end_index)..cmp::max(start_index, end_index);
+    cursor_data.cursor_pos = end_index;
+}
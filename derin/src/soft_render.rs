@@ -0,0 +1,362 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Implements a renderer that rasterizes widgets into an in-memory framebuffer on the CPU.
+//!
+//! This is useful when there's no GPU available, such as when running tests in CI. Images and
+//! glyphs go through the same atlas and text shaping code as the OpenGL renderer, so the output
+//! should closely match what gets drawn on-screen.
+//!
+//! Widgets get laid out in logical pixels, while the framebuffer is sized in physical pixels. With
+//! a scale factor of `2`, a 100x100 window gets drawn into a 200x200 framebuffer, with text and
+//! images rendered at the higher resolution.
+
+mod raster;
+pub mod snapshot;
+
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use gullery::image_format::Rgba;
+use cgmath_geometry::{D2, rect::{BoundBox, DimsBox, GeoBox}};
+use derin_common_types::layout::SizeBounds;
+
+use crate::core::{
+    render::{Renderer, WidgetRenderer, SubFrame, CursorData},
+    widget::WidgetId,
+};
+use crate::render_common::{
+    atlas::Atlas,
+    content::{self, Content, ContentLayout, WidgetContent},
+    shape::{self, ShapeVertex},
+    text::{TextCache, TextDraw, ShapedString},
+};
+use crate::theme::{Theme, ThemePath, ThemeWidget, ThemeText, Image, Shape, RescaleRules};
+
+use self::raster::Texture;
+
+pub use self::raster::Framebuffer;
+
+/// A renderer that draws into a `Framebuffer` instead of a window.
+pub struct SoftRenderer {
+    clear_color: Rgba<u8>,
+    /// The size of the window, in logical pixels.
+    dims: DimsBox<D2, u32>,
+    scale_factor: f32,
+    widget_content: HashMap<WidgetId, WidgetContent>,
+    frame: SoftFrame,
+    layout: ContentLayout,
+}
+
+pub struct SoftFrame {
+    draw: FrameDraw,
+    widget: WidgetContent,
+    theme_widget: ThemeWidget,
+    /// The images for the widget's icons, along with the colors they get multiplied by.
+    icons: Vec<Option<(Rc<Image>, Rgba<u8>)>>,
+    /// The color the widget's image gets multiplied by. Used to fade out disabled widgets.
+    image_color: Rgba<u8>,
+    /// The widget's rectangle, in physical pixels.
+    transform: BoundBox<D2, i32>,
+    /// The clipping rectangle, in physical pixels.
+    clip: BoundBox<D2, i32>,
+}
+
+struct FrameDraw {
+    framebuffer: Framebuffer,
+    atlas: Atlas,
+    text_cache: Rc<RefCell<TextCache>>,
+    shape_vertices: Vec<ShapeVertex>,
+    scale_factor: f32,
+}
+
+impl SoftRenderer {
+    /// Creates a new renderer with a framebuffer of the given size.
+    pub fn new(dims: DimsBox<D2, u32>) -> SoftRenderer {
+        let clear_color = Rgba::new(255, 255, 255, 255);
+        let text_cache = Rc::new(RefCell::new(TextCache::new()));
+
+        SoftRenderer {
+            clear_color,
+            dims,
+            scale_factor: 1.0,
+            widget_content: HashMap::new(),
+            frame: SoftFrame {
+                draw: FrameDraw {
+                    framebuffer: Framebuffer::new(dims, clear_color),
+                    atlas: Atlas::new(),
+                    text_cache: text_cache.clone(),
+                    shape_vertices: Vec::new(),
+                    scale_factor: 1.0,
+                },
+                widget: WidgetContent::default(),
+                theme_widget: ThemeWidget::default(),
+                icons: Vec::new(),
+                image_color: Rgba::new(255, 255, 255, 255),
+                transform: BoundBox::new2(0, 0, 0, 0),
+                clip: BoundBox::new2(0, 0, 0, 0),
+            },
+            layout: ContentLayout::new(text_cache),
+        }
+    }
+
+    /// Retrieves the color the framebuffer gets cleared to at the start of every frame.
+    #[inline]
+    pub fn clear_color(&self) -> Rgba<u8> {
+        self.clear_color
+    }
+
+    /// Sets the color the framebuffer gets cleared to at the start of every frame.
+    #[inline]
+    pub fn set_clear_color(&mut self, clear_color: Rgba<u8>) {
+        self.clear_color = clear_color;
+    }
+
+    /// Retrieves the framebuffer, containing the most recently drawn frame. The framebuffer is sized
+    /// in physical pixels.
+    #[inline]
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.frame.draw.framebuffer
+    }
+}
+
+impl Renderer for SoftRenderer {
+    type SubFrame = SoftFrame;
+    type Theme = Theme;
+    type Layout = ContentLayout;
+
+    fn resized(&mut self, new_size: DimsBox<D2, u32>) {
+        self.dims = new_size;
+        let physical_dims = DimsBox::new2(
+            (new_size.width() as f32 * self.scale_factor).round() as u32,
+            (new_size.height() as f32 * self.scale_factor).round() as u32,
+        );
+        self.frame.draw.framebuffer.resize(physical_dims, self.clear_color);
+    }
+
+    fn dims(&self) -> DimsBox<D2, u32> {
+        self.dims
+    }
+
+    fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f32) {
+        self.scale_factor = scale_factor;
+        self.frame.draw.scale_factor = scale_factor;
+        self.frame.draw.text_cache.borrow_mut().set_scale_factor(scale_factor);
+        self.layout.set_scale_factor(scale_factor);
+        let dims = self.dims;
+        self.resized(dims);
+    }
+
+    fn widget_removed(&mut self, widget_id: WidgetId) {
+        self.widget_content.remove(&widget_id);
+    }
+
+    fn layout(
+        &mut self,
+        widget_id: WidgetId,
+        layout: impl FnOnce(&mut ContentLayout)
+    ) {
+        let widget = self.widget_content.remove(&widget_id).unwrap_or_default();
+        let widget = self.layout.layout(widget, layout);
+        self.widget_content.insert(widget_id, widget);
+    }
+
+    fn start_frame(&mut self, _: &Theme) {
+        self.frame.draw.framebuffer.clear(self.clear_color);
+    }
+
+    fn render_focus_ring(&mut self, theme: &Theme, rect: BoundBox<D2, i32>, clip: BoundBox<D2, i32>) {
+        if let Some(focus_ring) = theme.focus_ring() {
+            let offset = focus_ring.offset as i32;
+            let ring_rect = BoundBox::new2(
+                rect.min.x - offset,
+                rect.min.y - offset,
+                rect.max.x + offset,
+                rect.max.y + offset,
+            );
+            self.frame.draw.draw_shape(
+                &focus_ring.shape(),
+                content::to_physical(ring_rect, self.scale_factor),
+                content::to_physical(clip, self.scale_factor)
+            );
+        }
+    }
+
+    fn finish_frame(&mut self, _: &Theme) {
+        self.frame.draw.atlas.bump_frame_count();
+    }
+}
+
+impl<T> WidgetRenderer<T> for SoftRenderer
+    where T: ThemePath
+{
+    fn render_widget(
+        &mut self,
+        widget_id: WidgetId,
+        theme: &Theme,
+        transform: BoundBox<D2, i32>,
+        clip: BoundBox<D2, i32>,
+        enabled: bool,
+        widget_theme: T,
+        render_widget: impl FnOnce(&mut SoftFrame),
+    ) {
+        let (theme_widget, image_color) = content::resolve_theme(theme, widget_theme.theme_path(), enabled);
+        let mut widget = self.widget_content.remove(&widget_id).unwrap_or_default();
+        widget.theme_widget = Some(theme_widget.clone());
+
+        let frame = &mut self.frame;
+        frame.icons.clear();
+        frame.icons.extend(widget.content.iter().filter_map(|c| match *c {
+            Content::Icon(ref icon) => {
+                let (icon_theme, color) = content::resolve_theme(theme, icon, enabled);
+                Some(icon_theme.image.map(|image| (image, color)))
+            },
+            _ => None
+        }));
+        frame.widget = widget;
+        frame.theme_widget = theme_widget;
+        frame.image_color = image_color;
+        frame.transform = content::to_physical(transform, self.scale_factor);
+        frame.clip = content::to_physical(clip, self.scale_factor);
+
+        render_widget(frame);
+
+        let widget = std::mem::replace(&mut frame.widget, WidgetContent::default());
+        self.widget_content.insert(widget_id, widget);
+    }
+}
+
+impl SubFrame for SoftFrame {
+    fn render_laid_out_content(&mut self) {
+        let SoftFrame {
+            ref mut draw,
+            ref mut widget,
+            ref theme_widget,
+            ref icons,
+            image_color,
+            transform,
+            clip,
+        } = *self;
+
+        if let Some(ref shape) = theme_widget.shape {
+            draw.draw_shape(shape, transform, clip);
+        }
+        if let Some(ref image) = theme_widget.image {
+            draw.draw_image(image, transform, clip, image_color);
+        }
+
+        let mut icons = icons.iter();
+        for content in &widget.content {
+            match *content {
+                Content::String(ref string) => {
+                    if let Some(ref text_style) = theme_widget.text {
+                        let mut shaped = ShapedString::default();
+                        draw.text_cache.borrow_mut().shape_in_widget(string, transform, text_style, &mut shaped);
+                        draw.draw_text(&shaped, transform, clip, text_style, None);
+                    }
+                },
+                Content::EditString{ref string, ref cursor_data} => {
+                    if let Some(ref text_style) = theme_widget.text {
+                        draw.text_cache.borrow_mut().shape_in_widget(string, transform, text_style, &mut widget.edit_shaped);
+                        draw.draw_text(&widget.edit_shaped, transform, clip, text_style, Some(cursor_data));
+                    }
+                },
+                Content::Icon(_) => {
+                    if let Some(Some((ref image, color))) = icons.next() {
+                        draw.draw_image(image, transform, clip, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl FrameDraw {
+    fn draw_shape(&mut self, shape: &Shape, rect: BoundBox<D2, i32>, clip: BoundBox<D2, i32>) {
+        self.shape_vertices.clear();
+        shape::tessellate_shape(shape, rect, clip, self.scale_factor, &mut self.shape_vertices);
+        for triangle in self.shape_vertices.chunks(3) {
+            raster::fill_triangle(&mut self.framebuffer, [triangle[0], triangle[1], triangle[2]], clip);
+        }
+    }
+
+    fn draw_text(
+        &mut self,
+        shaped: &ShapedString,
+        rect: BoundBox<D2, i32>,
+        clip: BoundBox<D2, i32>,
+        text_style: &ThemeText,
+        cursor_data: Option<&CursorData>,
+    ) {
+        let FrameDraw {
+            ref mut framebuffer,
+            ref mut atlas,
+            ref text_cache,
+            ..
+        } = *self;
+        text_cache.borrow_mut().draw_glyphs(atlas, shaped, rect, clip, text_style, cursor_data, |atlas, text_draw, clip| match text_draw {
+            TextDraw::Rect(rect, color) => raster::fill_rect(framebuffer, rect, clip, color),
+            TextDraw::Glyph{rect, atlas_rect, color} => raster::draw_image(
+                framebuffer,
+                rect,
+                clip,
+                Texture {
+                    pixels: atlas.pixels(),
+                    dims: atlas.dims(),
+                },
+                atlas_rect,
+                color,
+                RescaleRules::Stretch,
+                1.0,
+                1.0,
+            ),
+        });
+    }
+
+    fn draw_image(&mut self, image: &Image, mut rect: BoundBox<D2, i32>, clip: BoundBox<D2, i32>, color: Rgba<u8>) {
+        // Bound the image by its size bounds and center it within the rectangle, in the same way
+        // the OpenGL renderer does.
+        let scale_factor = self.scale_factor;
+        let scale_dims = |dims: DimsBox<D2, i32>| DimsBox::new2(
+            (dims.width() as f32 * scale_factor).round() as i32,
+            (dims.height() as f32 * scale_factor).round() as i32,
+        );
+        let size_bounds = SizeBounds {
+            min: scale_dims(image.size_bounds.min),
+            // Unbounded maximums stay unbounded, instead of overflowing when they get scaled.
+            max: if image.size_bounds.max == SizeBounds::default().max {
+                image.size_bounds.max
+            } else {
+                scale_dims(image.size_bounds.max)
+            }
+        };
+        let rect_dims = rect.dims();
+        let rect_dims_bounded = size_bounds.bound_rect(rect_dims);
+        rect.max.x = rect.min.x + rect_dims_bounded.width();
+        rect.max.y = rect.min.y + rect_dims_bounded.height();
+        rect = rect + (rect_dims.dims - rect_dims_bounded.dims) / 2;
+
+        let (pixels, dims, image_scale) = image.pixels_for_scale(scale_factor);
+        let atlas_rect = self.atlas.image_rect("", || (pixels, dims));
+        raster::draw_image(
+            &mut self.framebuffer,
+            rect,
+            clip,
+            Texture {
+                pixels: self.atlas.pixels(),
+                dims: self.atlas.dims(),
+            },
+            atlas_rect,
+            color,
+            image.rescale,
+            image_scale as f32,
+            scale_factor,
+        );
+    }
+}
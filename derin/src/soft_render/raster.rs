@@ -0,0 +1,516 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use gullery::image_format::Rgba;
+
+use cgmath_geometry::{D2, rect::{BoundBox, DimsBox, OffsetBox, GeoBox}};
+use derin_common_types::layout::Align;
+
+use crate::cgmath::Point2;
+use crate::render_common::shape::ShapeVertex;
+use crate::theme::RescaleRules;
+
+/// An in-memory RGBA image, stored row-by-row with the origin in the top-left corner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Framebuffer {
+    pixels: Vec<Rgba<u8>>,
+    dims: DimsBox<D2, u32>,
+}
+
+/// A view into a buffer of pixels that images get sampled from.
+#[derive(Debug, Clone, Copy)]
+pub(in crate::soft_render) struct Texture<'a> {
+    pub pixels: &'a [Rgba<u8>],
+    pub dims: DimsBox<D2, u32>,
+}
+
+impl Framebuffer {
+    pub fn new(dims: DimsBox<D2, u32>, fill: Rgba<u8>) -> Framebuffer {
+        Framebuffer {
+            pixels: vec![fill; (dims.width() * dims.height()) as usize],
+            dims,
+        }
+    }
+
+    /// Create a framebuffer from a row-major buffer of pixels.
+    ///
+    /// Panics if the buffer's length doesn't match `dims`.
+    pub fn from_pixels(pixels: Vec<Rgba<u8>>, dims: DimsBox<D2, u32>) -> Framebuffer {
+        assert_eq!(pixels.len(), (dims.width() * dims.height()) as usize);
+        Framebuffer{ pixels, dims }
+    }
+
+    #[inline]
+    pub fn dims(&self) -> DimsBox<D2, u32> {
+        self.dims
+    }
+
+    /// Retrieve the framebuffer's pixels, in row-major order.
+    #[inline]
+    pub fn pixels(&self) -> &[Rgba<u8>] {
+        &self.pixels
+    }
+
+    /// Retrieve the pixel at the given location, or `None` if it's outside of the framebuffer.
+    #[inline]
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Rgba<u8>> {
+        match x < self.dims.width() && y < self.dims.height() {
+            true => Some(self.pixels[(y * self.dims.width() + x) as usize]),
+            false => None
+        }
+    }
+
+    #[inline]
+    pub fn into_pixels(self) -> Vec<Rgba<u8>> {
+        self.pixels
+    }
+
+    pub(in crate::soft_render) fn resize(&mut self, dims: DimsBox<D2, u32>, fill: Rgba<u8>) {
+        self.pixels.clear();
+        self.pixels.resize((dims.width() * dims.height()) as usize, fill);
+        self.dims = dims;
+    }
+
+    pub(in crate::soft_render) fn clear(&mut self, color: Rgba<u8>) {
+        for pixel in &mut self.pixels {
+            *pixel = color;
+        }
+    }
+
+    fn bounds(&self) -> BoundBox<D2, i32> {
+        BoundBox::new2(0, 0, self.dims.width() as i32, self.dims.height() as i32)
+    }
+
+    /// Blend `color` onto the pixel at the given location, using the same blend function as the
+    /// OpenGL renderer.
+    #[inline]
+    fn blend_pixel(&mut self, x: i32, y: i32, color: Rgba<u8>) {
+        let index = (y as u32 * self.dims.width() + x as u32) as usize;
+        let dst = self.pixels[index];
+        let src_alpha = color.a as u32;
+        let blend = |s: u8, d: u8| ((s as u32 * src_alpha + d as u32 * (255 - src_alpha) + 127) / 255) as u8;
+
+        self.pixels[index] = Rgba::new(
+            blend(color.r, dst.r),
+            blend(color.g, dst.g),
+            blend(color.b, dst.b),
+            blend(color.a, dst.a),
+        );
+    }
+}
+
+impl<'a> Texture<'a> {
+    /// Sample the texel nearest to the given point.
+    #[inline]
+    fn sample(&self, x: f32, y: f32) -> Rgba<u8> {
+        let x = (x.max(0.) as u32).min(self.dims.width().saturating_sub(1));
+        let y = (y.max(0.) as u32).min(self.dims.height().saturating_sub(1));
+        self.pixels[(y * self.dims.width() + x) as usize]
+    }
+}
+
+#[inline]
+fn modulate(texel: Rgba<u8>, color: Rgba<u8>) -> Rgba<u8> {
+    let mul = |t: u8, c: u8| ((t as u32 * c as u32 + 127) / 255) as u8;
+    Rgba::new(
+        mul(texel.r, color.r),
+        mul(texel.g, color.g),
+        mul(texel.b, color.b),
+        mul(texel.a, color.a),
+    )
+}
+
+/// Fill `rect` with a solid color.
+pub(in crate::soft_render) fn fill_rect(
+    fb: &mut Framebuffer,
+    rect: BoundBox<D2, i32>,
+    clip: BoundBox<D2, i32>,
+    color: Rgba<u8>
+) {
+    let clipped = match clip.intersect_rect(fb.bounds()).and_then(|c| c.intersect_rect(rect)) {
+        Some(clipped) => clipped,
+        None => return
+    };
+
+    for y in clipped.min.y..clipped.max.y {
+        for x in clipped.min.x..clipped.max.x {
+            fb.blend_pixel(x, y, color);
+        }
+    }
+}
+
+/// Fill a triangle, interpolating the vertex colors across it.
+///
+/// Pixels are sampled at their centers. Pixels lying exactly on an edge shared by two triangles only
+/// get drawn by one of them, so meshes don't get double-blended along their seams.
+pub(in crate::soft_render) fn fill_triangle(
+    fb: &mut Framebuffer,
+    triangle: [ShapeVertex; 3],
+    clip: BoundBox<D2, i32>
+) {
+    let [mut a, mut b, c] = triangle;
+    let edge = |a: Point2<f32>, b: Point2<f32>, p: Point2<f32>| (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+    let mut area = edge(a.pos, b.pos, c.pos);
+    if area == 0. {
+        return;
+    }
+    if area < 0. {
+        std::mem::swap(&mut a, &mut b);
+        area = -area;
+    }
+
+    let bounds = BoundBox::new2(
+        a.pos.x.min(b.pos.x).min(c.pos.x).floor() as i32,
+        a.pos.y.min(b.pos.y).min(c.pos.y).floor() as i32,
+        a.pos.x.max(b.pos.x).max(c.pos.x).ceil() as i32,
+        a.pos.y.max(b.pos.y).max(c.pos.y).ceil() as i32,
+    );
+    let clipped = match clip.intersect_rect(fb.bounds()).and_then(|c| c.intersect_rect(bounds)) {
+        Some(clipped) => clipped,
+        None => return
+    };
+
+    // Whether pixels exactly on the edge from `a` to `b` belong to this triangle. Since adjacent
+    // triangles traverse their shared edge in opposite directions, exactly one of them owns it.
+    let owns_edge = |a: Point2<f32>, b: Point2<f32>| {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        dy > 0. || (dy == 0. && dx < 0.)
+    };
+    let owns = [owns_edge(b.pos, c.pos), owns_edge(c.pos, a.pos), owns_edge(a.pos, b.pos)];
+    let channel = |w: [f32; 3], f: fn(Rgba<u8>) -> u8| {
+        ((f(a.color) as f32 * w[0] + f(b.color) as f32 * w[1] + f(c.color) as f32 * w[2]) / area).round() as u8
+    };
+
+    for y in clipped.min.y..clipped.max.y {
+        for x in clipped.min.x..clipped.max.x {
+            let p = Point2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let w = [edge(b.pos, c.pos, p), edge(c.pos, a.pos, p), edge(a.pos, b.pos, p)];
+            let inside = w.iter().zip(&owns).all(|(&w, &owns)| w > 0. || (w == 0. && owns));
+            if inside {
+                let color = Rgba::new(
+                    channel(w, |c| c.r),
+                    channel(w, |c| c.g),
+                    channel(w, |c| c.b),
+                    channel(w, |c| c.a),
+                );
+                fb.blend_pixel(x, y, color);
+            }
+        }
+    }
+}
+
+/// Stretch the `src` section of `texture` over `dst`, tinting it with `color`.
+fn blit_stretched(
+    fb: &mut Framebuffer,
+    dst: BoundBox<D2, i32>,
+    clip: BoundBox<D2, i32>,
+    texture: Texture,
+    src: BoundBox<D2, f32>,
+    color: Rgba<u8>
+) {
+    if dst.width() <= 0 || dst.height() <= 0 {
+        return;
+    }
+    let clipped = match clip.intersect_rect(fb.bounds()).and_then(|c| c.intersect_rect(dst)) {
+        Some(clipped) => clipped,
+        None => return
+    };
+
+    let scale_x = src.width() / dst.width() as f32;
+    let scale_y = src.height() / dst.height() as f32;
+
+    for y in clipped.min.y..clipped.max.y {
+        // Sample from the center of the destination pixel.
+        let src_y = src.min.y + ((y - dst.min.y) as f32 + 0.5) * scale_y;
+        for x in clipped.min.x..clipped.max.x {
+            let src_x = src.min.x + ((x - dst.min.x) as f32 + 0.5) * scale_x;
+            let texel = texture.sample(src_x, src_y);
+            fb.blend_pixel(x, y, modulate(texel, color));
+        }
+    }
+}
+
+/// Draw the `atlas_rect` section of `texture` into `rect`, rescaling it with `rescale`.
+///
+/// The margins in `rescale` are in logical pixels. `src_scale` is the number of texels per logical
+/// pixel, and `dst_scale` is the number of framebuffer pixels per logical pixel.
+///
+/// Returns the rectangle the image was drawn to, mirroring `ImageToVertices::rect` in the OpenGL
+/// renderer.
+pub(in crate::soft_render) fn draw_image(
+    fb: &mut Framebuffer,
+    rect: BoundBox<D2, i32>,
+    clip: BoundBox<D2, i32>,
+    texture: Texture,
+    atlas_rect: OffsetBox<D2, u32>,
+    color: Rgba<u8>,
+    rescale: RescaleRules,
+    src_scale: f32,
+    dst_scale: f32,
+) -> Option<BoundBox<D2, i32>> {
+    let clipped_rect = clip.intersect_rect(rect)?;
+    if clipped_rect.min == clipped_rect.max {
+        return None;
+    }
+    let src = BoundBox::from(atlas_rect).cast::<f32>().unwrap();
+
+    match rescale {
+        RescaleRules::Stretch => {
+            blit_stretched(fb, rect, clipped_rect, texture, src, color);
+            Some(rect)
+        },
+        RescaleRules::StretchOnPixelCenter => {
+            let src_centered = BoundBox::new2(src.min.x + 0.5, src.min.y + 0.5, src.max.x - 0.5, src.max.y - 0.5);
+            blit_stretched(fb, rect, clipped_rect, texture, src_centered, color);
+            Some(rect)
+        },
+        RescaleRules::Slice(mut margins) => {
+            // Shrink the margins the same way the OpenGL renderer does when they don't fit in the
+            // rectangle.
+            let margins_width = margins.width();
+            if (margins_width as f32 * dst_scale) as i32 > rect.width() {
+                margins.left = margins.left.saturating_sub(margins_width / 2);
+                margins.right = margins.right.saturating_sub((margins_width + 1) / 2);
+            }
+            let margins_height = margins.height();
+            if (margins_height as f32 * dst_scale) as i32 > rect.height() {
+                margins.top = margins.top.saturating_sub(margins_height / 2);
+                margins.bottom = margins.bottom.saturating_sub((margins_height + 1) / 2);
+            }
+
+            let dst = |m: u16| (m as f32 * dst_scale).round() as i32;
+            let src_margin = |m: u16| m as f32 * src_scale;
+            let dst_x = [rect.min.x, rect.min.x + dst(margins.left), rect.max.x - dst(margins.right), rect.max.x];
+            let dst_y = [rect.min.y, rect.min.y + dst(margins.top), rect.max.y - dst(margins.bottom), rect.max.y];
+            let src_x = [src.min.x, src.min.x + src_margin(margins.left), src.max.x - src_margin(margins.right), src.max.x];
+            let src_y = [src.min.y, src.min.y + src_margin(margins.top), src.max.y - src_margin(margins.bottom), src.max.y];
+
+            for row in 0..3 {
+                for col in 0..3 {
+                    blit_stretched(
+                        fb,
+                        BoundBox::new2(dst_x[col], dst_y[row], dst_x[col + 1], dst_y[row + 1]),
+                        clipped_rect,
+                        texture,
+                        BoundBox::new2(src_x[col], src_y[row], src_x[col + 1], src_y[row + 1]),
+                        color
+                    );
+                }
+            }
+            Some(rect)
+        },
+        RescaleRules::Align(alignment) => {
+            let get_dims = |align, atlas_size, fill_size| match align {
+                Align::Start => (0, atlas_size),
+                Align::Center => ((fill_size - atlas_size) / 2, (fill_size + atlas_size) / 2),
+                Align::End => (fill_size - atlas_size, fill_size),
+                Align::Stretch => (0, fill_size)
+            };
+
+            // The image's size in framebuffer pixels.
+            let image_size = |texels: u32| (texels as f32 * dst_scale / src_scale).round() as i32;
+            let (min_x, max_x) = get_dims(alignment.x, image_size(atlas_rect.width()), rect.width());
+            let (min_y, max_y) = get_dims(alignment.y, image_size(atlas_rect.height()), rect.height());
+            let aligned_rect = BoundBox::new2(
+                rect.min.x + min_x,
+                rect.min.y + min_y,
+                rect.min.x + max_x,
+                rect.min.y + max_y,
+            );
+
+            blit_stretched(fb, aligned_rect, clipped_rect, texture, src, color);
+            Some(aligned_rect)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use derin_common_types::layout::{Align2, Margins};
+
+    const BLACK: Rgba<u8> = Rgba{ r: 0, g: 0, b: 0, a: 255 };
+    const WHITE: Rgba<u8> = Rgba{ r: 255, g: 255, b: 255, a: 255 };
+    const RED: Rgba<u8> = Rgba{ r: 255, g: 0, b: 0, a: 255 };
+    const GREEN: Rgba<u8> = Rgba{ r: 0, g: 255, b: 0, a: 255 };
+    const BLUE: Rgba<u8> = Rgba{ r: 0, g: 0, b: 255, a: 255 };
+
+    fn row(fb: &Framebuffer, y: u32) -> Vec<Rgba<u8>> {
+        (0..fb.dims().width()).map(|x| fb.pixel(x, y).unwrap()).collect()
+    }
+
+    fn texture(pixels: &[Rgba<u8>], width: u32) -> Texture {
+        Texture {
+            pixels,
+            dims: DimsBox::new2(width, pixels.len() as u32 / width),
+        }
+    }
+
+    #[test]
+    fn fill_rect_clipped() {
+        let mut fb = Framebuffer::new(DimsBox::new2(4, 4), BLACK);
+        fill_rect(&mut fb, BoundBox::new2(1, 1, 3, 3), BoundBox::new2(0, 0, 4, 4), RED);
+        assert_eq!(row(&fb, 0), [BLACK, BLACK, BLACK, BLACK]);
+        assert_eq!(row(&fb, 1), [BLACK, RED, RED, BLACK]);
+        assert_eq!(row(&fb, 2), [BLACK, RED, RED, BLACK]);
+        assert_eq!(row(&fb, 3), [BLACK, BLACK, BLACK, BLACK]);
+
+        // Only the clipped part of the rectangle gets blended onto the framebuffer.
+        fill_rect(&mut fb, BoundBox::new2(-10, -10, 10, 10), BoundBox::new2(3, 0, 8, 1), Rgba::new(255, 255, 255, 128));
+        assert_eq!(row(&fb, 0), [BLACK, BLACK, BLACK, Rgba::new(128, 128, 128, 191)]);
+        assert_eq!(row(&fb, 1), [BLACK, RED, RED, BLACK]);
+    }
+
+    #[test]
+    fn fill_triangle_shared_edge() {
+        let gray = Rgba::new(255, 255, 255, 128);
+        let vertex = |x, y| ShapeVertex{ pos: Point2::new(x, y), color: gray };
+        let clip = BoundBox::new2(0, 0, 2, 2);
+
+        // Two triangles splitting a square along the diagonal, which runs through the centers of
+        // pixels (0, 0) and (1, 1). Every pixel should get blended exactly once.
+        let mut fb = Framebuffer::new(DimsBox::new2(2, 2), BLACK);
+        fill_triangle(&mut fb, [vertex(0., 0.), vertex(2., 0.), vertex(2., 2.)], clip);
+        fill_triangle(&mut fb, [vertex(0., 0.), vertex(2., 2.), vertex(0., 2.)], clip);
+        let blended = Rgba::new(128, 128, 128, 191);
+        assert_eq!(row(&fb, 0), [blended, blended]);
+        assert_eq!(row(&fb, 1), [blended, blended]);
+
+        // Degenerate triangles don't draw anything.
+        let mut fb = Framebuffer::new(DimsBox::new2(2, 2), BLACK);
+        fill_triangle(&mut fb, [vertex(0., 0.), vertex(1., 1.), vertex(2., 2.)], clip);
+        assert_eq!(row(&fb, 0), [BLACK, BLACK]);
+        assert_eq!(row(&fb, 1), [BLACK, BLACK]);
+    }
+
+    #[test]
+    fn blit_stretched_samples_pixel_centers() {
+        let pixels = [RED, BLUE];
+        let src = BoundBox::new2(0., 0., 2., 1.);
+
+        let mut fb = Framebuffer::new(DimsBox::new2(4, 1), BLACK);
+        blit_stretched(&mut fb, BoundBox::new2(0, 0, 4, 1), BoundBox::new2(0, 0, 4, 1), texture(&pixels, 2), src, WHITE);
+        assert_eq!(row(&fb, 0), [RED, RED, BLUE, BLUE]);
+
+        // Texels get tinted with the color, and pixels outside of the clip rect don't get touched.
+        let mut fb = Framebuffer::new(DimsBox::new2(4, 1), WHITE);
+        blit_stretched(&mut fb, BoundBox::new2(0, 0, 4, 1), BoundBox::new2(1, 0, 3, 1), texture(&pixels, 2), src, Rgba::new(255, 255, 0, 255));
+        assert_eq!(row(&fb, 0), [WHITE, RED, BLACK, WHITE]);
+    }
+
+    #[test]
+    fn draw_image_stretch() {
+        let pixels = [RED, GREEN, BLUE, WHITE];
+        let mut fb = Framebuffer::new(DimsBox::new2(2, 1), BLACK);
+        let rect = draw_image(
+            &mut fb,
+            BoundBox::new2(0, 0, 2, 1),
+            BoundBox::new2(0, 0, 2, 1),
+            texture(&pixels, 4),
+            OffsetBox::new2(0, 0, 4, 1),
+            WHITE,
+            RescaleRules::Stretch,
+            1.0,
+            1.0,
+        );
+        assert_eq!(rect, Some(BoundBox::new2(0, 0, 2, 1)));
+        assert_eq!(row(&fb, 0), [GREEN, WHITE]);
+    }
+
+    #[test]
+    fn draw_image_stretch_on_pixel_center() {
+        let pixels = [RED, GREEN, BLUE, WHITE];
+        let mut fb = Framebuffer::new(DimsBox::new2(2, 1), BLACK);
+        let rect = draw_image(
+            &mut fb,
+            BoundBox::new2(0, 0, 2, 1),
+            BoundBox::new2(0, 0, 2, 1),
+            texture(&pixels, 4),
+            OffsetBox::new2(0, 0, 4, 1),
+            WHITE,
+            RescaleRules::StretchOnPixelCenter,
+            1.0,
+            1.0,
+        );
+        assert_eq!(rect, Some(BoundBox::new2(0, 0, 2, 1)));
+        assert_eq!(row(&fb, 0), [GREEN, BLUE]);
+    }
+
+    #[test]
+    fn draw_image_slice() {
+        let pixels = [
+            RED, RED, RED,
+            RED, BLUE, RED,
+            RED, RED, RED,
+        ];
+        let mut fb = Framebuffer::new(DimsBox::new2(5, 5), BLACK);
+        let rect = draw_image(
+            &mut fb,
+            BoundBox::new2(0, 0, 5, 5),
+            BoundBox::new2(0, 0, 5, 5),
+            texture(&pixels, 3),
+            OffsetBox::new2(0, 0, 3, 3),
+            WHITE,
+            RescaleRules::Slice(Margins::new(1, 1, 1, 1)),
+            1.0,
+            1.0,
+        );
+        assert_eq!(rect, Some(BoundBox::new2(0, 0, 5, 5)));
+
+        // The borders stay one pixel wide, while the center gets stretched out.
+        assert_eq!(row(&fb, 0), [RED; 5]);
+        assert_eq!(row(&fb, 1), [RED, BLUE, BLUE, BLUE, RED]);
+        assert_eq!(row(&fb, 2), [RED, BLUE, BLUE, BLUE, RED]);
+        assert_eq!(row(&fb, 3), [RED, BLUE, BLUE, BLUE, RED]);
+        assert_eq!(row(&fb, 4), [RED; 5]);
+    }
+
+    #[test]
+    fn draw_image_align() {
+        let pixels = [GREEN; 4];
+        let mut fb = Framebuffer::new(DimsBox::new2(6, 4), BLACK);
+        let rect = draw_image(
+            &mut fb,
+            BoundBox::new2(0, 0, 6, 4),
+            BoundBox::new2(0, 0, 6, 4),
+            texture(&pixels, 2),
+            OffsetBox::new2(0, 0, 2, 2),
+            WHITE,
+            RescaleRules::Align(Align2::new(Align::Center, Align::End)),
+            1.0,
+            1.0,
+        );
+        assert_eq!(rect, Some(BoundBox::new2(2, 2, 4, 4)));
+        assert_eq!(row(&fb, 0), [BLACK; 6]);
+        assert_eq!(row(&fb, 1), [BLACK; 6]);
+        assert_eq!(row(&fb, 2), [BLACK, BLACK, GREEN, GREEN, BLACK, BLACK]);
+        assert_eq!(row(&fb, 3), [BLACK, BLACK, GREEN, GREEN, BLACK, BLACK]);
+    }
+
+    #[test]
+    fn draw_image_slice_scaled() {
+        let pixels = [
+            RED, RED, RED,
+            RED, BLUE, RED,
+            RED, RED, RED,
+        ];
+        // With a scale factor of 2, the one-pixel margins get drawn two pixels wide.
+        let mut fb = Framebuffer::new(DimsBox::new2(6, 6), BLACK);
+        draw_image(
+            &mut fb,
+            BoundBox::new2(0, 0, 6, 6),
+            BoundBox::new2(0, 0, 6, 6),
+            texture(&pixels, 3),
+            OffsetBox::new2(0, 0, 3, 3),
+            WHITE,
+            RescaleRules::Slice(Margins::new(1, 1, 1, 1)),
+            1.0,
+            2.0,
+        );
+        assert_eq!(row(&fb, 1), [RED; 6]);
+        assert_eq!(row(&fb, 2), [RED, RED, BLUE, BLUE, RED, RED]);
+        assert_eq!(row(&fb, 3), [RED, RED, BLUE, BLUE, RED, RED]);
+        assert_eq!(row(&fb, 4), [RED; 6]);
+    }
+}
@@ -0,0 +1,411 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Golden-image testing for widget trees.
+//!
+//! A [`Snapshot`] wraps a widget tree in a `Root` that draws with the [`SoftRenderer`], feeds it
+//! scripted `WindowEvent`s, and compares the rendered frame against a stored PNG. If the frame
+//! doesn't match, the rendered image and an image highlighting the differing pixels get written
+//! next to the golden file, as `<name>.actual.png` and `<name>.diff.png`.
+//!
+//! [`Snapshot`]: ./struct.Snapshot.html
+//! [`SoftRenderer`]: ../struct.SoftRenderer.html
+
+use crate::core::{Root, WindowEvent, event::ModifierKeys, widget::Widget};
+use crate::soft_render::{Framebuffer, SoftRenderer};
+use crate::theme::Theme;
+
+use gullery::image_format::Rgba;
+use cgmath_geometry::{D2, rect::{DimsBox, GeoBox}};
+
+use png::{self, HasParameters};
+
+use std::{cmp, fmt, fs, io};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// A widget tree that gets rendered off-screen and compared against golden images.
+pub struct Snapshot<N: Widget> {
+    root: Root<N, SoftRenderer>,
+    tolerance: u8,
+    rendered: bool,
+}
+
+/// An error encountered while comparing a frame against a golden image.
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    Decoding(png::DecodingError),
+    Encoding(png::EncodingError),
+    /// The golden image isn't stored in a format the comparison supports.
+    UnsupportedFormat(png::ColorType, png::BitDepth),
+    /// The frame didn't match the golden image.
+    Mismatch(SnapshotMismatch),
+}
+
+/// Information on how a frame differs from the golden image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotMismatch {
+    /// The number of pixels that differ by more than the tolerance.
+    pub differing_pixels: usize,
+    pub expected_dims: DimsBox<D2, u32>,
+    pub actual_dims: DimsBox<D2, u32>,
+    /// Where the rendered frame was written.
+    pub actual_path: PathBuf,
+    /// Where the image highlighting the differing pixels was written.
+    pub diff_path: PathBuf,
+}
+
+impl<N: Widget> Snapshot<N> {
+    /// Create a new snapshot with a window of the given size, in logical pixels.
+    pub fn new(root_widget: N, theme: Theme, dims: DimsBox<D2, u32>) -> Snapshot<N> {
+        Snapshot {
+            root: Root::new(root_widget, theme, SoftRenderer::new(dims), dims),
+            tolerance: 0,
+            rendered: false,
+        }
+    }
+
+    /// Set the maximum amount a pixel's channels can differ from the golden image before it counts
+    /// as a mismatch.
+    pub fn with_tolerance(mut self, tolerance: u8) -> Snapshot<N> {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Render the window at the given scale factor, such as `2.0` for a HiDPI display. The window's
+    /// size stays the same in logical pixels, so the rendered image gets scaled up.
+    pub fn with_scale_factor(mut self, scale_factor: f32) -> Snapshot<N> {
+        self.root.set_scale_factor(scale_factor);
+        self
+    }
+
+    /// Retrieves a reference to the root widget.
+    pub fn root(&self) -> &N {
+        &self.root.root_widget
+    }
+
+    /// Retrieves a mutable reference to the root widget.
+    pub fn root_mut(&mut self) -> &mut N {
+        &mut self.root.root_widget
+    }
+
+    /// Retrieves a mutable reference to the theme.
+    pub fn theme_mut(&mut self) -> &mut Theme {
+        self.root.queue_global_update();
+        &mut self.root.theme
+    }
+
+    /// Feed a sequence of events to the widget tree, as if they were received in a single frame.
+    pub fn process_events(&mut self, events: impl IntoIterator<Item=WindowEvent>) -> &mut Snapshot<N> {
+        let mut frame = self.root.start_frame();
+        for event in events {
+            frame.process_event(event);
+        }
+        let _ = frame.finish();
+        self
+    }
+
+    /// Set the modifier keys that are held down while processing later events.
+    pub fn set_modifiers(&mut self, modifiers: ModifierKeys) -> &mut Snapshot<N> {
+        let mut frame = self.root.start_frame();
+        frame.set_modifiers(modifiers);
+        let _ = frame.finish();
+        self
+    }
+
+    /// Lay out and draw the widget tree, returning the rendered frame.
+    pub fn render(&mut self) -> &Framebuffer {
+        self.root.relayout();
+        self.root.redraw();
+        if !self.rendered {
+            // Widgets get laid out with the theme they were last drawn with, which doesn't exist
+            // until the tree has been drawn once. Do a second pass so the first frame's layout is
+            // correct.
+            self.root.queue_global_update();
+            self.root.relayout();
+            self.root.redraw();
+            self.rendered = true;
+        }
+
+        self.root.renderer.framebuffer()
+    }
+
+    /// Render the widget tree and compare it against the PNG at `golden_path`.
+    pub fn compare(&mut self, golden_path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        let tolerance = self.tolerance;
+        let golden_path = golden_path.as_ref();
+        let actual_path = golden_path.with_extension("actual.png");
+        let diff_path = golden_path.with_extension("diff.png");
+
+        let actual = self.render();
+        let expected = match read_png(golden_path) {
+            Ok(expected) => expected,
+            Err(e) => {
+                // Write out the frame so it can be checked and promoted to the golden image.
+                write_png(&actual_path, actual.pixels(), actual.dims())?;
+                return Err(e);
+            }
+        };
+
+        let diff = diff_images(&expected, actual, tolerance);
+        if diff.differing_pixels == 0 {
+            fs::remove_file(&actual_path).ok();
+            fs::remove_file(&diff_path).ok();
+            return Ok(());
+        }
+
+        write_png(&actual_path, actual.pixels(), actual.dims())?;
+        write_png(&diff_path, diff.image.pixels(), diff.image.dims())?;
+        Err(SnapshotError::Mismatch(SnapshotMismatch {
+            differing_pixels: diff.differing_pixels,
+            expected_dims: expected.dims(),
+            actual_dims: actual.dims(),
+            actual_path,
+            diff_path,
+        }))
+    }
+
+    /// Render the widget tree and compare it against the PNG at `golden_path`, panicking if they
+    /// don't match.
+    pub fn assert_matches(&mut self, golden_path: impl AsRef<Path>) {
+        let golden_path = golden_path.as_ref();
+        if let Err(e) = self.compare(golden_path) {
+            panic!("snapshot {} failed: {}", golden_path.display(), e);
+        }
+    }
+}
+
+struct ImageDiff {
+    image: Framebuffer,
+    differing_pixels: usize,
+}
+
+/// Compare two images. The resulting image shows matching pixels as faded grayscale, and differing
+/// pixels as solid red.
+fn diff_images(expected: &Framebuffer, actual: &Framebuffer, tolerance: u8) -> ImageDiff {
+    let dims = DimsBox::new2(
+        cmp::max(expected.dims().width(), actual.dims().width()),
+        cmp::max(expected.dims().height(), actual.dims().height()),
+    );
+    let mut pixels = Vec::with_capacity((dims.width() * dims.height()) as usize);
+    let mut differing_pixels = 0;
+
+    let channel_diff = |a: u8, b: u8| (a as i16 - b as i16).abs() as u8;
+    for y in 0..dims.height() {
+        for x in 0..dims.width() {
+            let diff_pixel = match (expected.pixel(x, y), actual.pixel(x, y)) {
+                (Some(e), Some(a)) if
+                    channel_diff(e.r, a.r) <= tolerance &&
+                    channel_diff(e.g, a.g) <= tolerance &&
+                    channel_diff(e.b, a.b) <= tolerance &&
+                    channel_diff(e.a, a.a) <= tolerance
+                => {
+                    let luma = ((e.r as u32 * 3 + e.g as u32 * 6 + e.b as u32) / 10) as u8;
+                    let faded = 192 + luma / 4;
+                    Rgba::new(faded, faded, faded, 255)
+                },
+                _ => {
+                    differing_pixels += 1;
+                    Rgba::new(255, 0, 0, 255)
+                }
+            };
+            pixels.push(diff_pixel);
+        }
+    }
+
+    ImageDiff {
+        image: Framebuffer::from_pixels(pixels, dims),
+        differing_pixels,
+    }
+}
+
+fn read_png(path: &Path) -> Result<Framebuffer, SnapshotError> {
+    let decoder = png::Decoder::new(File::open(path)?);
+    let (info, mut reader) = decoder.read_info()?;
+    let mut buf = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buf)?;
+
+    let pixels: Vec<Rgba<u8>> = match (info.color_type, info.bit_depth) {
+        (png::ColorType::RGBA, png::BitDepth::Eight) =>
+            buf.chunks(4).map(|p| Rgba::new(p[0], p[1], p[2], p[3])).collect(),
+        (png::ColorType::RGB, png::BitDepth::Eight) =>
+            buf.chunks(3).map(|p| Rgba::new(p[0], p[1], p[2], 255)).collect(),
+        (color_type, bit_depth) => return Err(SnapshotError::UnsupportedFormat(color_type, bit_depth))
+    };
+
+    Ok(Framebuffer::from_pixels(pixels, DimsBox::new2(info.width, info.height)))
+}
+
+fn write_png(path: &Path, pixels: &[Rgba<u8>], dims: DimsBox<D2, u32>) -> Result<(), SnapshotError> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, dims.width(), dims.height());
+    encoder.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+
+    let mut bytes = Vec::with_capacity(pixels.len() * 4);
+    for p in pixels {
+        bytes.extend_from_slice(&[p.r, p.g, p.b, p.a]);
+    }
+    writer.write_image_data(&bytes)?;
+    Ok(())
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> SnapshotError {
+        SnapshotError::Io(err)
+    }
+}
+
+impl From<png::DecodingError> for SnapshotError {
+    fn from(err: png::DecodingError) -> SnapshotError {
+        SnapshotError::Decoding(err)
+    }
+}
+
+impl From<png::EncodingError> for SnapshotError {
+    fn from(err: png::EncodingError) -> SnapshotError {
+        SnapshotError::Encoding(err)
+    }
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SnapshotError::Io(ref err) => write!(f, "{}", err),
+            SnapshotError::Decoding(ref err) => write!(f, "could not decode golden image: {}", err),
+            SnapshotError::Encoding(ref err) => write!(f, "could not encode image: {}", err),
+            SnapshotError::UnsupportedFormat(color_type, bit_depth) =>
+                write!(f, "unsupported golden image format {:?} {:?}", color_type, bit_depth),
+            SnapshotError::Mismatch(ref mismatch) => write!(
+                f,
+                "{} pixels differ (expected {}x{}, got {}x{}); see {} and {}",
+                mismatch.differing_pixels,
+                mismatch.expected_dims.width(),
+                mismatch.expected_dims.height(),
+                mismatch.actual_dims.width(),
+                mismatch.actual_dims.height(),
+                mismatch.actual_path.display(),
+                mismatch.diff_path.display(),
+            ),
+        }
+    }
+}
+
+impl Error for SnapshotError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cgmath::Point2;
+    use crate::widgets::{Button, ButtonHandler, Contents};
+
+    struct NoOp;
+    impl ButtonHandler for NoOp {
+        fn on_click(&mut self) {}
+    }
+
+    fn solid(dims: DimsBox<D2, u32>, color: Rgba<u8>) -> Framebuffer {
+        Framebuffer::new(dims, color)
+    }
+
+    #[test]
+    fn diff_tolerance() {
+        let dims = DimsBox::new2(4, 3);
+        let expected = solid(dims, Rgba::new(100, 100, 100, 255));
+
+        // Identical images match regardless of tolerance.
+        assert_eq!(0, diff_images(&expected, &expected, 0).differing_pixels);
+
+        // One pixel off by 3 in a single channel.
+        let mut pixels = expected.pixels().to_vec();
+        pixels[5] = Rgba::new(103, 100, 100, 255);
+        let actual = Framebuffer::from_pixels(pixels, dims);
+        assert_eq!(0, diff_images(&expected, &actual, 3).differing_pixels);
+
+        let diff = diff_images(&expected, &actual, 2);
+        assert_eq!(1, diff.differing_pixels);
+        assert_eq!(Some(Rgba::new(255, 0, 0, 255)), diff.image.pixel(1, 1));
+        assert_ne!(Some(Rgba::new(255, 0, 0, 255)), diff.image.pixel(0, 0));
+    }
+
+    #[test]
+    fn diff_size_mismatch() {
+        let expected = solid(DimsBox::new2(4, 3), Rgba::new(0, 0, 0, 255));
+        let actual = solid(DimsBox::new2(3, 4), Rgba::new(0, 0, 0, 255));
+
+        // The diff covers both images, and every pixel outside of the overlap differs.
+        let diff = diff_images(&expected, &actual, 255);
+        assert_eq!(DimsBox::new2(4, 4), diff.image.dims());
+        assert_eq!(16 - 9, diff.differing_pixels);
+        assert_eq!(Some(Rgba::new(255, 0, 0, 255)), diff.image.pixel(3, 0));
+        assert_eq!(Some(Rgba::new(255, 0, 0, 255)), diff.image.pixel(0, 3));
+        assert_eq!(Some(Rgba::new(255, 0, 0, 255)), diff.image.pixel(3, 3));
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let dims = DimsBox::new2(3, 2);
+        let pixels = vec![
+            Rgba::new(255, 0, 0, 255), Rgba::new(0, 255, 0, 255), Rgba::new(0, 0, 255, 255),
+            Rgba::new(0, 0, 0, 0), Rgba::new(12, 34, 56, 78), Rgba::new(255, 255, 255, 255),
+        ];
+        let image = Framebuffer::from_pixels(pixels, dims);
+
+        let path = std::env::temp_dir().join(format!("derin_snapshot_round_trip_{}.png", std::process::id()));
+        write_png(&path, image.pixels(), image.dims()).unwrap();
+        let read = read_png(&path);
+        fs::remove_file(&path).ok();
+
+        assert_eq!(image, read.unwrap());
+    }
+
+    #[test]
+    fn button_hover() {
+        let dir = std::env::temp_dir().join(format!("derin_snapshot_button_hover_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let golden_path = dir.join("button.png");
+        let actual_path = dir.join("button.actual.png");
+        let diff_path = dir.join("button.diff.png");
+
+        let button = Button::new(Contents::Text("Button".to_string()), NoOp);
+        let mut snapshot = Snapshot::new(button, Theme::default(), DimsBox::new2(64, 32));
+
+        // Without a golden image, the rendered frame gets written out so it can be promoted.
+        match snapshot.compare(&golden_path) {
+            Err(SnapshotError::Io(_)) => (),
+            result => panic!("expected missing golden image, got {:?}", result)
+        }
+        fs::rename(&actual_path, &golden_path).unwrap();
+        snapshot.assert_matches(&golden_path);
+
+        // Hovering over the button draws it with a different image.
+        snapshot.process_events(vec![WindowEvent::MouseEnter, WindowEvent::MouseMove(Point2::new(32, 16))]);
+        let mismatch = match snapshot.compare(&golden_path) {
+            Err(SnapshotError::Mismatch(mismatch)) => mismatch,
+            result => panic!("expected mismatch, got {:?}", result)
+        };
+        assert_ne!(0, mismatch.differing_pixels);
+        assert_eq!(DimsBox::new2(64, 32), mismatch.expected_dims);
+        assert_eq!(DimsBox::new2(64, 32), mismatch.actual_dims);
+        assert_eq!(actual_path, mismatch.actual_path);
+        assert_eq!(diff_path, mismatch.diff_path);
+
+        let actual = read_png(&actual_path).unwrap();
+        assert_eq!(snapshot.render(), &actual);
+        let diff = read_png(&diff_path).unwrap();
+        let red = Rgba::new(255, 0, 0, 255);
+        assert_eq!(mismatch.differing_pixels, diff.pixels().iter().filter(|p| **p == red).count());
+
+        // Once the mouse leaves, the frame matches again and the outputs get cleaned up.
+        snapshot.process_events(vec![WindowEvent::MouseExit]);
+        snapshot.assert_matches(&golden_path);
+        assert!(!actual_path.exists());
+        assert!(!diff_path.exists());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -3,12 +3,19 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 //! Types used to specify how widgets should be drawn.
+//!
+//! Themes can either be built in Rust with `Theme::insert_widget`, or loaded from a theme file with
+//! `Theme::load`. See the [`ThemeWatcher`] for reloading themes when their files change. Theme
+//! files require the `derin-serde` feature.
+//!
+//! [`ThemeWatcher`]: ./struct.ThemeWatcher.html
 
 use png;
 use gullery::image_format::Rgba;
 
 use cgmath_geometry::{D2, rect::DimsBox};
 use derin_common_types::layout::{Align, Align2, Margins, SizeBounds};
+use crate::core::render::WidgetTheme;
 
 use std::io;
 use std::rc::Rc;
@@ -19,18 +26,37 @@ use std::collections::hash_map::RandomState;
 
 
 pub use derin_common_types::cursor::CursorIcon;
+#[cfg(feature = "derin-serde")]
+pub use self::file::{ThemeLoadError, ThemeWatcher};
+
+#[cfg(feature = "derin-serde")]
+mod file;
 
 pub mod color {
     pub use gullery::image_format::Rgba;
 }
 
 /// An RGBA representation of an image.
+///
+/// `dims`, `size_bounds` and the rescale margins are all measured in logical pixels, which line
+/// up with the base image's pixels. On displays with a scale factor above `1`, the renderer draws
+/// the best-fitting of the higher-resolution `variants` instead.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Image {
     pub pixels: Vec<Rgba<u8>>,
     pub dims: DimsBox<D2, u32>,
     pub rescale: RescaleRules,
-    pub size_bounds: SizeBounds
+    pub size_bounds: SizeBounds,
+    pub variants: Vec<ImageVariant>,
+}
+
+/// A higher-resolution version of an `Image`, such as a `2x` image for HiDPI displays.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImageVariant {
+    /// The number of the variant's pixels per logical pixel. A `2x` image has a scale of `2`.
+    pub scale: u32,
+    pub pixels: Vec<Rgba<u8>>,
+    pub dims: DimsBox<D2, u32>,
 }
 
 /// The algorithm used to rescale an image.
@@ -81,12 +107,115 @@ pub struct ThemeText {
     pub line_wrap: LineWrap
 }
 
-/// The text style and image used to draw a widget with a given style.
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// The text style, image and shape used to draw a widget with a given style.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct ThemeWidget {
     pub text: Option<ThemeText>,
     pub image: Option<Rc<Image>>,
     pub content_margins: Margins<u16>,
+    /// A procedurally-drawn shape, drawn beneath the image.
+    pub shape: Option<Shape>,
+}
+
+/// A rounded rectangle that gets drawn procedurally, rather than from an image.
+///
+/// Unlike images, shapes scale cleanly to any size and resolution. The shadow is drawn first,
+/// followed by the fill, followed by the border.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Shape {
+    pub fill: Option<Fill>,
+    pub border: Option<Border>,
+    pub shadow: Option<Shadow>,
+    /// The radius of the rectangle's corners, in pixels.
+    pub corner_radius: u16,
+}
+
+/// The color used to fill a `Shape`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fill {
+    Solid(Rgba<u8>),
+    LinearGradient {
+        start: Rgba<u8>,
+        end: Rgba<u8>,
+        /// The direction the gradient runs, in degrees. Like in CSS, `0` runs from bottom to top
+        /// and `90` runs from left to right.
+        angle: u16,
+    },
+}
+
+/// A border drawn along the inside edges of a `Shape`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Border {
+    /// The width of each side of the border, in pixels.
+    pub widths: Margins<u16>,
+    pub color: Rgba<u8>,
+}
+
+/// A shadow drawn beneath a `Shape`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Shadow {
+    pub offset_x: i16,
+    pub offset_y: i16,
+    /// The distance over which the shadow fades out, in pixels.
+    pub blur_radius: u16,
+    /// The distance the shadow extends past the edges of the shape, in pixels.
+    pub spread: u16,
+    pub color: Rgba<u8>,
+}
+
+/// The outline drawn around a widget that got focus via keyboard navigation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FocusRing {
+    pub color: Rgba<u8>,
+    /// The width of the outline's stroke, in pixels.
+    pub width: u16,
+    /// The distance between the focused widget's edges and the outside of the outline, in pixels.
+    pub offset: u16,
+    /// The radius of the outline's corners, in pixels.
+    pub corner_radius: u16,
+}
+
+impl FocusRing {
+    /// The shape used to draw the ring, drawn in a rect `offset` pixels larger than the focused
+    /// widget's rect.
+    pub fn shape(&self) -> Shape {
+        Shape {
+            border: Some(Border {
+                widths: Margins::new(self.width, self.width, self.width, self.width),
+                color: self.color,
+            }),
+            corner_radius: self.corner_radius,
+            ..Shape::default()
+        }
+    }
+}
+
+/// A `ThemeWidget` that only sets some of its fields.
+///
+/// Unset fields get inherited from the widget's ancestor paths, and from the theme's parent theme.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PartialThemeWidget {
+    pub text: Option<PartialThemeText>,
+    pub image: Option<Rc<Image>>,
+    pub content_margins: Option<Margins<u16>>,
+    pub shape: Option<Shape>,
+}
+
+/// A `ThemeText` that only sets some of its fields.
+///
+/// If no `ThemeText` gets inherited, the text is only drawn if `face` is set, and any other unset
+/// fields use the same values as the default theme.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PartialThemeText {
+    pub face: Option<ThemeFace>,
+    pub color: Option<Rgba<u8>>,
+    pub highlight_bg_color: Option<Rgba<u8>>,
+    pub highlight_text_color: Option<Rgba<u8>>,
+    pub face_size: Option<u32>,
+    pub tab_size: Option<u32>,
+    pub justify: Option<Align2>,
+    pub margins: Option<Margins<u16>>,
+    pub line_wrap: Option<LineWrap>,
 }
 
 /// Reference-counted face handle. This is cheap to clone.
@@ -110,8 +239,21 @@ pub struct ThemeFaceBuffer {
     fingerprint: u64,
 }
 
+/// A collection of `ThemeWidget`s, keyed by theme path.
+///
+/// Theme paths are hierarchical, with each level separated by `::`. When looking up a path, any
+/// fields the entry leaves unset get inherited from its ancestors - so `"Button::Hover"` inherits
+/// from `"Button"`. Once the path's ancestors have been exhausted, lookup continues in the parent
+/// theme, if there is one.
 pub struct Theme {
-    map: HashMap<String, ThemeWidget>
+    map: HashMap<String, PartialThemeWidget>,
+    focus_ring: Option<FocusRing>,
+    parent: Option<Rc<Theme>>,
+}
+
+/// Maps a widget's theme parameters onto the key of the `ThemeWidget` used to draw it.
+pub trait ThemePath: WidgetTheme {
+    fn theme_path(&self) -> &'static str;
 }
 
 
@@ -206,25 +348,237 @@ impl ThemeFaceBuffer {
     }
 }
 
+impl ThemeWidget {
+    /// Halve the opacity of the text and shape colors. Used to draw disabled widgets when the theme
+    /// doesn't have a `::Disabled` entry for them.
+    ///
+    /// Images can't be dimmed here, so renderers are expected to draw them translucently.
+    pub fn dimmed(mut self) -> ThemeWidget {
+        if let Some(ref mut text) = self.text {
+            text.color = dim(text.color);
+            text.highlight_bg_color = dim(text.highlight_bg_color);
+            text.highlight_text_color = dim(text.highlight_text_color);
+        }
+        if let Some(ref mut shape) = self.shape {
+            shape.fill = shape.fill.map(|fill| match fill {
+                Fill::Solid(color) => Fill::Solid(dim(color)),
+                Fill::LinearGradient{start, end, angle} => Fill::LinearGradient {
+                    start: dim(start),
+                    end: dim(end),
+                    angle
+                }
+            });
+            if let Some(ref mut border) = shape.border {
+                border.color = dim(border.color);
+            }
+            if let Some(ref mut shadow) = shape.shadow {
+                shadow.color = dim(shadow.color);
+            }
+        }
+        self
+    }
+}
+
+fn dim(color: Rgba<u8>) -> Rgba<u8> {
+    Rgba::new(color.r, color.g, color.b, color.a / 2)
+}
+
+impl PartialThemeWidget {
+    /// Apply the fields set in `self` on top of `base`.
+    pub fn apply_to(&self, base: &mut ThemeWidget) {
+        if let Some(ref text) = self.text {
+            base.text = text.apply_to(base.text.take());
+        }
+        if let Some(ref image) = self.image {
+            base.image = Some(image.clone());
+        }
+        if let Some(content_margins) = self.content_margins {
+            base.content_margins = content_margins;
+        }
+        if let Some(shape) = self.shape {
+            base.shape = Some(shape);
+        }
+    }
+}
+
+impl PartialThemeText {
+    /// Apply the fields set in `self` on top of `base`.
+    ///
+    /// Returns `None` if there's no base text and `self` doesn't set a face.
+    pub fn apply_to(&self, base: Option<ThemeText>) -> Option<ThemeText> {
+        let base = match (base, &self.face) {
+            (Some(base), _) => base,
+            (None, Some(face)) => ThemeText {
+                face: face.clone(),
+                color: Rgba::new(0, 0, 0, 255),
+                highlight_bg_color: Rgba::new(0, 120, 215, 255),
+                highlight_text_color: Rgba::new(255, 255, 255, 255),
+                face_size: 16 * 64,
+                tab_size: 8,
+                justify: Align2::new(Align::Start, Align::Start),
+                margins: Margins::default(),
+                line_wrap: LineWrap::None
+            },
+            (None, None) => return None
+        };
+
+        Some(ThemeText {
+            face: self.face.clone().unwrap_or(base.face),
+            color: self.color.unwrap_or(base.color),
+            highlight_bg_color: self.highlight_bg_color.unwrap_or(base.highlight_bg_color),
+            highlight_text_color: self.highlight_text_color.unwrap_or(base.highlight_text_color),
+            face_size: self.face_size.unwrap_or(base.face_size),
+            tab_size: self.tab_size.unwrap_or(base.tab_size),
+            justify: self.justify.unwrap_or(base.justify),
+            margins: self.margins.unwrap_or(base.margins),
+            line_wrap: self.line_wrap.unwrap_or(base.line_wrap),
+        })
+    }
+}
+
+impl From<ThemeWidget> for PartialThemeWidget {
+    fn from(theme: ThemeWidget) -> PartialThemeWidget {
+        PartialThemeWidget {
+            text: theme.text.map(PartialThemeText::from),
+            image: theme.image,
+            content_margins: Some(theme.content_margins),
+            shape: theme.shape,
+        }
+    }
+}
+
+impl From<ThemeText> for PartialThemeText {
+    fn from(text: ThemeText) -> PartialThemeText {
+        PartialThemeText {
+            face: Some(text.face),
+            color: Some(text.color),
+            highlight_bg_color: Some(text.highlight_bg_color),
+            highlight_text_color: Some(text.highlight_text_color),
+            face_size: Some(text.face_size),
+            tab_size: Some(text.tab_size),
+            justify: Some(text.justify),
+            margins: Some(text.margins),
+            line_wrap: Some(text.line_wrap),
+        }
+    }
+}
+
 impl Theme {
     pub fn empty() -> Theme {
         Theme {
-            map: HashMap::new()
+            map: HashMap::new(),
+            focus_ring: None,
+            parent: None,
         }
     }
 
-    pub fn insert_widget(&mut self, key: String, theme: ThemeWidget) -> Option<ThemeWidget> {
-        self.map.insert(key, theme)
+    /// Create an empty theme that inherits every entry from `parent`.
+    pub fn with_parent(parent: Rc<Theme>) -> Theme {
+        Theme {
+            map: HashMap::new(),
+            focus_ring: None,
+            parent: Some(parent),
+        }
     }
 
+    #[inline]
+    pub fn parent(&self) -> Option<&Rc<Theme>> {
+        self.parent.as_ref()
+    }
+
+    #[inline]
+    pub fn set_parent(&mut self, parent: Option<Rc<Theme>>) {
+        self.parent = parent;
+    }
+
+    pub fn insert_widget<T>(&mut self, key: String, theme: T) -> Option<PartialThemeWidget>
+        where T: Into<PartialThemeWidget>
+    {
+        self.map.insert(key, theme.into())
+    }
+
+    /// The focus ring drawn around keyboard-focused widgets. If this theme doesn't set one, it gets
+    /// inherited from the parent theme.
+    pub fn focus_ring(&self) -> Option<FocusRing> {
+        match (self.focus_ring, &self.parent) {
+            (Some(focus_ring), _) => Some(focus_ring),
+            (None, Some(parent)) => parent.focus_ring(),
+            (None, None) => None
+        }
+    }
+
+    #[inline]
+    pub fn set_focus_ring(&mut self, focus_ring: Option<FocusRing>) {
+        self.focus_ring = focus_ring;
+    }
+
+    /// Retrieve the entry stored at exactly `path`, without resolving any inherited fields.
+    pub fn get_widget(&self, path: &str) -> Option<&PartialThemeWidget> {
+        self.map.get(path)
+    }
+
+    /// Resolve the `ThemeWidget` used to draw widgets with the given theme path.
     pub fn widget_theme(&self, path: &str) -> ThemeWidget {
-        self.map.get(path).cloned().unwrap_or(
-            ThemeWidget {
-                text: None,
-                image: None,
-                content_margins: Margins::default(),
-            }
-        )
+        let mut widget_theme = ThemeWidget::default();
+        self.apply_widget_theme(path, &mut widget_theme);
+        widget_theme
+    }
+
+    /// Resolve the `ThemeWidget` used to draw disabled widgets with the given theme path.
+    ///
+    /// The `::Disabled` entries for the path and its ancestors get applied on top of the path's
+    /// regular theme, so a disabled `"Button::Hover"` gets drawn with `"Button::Hover"`, overridden
+    /// by `"Button::Disabled"` and then by `"Button::Hover::Disabled"`. Returns `None` if there
+    /// aren't any such entries, in which case renderers should fall back to drawing a dimmed
+    /// version of the path's regular theme.
+    pub fn disabled_widget_theme(&self, path: &str) -> Option<ThemeWidget> {
+        let mut widget_theme = self.widget_theme(path);
+        match self.apply_disabled_path(path, &mut widget_theme) {
+            true => Some(widget_theme),
+            false => None
+        }
+    }
+
+    /// Apply the `::Disabled` entries for `path` and its ancestors. Returns whether there were any.
+    fn apply_disabled_path(&self, path: &str, widget_theme: &mut ThemeWidget) -> bool {
+        let mut applied = match path.rfind("::") {
+            Some(separator) => self.apply_disabled_path(&path[..separator], widget_theme),
+            None => false
+        };
+        applied |= self.apply_exact_path(&format!("{}::Disabled", path), widget_theme);
+        applied
+    }
+
+    /// Apply the entries stored at exactly `path` in this theme and its parents. Returns whether
+    /// there were any.
+    fn apply_exact_path(&self, path: &str, widget_theme: &mut ThemeWidget) -> bool {
+        let mut applied = match self.parent {
+            Some(ref parent) => parent.apply_exact_path(path, widget_theme),
+            None => false
+        };
+        if let Some(partial) = self.map.get(path) {
+            partial.apply_to(widget_theme);
+            applied = true;
+        }
+        applied
+    }
+
+    fn apply_widget_theme(&self, path: &str, widget_theme: &mut ThemeWidget) {
+        // Apply the least specific entries first, so that more specific entries override them.
+        if let Some(ref parent) = self.parent {
+            parent.apply_widget_theme(path, widget_theme);
+        }
+        self.apply_path(path, widget_theme);
+    }
+
+    /// Apply the entries for `path` and its ancestors, without looking at the parent theme.
+    fn apply_path(&self, path: &str, widget_theme: &mut ThemeWidget) {
+        if let Some(separator) = path.rfind("::") {
+            self.apply_path(&path[..separator], widget_theme);
+        }
+        if let Some(partial) = self.map.get(path) {
+            partial.apply_to(widget_theme);
+        }
     }
 }
 
@@ -281,9 +635,11 @@ impl Default for Theme {
                             size_bounds: SizeBounds {
                                 min: DimsBox::new2($border * 2, $border * 2),
                                 ..SizeBounds::default()
-                            }
+                            },
+                            variants: Vec::new()
                         })),
                         content_margins: Margins::default(),
+                        shape: None,
                     }
                 );
             }}
@@ -293,8 +649,44 @@ impl Default for Theme {
         upload_image!("Button::Normal", "./default_theme_resources/button/base.png", (16, 16), 4, Align2::new(Align::Center, Align::Center));
         upload_image!("Button::Hover", "./default_theme_resources/button/hover.png", (16, 16), 4, Align2::new(Align::Center, Align::Center));
         upload_image!("Button::Pressed", "./default_theme_resources/button/pressed.png", (16, 16), 4, Align2::new(Align::Center, Align::Center));
+        upload_image!("ComboBox::Normal", "./default_theme_resources/button/base.png", (16, 16), 4, Align2::new(Align::Start, Align::Center));
+        upload_image!("ComboBox::Hover", "./default_theme_resources/button/hover.png", (16, 16), 4, Align2::new(Align::Start, Align::Center));
+        upload_image!("ComboBox::Pressed", "./default_theme_resources/button/pressed.png", (16, 16), 4, Align2::new(Align::Start, Align::Center));
+        upload_image!("ComboBox::Open", "./default_theme_resources/button/pressed.png", (16, 16), 4, Align2::new(Align::Start, Align::Center));
+        upload_image!("ComboBox::List", "./default_theme_resources/group.png", (3, 3), 1, Align2::new(Align::Start, Align::Start));
+        upload_image!("TreeView::Background", "./default_theme_resources/group.png", (3, 3), 1, Align2::new(Align::Start, Align::Start));
+        upload_image!("VirtualList::Background", "./default_theme_resources/group.png", (3, 3), 1, Align2::new(Align::Start, Align::Start));
+        upload_image!("Table::Background", "./default_theme_resources/group.png", (3, 3), 1, Align2::new(Align::Start, Align::Start));
+        upload_image!("Table::Header::Normal", "./default_theme_resources/button/base.png", (16, 16), 4, Align2::new(Align::Start, Align::Center));
+        upload_image!("Table::Header::Hover", "./default_theme_resources/button/hover.png", (16, 16), 4, Align2::new(Align::Start, Align::Center));
+        upload_image!("Table::Header::Pressed", "./default_theme_resources/button/pressed.png", (16, 16), 4, Align2::new(Align::Start, Align::Center));
+        upload_image!("MenuBar::Background", "./default_theme_resources/group.png", (3, 3), 1, Align2::new(Align::Start, Align::Start));
+        upload_image!("Menu::Popup", "./default_theme_resources/group.png", (3, 3), 1, Align2::new(Align::Start, Align::Start));
         upload_image!("ScrollBar", "./default_theme_resources/scroll_bar.png", (3, 3), 1, Align2::new(Align::Center, Align::Center));
         upload_image!("ScrollBackground", "./default_theme_resources/scroll_bg.png", (3, 3), 1, Align2::new(Align::Center, Align::Center));
+        macro_rules! scroll_arrow {
+            ($name:expr, $path:expr) => {
+                theme.insert_widget(
+                    concat!("ScrollArrow::", $name).to_string(),
+                    ThemeWidget {
+                        text: None,
+                        image: Some(Rc::new(Image {
+                            pixels: image_buf!($path),
+                            dims: DimsBox::new2(16, 16),
+                            rescale: RescaleRules::Align(Align2::new(Align::Center, Align::Center)),
+                            size_bounds: SizeBounds::default(),
+                            variants: Vec::new()
+                        })),
+                        content_margins: Margins::default(),
+                        shape: None,
+                    }
+                );
+            }
+        }
+        scroll_arrow!("Up", "./default_theme_resources/scroll.up.png");
+        scroll_arrow!("Down", "./default_theme_resources/scroll.down.png");
+        scroll_arrow!("Left", "./default_theme_resources/scroll.left.png");
+        scroll_arrow!("Right", "./default_theme_resources/scroll.right.png");
         theme.insert_widget(
             "Slider::Bar".to_string(),
             ThemeWidget {
@@ -306,9 +698,11 @@ impl Default for Theme {
                     size_bounds: SizeBounds {
                         min: DimsBox::new2(32, 8),
                         max: DimsBox::new2(i32::max_value(), 8)
-                    }
+                    },
+                    variants: Vec::new()
                 })),
                 content_margins: Margins::default(),
+                shape: None,
             }
         );
         theme.insert_widget(
@@ -322,9 +716,11 @@ impl Default for Theme {
                     size_bounds: SizeBounds {
                         min: DimsBox::new2(8, 16),
                         ..SizeBounds::default()
-                    }
+                    },
+                    variants: Vec::new()
                 })),
                 content_margins: Margins::default(),
+                shape: None,
             }
         );
         theme.insert_widget(
@@ -343,6 +739,7 @@ impl Default for Theme {
                 }),
                 image: None,
                 content_margins: Margins::default(),
+                shape: None,
             }
         );
         theme.insert_widget(
@@ -361,8 +758,59 @@ impl Default for Theme {
                 }),
                 image: None,
                 content_margins: Margins::default(),
+                shape: None,
             }
         );
+        macro_rules! list_item {
+            ($name:expr, $text_color:expr, $fill:expr) => {{
+                theme.insert_widget(
+                    $name.to_string(),
+                    ThemeWidget {
+                        text: Some(ThemeText {
+                            face: font.clone(),
+                            color: $text_color,
+                            highlight_bg_color: Rgba::new(0, 120, 215, 255),
+                            highlight_text_color: Rgba::new(255, 255, 255, 255),
+                            face_size: 16 * 64,
+                            tab_size: 8,
+                            justify: Align2::new(Align::Start, Align::Center),
+                            margins: Margins::new(4, 2, 4, 2),
+                            line_wrap: LineWrap::None
+                        }),
+                        image: None,
+                        content_margins: Margins::default(),
+                        shape: $fill.map(|fill| Shape {
+                            fill: Some(Fill::Solid(fill)),
+                            ..Shape::default()
+                        }),
+                    }
+                );
+            }}
+        }
+        list_item!("ComboBox::Item::Normal", Rgba::new(0, 0, 0, 255), None);
+        list_item!("ComboBox::Item::Selected", Rgba::new(0, 0, 0, 255), Some(Rgba::new(204, 228, 247, 255)));
+        list_item!("ComboBox::Item::Highlighted", Rgba::new(255, 255, 255, 255), Some(Rgba::new(0, 120, 215, 255)));
+        list_item!("TreeView::Row", Rgba::new(0, 0, 0, 255), None);
+        list_item!("TreeView::Row::Selected", Rgba::new(255, 255, 255, 255), Some(Rgba::new(0, 120, 215, 255)));
+        list_item!("TreeView::Row::Rename", Rgba::new(0, 0, 0, 255), Some(Rgba::new(255, 255, 255, 255)));
+        list_item!("TreeView::Expander", Rgba::new(0, 0, 0, 255), None);
+        list_item!("VirtualList::Row", Rgba::new(0, 0, 0, 255), None);
+        list_item!("VirtualList::Row::Selected", Rgba::new(255, 255, 255, 255), Some(Rgba::new(0, 120, 215, 255)));
+        list_item!("Table::Cell", Rgba::new(0, 0, 0, 255), None);
+        list_item!("Table::Cell::Selected", Rgba::new(255, 255, 255, 255), Some(Rgba::new(0, 120, 215, 255)));
+        list_item!("Table::Cell::Edit", Rgba::new(0, 0, 0, 255), Some(Rgba::new(255, 255, 255, 255)));
+        list_item!("MenuBar::Item::Normal", Rgba::new(0, 0, 0, 255), None);
+        list_item!("MenuBar::Item::Hover", Rgba::new(0, 0, 0, 255), Some(Rgba::new(204, 228, 247, 255)));
+        list_item!("MenuBar::Item::Open", Rgba::new(255, 255, 255, 255), Some(Rgba::new(0, 120, 215, 255)));
+        list_item!("MenuBar::Item::Disabled", Rgba::new(128, 128, 128, 255), None);
+        list_item!("Menu::Item::Normal", Rgba::new(0, 0, 0, 255), None);
+        list_item!("Menu::Item::Highlighted", Rgba::new(255, 255, 255, 255), Some(Rgba::new(0, 120, 215, 255)));
+        list_item!("Menu::Item::Disabled", Rgba::new(128, 128, 128, 255), None);
+        list_item!("Menu::Accelerator::Normal", Rgba::new(96, 96, 96, 255), None);
+        list_item!("Menu::Accelerator::Highlighted", Rgba::new(255, 255, 255, 255), None);
+        list_item!("Menu::Accelerator::Disabled", Rgba::new(128, 128, 128, 255), None);
+        list_item!("Menu::Separator", Rgba::new(0, 0, 0, 255), Some(Rgba::new(192, 192, 192, 255)));
+        list_item!("Tooltip::Popup", Rgba::new(0, 0, 0, 255), Some(Rgba::new(255, 255, 225, 255)));
         macro_rules! checkbox {
             ($name:expr, $path:expr) => {
                 theme.insert_widget(
@@ -376,9 +824,11 @@ impl Default for Theme {
                             size_bounds: SizeBounds {
                                 min: DimsBox::new2(16, 16),
                                 ..SizeBounds::default()
-                            }
+                            },
+                            variants: Vec::new()
                         })),
                         content_margins: Margins::default(),
+                        shape: None,
                     }
                 );
             }
@@ -405,6 +855,7 @@ impl Default for Theme {
                 }),
                 image: None,
                 content_margins: Margins::default(),
+                shape: None,
             }
         );
         macro_rules! radiobutton {
@@ -420,9 +871,11 @@ impl Default for Theme {
                             size_bounds: SizeBounds {
                                 min: DimsBox::new2(16, 16),
                                 ..SizeBounds::default()
-                            }
+                            },
+                            variants: Vec::new()
                         })),
                         content_margins: Margins::default(),
+                        shape: None,
                     }
                 );
             }
@@ -447,9 +900,11 @@ impl Default for Theme {
                             size_bounds: SizeBounds {
                                 min: $min,
                                 ..SizeBounds::default()
-                            }
+                            },
+                            variants: Vec::new()
                         })),
                         content_margins: Margins::default(),
+                        shape: None,
                     }
                 );
             }
@@ -469,7 +924,7 @@ impl Default for Theme {
                             highlight_text_color: Rgba::new(255, 255, 255, 255),
                             face_size: 16 * 64,
                             tab_size: 8,
-                            justify: Align2::new(Align::Center, Align::Center),
+                            justify: Align2::new(Align::Start, Align::Center),
                             margins: Margins::new(4, 4, 4, 4),
                             line_wrap: LineWrap::None
                         }),
@@ -480,9 +935,11 @@ impl Default for Theme {
                             size_bounds: SizeBounds {
                                 min: DimsBox::new2(8, 4),
                                 ..SizeBounds::default()
-                            }
+                            },
+                            variants: Vec::new()
                         })),
                         content_margins: Margins::default(),
+                        shape: None,
                     }
                 );
             }
@@ -490,6 +947,10 @@ impl Default for Theme {
         tab!("Normal", "./default_theme_resources/tab/base.png");
         tab!("Hover", "./default_theme_resources/tab/hover.png");
         tab!("Pressed", "./default_theme_resources/tab/pressed.png");
+        tab!("Open", "./default_theme_resources/tab/pressed.png");
+        list_item!("Tab::Close", Rgba::new(96, 96, 96, 255), None);
+        list_item!("Tab::Close::Hover", Rgba::new(0, 0, 0, 255), Some(Rgba::new(204, 228, 247, 255)));
+        list_item!("Tab::Close::Pressed", Rgba::new(255, 255, 255, 255), Some(Rgba::new(0, 120, 215, 255)));
         theme.insert_widget(
             "EditBox".to_string(),
             ThemeWidget {
@@ -511,9 +972,11 @@ impl Default for Theme {
                     size_bounds: SizeBounds {
                         min: DimsBox::new2(3 * 2, 3 * 2),
                         ..SizeBounds::default()
-                    }
+                    },
+                    variants: Vec::new()
                 })),
                 content_margins: Margins::default(),
+                shape: None,
             }
         );
         theme.insert_widget(
@@ -537,9 +1000,33 @@ impl Default for Theme {
                     size_bounds: SizeBounds {
                         min: DimsBox::new2(3 * 2, 3 * 2),
                         ..SizeBounds::default()
-                    }
+                    },
+                    variants: Vec::new()
                 })),
                 content_margins: Margins::default(),
+                shape: None,
+            }
+        );
+        theme.set_focus_ring(Some(FocusRing {
+            color: Rgba::new(0, 120, 215, 255),
+            width: 2,
+            offset: 2,
+            corner_radius: 3,
+        }));
+        theme.insert_widget(
+            "DockPreview".to_string(),
+            ThemeWidget {
+                text: None,
+                image: None,
+                content_margins: Margins::default(),
+                shape: Some(Shape {
+                    fill: Some(Fill::Solid(Rgba::new(0, 120, 215, 64))),
+                    border: Some(Border {
+                        widths: Margins::new(2, 2, 2, 2),
+                        color: Rgba::new(0, 120, 215, 192),
+                    }),
+                    ..Shape::default()
+                }),
             }
         );
 
@@ -557,4 +1044,28 @@ impl Image {
         //     RescaleRules::Slice(margins) => DimsBox::new2(margins.width() as i32, margins.height() as i32),
         // }
     }
+    /// Pick the pixels best suited to drawing the image at `scale_factor`, returning the pixels,
+    /// their dimensions, and the number of pixels per logical pixel.
+    ///
+    /// This is the lowest-resolution variant that's at least as sharp as the display, or the
+    /// highest-resolution variant if none of them are.
+    pub fn pixels_for_scale(&self, scale_factor: f32) -> (&[Rgba<u8>], DimsBox<D2, u32>, u32) {
+        let base = (&self.pixels[..], self.dims, 1);
+        let variants = self.variants.iter().map(|v| (&v.pixels[..], v.dims, v.scale));
+
+        let mut best = base;
+        for variant in variants {
+            let (_, _, best_scale) = best;
+            let (_, _, scale) = variant;
+            let best_fits = best_scale as f32 >= scale_factor;
+            let fits = scale as f32 >= scale_factor;
+            best = match (best_fits, fits) {
+                (false, true) => variant,
+                (true, true) if scale < best_scale => variant,
+                (false, false) if scale > best_scale => variant,
+                _ => best
+            };
+        }
+        best
+    }
 }
@@ -0,0 +1,775 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Loading for theme files.
+//!
+//! Theme files are written in [RON](https://github.com/ron-rs/ron), and map theme paths onto
+//! widget descriptions. Image and font paths are resolved relative to the directory containing the
+//! theme file. Every field is optional, and unset fields get inherited as described in the
+//! [`Theme`] docs. The parent theme can either be `Default`, for the default theme, or
+//! `File(path)`, for another theme file:
+//!
+//! ```text
+//! (
+//!     parent: Some(Default),
+//!     widgets: {
+//!         "Button": (
+//!             text: Some((
+//!                 color: Some((255, 255, 255, 255)),
+//!             )),
+//!         ),
+//!         "Button::Normal": (
+//!             text: Some((
+//!                 face: Some("DejaVuSans.ttf"),
+//!                 face_size: Some(16),
+//!                 justify: Some((x: Center, y: Center)),
+//!             )),
+//!             image: Some((
+//!                 path: "button/base.png",
+//!                 rescale: Slice((left: 4, top: 4, right: 4, bottom: 4)),
+//!                 min_size: Some((8, 8)),
+//!                 variants: {2: "button/base@2x.png"},
+//!             )),
+//!         ),
+//!         "Button::Hover": (
+//!             shape: Some((
+//!                 fill: Some(LinearGradient(start: (250, 250, 250, 255), end: (220, 220, 220, 255), angle: 180)),
+//!                 border: Some((widths: (left: 1, top: 1, right: 1, bottom: 2), color: (0, 120, 215, 255))),
+//!                 shadow: Some((offset_y: 1, blur_radius: 4, color: (0, 0, 0, 64))),
+//!                 corner_radius: 3,
+//!             )),
+//!         ),
+//!     },
+//!     focus_ring: Some((color: (0, 120, 215, 255), width: 2, offset: 2, corner_radius: 3)),
+//! )
+//! ```
+//!
+//! The optional `focus_ring` describes the outline drawn around widgets that got focus from the
+//! keyboard. If it's unset, it gets inherited from the parent theme.
+//!
+//! Images can list higher-resolution `variants` for HiDPI displays, keyed by how many of the
+//! variant's pixels cover one of the base image's pixels.
+//!
+//! [`Theme`]: ../struct.Theme.html
+
+use super::{Border, Fill, FocusRing, Image, ImageVariant, LineWrap, Shadow, Shape, RescaleRules, Theme, ThemeFace, ThemeFacePath, PartialThemeText, PartialThemeWidget};
+
+use gullery::image_format::Rgba;
+use cgmath_geometry::{D2, rect::DimsBox};
+use derin_common_types::layout::{Align, Align2, Margins, SizeBounds};
+
+use serde::Deserialize;
+
+use std::{error, fmt, fs, io};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+/// An error encountered while loading a theme file.
+#[derive(Debug)]
+pub enum ThemeLoadError {
+    /// The file at the given path couldn't be read.
+    Io(PathBuf, io::Error),
+    /// The theme file isn't a valid theme description.
+    Parse(ron::de::Error),
+    /// The image at the given path couldn't be decoded.
+    Image(PathBuf, png::DecodingError),
+    /// The image at the given path isn't stored as 8-bit RGB or RGBA.
+    UnsupportedImageFormat(PathBuf, png::ColorType, png::BitDepth),
+    /// The theme file at the given path is its own ancestor.
+    InheritanceCycle(PathBuf),
+}
+
+/// Watches a theme file, and the images and fonts it references, for changes.
+pub struct ThemeWatcher {
+    path: PathBuf,
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeDesc {
+    #[serde(default)]
+    parent: Option<ParentDesc>,
+    #[serde(default)]
+    widgets: HashMap<String, WidgetDesc>,
+    #[serde(default)]
+    focus_ring: Option<FocusRingDesc>,
+}
+
+#[derive(Deserialize)]
+enum ParentDesc {
+    Default,
+    File(PathBuf),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WidgetDesc {
+    #[serde(default)]
+    text: Option<TextDesc>,
+    #[serde(default)]
+    image: Option<ImageDesc>,
+    #[serde(default)]
+    content_margins: Option<MarginsDesc>,
+    #[serde(default)]
+    shape: Option<ShapeDesc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextDesc {
+    #[serde(default)]
+    face: Option<PathBuf>,
+    #[serde(default)]
+    face_index: i32,
+    /// The size of the text, in points.
+    #[serde(default)]
+    face_size: Option<f32>,
+    #[serde(default)]
+    color: Option<(u8, u8, u8, u8)>,
+    #[serde(default)]
+    highlight_bg_color: Option<(u8, u8, u8, u8)>,
+    #[serde(default)]
+    highlight_text_color: Option<(u8, u8, u8, u8)>,
+    #[serde(default)]
+    tab_size: Option<u32>,
+    #[serde(default)]
+    justify: Option<Align2Desc>,
+    #[serde(default)]
+    margins: Option<MarginsDesc>,
+    #[serde(default)]
+    line_wrap: Option<LineWrapDesc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ImageDesc {
+    path: PathBuf,
+    #[serde(default)]
+    rescale: RescaleDesc,
+    #[serde(default)]
+    min_size: Option<(i32, i32)>,
+    #[serde(default)]
+    max_size: Option<(i32, i32)>,
+    #[serde(default)]
+    variants: HashMap<u32, PathBuf>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+struct ShapeDesc {
+    #[serde(default)]
+    fill: Option<FillDesc>,
+    #[serde(default)]
+    border: Option<BorderDesc>,
+    #[serde(default)]
+    shadow: Option<ShadowDesc>,
+    #[serde(default)]
+    corner_radius: u16,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+enum FillDesc {
+    Solid((u8, u8, u8, u8)),
+    LinearGradient {
+        start: (u8, u8, u8, u8),
+        end: (u8, u8, u8, u8),
+        #[serde(default)]
+        angle: u16,
+    },
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+struct BorderDesc {
+    widths: MarginsDesc,
+    color: (u8, u8, u8, u8),
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+struct ShadowDesc {
+    #[serde(default)]
+    offset_x: i16,
+    #[serde(default)]
+    offset_y: i16,
+    #[serde(default)]
+    blur_radius: u16,
+    #[serde(default)]
+    spread: u16,
+    color: (u8, u8, u8, u8),
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+struct FocusRingDesc {
+    color: (u8, u8, u8, u8),
+    width: u16,
+    #[serde(default)]
+    offset: u16,
+    #[serde(default)]
+    corner_radius: u16,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+struct MarginsDesc {
+    #[serde(default)]
+    left: u16,
+    #[serde(default)]
+    top: u16,
+    #[serde(default)]
+    right: u16,
+    #[serde(default)]
+    bottom: u16,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+struct Align2Desc {
+    x: AlignDesc,
+    y: AlignDesc,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+enum AlignDesc {
+    Stretch,
+    Start,
+    End,
+    Center,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+enum LineWrapDesc {
+    None,
+    Normal,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+enum RescaleDesc {
+    Stretch,
+    StretchOnPixelCenter,
+    Slice(MarginsDesc),
+    Align(Align2Desc),
+}
+
+impl Default for RescaleDesc {
+    fn default() -> RescaleDesc {
+        RescaleDesc::Stretch
+    }
+}
+
+impl From<MarginsDesc> for Margins<u16> {
+    fn from(m: MarginsDesc) -> Margins<u16> {
+        Margins::new(m.left, m.top, m.right, m.bottom)
+    }
+}
+
+impl From<AlignDesc> for Align {
+    fn from(a: AlignDesc) -> Align {
+        match a {
+            AlignDesc::Stretch => Align::Stretch,
+            AlignDesc::Start => Align::Start,
+            AlignDesc::End => Align::End,
+            AlignDesc::Center => Align::Center,
+        }
+    }
+}
+
+impl From<Align2Desc> for Align2 {
+    fn from(a: Align2Desc) -> Align2 {
+        Align2::new(a.x.into(), a.y.into())
+    }
+}
+
+impl From<LineWrapDesc> for LineWrap {
+    fn from(l: LineWrapDesc) -> LineWrap {
+        match l {
+            LineWrapDesc::None => LineWrap::None,
+            LineWrapDesc::Normal => LineWrap::Normal,
+        }
+    }
+}
+
+impl From<RescaleDesc> for RescaleRules {
+    fn from(r: RescaleDesc) -> RescaleRules {
+        match r {
+            RescaleDesc::Stretch => RescaleRules::Stretch,
+            RescaleDesc::StretchOnPixelCenter => RescaleRules::StretchOnPixelCenter,
+            RescaleDesc::Slice(margins) => RescaleRules::Slice(margins.into()),
+            RescaleDesc::Align(align) => RescaleRules::Align(align.into()),
+        }
+    }
+}
+
+impl From<ShapeDesc> for Shape {
+    fn from(s: ShapeDesc) -> Shape {
+        Shape {
+            fill: s.fill.map(|fill| match fill {
+                FillDesc::Solid(c) => Fill::Solid(color(c)),
+                FillDesc::LinearGradient{start, end, angle} => Fill::LinearGradient {
+                    start: color(start),
+                    end: color(end),
+                    angle,
+                },
+            }),
+            border: s.border.map(|border| Border {
+                widths: border.widths.into(),
+                color: color(border.color),
+            }),
+            shadow: s.shadow.map(|shadow| Shadow {
+                offset_x: shadow.offset_x,
+                offset_y: shadow.offset_y,
+                blur_radius: shadow.blur_radius,
+                spread: shadow.spread,
+                color: color(shadow.color),
+            }),
+            corner_radius: s.corner_radius,
+        }
+    }
+}
+
+impl From<FocusRingDesc> for FocusRing {
+    fn from(f: FocusRingDesc) -> FocusRing {
+        FocusRing {
+            color: color(f.color),
+            width: f.width,
+            offset: f.offset,
+            corner_radius: f.corner_radius,
+        }
+    }
+}
+
+fn color((r, g, b, a): (u8, u8, u8, u8)) -> Rgba<u8> {
+    Rgba::new(r, g, b, a)
+}
+
+/// Loads themes, keeping track of every file that gets read.
+struct ThemeLoader {
+    files: Vec<PathBuf>,
+    /// The theme files currently being loaded, used to detect inheritance cycles.
+    loading: Vec<PathBuf>,
+    faces: HashMap<(PathBuf, i32), ThemeFace>,
+    images: HashMap<PathBuf, (Vec<Rgba<u8>>, DimsBox<D2, u32>)>,
+}
+
+impl ThemeLoader {
+    fn new() -> ThemeLoader {
+        ThemeLoader {
+            files: Vec::new(),
+            loading: Vec::new(),
+            faces: HashMap::new(),
+            images: HashMap::new(),
+        }
+    }
+
+    fn load_file(&mut self, path: &Path) -> Result<Theme, ThemeLoadError> {
+        let canonical_path = path.canonicalize().map_err(|e| ThemeLoadError::Io(path.to_owned(), e))?;
+        if self.loading.contains(&canonical_path) {
+            return Err(ThemeLoadError::InheritanceCycle(path.to_owned()));
+        }
+
+        let source = fs::read_to_string(path).map_err(|e| ThemeLoadError::Io(path.to_owned(), e))?;
+        self.files.push(path.to_owned());
+        self.loading.push(canonical_path);
+        let theme = self.load_str(&source, path.parent().unwrap_or(Path::new("")));
+        self.loading.pop();
+        theme
+    }
+
+    fn load_str(&mut self, source: &str, theme_dir: &Path) -> Result<Theme, ThemeLoadError> {
+        let desc: ThemeDesc = ron::de::from_str(source).map_err(ThemeLoadError::Parse)?;
+
+        let mut theme = match desc.parent {
+            Some(ParentDesc::Default) => Theme::with_parent(Rc::new(Theme::default())),
+            Some(ParentDesc::File(parent_path)) => Theme::with_parent(Rc::new(self.load_file(&theme_dir.join(parent_path))?)),
+            None => Theme::empty()
+        };
+        for (key, widget) in desc.widgets {
+            let text = match widget.text {
+                Some(text) => Some(self.load_text(text, theme_dir)?),
+                None => None
+            };
+            let image = match widget.image {
+                Some(image) => Some(self.load_image(image, theme_dir)?),
+                None => None
+            };
+            theme.insert_widget(key, PartialThemeWidget {
+                text,
+                image,
+                content_margins: widget.content_margins.map(Margins::from),
+                shape: widget.shape.map(Shape::from),
+            });
+        }
+        if let Some(focus_ring) = desc.focus_ring {
+            theme.set_focus_ring(Some(focus_ring.into()));
+        }
+
+        Ok(theme)
+    }
+
+    fn load_text(&mut self, text: TextDesc, theme_dir: &Path) -> Result<PartialThemeText, ThemeLoadError> {
+        let face = match text.face {
+            Some(face) => Some(self.load_face(theme_dir.join(face), text.face_index)?),
+            None => None
+        };
+
+        Ok(PartialThemeText {
+            face,
+            color: text.color.map(color),
+            highlight_bg_color: text.highlight_bg_color.map(color),
+            highlight_text_color: text.highlight_text_color.map(color),
+            face_size: text.face_size.map(|size| (size * 64.).round() as u32),
+            tab_size: text.tab_size,
+            justify: text.justify.map(Align2::from),
+            margins: text.margins.map(Margins::from),
+            line_wrap: text.line_wrap.map(LineWrap::from),
+        })
+    }
+
+    fn load_face(&mut self, face_path: PathBuf, face_index: i32) -> Result<ThemeFace, ThemeLoadError> {
+        if let Some(face) = self.faces.get(&(face_path.clone(), face_index)) {
+            return Ok(face.clone());
+        }
+
+        let face = ThemeFace::Path(
+            ThemeFacePath::new(&face_path, face_index)
+                .map_err(|e| ThemeLoadError::Io(face_path.clone(), e))?
+        );
+        self.files.push(face_path.clone());
+        self.faces.insert((face_path, face_index), face.clone());
+        Ok(face)
+    }
+
+    fn load_image(&mut self, image: ImageDesc, theme_dir: &Path) -> Result<Rc<Image>, ThemeLoadError> {
+        let (pixels, dims) = self.load_pixels(theme_dir.join(&image.path))?;
+
+        let mut variants = Vec::with_capacity(image.variants.len());
+        for (scale, path) in image.variants {
+            let (pixels, dims) = self.load_pixels(theme_dir.join(path))?;
+            variants.push(ImageVariant{ scale, pixels, dims });
+        }
+        variants.sort_by_key(|v| v.scale);
+
+        let mut size_bounds = SizeBounds::default();
+        if let Some((width, height)) = image.min_size {
+            size_bounds.min = DimsBox::new2(width, height);
+        }
+        if let Some((width, height)) = image.max_size {
+            size_bounds.max = DimsBox::new2(width, height);
+        }
+
+        Ok(Rc::new(Image {
+            pixels,
+            dims,
+            rescale: image.rescale.into(),
+            size_bounds,
+            variants,
+        }))
+    }
+
+    fn load_pixels(&mut self, image_path: PathBuf) -> Result<(Vec<Rgba<u8>>, DimsBox<D2, u32>), ThemeLoadError> {
+        if let Some(loaded) = self.images.get(&image_path) {
+            return Ok(loaded.clone());
+        }
+
+        self.files.push(image_path.clone());
+        let loaded = decode_png(&image_path)?;
+        self.images.insert(image_path, loaded.clone());
+        Ok(loaded)
+    }
+}
+
+fn decode_png(path: &Path) -> Result<(Vec<Rgba<u8>>, DimsBox<D2, u32>), ThemeLoadError> {
+    let file = File::open(path).map_err(|e| ThemeLoadError::Io(path.to_owned(), e))?;
+    let decoder = png::Decoder::new(file);
+    let (info, mut reader) = decoder.read_info().map_err(|e| ThemeLoadError::Image(path.to_owned(), e))?;
+    let mut buf = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buf).map_err(|e| ThemeLoadError::Image(path.to_owned(), e))?;
+
+    let pixels = match (info.color_type, info.bit_depth) {
+        (png::ColorType::RGBA, png::BitDepth::Eight) =>
+            buf.chunks(4).map(|p| Rgba::new(p[0], p[1], p[2], p[3])).collect(),
+        (png::ColorType::RGB, png::BitDepth::Eight) =>
+            buf.chunks(3).map(|p| Rgba::new(p[0], p[1], p[2], 255)).collect(),
+        (color_type, bit_depth) =>
+            return Err(ThemeLoadError::UnsupportedImageFormat(path.to_owned(), color_type, bit_depth))
+    };
+    Ok((pixels, DimsBox::new2(info.width, info.height)))
+}
+
+/// Load the theme file at `path`, returning the theme and every file it read, including the files
+/// of its parent themes.
+fn load_theme(path: &Path) -> Result<(Theme, Vec<PathBuf>), ThemeLoadError> {
+    let mut loader = ThemeLoader::new();
+    let theme = loader.load_file(path)?;
+    Ok((theme, loader.files))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl Theme {
+    /// Load a theme from the theme file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Theme, ThemeLoadError> {
+        load_theme(path.as_ref()).map(|(theme, _)| theme)
+    }
+
+    /// Parse a theme file, resolving the paths of the images and fonts it references relative to
+    /// `theme_dir`.
+    pub fn parse<P: AsRef<Path>>(source: &str, theme_dir: P) -> Result<Theme, ThemeLoadError> {
+        ThemeLoader::new().load_str(source, theme_dir.as_ref())
+    }
+}
+
+impl ThemeWatcher {
+    /// Load the theme file at `path`, and start watching it for changes.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Result<(ThemeWatcher, Theme), ThemeLoadError> {
+        let path = path.into();
+        let (theme, files) = load_theme(&path)?;
+        let watcher = ThemeWatcher {
+            path,
+            files: files.into_iter().map(|f| {let m = modified(&f); (f, m)}).collect(),
+        };
+        Ok((watcher, theme))
+    }
+
+    /// Retrieve the path of the theme file being watched.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Check whether the theme file, or any of the files it references, have been modified since
+    /// the theme was last loaded. If they have, reload the theme.
+    ///
+    /// Returns `None` if nothing changed.
+    pub fn poll(&mut self) -> Option<Result<Theme, ThemeLoadError>> {
+        let changed = self.files.iter().any(|&(ref file, modified_time)| modified(file) != modified_time);
+        if !changed {
+            return None;
+        }
+
+        match load_theme(&self.path) {
+            Ok((theme, files)) => {
+                self.files = files.into_iter().map(|f| {let m = modified(&f); (f, m)}).collect();
+                Some(Ok(theme))
+            },
+            Err(e) => {
+                // Keep watching the same set of files, so that the theme gets reloaded once the
+                // error gets fixed.
+                for &mut (ref file, ref mut modified_time) in &mut self.files {
+                    *modified_time = modified(file);
+                }
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl fmt::Display for ThemeLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ThemeLoadError::Io(ref path, ref err) => write!(f, "could not read {}: {}", path.display(), err),
+            ThemeLoadError::Parse(ref err) => write!(f, "invalid theme file: {}", err),
+            ThemeLoadError::Image(ref path, ref err) => write!(f, "could not decode {}: {}", path.display(), err),
+            ThemeLoadError::UnsupportedImageFormat(ref path, color_type, bit_depth) =>
+                write!(f, "{} has unsupported format {:?} {:?}", path.display(), color_type, bit_depth),
+            ThemeLoadError::InheritanceCycle(ref path) => write!(f, "{} inherits from itself", path.display()),
+        }
+    }
+}
+
+impl error::Error for ThemeLoadError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::{thread, time::Duration};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("derin_theme_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_png(path: &Path, pixels: &[[u8; 4]], width: u32, height: u32) {
+        let mut encoder = png::Encoder::new(File::create(path).unwrap(), width, height);
+        encoder.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&pixels.concat()).unwrap();
+    }
+
+    /// Rewrite the file at `path` until its modification time changes, since rewriting it right
+    /// away might not move the time on filesystems with coarse timestamps.
+    fn touch(path: &Path, contents: &str) {
+        let old_modified = modified(path);
+        while modified(path) == old_modified {
+            thread::sleep(Duration::from_millis(10));
+            File::create(path).unwrap().write_all(contents.as_bytes()).unwrap();
+        }
+    }
+
+    #[test]
+    fn parse_minimal() {
+        let dir = temp_dir("parse_minimal");
+        write_png(&dir.join("base.png"), &[[255, 0, 0, 255], [0, 0, 255, 255]], 2, 1);
+
+        let theme = Theme::parse(r#"(
+            widgets: {
+                "Button::Normal": (
+                    image: Some((
+                        path: "base.png",
+                        rescale: Slice((left: 1, right: 1)),
+                        min_size: Some((2, 1)),
+                    )),
+                    content_margins: Some((left: 1, top: 2, right: 3, bottom: 4)),
+                ),
+            },
+        )"#, &dir).unwrap();
+
+        let button = theme.widget_theme("Button::Normal");
+        assert_eq!(None, button.text);
+        assert_eq!(Margins::new(1, 2, 3, 4), button.content_margins);
+        let image = button.image.unwrap();
+        assert_eq!(vec![Rgba::new(255, 0, 0, 255), Rgba::new(0, 0, 255, 255)], image.pixels);
+        assert_eq!(DimsBox::new2(2, 1), image.dims);
+        assert_eq!(RescaleRules::Slice(Margins::new(1, 0, 1, 0)), image.rescale);
+        assert_eq!(DimsBox::new2(2, 1), image.size_bounds.min);
+        assert_eq!(SizeBounds::default().max, image.size_bounds.max);
+
+        // Widgets that aren't in the file fall back to an empty theme.
+        assert_eq!(ThemeWidget::default(), theme.widget_theme("Label"));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn parse_relative_paths() {
+        let dir = temp_dir("parse_relative_paths");
+        let result = Theme::parse(r#"(
+            widgets: {
+                "Button::Normal": (image: Some((path: "missing.png"))),
+            },
+        )"#, &dir);
+        match result {
+            Err(ThemeLoadError::Io(ref path, _)) => assert_eq!(&dir.join("missing.png"), path),
+            result => panic!("expected missing image, got {:?}", result.map(|_| ()))
+        }
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn parse_malformed() {
+        let malformed = [
+            "(widgets: {",
+            r#"(widgets: {"Label": (colour: (0, 0, 0, 255))})"#,
+            r#"(widgets: {"Label": (image: Some((path: "a.png", rescale: Squish)))})"#,
+        ];
+        for source in &malformed {
+            match Theme::parse(source, "") {
+                Err(ThemeLoadError::Parse(_)) => (),
+                result => panic!("expected parse error for {:?}, got {:?}", source, result.map(|_| ()))
+            }
+        }
+    }
+
+    #[test]
+    fn parent_chain() {
+        let dir = temp_dir("parent_chain");
+        fs::create_dir_all(dir.join("child")).unwrap();
+        write_png(&dir.join("base.png"), &[[0, 255, 0, 255]], 1, 1);
+        fs::write(dir.join("base.ron"), r#"(
+            parent: Some(Default),
+            widgets: {
+                "Button": (
+                    image: Some((path: "base.png")),
+                    content_margins: Some((left: 1, top: 1, right: 1, bottom: 1)),
+                ),
+            },
+        )"#).unwrap();
+        let child_path = dir.join("child").join("theme.ron");
+        fs::write(&child_path, r#"(
+            parent: Some(File("../base.ron")),
+            widgets: {
+                "Button::Normal": (content_margins: Some((left: 5))),
+            },
+        )"#).unwrap();
+
+        let (mut watcher, theme) = ThemeWatcher::new(&child_path).unwrap();
+
+        // Unset fields get inherited from the ancestor path in the parent file.
+        let normal = theme.widget_theme("Button::Normal");
+        assert_eq!(vec![Rgba::new(0, 255, 0, 255)], normal.image.unwrap().pixels);
+        assert_eq!(Margins::new(5, 0, 0, 0), normal.content_margins);
+        let hover = theme.widget_theme("Button::Hover");
+        assert_eq!(vec![Rgba::new(0, 255, 0, 255)], hover.image.unwrap().pixels);
+        assert_eq!(Margins::new(1, 1, 1, 1), hover.content_margins);
+
+        // Entries that neither file sets come from the default theme.
+        assert_eq!(Theme::default().widget_theme("Label"), theme.widget_theme("Label"));
+
+        // Changing the parent file reloads the child.
+        assert!(watcher.poll().is_none());
+        touch(&dir.join("base.ron"), r#"(widgets: {})"#);
+        let theme = watcher.poll().unwrap().unwrap();
+        assert_eq!(None, theme.widget_theme("Button::Hover").image);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn parent_cycle() {
+        let dir = temp_dir("parent_cycle");
+        fs::write(dir.join("a.ron"), r#"(parent: Some(File("b.ron")))"#).unwrap();
+        fs::write(dir.join("b.ron"), r#"(parent: Some(File("a.ron")))"#).unwrap();
+        fs::write(dir.join("self.ron"), r#"(parent: Some(File("self.ron")))"#).unwrap();
+
+        for name in &["a.ron", "self.ron"] {
+            match Theme::load(dir.join(name)) {
+                Err(ThemeLoadError::InheritanceCycle(ref path)) => assert_eq!(&dir.join(name), path),
+                result => panic!("expected inheritance cycle for {}, got {:?}", name, result.map(|_| ()))
+            }
+        }
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn watcher_poll() {
+        let dir = temp_dir("watcher_poll");
+        let path = dir.join("theme.ron");
+        fs::write(&path, r#"(widgets: {"Label": ()})"#).unwrap();
+
+        let (mut watcher, theme) = ThemeWatcher::new(&path).unwrap();
+        assert_eq!(Margins::default(), theme.widget_theme("Label").content_margins);
+        assert_eq!(&path, watcher.path());
+        assert!(watcher.poll().is_none());
+
+        touch(&path, r#"(widgets: {"Label": (content_margins: Some((left: 2)))})"#);
+        match watcher.poll() {
+            Some(Ok(theme)) => assert_eq!(Margins::new(2, 0, 0, 0), theme.widget_theme("Label").content_margins),
+            result => panic!("expected reloaded theme, got {:?}", result.map(|r| r.map(|_| ())))
+        }
+        assert!(watcher.poll().is_none());
+
+        // Errors get reported once, and the file keeps getting watched until it gets fixed.
+        touch(&path, "(widgets: {");
+        match watcher.poll() {
+            Some(Err(ThemeLoadError::Parse(_))) => (),
+            result => panic!("expected parse error, got {:?}", result.map(|r| r.map(|_| ())))
+        }
+        assert!(watcher.poll().is_none());
+        touch(&path, r#"(widgets: {})"#);
+        assert!(watcher.poll().unwrap().is_ok());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
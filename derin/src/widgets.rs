@@ -9,28 +9,44 @@ pub mod assistants;
 mod button;
 mod check_box;
 mod clip;
+mod combo_box;
+mod dock;
 // mod direct_render;
 mod edit_box;
 mod group;
 mod label;
+mod menu;
 mod progress_bar;
 mod radio_buttons;
-// mod scroll_box;
+mod scroll_box;
 mod slider;
-// mod tabs;
+mod splitter;
+mod table;
+mod tabs;
+mod tooltip;
+mod tree_view;
+mod virtual_list;
 
 pub use self::button::*;
 pub use self::check_box::*;
 pub use self::clip::*;
+pub use self::combo_box::*;
+pub use self::dock::*;
 // pub use self::direct_render::*;
 pub use self::edit_box::*;
 pub use self::group::*;
 pub use self::label::*;
+pub use self::menu::*;
 pub use self::progress_bar::*;
 pub use self::radio_buttons::*;
-// pub use self::scroll_box::*;
+pub use self::scroll_box::*;
 pub use self::slider::*;
-// pub use self::tabs::*;
+pub use self::splitter::*;
+pub use self::table::*;
+pub use self::tabs::*;
+pub use self::tooltip::*;
+pub use self::tree_view::*;
+pub use self::virtual_list::*;
 
 /// The `Widget` trait, as well as associated types used to create custom widgets.
 pub mod custom {
@@ -4,11 +4,12 @@ use derin_core::{
     widget::{Parent, Widget, WidgetInfo, WidgetInfoMut, WidgetIdent, WidgetTag, WidgetRenderable},
     render::{Renderer, RendererLayout, SubFrame, WidgetTheme},
 };
+use crate::theme::ThemePath;
 use crate::widgets::{
     Contents, Label,
     assistants::ButtonState,
 };
-use crate::cgmath::Point2;
+use crate::cgmath::{EuclideanSpace, Point2};
 use cgmath_geometry::{D2, rect::{BoundBox, DimsBox, GeoBox, OffsetBox}};
 use derin_common_types::layout::SizeBounds;
 
@@ -28,10 +29,18 @@ pub struct Toggle<H, T>
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ToggleBoxTheme {
+    pub kind: ToggleKind,
     pub selected: bool,
     pub button_state: ButtonState,
 }
 
+/// Which kind of widget a toggle box belongs to, which determines the images it gets drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToggleKind {
+    CheckBox,
+    RadioButton,
+}
+
 pub trait ToggleOnClickHandler: 'static {
     fn on_click(&mut self, selected: &mut bool);
 }
@@ -43,6 +52,7 @@ struct ToggleBox {
     rect: BoundBox<D2, i32>,
     size_bounds: SizeBounds,
 
+    kind: ToggleKind,
     selected: bool,
     button_state: ButtonState,
 }
@@ -51,12 +61,16 @@ impl<H, T> Toggle<H, T>
     where H: ToggleOnClickHandler,
           T: WidgetTheme + Clone,
 {
-    /// Creates a new `Toggle` with the given selected state, contents, and [toggle handler].
+    /// Creates a new `Toggle` of the given kind, with the given selected state, contents, and
+    /// [toggle handler].
     ///
     /// [toggle handler]: ./trait.ToggleOnClickHandler.html
-    pub fn new(selected: bool, contents: Contents, handler: H, theme: T) -> Toggle<H, T> {
+    pub fn new(kind: ToggleKind, selected: bool, contents: Contents, handler: H, theme: T) -> Toggle<H, T> {
+        let widget_tag = WidgetTag::new();
+        widget_tag.set_focusable(true);
+
         Toggle {
-            widget_tag: WidgetTag::new(),
+            widget_tag,
             rect: BoundBox::new2(0, 0, 0, 0),
 
             tbox: ToggleBox {
@@ -64,6 +78,7 @@ impl<H, T> Toggle<H, T>
                 rect: BoundBox::new2(0, 0, 0, 0),
                 size_bounds: SizeBounds::default(),
 
+                kind,
                 selected,
                 button_state: ButtonState::Normal,
             },
@@ -123,6 +138,12 @@ impl<H, T> Widget for Toggle<H, T>
         &mut self.rect
     }
 
+    /// Draw the focus ring around the box, rather than around the box and the label.
+    #[inline]
+    fn focus_rect(&self) -> BoundBox<D2, i32> {
+        self.tbox.rect + self.rect.min().to_vec()
+    }
+
     fn size_bounds(&self) -> SizeBounds {
         let check_sb = self.tbox.size_bounds;
         let label_sb = self.label.size_bounds();
@@ -230,6 +251,25 @@ impl WidgetTheme for ToggleBoxTheme {
     fn fallback(self) -> Option<!> {None}
 }
 
+impl ThemePath for ToggleBoxTheme {
+    fn theme_path(&self) -> &'static str {
+        match (self.kind, self.selected, self.button_state) {
+            (ToggleKind::CheckBox, false, ButtonState::Normal) => "CheckBox::Empty",
+            (ToggleKind::CheckBox, false, ButtonState::Hover) => "CheckBox::Empty::Hover",
+            (ToggleKind::CheckBox, false, ButtonState::Pressed) => "CheckBox::Empty::Pressed",
+            (ToggleKind::CheckBox, true, ButtonState::Normal) => "CheckBox::Checked",
+            (ToggleKind::CheckBox, true, ButtonState::Hover) => "CheckBox::Checked::Hover",
+            (ToggleKind::CheckBox, true, ButtonState::Pressed) => "CheckBox::Checked::Pressed",
+            (ToggleKind::RadioButton, false, ButtonState::Normal) => "RadioButton::Empty",
+            (ToggleKind::RadioButton, false, ButtonState::Hover) => "RadioButton::Empty::Hover",
+            (ToggleKind::RadioButton, false, ButtonState::Pressed) => "RadioButton::Empty::Pressed",
+            (ToggleKind::RadioButton, true, ButtonState::Normal) => "RadioButton::Selected",
+            (ToggleKind::RadioButton, true, ButtonState::Hover) => "RadioButton::Selected::Hover",
+            (ToggleKind::RadioButton, true, ButtonState::Pressed) => "RadioButton::Selected::Pressed",
+        }
+    }
+}
+
 impl Widget for ToggleBox {
     #[inline]
     fn widget_tag(&self) -> &WidgetTag {
@@ -296,6 +336,7 @@ impl<R> WidgetRenderable<R> for ToggleBox
 
     fn theme(&self) -> ToggleBoxTheme {
         ToggleBoxTheme {
+            kind: self.kind,
             selected: self.selected,
             button_state: self.button_state,
         }
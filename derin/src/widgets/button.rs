@@ -7,6 +7,7 @@ use derin_core::{
     widget::{WidgetTag, WidgetRenderable, Widget},
     render::{Renderer, RendererLayout, SubFrame, WidgetTheme},
 };
+use crate::theme::ThemePath;
 use crate::widgets::{
     Contents,
     assistants::ButtonState,
@@ -44,8 +45,11 @@ pub struct ButtonTheme {
 impl<H> Button<H> {
     /// Creates a new button with the given contents and
     pub fn new(contents: Contents, handler: H) -> Button<H> {
+        let widget_tag = WidgetTag::new();
+        widget_tag.set_focusable(true);
+
         Button {
-            widget_tag: WidgetTag::new(),
+            widget_tag,
             bounds: BoundBox::new2(0, 0, 0, 0),
             state: ButtonState::Normal,
             handler,
@@ -155,3 +159,13 @@ impl WidgetTheme for ButtonTheme {
         None
     }
 }
+
+impl ThemePath for ButtonTheme {
+    fn theme_path(&self) -> &'static str {
+        match self.state {
+            ButtonState::Normal => "Button::Normal",
+            ButtonState::Hover => "Button::Hover",
+            ButtonState::Pressed => "Button::Pressed",
+        }
+    }
+}
@@ -7,9 +7,10 @@ use derin_core::{
     widget::{WidgetTag, WidgetRenderable, Widget},
     render::{Renderer, WidgetTheme},
 };
+use crate::theme::ThemePath;
 use crate::widgets::{
     Contents,
-    assistants::toggle_button::{Toggle, ToggleOnClickHandler},
+    assistants::toggle_button::{Toggle, ToggleKind, ToggleOnClickHandler},
 };
 use cgmath_geometry::{D2, rect::BoundBox};
 use derin_common_types::layout::SizeBounds;
@@ -40,7 +41,7 @@ impl<H: CheckToggleHandler> CheckBox<H> {
     /// [toggle handler]: ./trait.CheckToggleHandler.html
     pub fn new(checked: bool, contents: Contents, handler: H) -> CheckBox<H> {
         CheckBox {
-            toggle: Toggle::new(checked, contents, handler, CheckBoxTheme(())),
+            toggle: Toggle::new(ToggleKind::CheckBox, checked, contents, handler, CheckBoxTheme(())),
         }
     }
 
@@ -89,6 +90,11 @@ impl<H> Widget for CheckBox<H>
         self.toggle.rect_mut()
     }
 
+    #[inline]
+    fn focus_rect(&self) -> BoundBox<D2, i32> {
+        self.toggle.focus_rect()
+    }
+
     fn size_bounds(&self) -> SizeBounds {
         self.toggle.size_bounds()
     }
@@ -122,6 +128,12 @@ impl WidgetTheme for CheckBoxTheme {
     fn fallback(self) -> Option<!> {None}
 }
 
+impl ThemePath for CheckBoxTheme {
+    fn theme_path(&self) -> &'static str {
+        "CheckBox"
+    }
+}
+
 impl<H: CheckToggleHandler> ToggleOnClickHandler for H {
     fn on_click(&mut self, checked: &mut bool) {
         *checked = !*checked;
@@ -8,9 +8,11 @@ use derin_core::{
     widget::{WidgetIdent, WidgetRenderable, WidgetTag, WidgetInfo, WidgetInfoMut, Widget, Parent},
     render::{Renderer, SubFrame, WidgetTheme},
 };
+use crate::theme::ThemePath;
 
 use crate::cgmath::EuclideanSpace;
 use cgmath_geometry::{D2, rect::{BoundBox, GeoBox}};
+use derin_common_types::layout::SizeBounds;
 
 /// Assistant widget that is used to clip another widget
 ///
@@ -20,6 +22,8 @@ pub struct Clip<W> {
     widget_tag: WidgetTag,
     rect: BoundBox<D2, i32>,
     widget: W,
+    /// The clipped widget's size bounds, as of the last time the clip was laid out.
+    widget_size_bounds: SizeBounds,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -32,6 +36,7 @@ impl<W> Clip<W> {
             widget_tag: WidgetTag::new(),
             rect: BoundBox::new2(0, 0, 0, 0),
             widget,
+            widget_size_bounds: SizeBounds::default(),
         }
     }
 
@@ -141,6 +146,13 @@ impl<W, R> WidgetRenderable<R> for Clip<W>
         let widget_rect = self.widget.rect();
         let size_bounds = self.widget.size_bounds();
 
+        // The clip hides the widget's size bounds from the clip's parent, so let the parent know
+        // when they change.
+        if size_bounds != self.widget_size_bounds {
+            self.widget_size_bounds = size_bounds;
+            self.widget_tag.request_relayout_parent();
+        }
+
         let dims_clipped = size_bounds.bound_rect(widget_rect.dims());
         if dims_clipped.dims() != widget_rect.dims() {
             *self.widget.rect_mut() = BoundBox::from(dims_clipped) + widget_rect.min().to_vec();
@@ -154,3 +166,9 @@ impl WidgetTheme for ClipTheme {
         None
     }
 }
+
+impl ThemePath for ClipTheme {
+    fn theme_path(&self) -> &'static str {
+        "Clip"
+    }
+}
@@ -0,0 +1,947 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use derin_core::{
+    LoopFlow,
+    event::{EventOps, WidgetEvent, WidgetEventSourced, InputState, MouseHoverChange, MouseButton, FocusChange, Key},
+    overlay::{Overlay, OverlayDismissed},
+    timer::{Timer, TimerId},
+    widget::{MessageTarget, WidgetIdent, WidgetTag, WidgetRenderable, WidgetInfo, WidgetInfoMut, WidgetId, Widget, Parent},
+    render::{Renderer, RendererLayout, SubFrame, WidgetTheme},
+};
+use crate::theme::ThemePath;
+use crate::widgets::{
+    Contents,
+    assistants::ButtonState,
+};
+
+use cgmath_geometry::{D2, rect::{BoundBox, DimsBox, GeoBox}};
+use derin_common_types::layout::SizeBounds;
+use std::time::Duration;
+
+/// A drop-down list that lets the user pick one of several items.
+///
+/// Clicking the box, or pressing `Enter` or `Space` while it has keyboard focus, opens a scrollable
+/// popup list of the items. While the box has focus, typing the start of an item's text jumps to
+/// that item. Whenever the selected item changes, the provided handler's [`on_select`] function is
+/// called.
+///
+/// [`on_select`]: ./trait.ComboBoxHandler.html#tymethod.on_select
+#[derive(Debug, Clone)]
+pub struct ComboBox<H: ComboBoxHandler> {
+    widget_tag: WidgetTag,
+    bounds: BoundBox<D2, i32>,
+    state: ButtonState,
+    size_bounds: SizeBounds,
+
+    selected: Option<usize>,
+    open: bool,
+    search: String,
+    search_timer: Option<TimerId>,
+    list: ComboBoxList,
+
+    pub handler: H,
+}
+
+/// Determines which action, if any, should be taken when the selected item changes.
+pub trait ComboBoxHandler: 'static {
+    type Action: 'static;
+
+    fn on_select(&mut self, old_index: Option<usize>, new_index: usize) -> Option<Self::Action>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ComboBoxTheme {
+    pub state: ButtonState,
+    pub open: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ComboBoxListTheme(());
+
+#[derive(Debug, Clone, Copy)]
+pub struct ComboBoxItemTheme {
+    pub highlighted: bool,
+    pub selected: bool,
+}
+
+/// The popup list shown while the combo box is open.
+#[derive(Debug, Clone)]
+struct ComboBoxList {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    items: Vec<ComboBoxItem>,
+    highlighted: Option<usize>,
+    visible_items: usize,
+    min_width: i32,
+    scroll: i32,
+    scroll_to: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct ComboBoxItem {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    contents: Contents,
+    highlighted: bool,
+    selected: bool,
+}
+
+/// Sent from an item to the list that contains it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ItemEvent {
+    Hover(WidgetId),
+    Click(WidgetId),
+    /// Scroll wheel input over an item, which the list handles on the item's behalf.
+    Scroll(ScrollDelta),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScrollDelta {
+    Lines(i32),
+    Px(i32),
+}
+
+/// Sent from the list to the combo box that owns it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ItemChosen(usize);
+
+const SEARCH_RESET: Duration = Duration::from_secs(1);
+
+impl<H: ComboBoxHandler> ComboBox<H> {
+    /// Creates a new combo box with the given items, selected item, and action handler.
+    pub fn new(items: Vec<Contents>, selected: Option<usize>, handler: H) -> ComboBox<H> {
+        let mut widget_tag = WidgetTag::new();
+        widget_tag.register_message(Self::on_item_chosen);
+        widget_tag.register_message(Self::on_overlay_dismissed);
+        widget_tag.set_focusable(true);
+
+        ComboBox {
+            widget_tag,
+            bounds: BoundBox::new2(0, 0, 0, 0),
+            state: ButtonState::Normal,
+            size_bounds: SizeBounds::default(),
+
+            selected,
+            open: false,
+            search: String::new(),
+            search_timer: None,
+            list: ComboBoxList::new(items),
+
+            handler,
+        }
+    }
+
+    /// Retrieves the index of the selected item.
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Retrieves the index of the selected item, for mutation.
+    ///
+    /// Changing the selection through this function doesn't call the handler. Calling this
+    /// function forces the combo box to be re-drawn, so you're discouraged from calling it unless
+    /// you're actually changing the selection.
+    pub fn selected_mut(&mut self) -> &mut Option<usize> {
+        self.widget_tag.request_redraw().request_relayout();
+        &mut self.selected
+    }
+
+    /// Retrieves the number of items in the combo box.
+    pub fn num_items(&self) -> usize {
+        self.list.items.len()
+    }
+
+    /// Retrieves the contents of the item at `index`.
+    pub fn item(&self, index: usize) -> Option<&Contents> {
+        self.list.items.get(index).map(|i| &i.contents)
+    }
+
+    /// Retrieves the contents of the item at `index`, for mutation.
+    ///
+    /// Calling this function forces the combo box to be re-drawn, so you're discouraged from
+    /// calling it unless you're actually changing the contents.
+    pub fn item_mut(&mut self, index: usize) -> Option<&mut Contents> {
+        self.widget_tag.request_redraw().request_relayout();
+        self.list.items.get_mut(index).map(|i| i.contents_mut())
+    }
+
+    /// Adds an item to the end of the list.
+    pub fn push_item(&mut self, contents: Contents) {
+        let index = self.list.items.len();
+        self.insert_item(index, contents);
+    }
+
+    /// Inserts an item at `index`, shifting the items after it down.
+    ///
+    /// Panics if `index > num_items()`.
+    pub fn insert_item(&mut self, index: usize, contents: Contents) {
+        self.list.items.insert(index, ComboBoxItem::new(contents));
+        match self.selected {
+            Some(ref mut selected) if *selected >= index => *selected += 1,
+            _ => ()
+        }
+        self.close();
+        self.list.widget_tag.request_relayout();
+        self.widget_tag.request_redraw().request_relayout();
+    }
+
+    /// Removes the item at `index`, shifting the items after it up. If the removed item was
+    /// selected, the combo box is left without a selection.
+    ///
+    /// Panics if `index >= num_items()`.
+    pub fn remove_item(&mut self, index: usize) -> Contents {
+        let item = self.list.items.remove(index);
+        self.selected = match self.selected {
+            Some(selected) if selected == index => None,
+            Some(selected) if selected > index => Some(selected - 1),
+            selected => selected
+        };
+        self.close();
+        self.list.widget_tag.request_relayout();
+        self.widget_tag.request_redraw().request_relayout();
+        item.contents
+    }
+
+    /// Retrieves the maximum number of items shown in the popup list before it starts scrolling.
+    pub fn visible_items(&self) -> usize {
+        self.list.visible_items
+    }
+
+    /// Retrieves the maximum number of items shown in the popup list before it starts scrolling,
+    /// for mutation.
+    pub fn visible_items_mut(&mut self) -> &mut usize {
+        self.list.widget_tag.request_relayout();
+        &mut self.list.visible_items
+    }
+
+    /// Retrieves whether or not the popup list is open.
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    fn open(&mut self) {
+        if self.open || self.list.items.is_empty() {
+            return;
+        }
+
+        let overlay = Overlay::popup(self.widget_tag.widget_id());
+        if self.widget_tag.show_overlay(self.list.widget_tag.widget_id(), overlay).is_ok() {
+            self.open = true;
+            self.list.min_width = self.bounds.width();
+            self.list.set_selected(self.selected);
+            self.list.set_highlighted(self.selected);
+            self.list.scroll_to = self.selected;
+            self.list.widget_tag.request_relayout();
+            self.widget_tag.request_redraw().request_relayout();
+        }
+    }
+
+    fn close(&mut self) {
+        if !self.open {
+            return;
+        }
+
+        self.open = false;
+        self.widget_tag.hide_overlay(self.list.widget_tag.widget_id());
+        self.widget_tag.request_redraw().request_relayout();
+    }
+
+    fn select(&mut self, index: usize) {
+        if index >= self.list.items.len() || Some(index) == self.selected {
+            return;
+        }
+
+        let old_index = self.selected;
+        self.selected = Some(index);
+        self.list.set_selected(self.selected);
+        self.widget_tag.request_redraw().request_relayout();
+        if let Some(action) = self.handler.on_select(old_index, index) {
+            self.widget_tag.broadcast_message(action);
+        }
+    }
+
+    /// The item keyboard navigation starts from: the highlighted item while the list is open, and
+    /// the selected item while it's closed.
+    fn cursor(&self) -> Option<usize> {
+        match self.open {
+            true => self.list.highlighted,
+            false => self.selected
+        }
+    }
+
+    fn move_cursor(&mut self, index: usize) {
+        match self.open {
+            true => {
+                self.list.set_highlighted(Some(index));
+                self.list.scroll_to = Some(index);
+                self.list.widget_tag.request_relayout();
+            },
+            false => self.select(index)
+        }
+    }
+
+    /// Add `c` to the type-ahead search string, and move to the next item starting with the string.
+    fn search_char(&mut self, c: char) {
+        if let Some(timer_id) = self.search_timer.take() {
+            self.widget_tag.timers_mut().remove(&timer_id);
+        }
+        let timer_id = TimerId::new();
+        self.widget_tag.timers_mut().insert(timer_id, Timer::new(SEARCH_RESET));
+        self.search_timer = Some(timer_id);
+
+        self.search.extend(c.to_lowercase());
+
+        // Typing the same character repeatedly cycles through the items starting with it.
+        let mut chars = self.search.chars();
+        let first = chars.next();
+        let repeated = chars.all(|c| Some(c) == first);
+        let (search, start_offset) = match repeated {
+            true => (&self.search[..first.map(|c| c.len_utf8()).unwrap_or(0)], 1),
+            false => (&self.search[..], 0)
+        };
+
+        let num_items = self.list.items.len();
+        let start = self.cursor().map(|c| c + start_offset).unwrap_or(0);
+        let found = (0..num_items)
+            .map(|i| (start + i) % num_items)
+            .find(|&i| match self.list.items[i].contents {
+                Contents::Text(ref text) => text.to_lowercase().starts_with(search),
+                Contents::Icon(_) => false
+            });
+
+        if let Some(index) = found {
+            self.move_cursor(index);
+        }
+    }
+
+    fn on_item_chosen(&mut self, chosen: &ItemChosen) {
+        self.select(chosen.0);
+        self.close();
+    }
+
+    fn on_overlay_dismissed(&mut self, dismissed: &OverlayDismissed) {
+        if dismissed.overlay == self.list.widget_tag.widget_id() {
+            self.open = false;
+            self.state = ButtonState::Normal;
+            self.widget_tag.request_redraw().request_relayout();
+        }
+    }
+}
+
+impl ComboBoxList {
+    fn new(items: Vec<Contents>) -> ComboBoxList {
+        let mut widget_tag = WidgetTag::new();
+        widget_tag.register_message(Self::on_item_event);
+
+        ComboBoxList {
+            widget_tag,
+            rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+
+            items: items.into_iter().map(ComboBoxItem::new).collect(),
+            highlighted: None,
+            visible_items: 8,
+            min_width: 0,
+            scroll: 0,
+            scroll_to: None,
+        }
+    }
+
+    fn set_highlighted(&mut self, highlighted: Option<usize>) {
+        self.highlighted = highlighted;
+        for (i, item) in self.items.iter_mut().enumerate() {
+            item.set_flags(Some(i) == highlighted, item.selected);
+        }
+    }
+
+    fn set_selected(&mut self, selected: Option<usize>) {
+        for (i, item) in self.items.iter_mut().enumerate() {
+            item.set_flags(item.highlighted, Some(i) == selected);
+        }
+    }
+
+    fn on_item_event(&mut self, event: &ItemEvent) {
+        match *event {
+            ItemEvent::Hover(item_id) => {
+                let index = self.items.iter().position(|i| i.widget_tag.widget_id() == item_id);
+                if index.is_some() {
+                    self.set_highlighted(index);
+                }
+            },
+            ItemEvent::Click(item_id) => {
+                if let Some(index) = self.items.iter().position(|i| i.widget_tag.widget_id() == item_id) {
+                    let list_id = self.widget_tag.widget_id();
+                    self.widget_tag.send_message_to(ItemChosen(index), MessageTarget::ParentOf(list_id));
+                }
+            },
+            ItemEvent::Scroll(delta) => self.scroll_by(delta)
+        }
+    }
+
+    fn scroll_by(&mut self, delta: ScrollDelta) {
+        let line_height = self.items.get(0).map(|i| i.size_bounds.min.height()).unwrap_or(24);
+        self.scroll -= match delta {
+            ScrollDelta::Lines(lines) => line_height * lines,
+            ScrollDelta::Px(px) => px
+        };
+        self.widget_tag.request_relayout();
+    }
+}
+
+impl ComboBoxItem {
+    fn new(contents: Contents) -> ComboBoxItem {
+        ComboBoxItem {
+            widget_tag: WidgetTag::new(),
+            rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+
+            contents,
+            highlighted: false,
+            selected: false,
+        }
+    }
+
+    fn contents_mut(&mut self) -> &mut Contents {
+        self.widget_tag.request_redraw().request_relayout();
+        &mut self.contents
+    }
+
+    fn set_flags(&mut self, highlighted: bool, selected: bool) {
+        if (highlighted, selected) != (self.highlighted, self.selected) {
+            self.highlighted = highlighted;
+            self.selected = selected;
+            self.widget_tag.request_redraw();
+        }
+    }
+}
+
+impl<H> Widget for ComboBox<H>
+    where H: ComboBoxHandler
+{
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.bounds
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.bounds
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        use self::WidgetEvent::*;
+        // Events bubbled up from the popup list have already been handled there.
+        if event.is_bubble() {
+            return EventOps {
+                focus: None,
+                bubble: event.default_bubble(),
+            };
+        }
+        let event = event.unwrap();
+
+        let mut focus = None;
+        let mut bubble = event.default_bubble();
+        let new_state = match event {
+            MouseMove{hover_change: Some(ref change), ..} => match change {
+                MouseHoverChange::Enter => ButtonState::Hover,
+                MouseHoverChange::Exit => ButtonState::Normal,
+                _ => self.state
+            },
+            MouseDown{in_widget: true, button: MouseButton::Left, ..} => {
+                focus = Some(FocusChange::Take);
+                ButtonState::Pressed
+            },
+            // Clicks outside of the box while the list is open land on the list, so the box keeps
+            // focus.
+            MouseDown{in_widget: false, ..} if !self.open => {
+                focus = Some(FocusChange::Remove);
+                self.state
+            },
+            MouseUp{in_widget: true, pressed_in_widget: true, button: MouseButton::Left, ..} => {
+                match self.open {
+                    true => self.close(),
+                    false => self.open()
+                }
+                ButtonState::Hover
+            },
+            MouseUp{in_widget: false, ..} => ButtonState::Normal,
+            KeyDown(key, _) => {
+                bubble = false;
+                let num_items = self.list.items.len();
+                // Spaces typed during a type-ahead search are part of the search.
+                let searching_space = key == Key::Space && !self.search.is_empty();
+                match (key, self.open) {
+                    (Key::Enter, false) |
+                    (Key::Space, false) if !searching_space => self.open(),
+                    (Key::Enter, true) |
+                    (Key::Space, true) if !searching_space => {
+                        if let Some(highlighted) = self.list.highlighted {
+                            self.select(highlighted);
+                        }
+                        self.close();
+                    },
+                    (Key::Escape, true) => self.close(),
+                    (Key::UArrow, _) if num_items > 0 => {
+                        let index = self.cursor().map(|c| c.saturating_sub(1)).unwrap_or(0);
+                        self.move_cursor(index);
+                    },
+                    (Key::DArrow, _) if num_items > 0 => {
+                        let index = self.cursor().map(|c| (c + 1).min(num_items - 1)).unwrap_or(0);
+                        self.move_cursor(index);
+                    },
+                    (Key::Home, _) if num_items > 0 => self.move_cursor(0),
+                    (Key::End, _) if num_items > 0 => self.move_cursor(num_items - 1),
+                    (Key::Space, _) => (),
+                    _ => bubble = true
+                }
+                self.state
+            },
+            Char(c) => {
+                // Spaces only count towards the search if a search is already in progress.
+                if !c.is_control() && (c != ' ' || !self.search.is_empty()) && !self.list.items.is_empty() {
+                    bubble = false;
+                    self.search_char(c);
+                }
+                self.state
+            },
+            Timer{timer_id, ..} if Some(timer_id) == self.search_timer => {
+                self.widget_tag.timers_mut().remove(&timer_id);
+                self.search_timer = None;
+                self.search.clear();
+                self.state
+            },
+            LoseFocus => {
+                self.close();
+                ButtonState::Normal
+            },
+            _ => self.state
+        };
+
+        if new_state != self.state {
+            self.widget_tag.request_redraw();
+            self.state = new_state;
+        }
+
+        EventOps {
+            focus,
+            bubble,
+        }
+    }
+}
+
+impl<H> Parent for ComboBox<H>
+    where H: ComboBoxHandler
+{
+    fn num_children(&self) -> usize {
+        self.open as usize
+    }
+
+    fn framed_child<R: Renderer>(&self, widget_ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
+        match (widget_ident, self.open) {
+            (WidgetIdent::Num(0), true) => Some(WidgetInfo::new(WidgetIdent::Num(0), 0, &self.list)),
+            _ => None
+        }
+    }
+    fn framed_child_mut<R: Renderer>(&mut self, widget_ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
+        match (widget_ident, self.open) {
+            (WidgetIdent::Num(0), true) => Some(WidgetInfoMut::new(WidgetIdent::Num(0), 0, &mut self.list)),
+            _ => None
+        }
+    }
+
+    fn framed_children<'a, R, G>(&'a self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
+    {
+        if self.open {
+            let _ = for_each(WidgetInfo::new(WidgetIdent::Num(0), 0, &self.list));
+        }
+    }
+
+    fn framed_children_mut<'a, R, G>(&'a mut self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
+    {
+        if self.open {
+            let _ = for_each(WidgetInfoMut::new(WidgetIdent::Num(0), 0, &mut self.list));
+        }
+    }
+
+    fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
+        match (index, self.open) {
+            (0, true) => Some(WidgetInfo::new(WidgetIdent::Num(0), 0, &self.list)),
+            _ => None
+        }
+    }
+    fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
+        match (index, self.open) {
+            (0, true) => Some(WidgetInfoMut::new(WidgetIdent::Num(0), 0, &mut self.list)),
+            _ => None
+        }
+    }
+}
+
+impl Widget for ComboBoxList {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        self.widget_tag.request_relayout();
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        let event = event.unwrap();
+        let scroll_delta = scroll_delta(&event);
+        if let Some(scroll_delta) = scroll_delta {
+            self.scroll_by(scroll_delta);
+        }
+
+        EventOps {
+            focus: None,
+            bubble: scroll_delta.is_none() && event.default_bubble(),
+        }
+    }
+}
+
+fn scroll_delta(event: &WidgetEvent) -> Option<ScrollDelta> {
+    match *event {
+        WidgetEvent::MouseScrollLines{dir, ..} => Some(ScrollDelta::Lines(dir.y)),
+        WidgetEvent::MouseScrollPx{dir, ..} => Some(ScrollDelta::Px(dir.y)),
+        _ => None
+    }
+}
+
+impl Parent for ComboBoxList {
+    fn num_children(&self) -> usize {
+        self.items.len()
+    }
+
+    fn framed_child<R: Renderer>(&self, widget_ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(index) => self.framed_child_by_index(index as usize),
+            _ => None
+        }
+    }
+    fn framed_child_mut<R: Renderer>(&mut self, widget_ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(index) => self.framed_child_by_index_mut(index as usize),
+            _ => None
+        }
+    }
+
+    fn framed_children<'a, R, G>(&'a self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
+    {
+        for (index, item) in self.items.iter().enumerate() {
+            match for_each(WidgetInfo::new(WidgetIdent::Num(index as u32), index, item)) {
+                LoopFlow::Continue => (),
+                LoopFlow::Break => return
+            }
+        }
+    }
+
+    fn framed_children_mut<'a, R, G>(&'a mut self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
+    {
+        for (index, item) in self.items.iter_mut().enumerate() {
+            match for_each(WidgetInfoMut::new(WidgetIdent::Num(index as u32), index, item)) {
+                LoopFlow::Continue => (),
+                LoopFlow::Break => return
+            }
+        }
+    }
+
+    fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
+        self.items.get(index).map(|item| WidgetInfo::new(WidgetIdent::Num(index as u32), index, item))
+    }
+    fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
+        self.items.get_mut(index).map(|item| WidgetInfoMut::new(WidgetIdent::Num(index as u32), index, item))
+    }
+}
+
+impl Widget for ComboBoxItem {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        let event = event.unwrap();
+        let item_id = self.widget_tag.widget_id();
+
+        let item_event = match event {
+            WidgetEvent::MouseMove{hover_change: Some(MouseHoverChange::Enter), ..} => Some(ItemEvent::Hover(item_id)),
+            WidgetEvent::MouseUp{in_widget: true, pressed_in_widget: true, button: MouseButton::Left, ..} => Some(ItemEvent::Click(item_id)),
+            _ => scroll_delta(&event).map(ItemEvent::Scroll)
+        };
+        if let Some(item_event) = item_event {
+            self.widget_tag.send_message_to(item_event, MessageTarget::ParentOf(item_id));
+        }
+
+        EventOps {
+            focus: None,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
+impl<R, H> WidgetRenderable<R> for ComboBox<H>
+    where R: Renderer,
+          H: ComboBoxHandler
+{
+    type Theme = ComboBoxTheme;
+
+    fn theme(&self) -> ComboBoxTheme {
+        ComboBoxTheme {
+            state: self.state,
+            open: self.open,
+        }
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        match self.selected.and_then(|i| self.list.items.get(i)) {
+            Some(ComboBoxItem{contents: Contents::Text(ref s), ..}) => layout.prepare_string(s),
+            Some(ComboBoxItem{contents: Contents::Icon(ref i), ..}) => layout.prepare_icon(i),
+            None => layout.prepare_string("")
+        }
+
+        let result = layout.finish();
+        self.size_bounds = result.size_bounds;
+        if self.open && self.list.min_width != self.bounds.width() {
+            self.list.min_width = self.bounds.width();
+            self.list.widget_tag.request_relayout();
+        }
+    }
+}
+
+impl<R> WidgetRenderable<R> for ComboBoxList
+    where R: Renderer
+{
+    type Theme = ComboBoxListTheme;
+
+    fn theme(&self) -> ComboBoxListTheme {
+        ComboBoxListTheme(())
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        let result = layout.finish();
+        let content_rect = result.content_rect;
+        let (margin_left, margin_top) = (content_rect.min.x, content_rect.min.y);
+        let margin_width = margin_left + (self.rect.width() - content_rect.max.x);
+        let margin_height = margin_top + (self.rect.height() - content_rect.max.y);
+
+        let item_width = self.items.iter()
+            .map(|i| i.size_bounds.min.width())
+            .fold(self.min_width - margin_width, i32::max);
+        let item_height = |i: &ComboBoxItem| i.size_bounds.min.height();
+        let visible_height = self.items.iter().take(self.visible_items).map(item_height).sum::<i32>();
+        let total_height = self.items.iter().map(item_height).sum::<i32>();
+
+        // Scroll just far enough to bring the requested item into view.
+        if let Some(index) = self.scroll_to.take() {
+            let item_top = self.items.iter().take(index).map(item_height).sum::<i32>();
+            let item_bottom = item_top + self.items.get(index).map(item_height).unwrap_or(0);
+            if item_top < self.scroll {
+                self.scroll = item_top;
+            } else if item_bottom > self.scroll + visible_height {
+                self.scroll = item_bottom - visible_height;
+            }
+        }
+        self.scroll = self.scroll.min(total_height - visible_height).max(0);
+
+        let mut y = margin_top - self.scroll;
+        for item in &mut self.items {
+            let height = item_height(item);
+            let rect = BoundBox::new2(margin_left, y, margin_left + item_width, y + height);
+            if item.rect != rect {
+                item.rect = rect;
+                item.widget_tag.request_relayout();
+            }
+            y += height;
+        }
+
+        let dims = DimsBox::new2(
+            (item_width + margin_width).max(result.size_bounds.min.width()),
+            (visible_height + margin_height).max(result.size_bounds.min.height()),
+        );
+        self.size_bounds = SizeBounds::new(dims, dims);
+    }
+}
+
+impl<R> WidgetRenderable<R> for ComboBoxItem
+    where R: Renderer
+{
+    type Theme = ComboBoxItemTheme;
+
+    fn theme(&self) -> ComboBoxItemTheme {
+        ComboBoxItemTheme {
+            highlighted: self.highlighted,
+            selected: self.selected,
+        }
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        match self.contents {
+            Contents::Text(ref s) => layout.prepare_string(s),
+            Contents::Icon(ref i) => layout.prepare_icon(i),
+        }
+
+        let result = layout.finish();
+        self.size_bounds = result.size_bounds;
+    }
+}
+
+impl WidgetTheme for ComboBoxTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for ComboBoxTheme {
+    fn theme_path(&self) -> &'static str {
+        match (self.open, self.state) {
+            (true, _) => "ComboBox::Open",
+            (false, ButtonState::Normal) => "ComboBox::Normal",
+            (false, ButtonState::Hover) => "ComboBox::Hover",
+            (false, ButtonState::Pressed) => "ComboBox::Pressed",
+        }
+    }
+}
+
+impl WidgetTheme for ComboBoxListTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for ComboBoxListTheme {
+    fn theme_path(&self) -> &'static str {
+        "ComboBox::List"
+    }
+}
+
+impl WidgetTheme for ComboBoxItemTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for ComboBoxItemTheme {
+    fn theme_path(&self) -> &'static str {
+        match (self.highlighted, self.selected) {
+            (true, _) => "ComboBox::Item::Highlighted",
+            (false, true) => "ComboBox::Item::Selected",
+            (false, false) => "ComboBox::Item::Normal",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::WindowEvent;
+    use crate::cgmath::Point2;
+    use crate::soft_render::snapshot::Snapshot;
+    use crate::theme::Theme;
+
+    #[derive(Default)]
+    struct SelectLog(Vec<(Option<usize>, usize)>);
+    impl ComboBoxHandler for SelectLog {
+        type Action = ();
+        fn on_select(&mut self, old_index: Option<usize>, new_index: usize) -> Option<()> {
+            self.0.push((old_index, new_index));
+            None
+        }
+    }
+
+    fn items(items: &[&str]) -> Vec<Contents> {
+        items.iter().map(|s| Contents::Text(s.to_string())).collect()
+    }
+
+    #[test]
+    fn type_ahead_enter() {
+        let combo_box = ComboBox::new(items(&["Apple", "Banana", "Blueberry", "Cherry"]), Some(0), SelectLog::default());
+        let mut snapshot = Snapshot::new(combo_box, Theme::default(), DimsBox::new2(100, 24));
+        snapshot.render();
+
+        // Clicking the box focuses it and opens the list.
+        snapshot.process_events(vec![
+            WindowEvent::MouseEnter,
+            WindowEvent::MouseMove(Point2::new(10, 10)),
+            WindowEvent::MouseDown(MouseButton::Left),
+            WindowEvent::MouseUp(MouseButton::Left),
+        ]);
+        snapshot.render();
+        assert!(snapshot.root().is_open());
+
+        // Typing moves the highlight without changing the selection.
+        snapshot.process_events(vec![WindowEvent::Char('b'), WindowEvent::Char('l')]);
+        assert_eq!(Some(2), snapshot.root().list.highlighted);
+        assert_eq!(Some(0), snapshot.root().selected());
+
+        // Enter picks the highlighted item, even while the search is still in progress.
+        snapshot.process_events(vec![WindowEvent::KeyDown(Key::Enter), WindowEvent::KeyUp(Key::Enter)]);
+        assert_eq!(Some(2), snapshot.root().selected());
+        assert!(!snapshot.root().is_open());
+        assert_eq!(vec![(Some(0), 2)], snapshot.root().handler.0);
+    }
+}
@@ -0,0 +1,995 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use derin_core::{
+    LoopFlow,
+    event::{EventOps, WidgetEventSourced, InputState},
+    widget::{WidgetIdent, WidgetRenderable, WidgetTag, WidgetInfo, WidgetInfoMut, WidgetId, Widget, Parent, Visibility},
+    render::{Renderer, RendererLayout, SubFrame, WidgetTheme},
+};
+use crate::theme::{CursorIcon, ThemePath};
+use crate::widgets::{
+    Orientation, Splitter, TabList, TabListHandler, TabPage,
+    tabs::TabDrag,
+};
+
+use crate::cgmath::{EuclideanSpace, Point2};
+use cgmath_geometry::{D2, rect::{BoundBox, DimsBox, GeoBox}};
+use derin_common_types::layout::SizeBounds;
+#[cfg(feature = "derin-serde")]
+use serde::{Serialize, Deserialize};
+use std::{
+    any::Any,
+    mem,
+};
+
+/// How close to the edge of a tab group, as a fraction of the group's size, a panel has to be
+/// dropped to be docked beside the group rather than added to it.
+const DOCK_EDGE: f32 = 0.25;
+
+/// An IDE-style area of panels, which the user can rearrange by dragging them by their tabs.
+///
+/// Panels are grouped into [`TabList`]s, which are arranged with nested [`Splitter`]s. Dropping a
+/// dragged panel onto the middle of a tab group adds the panel to the group, and dropping it onto
+/// an edge of a group splits the group, docking the panel beside it. A translucent preview shows
+/// where the panel will end up while it's being dragged.
+///
+/// Each panel is identified by a key, which is used by [`layout`] to describe the arrangement of
+/// the panels. That arrangement can be restored later with [`set_layout`], and with the
+/// `derin-serde` feature enabled it can be saved with any `serde` format.
+///
+/// [`TabList`]: ./struct.TabList.html
+/// [`Splitter`]: ./struct.Splitter.html
+/// [`layout`]: ./struct.DockArea.html#method.layout
+/// [`set_layout`]: ./struct.DockArea.html#method.set_layout
+#[derive(Debug, Clone)]
+pub struct DockArea<W> {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    root: DockNode<W>,
+    preview: DockPreview,
+}
+
+/// The arrangement of the panels in a [`DockArea`].
+///
+/// [`DockArea`]: ./struct.DockArea.html
+#[cfg_attr(feature = "derin-serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum DockLayout {
+    /// Panels laid out beside each other, with each getting the share of the space given by the
+    /// matching entry in `ratios`.
+    Split {
+        orientation: Orientation,
+        ratios: Vec<f32>,
+        children: Vec<DockLayout>,
+    },
+    /// A group of panels shown as tabs, identified by their keys.
+    Tabs {
+        panels: Vec<String>,
+        open: usize,
+    },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DockAreaTheme(());
+
+#[derive(Debug, Clone, Default)]
+pub struct DockPreviewTheme(());
+
+/// The theme of whichever widget a `DockNode` contains.
+#[derive(Debug, Clone)]
+struct DockNodeTheme {
+    path: &'static str,
+}
+
+/// A node in the tree of splits and tab groups that make up a dock area.
+#[derive(Debug, Clone)]
+enum DockNode<W> {
+    Split(Splitter<Vec<DockNode<W>>>),
+    Tabs {
+        list: TabList<W, DockTabs>,
+        /// The keys of the panels in `list`, in the same order as the tabs.
+        keys: Vec<String>,
+    },
+}
+
+/// The tab list handler used by dock areas' tab groups.
+#[derive(Debug, Clone, Copy)]
+struct DockTabs;
+
+/// The translucent rectangle that shows where a dragged panel will get docked.
+#[derive(Debug, Clone)]
+struct DockPreview {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+}
+
+/// Where, within a tab group, a panel got dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DropZone {
+    Center,
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+/// Apply `$body` to the widget inside of a `DockNode`.
+macro_rules! node_widget {
+    ($node:expr, $widget:ident => $body:expr) => {
+        match $node {
+            DockNode::Split($widget) => $body,
+            DockNode::Tabs{list: $widget, ..} => $body,
+        }
+    };
+}
+
+impl<W> DockArea<W>
+    where W: Widget
+{
+    /// Create a new dock area, with all of the given panels in a single tab group.
+    pub fn new(panels: Vec<(String, TabPage<W>)>) -> DockArea<W> {
+        let mut widget_tag = WidgetTag::new();
+        widget_tag.register_message(Self::on_tab_drag);
+
+        let preview = DockPreview {
+            widget_tag: WidgetTag::new(),
+            rect: BoundBox::new2(0, 0, 0, 0),
+        };
+        preview.widget_tag.set_visibility(Visibility::Hidden);
+
+        DockArea {
+            widget_tag,
+            rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+
+            root: DockNode::tabs(panels),
+            preview,
+        }
+    }
+
+    /// Retrieve the current arrangement of the panels.
+    pub fn layout(&self) -> DockLayout {
+        self.root.layout()
+    }
+
+    /// Rearrange the panels to match `layout`.
+    ///
+    /// Panels that aren't mentioned in `layout` get added to the first tab group, and keys in
+    /// `layout` that don't match any panel are ignored.
+    pub fn set_layout(&mut self, layout: &DockLayout) {
+        let mut panels = Vec::new();
+        mem::replace(&mut self.root, DockNode::tabs(Vec::new())).into_panels(&mut panels);
+
+        self.root = DockNode::from_layout(layout, &mut panels);
+        for (key, panel) in panels {
+            self.root.first_tabs_mut().push_tab(key, panel);
+        }
+        self.normalize();
+    }
+
+    /// Retrieve the panel with the given key.
+    pub fn panel(&self, key: &str) -> Option<&TabPage<W>> {
+        let mut path = Vec::new();
+        let index = self.root.find_panel(key, &mut path)?;
+        match *self.root.node(&path) {
+            DockNode::Tabs{ref list, ..} => Some(&list.tabs()[index]),
+            DockNode::Split(_) => None
+        }
+    }
+
+    /// Retrieve the panel with the given key, for mutation.
+    pub fn panel_mut(&mut self, key: &str) -> Option<&mut TabPage<W>> {
+        let mut path = Vec::new();
+        let index = self.root.find_panel(key, &mut path)?;
+        match *self.root.node_mut(&path) {
+            DockNode::Tabs{ref mut list, ..} => Some(&mut list.tabs_mut()[index]),
+            DockNode::Split(_) => None
+        }
+    }
+
+    /// Add a panel to the first tab group.
+    pub fn add_panel(&mut self, key: String, panel: TabPage<W>) {
+        self.root.first_tabs_mut().push_tab(key, panel);
+        self.widget_tag.request_relayout().request_redraw();
+    }
+
+    /// Remove the panel with the given key, closing its tab group if the group is left empty.
+    pub fn remove_panel(&mut self, key: &str) -> Option<TabPage<W>> {
+        let mut path = Vec::new();
+        let index = self.root.find_panel(key, &mut path)?;
+        let (_, panel) = self.root.node_mut(&path).remove_tab(index)?;
+        self.normalize();
+        Some(panel)
+    }
+
+    /// Clean up the tree after panels have been moved around.
+    fn normalize(&mut self) {
+        if self.root.normalize() {
+            self.root = DockNode::tabs(Vec::new());
+        }
+        self.widget_tag.request_relayout().request_redraw();
+    }
+
+    fn on_tab_drag(&mut self, drag: &TabDrag) {
+        let mut source = Vec::new();
+        if !self.root.find_tab_list(drag.tab_list, &mut source) {
+            return;
+        }
+
+        let pos = drag.pos + self.root.rect_at(&source).min().to_vec();
+        let target = self.drop_target(&source, pos);
+
+        match drag.dropped {
+            false => {
+                let cursor_icon = match target {
+                    Some(_) => CursorIcon::Move,
+                    None => CursorIcon::NotAllowed
+                };
+                self.widget_tag.set_cursor_icon(cursor_icon).ok();
+                self.preview.show(target.as_ref().map(|&(_, zone, rect)| zone.preview_rect(rect)));
+            },
+            true => {
+                self.widget_tag.set_cursor_icon(CursorIcon::default()).ok();
+                self.preview.show(None);
+                if let Some((target, zone, _)) = target {
+                    self.move_panel(&source, drag.index, &target, zone);
+                }
+            }
+        }
+    }
+
+    /// Find the tab group that a panel dragged from the group at `source` would get dropped into,
+    /// if the mouse were released at `pos`. Returns the group's path, where in the group the panel
+    /// would go, and the group's rectangle.
+    fn drop_target(&self, source: &[usize], pos: Point2<i32>) -> Option<(Vec<usize>, DropZone, BoundBox<D2, i32>)> {
+        let mut target = Vec::new();
+        let rect = self.root.tabs_at(pos, &mut target)?;
+        let zone = DropZone::new(rect, pos);
+
+        // Dropping a panel back into its own group, or splitting a group with the group's only
+        // panel, wouldn't do anything.
+        if target == source {
+            let num_panels = match *self.root.node(&target) {
+                DockNode::Tabs{ref keys, ..} => keys.len(),
+                DockNode::Split(_) => 0
+            };
+            if zone == DropZone::Center || num_panels <= 1 {
+                return None;
+            }
+        }
+
+        Some((target, zone, rect))
+    }
+
+    fn move_panel(&mut self, source: &[usize], index: usize, target: &[usize], zone: DropZone) {
+        let (key, panel) = match self.root.node_mut(source).remove_tab(index) {
+            Some(removed) => removed,
+            None => return
+        };
+
+        match zone.split() {
+            None => self.root.node_mut(target).push_tab(key, panel),
+            Some((orientation, before)) => self.root.dock_beside(target, DockNode::tabs(vec![(key, panel)]), orientation, before)
+        }
+        self.normalize();
+    }
+}
+
+impl<W> DockNode<W>
+    where W: Widget
+{
+    fn tabs(panels: Vec<(String, TabPage<W>)>) -> DockNode<W> {
+        let (keys, tabs): (Vec<_>, Vec<_>) = panels.into_iter().unzip();
+        DockNode::Tabs {
+            list: TabList::new(tabs, DockTabs),
+            keys,
+        }
+    }
+
+    fn from_layout(layout: &DockLayout, panels: &mut Vec<(String, TabPage<W>)>) -> DockNode<W> {
+        match *layout {
+            DockLayout::Split{orientation, ref ratios, ref children} => {
+                let children = children.iter().map(|child| DockNode::from_layout(child, panels)).collect();
+                let mut split = Splitter::new(children, orientation);
+                split.set_ratios(ratios);
+                DockNode::Split(split)
+            },
+            DockLayout::Tabs{panels: ref keys, open} => {
+                let mut group = Vec::new();
+                for key in keys {
+                    if let Some(index) = panels.iter().position(|&(ref k, _)| k == key) {
+                        group.push(panels.remove(index));
+                    }
+                }
+
+                let mut node = DockNode::tabs(group);
+                if let DockNode::Tabs{ref mut list, ..} = node {
+                    if open < list.tabs().len() {
+                        list.set_open(open);
+                    }
+                }
+                node
+            }
+        }
+    }
+
+    fn layout(&self) -> DockLayout {
+        match *self {
+            DockNode::Split(ref split) => DockLayout::Split {
+                orientation: split.orientation(),
+                ratios: split.ratios(),
+                children: split.container().iter().map(|child| child.layout()).collect(),
+            },
+            DockNode::Tabs{ref list, ref keys} => DockLayout::Tabs {
+                panels: keys.clone(),
+                open: list.open(),
+            }
+        }
+    }
+
+    fn into_panels(self, panels: &mut Vec<(String, TabPage<W>)>) {
+        match self {
+            DockNode::Split(mut split) => {
+                for child in mem::replace(split.container_mut(), Vec::new()) {
+                    child.into_panels(panels);
+                }
+            },
+            DockNode::Tabs{mut list, keys} => panels.extend(keys.into_iter().zip(list.tabs_mut().drain(..)))
+        }
+    }
+
+    fn children(&self) -> &[DockNode<W>] {
+        match *self {
+            DockNode::Split(ref split) => split.container(),
+            DockNode::Tabs{..} => &[]
+        }
+    }
+
+    fn node(&self, path: &[usize]) -> &DockNode<W> {
+        path.iter().fold(self, |node, &index| &node.children()[index])
+    }
+
+    fn node_mut(&mut self, path: &[usize]) -> &mut DockNode<W> {
+        let mut node = self;
+        for &index in path {
+            node = match *node {
+                DockNode::Split(ref mut split) => &mut split.container_mut()[index],
+                DockNode::Tabs{..} => panic!("dock path goes through a tab group")
+            };
+        }
+        node
+    }
+
+    /// The rectangle of the node at `path`, in the coordinate space of this node's rectangle.
+    fn rect_at(&self, path: &[usize]) -> BoundBox<D2, i32> {
+        let mut node = self;
+        let mut rect = self.rect();
+        for &index in path {
+            node = &node.children()[index];
+            rect = node.rect() + rect.min().to_vec();
+        }
+        rect
+    }
+
+    /// Find the tab group under `pos`, pushing the path to it onto `path`. Returns the group's
+    /// rectangle in the same coordinate space as `pos`.
+    fn tabs_at(&self, pos: Point2<i32>, path: &mut Vec<usize>) -> Option<BoundBox<D2, i32>> {
+        let mut node = self;
+        let mut rect = self.rect();
+        if !rect.contains(pos) {
+            return None;
+        }
+
+        while let DockNode::Split(ref split) = *node {
+            let origin = rect.min().to_vec();
+            let (index, child) = split.container().iter().enumerate()
+                .filter(|&(_, child)| child.widget_tag().visibility() == Visibility::Visible)
+                .find(|&(_, child)| (child.rect() + origin).contains(pos))?;
+            path.push(index);
+            rect = child.rect() + origin;
+            node = child;
+        }
+        Some(rect)
+    }
+
+    fn find_tab_list(&self, tab_list: WidgetId, path: &mut Vec<usize>) -> bool {
+        match *self {
+            DockNode::Tabs{ref list, ..} => list.widget_id() == tab_list,
+            DockNode::Split(ref split) => {
+                for (index, child) in split.container().iter().enumerate() {
+                    path.push(index);
+                    if child.find_tab_list(tab_list, path) {
+                        return true;
+                    }
+                    path.pop();
+                }
+                false
+            }
+        }
+    }
+
+    /// Find the panel with the given key, pushing the path to its tab group onto `path`. Returns
+    /// the panel's index within the group.
+    fn find_panel(&self, key: &str, path: &mut Vec<usize>) -> Option<usize> {
+        match *self {
+            DockNode::Tabs{ref keys, ..} => keys.iter().position(|k| k == key),
+            DockNode::Split(ref split) => {
+                for (index, child) in split.container().iter().enumerate() {
+                    path.push(index);
+                    if let Some(panel_index) = child.find_panel(key, path) {
+                        return Some(panel_index);
+                    }
+                    path.pop();
+                }
+                None
+            }
+        }
+    }
+
+    fn first_tabs_mut(&mut self) -> &mut DockNode<W> {
+        // Splits are never left empty by `normalize`, but the layout passed to `set_layout` might
+        // contain one.
+        if self.children().is_empty() {
+            if let DockNode::Split(_) = *self {
+                *self = DockNode::tabs(Vec::new());
+            }
+        }
+
+        match *self {
+            DockNode::Split(ref mut split) => split.container_mut()[0].first_tabs_mut(),
+            DockNode::Tabs{..} => self
+        }
+    }
+
+    /// Add a panel to this tab group and open it.
+    fn push_tab(&mut self, key: String, panel: TabPage<W>) {
+        if let DockNode::Tabs{ref mut list, ref mut keys} = *self {
+            keys.push(key);
+            list.tabs_mut().push(panel);
+            let open = list.tabs().len() - 1;
+            list.set_open(open);
+        }
+    }
+
+    fn remove_tab(&mut self, index: usize) -> Option<(String, TabPage<W>)> {
+        match *self {
+            DockNode::Tabs{ref mut list, ref mut keys} => match index < keys.len() {
+                true => Some((keys.remove(index), list.remove_tab(index))),
+                false => None
+            },
+            DockNode::Split(_) => None
+        }
+    }
+
+    /// Dock `group` beside the node at `path`, placing it before the node if `before` is set.
+    fn dock_beside(&mut self, path: &[usize], group: DockNode<W>, orientation: Orientation, before: bool) {
+        // If the node's parent already splits in the same direction, add the group to the parent
+        // instead of nesting another split inside of it. The group takes half of the node's space.
+        if let Some((&index, parent_path)) = path.split_last() {
+            if let DockNode::Split(ref mut split) = *self.node_mut(parent_path) {
+                if split.orientation() == orientation {
+                    let mut ratios = split.ratios();
+                    let insert_at = index + !before as usize;
+                    ratios[index] /= 2.0;
+                    let ratio = ratios[index];
+                    ratios.insert(insert_at, ratio);
+
+                    split.container_mut().insert(insert_at, group);
+                    split.set_ratios(&ratios);
+                    return;
+                }
+            }
+        }
+
+        let node = self.node_mut(path);
+        let old = mem::replace(node, DockNode::Split(Splitter::new(Vec::new(), orientation)));
+        if let DockNode::Split(ref mut split) = *node {
+            *split.container_mut() = match before {
+                true => vec![group, old],
+                false => vec![old, group]
+            };
+        }
+    }
+
+    /// Remove empty tab groups, and replace splits that only have one child with the child.
+    /// Returns whether the node is empty.
+    fn normalize(&mut self) -> bool {
+        let only_child = match *self {
+            DockNode::Tabs{ref keys, ..} => return keys.is_empty(),
+            DockNode::Split(ref mut split) => {
+                let mut ratios = split.ratios();
+                let mut children = mem::replace(split.container_mut(), Vec::new());
+
+                let mut index = 0;
+                while index < children.len() {
+                    match children[index].normalize() {
+                        true => {
+                            children.remove(index);
+                            ratios.remove(index);
+                        },
+                        false => index += 1
+                    }
+                }
+
+                match children.len() {
+                    0 => return true,
+                    1 => children.pop(),
+                    _ => {
+                        *split.container_mut() = children;
+                        split.set_ratios(&ratios);
+                        None
+                    }
+                }
+            }
+        };
+
+        if let Some(only_child) = only_child {
+            *self = only_child;
+        }
+        false
+    }
+}
+
+impl DropZone {
+    /// Find where within `rect` the point `pos` lies.
+    fn new(rect: BoundBox<D2, i32>, pos: Point2<i32>) -> DropZone {
+        let x = (pos.x - rect.min.x) as f32 / rect.width().max(1) as f32;
+        let y = (pos.y - rect.min.y) as f32 / rect.height().max(1) as f32;
+
+        let edges = [
+            (x, DropZone::Left),
+            (1.0 - x, DropZone::Right),
+            (y, DropZone::Top),
+            (1.0 - y, DropZone::Bottom),
+        ];
+        // Use the nearest edge, if the point is close enough to any of them.
+        edges.iter().cloned()
+            .fold((DOCK_EDGE, DropZone::Center), |nearest, edge| match edge.0 < nearest.0 {
+                true => edge,
+                false => nearest
+            }).1
+    }
+
+    /// The direction to split the target group in, and whether the dropped panel goes before the
+    /// group. Returns `None` if the panel gets added to the group instead.
+    fn split(self) -> Option<(Orientation, bool)> {
+        match self {
+            DropZone::Center => None,
+            DropZone::Left => Some((Orientation::Horizontal, true)),
+            DropZone::Right => Some((Orientation::Horizontal, false)),
+            DropZone::Top => Some((Orientation::Vertical, true)),
+            DropZone::Bottom => Some((Orientation::Vertical, false)),
+        }
+    }
+
+    /// The area of `rect` that the dropped panel would take up.
+    fn preview_rect(self, rect: BoundBox<D2, i32>) -> BoundBox<D2, i32> {
+        let center = Point2::new((rect.min.x + rect.max.x) / 2, (rect.min.y + rect.max.y) / 2);
+        match self {
+            DropZone::Center => rect,
+            DropZone::Left => BoundBox::new2(rect.min.x, rect.min.y, center.x, rect.max.y),
+            DropZone::Right => BoundBox::new2(center.x, rect.min.y, rect.max.x, rect.max.y),
+            DropZone::Top => BoundBox::new2(rect.min.x, rect.min.y, rect.max.x, center.y),
+            DropZone::Bottom => BoundBox::new2(rect.min.x, center.y, rect.max.x, rect.max.y),
+        }
+    }
+}
+
+impl DockPreview {
+    /// Show the preview over `rect`, or hide it if `rect` is `None`.
+    fn show(&mut self, rect: Option<BoundBox<D2, i32>>) {
+        self.widget_tag.set_visibility(match rect {
+            Some(_) => Visibility::Visible,
+            None => Visibility::Hidden
+        });
+        if let Some(rect) = rect {
+            if rect != self.rect {
+                self.rect = rect;
+                self.widget_tag.request_redraw();
+            }
+        }
+    }
+}
+
+impl TabListHandler for DockTabs {
+    type Action = !;
+
+    fn on_select(&mut self, _: usize) -> Option<!> {
+        None
+    }
+}
+
+impl<W> Widget for DockArea<W>
+    where W: Widget
+{
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        self.widget_tag.request_relayout();
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    #[inline]
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        EventOps {
+            focus: None,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
+impl<W> Parent for DockArea<W>
+    where W: Widget
+{
+    fn num_children(&self) -> usize {
+        2
+    }
+
+    fn framed_child<R: Renderer>(&self, widget_ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(0) => Some(WidgetInfo::new(WidgetIdent::Num(0), 0, &self.root)),
+            WidgetIdent::Num(1) => Some(WidgetInfo::new(WidgetIdent::Num(1), 1, &self.preview)),
+            _ => None
+        }
+    }
+    fn framed_child_mut<R: Renderer>(&mut self, widget_ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(0) => Some(WidgetInfoMut::new(WidgetIdent::Num(0), 0, &mut self.root)),
+            WidgetIdent::Num(1) => Some(WidgetInfoMut::new(WidgetIdent::Num(1), 1, &mut self.preview)),
+            _ => None
+        }
+    }
+
+    fn framed_children<'a, R, G>(&'a self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
+    {
+        // The preview comes last, so that it gets drawn over the panels.
+        if let LoopFlow::Break = for_each(WidgetInfo::new(WidgetIdent::Num(0), 0, &self.root)) {
+            return;
+        }
+        let _ = for_each(WidgetInfo::new(WidgetIdent::Num(1), 1, &self.preview));
+    }
+
+    fn framed_children_mut<'a, R, G>(&'a mut self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
+    {
+        if let LoopFlow::Break = for_each(WidgetInfoMut::new(WidgetIdent::Num(0), 0, &mut self.root)) {
+            return;
+        }
+        let _ = for_each(WidgetInfoMut::new(WidgetIdent::Num(1), 1, &mut self.preview));
+    }
+
+    fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
+        match index {
+            0 => self.framed_child(WidgetIdent::Num(0)),
+            1 => self.framed_child(WidgetIdent::Num(1)),
+            _ => None
+        }
+    }
+    fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
+        match index {
+            0 => self.framed_child_mut(WidgetIdent::Num(0)),
+            1 => self.framed_child_mut(WidgetIdent::Num(1)),
+            _ => None
+        }
+    }
+}
+
+impl<R, W> WidgetRenderable<R> for DockArea<W>
+    where R: Renderer,
+          W: Widget
+{
+    type Theme = DockAreaTheme;
+
+    fn theme(&self) -> DockAreaTheme {
+        DockAreaTheme(())
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        let result = layout.finish();
+        let content_rect = result.content_rect;
+        let margin_width = content_rect.min.x + (self.rect.width() - content_rect.max.x);
+        let margin_height = content_rect.min.y + (self.rect.height() - content_rect.max.y);
+
+        if self.root.rect() != content_rect {
+            *self.root.rect_mut() = content_rect;
+        }
+
+        let root_min = self.root.size_bounds().min;
+        self.size_bounds = SizeBounds {
+            min: DimsBox::new2(
+                (root_min.width() + margin_width).max(result.size_bounds.min.width()),
+                (root_min.height() + margin_height).max(result.size_bounds.min.height()),
+            ),
+            ..result.size_bounds
+        };
+    }
+}
+
+// `DockNode` forwards everything to the widget it contains, so that the node is invisible to the
+// rest of the widget tree.
+impl<W> Widget for DockNode<W>
+    where W: Widget
+{
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        node_widget!(self, widget => widget.widget_tag())
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        node_widget!(self, widget => widget.rect())
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        node_widget!(self, widget => widget.rect_mut())
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        node_widget!(self, widget => widget.size_bounds())
+    }
+
+    fn focus_rect(&self) -> BoundBox<D2, i32> {
+        node_widget!(self, widget => widget.focus_rect())
+    }
+
+    fn scroll_into_view(&mut self, rect: BoundBox<D2, i32>) -> BoundBox<D2, i32> {
+        node_widget!(self, widget => widget.scroll_into_view(rect))
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, input_state: InputState) -> EventOps {
+        node_widget!(self, widget => widget.on_widget_event(event, input_state))
+    }
+
+    fn dispatch_message(&mut self, message: &Any) {
+        node_widget!(self, widget => widget.dispatch_message(message))
+    }
+}
+
+impl<W> Parent for DockNode<W>
+    where W: Widget
+{
+    fn num_children(&self) -> usize {
+        node_widget!(self, widget => widget.num_children())
+    }
+
+    fn framed_child<R: Renderer>(&self, widget_ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
+        node_widget!(self, widget => widget.framed_child(widget_ident))
+    }
+    fn framed_child_mut<R: Renderer>(&mut self, widget_ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
+        node_widget!(self, widget => widget.framed_child_mut(widget_ident))
+    }
+
+    fn framed_children<'a, R, G>(&'a self, for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
+    {
+        node_widget!(self, widget => widget.framed_children(for_each))
+    }
+
+    fn framed_children_mut<'a, R, G>(&'a mut self, for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
+    {
+        node_widget!(self, widget => widget.framed_children_mut(for_each))
+    }
+
+    fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
+        node_widget!(self, widget => widget.framed_child_by_index(index))
+    }
+    fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
+        node_widget!(self, widget => widget.framed_child_by_index_mut(index))
+    }
+}
+
+impl<R, W> WidgetRenderable<R> for DockNode<W>
+    where R: Renderer,
+          W: Widget
+{
+    type Theme = DockNodeTheme;
+
+    fn theme(&self) -> DockNodeTheme {
+        DockNodeTheme {
+            path: node_widget!(self, widget => WidgetRenderable::<R>::theme(widget).theme_path()),
+        }
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        node_widget!(self, widget => WidgetRenderable::<R>::render(widget, frame))
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        node_widget!(self, widget => WidgetRenderable::<R>::update_layout(widget, layout))
+    }
+}
+
+impl Widget for DockPreview {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
+
+    #[inline]
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        EventOps {
+            focus: None,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
+impl<R> WidgetRenderable<R> for DockPreview
+    where R: Renderer
+{
+    type Theme = DockPreviewTheme;
+
+    fn theme(&self) -> DockPreviewTheme {
+        DockPreviewTheme(())
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+}
+
+impl WidgetTheme for DockAreaTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for DockAreaTheme {
+    fn theme_path(&self) -> &'static str {
+        "DockArea"
+    }
+}
+
+impl WidgetTheme for DockPreviewTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for DockPreviewTheme {
+    fn theme_path(&self) -> &'static str {
+        "DockPreview"
+    }
+}
+
+impl WidgetTheme for DockNodeTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for DockNodeTheme {
+    fn theme_path(&self) -> &'static str {
+        self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widgets::{Contents, Label};
+
+    fn panels(keys: &[&str]) -> Vec<(String, TabPage<Label>)> {
+        keys.iter().map(|key| {
+            let page = TabPage::new(Contents::Text(key.to_string()), Label::new(Contents::Text(key.to_string())));
+            (key.to_string(), page)
+        }).collect()
+    }
+
+    fn tabs(keys: &[&str], open: usize) -> DockLayout {
+        DockLayout::Tabs {
+            panels: keys.iter().map(|key| key.to_string()).collect(),
+            open,
+        }
+    }
+
+    #[test]
+    fn layout_round_trip() {
+        let layout = DockLayout::Split {
+            orientation: Orientation::Horizontal,
+            ratios: vec![1.0, 2.0],
+            children: vec![
+                tabs(&["a", "b"], 1),
+                DockLayout::Split {
+                    orientation: Orientation::Vertical,
+                    ratios: vec![3.0, 1.0],
+                    children: vec![tabs(&["c"], 0), tabs(&["d"], 0)],
+                },
+            ],
+        };
+
+        let mut dock = DockArea::new(panels(&["a", "b", "c", "d"]));
+        dock.set_layout(&layout);
+        assert_eq!(layout, dock.layout());
+    }
+
+    #[test]
+    fn edge_drop_inserts_sibling() {
+        let mut dock = DockArea::new(panels(&["a", "b", "c"]));
+        dock.set_layout(&DockLayout::Split {
+            orientation: Orientation::Horizontal,
+            ratios: vec![2.0, 2.0],
+            children: vec![tabs(&["a", "b"], 0), tabs(&["c"], 0)],
+        });
+
+        // Dropping `b` on the left edge of `c`'s group should add a group to the existing split,
+        // rather than nesting a new split inside of it.
+        dock.move_panel(&[0], 1, &[1], DropZone::Left);
+        let expected = DockLayout::Split {
+            orientation: Orientation::Horizontal,
+            ratios: vec![2.0, 1.0, 1.0],
+            children: vec![tabs(&["a"], 0), tabs(&["b"], 0), tabs(&["c"], 0)],
+        };
+        assert_eq!(expected, dock.layout());
+    }
+
+    #[test]
+    fn normalize_removes_empty_groups() {
+        let mut dock = DockArea::new(panels(&["a", "b", "c"]));
+        dock.set_layout(&DockLayout::Split {
+            orientation: Orientation::Horizontal,
+            ratios: vec![1.0, 1.0],
+            children: vec![
+                tabs(&["a"], 0),
+                DockLayout::Split {
+                    orientation: Orientation::Vertical,
+                    ratios: vec![1.0, 1.0],
+                    children: vec![tabs(&["b"], 0), tabs(&["c"], 0)],
+                },
+            ],
+        });
+
+        // Emptying `b`'s group leaves the vertical split with one child, which should replace it.
+        assert!(dock.remove_panel("b").is_some());
+        let expected = DockLayout::Split {
+            orientation: Orientation::Horizontal,
+            ratios: vec![1.0, 1.0],
+            children: vec![tabs(&["a"], 0), tabs(&["c"], 0)],
+        };
+        assert_eq!(expected, dock.layout());
+
+        assert!(dock.remove_panel("a").is_some());
+        assert_eq!(tabs(&["c"], 0), dock.layout());
+    }
+}
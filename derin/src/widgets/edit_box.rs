@@ -8,6 +8,7 @@ use derin_core::{
     widget::{WidgetTag, WidgetRenderable, Widget},
     render::{Renderer, RendererLayout, SubFrame, WidgetTheme},
 };
+use crate::theme::ThemePath;
 use crate::widgets::assistants::text_edit::{TextEditAssist, TextEditOps, CursorFlashOp, LineCharFilter};
 use cgmath_geometry::{D2, rect::BoundBox};
 use derin_common_types::layout::SizeBounds;
@@ -41,8 +42,11 @@ pub struct LineBoxTheme(());
 impl EditBox {
     /// Create a new `EditBox`, containing the included `String` by default.
     pub fn new(string: String) -> EditBox {
+        let widget_tag = WidgetTag::new();
+        widget_tag.set_focusable(true);
+
         EditBox {
-            widget_tag: WidgetTag::new(),
+            widget_tag,
             bounds: BoundBox::new2(0, 0, 0, 0),
             edit: TextEditAssist {
                 string,
@@ -71,8 +75,11 @@ impl EditBox {
 impl LineBox {
     /// Create a new `LineBox`, containing the included `String` by default.
     pub fn new(string: String) -> LineBox {
+        let widget_tag = WidgetTag::new();
+        widget_tag.set_focusable(true);
+
         LineBox {
-            widget_tag: WidgetTag::new(),
+            widget_tag,
             bounds: BoundBox::new2(0, 0, 0, 0),
             edit: TextEditAssist {
                 string,
@@ -233,9 +240,21 @@ impl WidgetTheme for EditBoxTheme {
     }
 }
 
+impl ThemePath for EditBoxTheme {
+    fn theme_path(&self) -> &'static str {
+        "EditBox"
+    }
+}
+
 impl WidgetTheme for LineBoxTheme {
     type Fallback = !;
     fn fallback(self) -> Option<!> {
         None
     }
 }
+
+impl ThemePath for LineBoxTheme {
+    fn theme_path(&self) -> &'static str {
+        "LineBox"
+    }
+}
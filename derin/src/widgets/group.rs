@@ -5,16 +5,17 @@
 use derin_core::{
     LoopFlow,
     event::{EventOps, WidgetEventSourced, InputState},
-    widget::{WidgetIdent, WidgetRenderable, WidgetTag, WidgetInfo, WidgetInfoMut, Widget, Parent},
+    widget::{Visibility, WidgetIdent, WidgetRenderable, WidgetTag, WidgetInfo, WidgetInfoMut, Widget, Parent},
     render::{Renderer, SubFrame, WidgetTheme},
 };
+use crate::theme::ThemePath;
 use crate::{
     container::WidgetContainer,
     layout::GridLayout,
 };
 
 use cgmath_geometry::{D2, rect::{BoundBox, DimsBox, GeoBox}};
-use derin_common_types::layout::{SizeBounds, WidgetPos};
+use derin_common_types::layout::{SizeBounds, Tr, TrackHints, WidgetPos, WidgetSpan};
 
 use std::cell::RefCell;
 
@@ -153,6 +154,7 @@ impl<R, C, L> WidgetRenderable<R> for Group<C, L>
         struct HeapCache {
             update_heap_cache: UpdateHeapCache,
             hints_vec: Vec<WidgetPos>,
+            collapsed_vec: Vec<bool>,
             rects_vec: Vec<Result<BoundBox<D2, i32>, SolveError>>
         }
         thread_local! {
@@ -165,6 +167,7 @@ impl<R, C, L> WidgetRenderable<R> for Group<C, L>
             let HeapCache {
                 ref mut update_heap_cache,
                 ref mut hints_vec,
+                ref mut collapsed_vec,
                 ref mut rects_vec
             } = *hc;
 
@@ -178,12 +181,14 @@ impl<R, C, L> WidgetRenderable<R> for Group<C, L>
                     max: layout_hints.size_bounds.bound_rect(widget_size_bounds.max),
                 };
                 hints_vec.push(layout_hints);
+                collapsed_vec.push(summary.widget().widget_tag().visibility() == Visibility::Collapsed);
                 rects_vec.push(Ok(BoundBox::new2(0, 0, 0, 0)));
                 LoopFlow::Continue
             });
 
             self.layout_engine.desired_size = DimsBox::new2(self.bounds.width(), self.bounds.height());
             self.layout_engine.set_grid_size(self.layout.grid_size(num_children));
+            collapse_tracks(&mut self.layout_engine, hints_vec, collapsed_vec);
             self.layout_engine.update_engine(hints_vec, rects_vec, update_heap_cache);
 
             let mut rects_iter = rects_vec.drain(..);
@@ -196,13 +201,60 @@ impl<R, C, L> WidgetRenderable<R> for Group<C, L>
             });
 
             hints_vec.clear();
+            collapsed_vec.clear();
         })
     }
 }
 
+/// Shrink the tracks that only hold collapsed widgets down to nothing, and take the collapsed
+/// widgets out of the layout so the rest of the widgets close the gap.
+fn collapse_tracks(layout_engine: &mut GridEngine, hints: &mut [WidgetPos], collapsed: &[bool]) {
+    let grid_size = layout_engine.grid_size();
+    // `None` if no widgets are in the track, `Some(true)` if every widget in the track is collapsed.
+    let mut cols_collapsed = vec![None; grid_size.x as usize];
+    let mut rows_collapsed = vec![None; grid_size.y as usize];
+
+    for (hint, &collapsed) in hints.iter_mut().zip(collapsed) {
+        let span = hint.widget_span;
+        let cols = span.x.start.unwrap_or(0)..span.x.end.unwrap_or(grid_size.x);
+        let rows = span.y.start.unwrap_or(0)..span.y.end.unwrap_or(grid_size.y);
+        for track in cols_collapsed.get_mut(cols.start as usize..cols.end as usize).into_iter().flatten() {
+            *track = Some(track.unwrap_or(true) && collapsed);
+        }
+        for track in rows_collapsed.get_mut(rows.start as usize..rows.end as usize).into_iter().flatten() {
+            *track = Some(track.unwrap_or(true) && collapsed);
+        }
+
+        if collapsed {
+            hint.widget_span = WidgetSpan::default();
+        }
+    }
+
+    let track_hints = |collapsed: Option<bool>| match collapsed {
+        Some(true) => TrackHints {
+            min_size: 0,
+            max_size: 0,
+            fr_size: 0.0,
+        },
+        _ => TrackHints::default()
+    };
+    for (col, &collapsed) in cols_collapsed.iter().enumerate() {
+        layout_engine.set_col_hints(col as Tr, track_hints(collapsed));
+    }
+    for (row, &collapsed) in rows_collapsed.iter().enumerate() {
+        layout_engine.set_row_hints(row as Tr, track_hints(collapsed));
+    }
+}
+
 impl WidgetTheme for GroupTheme {
     type Fallback = !;
     fn fallback(self) -> Option<!> {
         None
     }
 }
+
+impl ThemePath for GroupTheme {
+    fn theme_path(&self) -> &'static str {
+        "Group"
+    }
+}
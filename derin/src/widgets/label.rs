@@ -7,6 +7,7 @@ use derin_core::{
     widget::{WidgetTag, WidgetRenderable, Widget},
     render::{Renderer, RendererLayout, SubFrame, WidgetTheme},
 };
+use crate::theme::ThemePath;
 use crate::widgets::Contents;
 
 use cgmath_geometry::{D2, rect::BoundBox};
@@ -32,6 +33,12 @@ impl WidgetTheme for LabelTheme {
     fn fallback(self) -> Option<!> {None}
 }
 
+impl ThemePath for LabelTheme {
+    fn theme_path(&self) -> &'static str {
+        "Label"
+    }
+}
+
 impl Label {
     /// Create a new label with the given contents.
     pub fn new(contents: Contents) -> Label {
@@ -0,0 +1,1712 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use derin_core::{
+    LoopFlow,
+    event::{EventOps, WidgetEvent, WidgetEventSourced, InputState, MouseHoverChange, MouseButton, FocusChange, Key},
+    overlay::{Overlay, OverlayDismissed, OverlayPlacement},
+    widget::{MessageTarget, WidgetIdent, WidgetTag, WidgetRenderable, WidgetInfo, WidgetInfoMut, WidgetId, Widget, Parent},
+    render::{Renderer, RendererLayout, SubFrame, WidgetTheme},
+};
+use crate::cgmath::{EuclideanSpace, Point2};
+use crate::theme::ThemePath;
+
+use cgmath_geometry::{D2, rect::{BoundBox, DimsBox, GeoBox}};
+use derin_common_types::layout::SizeBounds;
+
+/// The vertical space taken up by a separator.
+const SEPARATOR_HEIGHT: i32 = 7;
+/// The minimum gap between an item's label and its accelerator text.
+const ACCELERATOR_GAP: i32 = 16;
+
+/// An entry in a menu.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MenuEntry {
+    Item(MenuItem),
+    /// A horizontal line separating groups of items.
+    Separator,
+}
+
+/// An item in a menu, which can either be activated or open a submenu.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuItem {
+    pub label: String,
+    /// Text describing the item's keyboard shortcut (e.g. "Ctrl+S"), which gets drawn next to the
+    /// label.
+    pub accelerator: Option<String>,
+    /// `Some` if the item can be checked. Activating a checkable item toggles it.
+    pub checked: Option<bool>,
+    /// Whether the item can be highlighted and activated.
+    pub enabled: bool,
+    /// The entries in the item's submenu. Items with submenus open them instead of getting
+    /// activated.
+    pub submenu: Vec<MenuEntry>,
+}
+
+/// Determines which action, if any, should be taken when the user activates a menu item.
+pub trait MenuHandler: 'static {
+    type Action: 'static;
+
+    /// Called when the item at `path` gets activated. `path` contains the index of the item in
+    /// each successive menu, starting with the outermost one. If the item is checkable, `item`
+    /// has already been toggled.
+    fn on_activate(&mut self, path: &[usize], item: &MenuItem) -> Option<Self::Action>;
+}
+
+/// A horizontal strip of menus, as found at the top of most desktop applications.
+///
+/// Clicking one of the bar's items opens its menu, and hovering over another item while a menu is
+/// open switches to that item's menu. While the bar has keyboard focus, the arrow keys move through
+/// the menus and their items, `Enter` activates the highlighted item, and `Escape` closes the
+/// innermost open menu. Activated items get passed to the provided handler's [`on_activate`]
+/// function.
+///
+/// [`on_activate`]: ./trait.MenuHandler.html#tymethod.on_activate
+#[derive(Debug, Clone)]
+pub struct MenuBar<H: MenuHandler> {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    menus: Vec<MenuItem>,
+    items: Vec<MenuBarItem>,
+    /// The bar item keyboard navigation starts from.
+    cursor: Option<usize>,
+    /// The index of the open menu, and its popup.
+    open: Option<(usize, MenuPopup)>,
+
+    pub handler: H,
+}
+
+/// A menu that pops up at a given position, usually in response to a right-click.
+///
+/// The context menu itself takes up no space, and can be placed anywhere in the widget tree. It gets
+/// opened either by calling [`open_at`], or by sending it a [`ShowContextMenu`] message, which lets
+/// custom widgets open it from their own right-click handling. Once the mouse enters the menu, it
+/// takes keyboard focus and can be navigated with the arrow keys. Activated items get passed to the
+/// provided handler's [`on_activate`] function.
+///
+/// [`open_at`]: ./struct.ContextMenu.html#method.open_at
+/// [`ShowContextMenu`]: ./struct.ShowContextMenu.html
+/// [`on_activate`]: ./trait.MenuHandler.html#tymethod.on_activate
+#[derive(Debug, Clone)]
+pub struct ContextMenu<H: MenuHandler> {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+
+    entries: Vec<MenuEntry>,
+    popup: Option<MenuPopup>,
+
+    pub handler: H,
+}
+
+/// Message that opens a [`ContextMenu`] when sent to it.
+///
+/// [`ContextMenu`]: ./struct.ContextMenu.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShowContextMenu {
+    /// The widget the menu gets positioned relative to.
+    pub anchor: WidgetId,
+    /// The position of the menu's top-left corner, relative to `anchor`'s top-left corner.
+    pub pos: Point2<i32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MenuBarTheme(());
+
+#[derive(Debug, Clone, Copy)]
+pub struct MenuBarItemTheme {
+    pub hover: bool,
+    pub open: bool,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ContextMenuTheme(());
+
+#[derive(Debug, Clone, Copy)]
+pub struct MenuPopupTheme(());
+
+#[derive(Debug, Clone, Copy)]
+pub struct MenuRowTheme {
+    pub separator: bool,
+    pub highlighted: bool,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MenuAcceleratorTheme {
+    pub highlighted: bool,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone)]
+struct MenuBarItem {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    label: String,
+    enabled: bool,
+    hover: bool,
+    active: bool,
+    open: bool,
+}
+
+/// A single open menu. Popups are shown as overlays, owned by the menu bar or context menu for the
+/// outermost popup and by the parent popup for submenus.
+#[derive(Debug, Clone)]
+struct MenuPopup {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    /// The path of the item this popup is the submenu of, relative to the outermost popup.
+    path: Vec<usize>,
+    entries: Vec<MenuEntry>,
+    rows: Vec<MenuRow>,
+    highlighted: Option<usize>,
+    submenu: Option<Box<MenuPopup>>,
+    /// Whether the popup takes keyboard focus when the mouse enters it.
+    take_focus: bool,
+    focused: bool,
+}
+
+#[derive(Debug, Clone)]
+struct MenuRow {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    label: String,
+    separator: bool,
+    enabled: bool,
+    highlighted: bool,
+    accelerator: MenuAccelerator,
+}
+
+#[derive(Debug, Clone)]
+struct MenuAccelerator {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    text: String,
+    enabled: bool,
+    highlighted: bool,
+}
+
+/// Sent from a bar item to the menu bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BarEvent {
+    Hover(WidgetId),
+    Press(WidgetId),
+}
+
+/// Sent from a row to the popup that contains it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RowEvent {
+    Hover(WidgetId),
+    Click(WidgetId),
+}
+
+/// Sent from an accelerator to its row, which handles the click on the accelerator's behalf.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct AcceleratorClicked;
+
+/// Sent from a popup to its parent, and forwarded through the parent popups to the menu bar or
+/// context menu.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MenuEvent {
+    /// The item at the given path, relative to the outermost popup, got activated.
+    Activate(Vec<usize>),
+    /// A key got pressed while a popup had keyboard focus.
+    Key(Key),
+}
+
+/// The result of handling a key press in a chain of popups.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MenuNav {
+    Handled,
+    Activate(Vec<usize>),
+    Unhandled(Key),
+}
+
+impl MenuItem {
+    /// Creates an enabled item with the given label, and no accelerator or submenu.
+    pub fn new(label: String) -> MenuItem {
+        MenuItem {
+            label,
+            accelerator: None,
+            checked: None,
+            enabled: true,
+            submenu: Vec::new(),
+        }
+    }
+
+    /// Creates an enabled item with the given label, which opens a submenu containing `entries`.
+    pub fn submenu(label: String, entries: Vec<MenuEntry>) -> MenuItem {
+        MenuItem {
+            submenu: entries,
+            ..MenuItem::new(label)
+        }
+    }
+}
+
+/// Retrieves the item at `path`, where `path` contains the index of the item in each successive
+/// list of entries.
+fn item_at_mut<'a>(entries: &'a mut [MenuEntry], path: &[usize]) -> Option<&'a mut MenuItem> {
+    let (&index, rest) = path.split_first()?;
+    match (entries.get_mut(index), rest.is_empty()) {
+        (Some(MenuEntry::Item(item)), true) => Some(item),
+        (Some(MenuEntry::Item(item)), false) => item_at_mut(&mut item.submenu, rest),
+        _ => None
+    }
+}
+
+impl<H: MenuHandler> MenuBar<H> {
+    /// Creates a new menu bar. Each of `menus` is shown as an item in the bar, which opens the
+    /// item's submenu when clicked.
+    pub fn new(menus: Vec<MenuItem>, handler: H) -> MenuBar<H> {
+        let mut widget_tag = WidgetTag::new();
+        widget_tag.register_message(Self::on_bar_event);
+        widget_tag.register_message(Self::on_menu_event);
+        widget_tag.register_message(Self::on_overlay_dismissed);
+
+        let mut menu_bar = MenuBar {
+            widget_tag,
+            rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+
+            menus,
+            items: Vec::new(),
+            cursor: None,
+            open: None,
+
+            handler,
+        };
+        menu_bar.sync_items();
+        menu_bar
+    }
+
+    /// Retrieves the menus in the bar.
+    pub fn menus(&self) -> &[MenuItem] {
+        &self.menus
+    }
+
+    /// Retrieves the menus in the bar, for mutation.
+    ///
+    /// Calling this function closes any open menu and forces the bar to be re-drawn, so you're
+    /// discouraged from calling it unless you're actually changing the menus.
+    pub fn menus_mut(&mut self) -> &mut Vec<MenuItem> {
+        self.close();
+        self.cursor = None;
+        self.items.clear();
+        self.widget_tag.request_redraw().request_relayout();
+        &mut self.menus
+    }
+
+    /// Retrieves the index of the open menu.
+    pub fn open_menu(&self) -> Option<usize> {
+        self.open.as_ref().map(|&(index, _)| index)
+    }
+
+    fn sync_items(&mut self) {
+        if self.items.len() != self.menus.len() {
+            self.items = self.menus.iter().map(|m| MenuBarItem::new(m.label.clone(), m.enabled)).collect();
+        }
+        let open = self.open_menu();
+        for (i, item) in self.items.iter_mut().enumerate() {
+            item.set_flags(Some(i) == self.cursor, Some(i) == open);
+        }
+    }
+
+    fn open(&mut self, index: usize, highlight_first: bool) {
+        self.close();
+        self.cursor = Some(index);
+
+        let menu = &self.menus[index];
+        if menu.enabled && !menu.submenu.is_empty() {
+            let mut popup = MenuPopup::new(Vec::new(), menu.submenu.clone(), false);
+            let overlay = Overlay::popup(self.items[index].widget_tag.widget_id());
+            if self.widget_tag.show_overlay(popup.widget_tag.widget_id(), overlay).is_ok() {
+                if highlight_first {
+                    popup.step_highlight(true);
+                }
+                self.open = Some((index, popup));
+            }
+        }
+
+        self.sync_items();
+        self.widget_tag.request_redraw().request_relayout();
+    }
+
+    fn close(&mut self) {
+        if let Some((_, mut popup)) = self.open.take() {
+            popup.close_submenu();
+            self.widget_tag.hide_overlay(popup.widget_tag.widget_id());
+            self.sync_items();
+            self.widget_tag.request_redraw().request_relayout();
+        }
+    }
+
+    fn activate(&mut self, path: &[usize]) {
+        self.close();
+        let (&menu, rest) = match path.split_first() {
+            Some(split) => split,
+            None => return
+        };
+        if let Some(item) = self.menus.get_mut(menu).and_then(|m| item_at_mut(&mut m.submenu, rest)) {
+            if let Some(ref mut checked) = item.checked {
+                *checked = !*checked;
+            }
+            if let Some(action) = self.handler.on_activate(path, item) {
+                self.widget_tag.broadcast_message(action);
+            }
+        }
+    }
+
+    fn on_key(&mut self, key: Key) -> bool {
+        let num_menus = self.menus.len();
+        if num_menus == 0 {
+            return false;
+        }
+
+        let nav = match self.open {
+            Some((_, ref mut popup)) => popup.navigate(key),
+            None => MenuNav::Unhandled(key)
+        };
+        let is_open = self.open.is_some();
+        let cursor = self.cursor.unwrap_or(0);
+        let move_cursor = |this: &mut Self, index: usize| match is_open {
+            true => this.open(index, true),
+            false => {
+                this.cursor = Some(index);
+                this.sync_items();
+            }
+        };
+
+        match nav {
+            MenuNav::Handled => (),
+            MenuNav::Activate(path) => {
+                let mut full_path = vec![self.open_menu().unwrap_or(cursor)];
+                full_path.extend(path);
+                self.activate(&full_path);
+            },
+            MenuNav::Unhandled(Key::LArrow) => move_cursor(self, (cursor + num_menus - 1) % num_menus),
+            MenuNav::Unhandled(Key::RArrow) => move_cursor(self, (cursor + 1) % num_menus),
+            MenuNav::Unhandled(Key::Escape) if is_open => self.close(),
+            MenuNav::Unhandled(Key::DArrow) |
+            MenuNav::Unhandled(Key::Enter) |
+            MenuNav::Unhandled(Key::Space) if !is_open => self.open(cursor, true),
+            MenuNav::Unhandled(_) => return false
+        }
+        true
+    }
+
+    fn on_bar_event(&mut self, event: &BarEvent) {
+        let item_id = match *event {
+            BarEvent::Hover(item_id) |
+            BarEvent::Press(item_id) => item_id
+        };
+        let index = match self.items.iter().position(|i| i.widget_tag.widget_id() == item_id) {
+            Some(index) => index,
+            None => return
+        };
+
+        match *event {
+            BarEvent::Hover(_) => if self.open.is_some() && self.open_menu() != Some(index) {
+                self.open(index, false);
+            },
+            BarEvent::Press(_) => match self.open_menu() == Some(index) {
+                true => self.close(),
+                false => self.open(index, false)
+            }
+        }
+    }
+
+    fn on_menu_event(&mut self, event: &MenuEvent) {
+        match *event {
+            MenuEvent::Activate(ref path) => if let Some(menu) = self.open_menu() {
+                let mut full_path = vec![menu];
+                full_path.extend(path);
+                self.activate(&full_path);
+            },
+            MenuEvent::Key(key) => {self.on_key(key);}
+        }
+    }
+
+    fn on_overlay_dismissed(&mut self, dismissed: &OverlayDismissed) {
+        let popup_id = self.open.as_ref().map(|(_, p)| p.widget_tag.widget_id());
+        if Some(dismissed.overlay) == popup_id {
+            self.open = None;
+            self.sync_items();
+            self.widget_tag.request_redraw().request_relayout();
+        }
+    }
+}
+
+impl<H: MenuHandler> ContextMenu<H> {
+    /// Creates a new, closed context menu containing `entries`.
+    pub fn new(entries: Vec<MenuEntry>, handler: H) -> ContextMenu<H> {
+        let mut widget_tag = WidgetTag::new();
+        widget_tag.register_message(Self::on_show);
+        widget_tag.register_message(Self::on_menu_event);
+        widget_tag.register_message(Self::on_overlay_dismissed);
+
+        ContextMenu {
+            widget_tag,
+            rect: BoundBox::new2(0, 0, 0, 0),
+
+            entries,
+            popup: None,
+
+            handler,
+        }
+    }
+
+    /// Retrieves the entries in the menu.
+    pub fn entries(&self) -> &[MenuEntry] {
+        &self.entries
+    }
+
+    /// Retrieves the entries in the menu, for mutation.
+    ///
+    /// Calling this function closes the menu if it's open.
+    pub fn entries_mut(&mut self) -> &mut Vec<MenuEntry> {
+        self.close();
+        &mut self.entries
+    }
+
+    /// Retrieves whether or not the menu is open.
+    pub fn is_open(&self) -> bool {
+        self.popup.is_some()
+    }
+
+    /// Open the menu with its top-left corner at `pos`, relative to the top-left corner of the
+    /// `anchor` widget.
+    pub fn open_at(&mut self, anchor: WidgetId, pos: Point2<i32>) {
+        self.close();
+        if self.entries.is_empty() {
+            return;
+        }
+
+        let popup = MenuPopup::new(Vec::new(), self.entries.clone(), true);
+        let overlay = Overlay {
+            placement: OverlayPlacement::Offset(pos.to_vec()),
+            ..Overlay::popup(anchor)
+        };
+        if self.widget_tag.show_overlay(popup.widget_tag.widget_id(), overlay).is_ok() {
+            self.popup = Some(popup);
+            self.widget_tag.request_relayout();
+        }
+    }
+
+    /// Close the menu if it's open.
+    pub fn close(&mut self) {
+        if let Some(mut popup) = self.popup.take() {
+            popup.close_submenu();
+            self.widget_tag.hide_overlay(popup.widget_tag.widget_id());
+            self.widget_tag.request_relayout();
+        }
+    }
+
+    fn activate(&mut self, path: &[usize]) {
+        self.close();
+        if let Some(item) = item_at_mut(&mut self.entries, path) {
+            if let Some(ref mut checked) = item.checked {
+                *checked = !*checked;
+            }
+            if let Some(action) = self.handler.on_activate(path, item) {
+                self.widget_tag.broadcast_message(action);
+            }
+        }
+    }
+
+    fn on_show(&mut self, show: &ShowContextMenu) {
+        self.open_at(show.anchor, show.pos);
+    }
+
+    fn on_menu_event(&mut self, event: &MenuEvent) {
+        match *event {
+            MenuEvent::Activate(ref path) => self.activate(path),
+            MenuEvent::Key(key) => {
+                let nav = match self.popup {
+                    Some(ref mut popup) => popup.navigate(key),
+                    None => return
+                };
+                match nav {
+                    MenuNav::Activate(path) => self.activate(&path),
+                    MenuNav::Unhandled(Key::Escape) => self.close(),
+                    _ => ()
+                }
+            }
+        }
+    }
+
+    fn on_overlay_dismissed(&mut self, dismissed: &OverlayDismissed) {
+        if Some(dismissed.overlay) == self.popup.as_ref().map(|p| p.widget_tag.widget_id()) {
+            self.popup = None;
+            self.widget_tag.request_relayout();
+        }
+    }
+}
+
+impl MenuBarItem {
+    fn new(label: String, enabled: bool) -> MenuBarItem {
+        MenuBarItem {
+            widget_tag: WidgetTag::new(),
+            rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+
+            label,
+            enabled,
+            hover: false,
+            active: false,
+            open: false,
+        }
+    }
+
+    fn set_flags(&mut self, active: bool, open: bool) {
+        if (active, open) != (self.active, self.open) {
+            self.active = active;
+            self.open = open;
+            self.widget_tag.request_redraw();
+        }
+    }
+}
+
+impl MenuPopup {
+    fn new(path: Vec<usize>, entries: Vec<MenuEntry>, take_focus: bool) -> MenuPopup {
+        let mut widget_tag = WidgetTag::new();
+        widget_tag.register_message(Self::on_row_event);
+        widget_tag.register_message(Self::on_menu_event);
+        widget_tag.register_message(Self::on_overlay_dismissed);
+
+        MenuPopup {
+            widget_tag,
+            rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+
+            path,
+            rows: entries.iter().map(MenuRow::new).collect(),
+            entries,
+            highlighted: None,
+            submenu: None,
+            take_focus,
+            focused: false,
+        }
+    }
+
+    fn is_selectable(&self, index: usize) -> bool {
+        match self.entries.get(index) {
+            Some(MenuEntry::Item(item)) => item.enabled,
+            _ => false
+        }
+    }
+
+    fn highlight(&mut self, index: Option<usize>) {
+        if index == self.highlighted {
+            return;
+        }
+
+        self.close_submenu();
+        self.highlighted = index;
+        for (i, row) in self.rows.iter_mut().enumerate() {
+            row.set_highlighted(Some(i) == index);
+        }
+    }
+
+    /// Highlight the next selectable item after the highlighted one, or the previous one if
+    /// `forward` is false, wrapping around at the ends.
+    fn step_highlight(&mut self, forward: bool) {
+        let num_rows = self.rows.len();
+        for step in 1..=num_rows {
+            let index = match (self.highlighted, forward) {
+                (Some(h), true) => (h + step) % num_rows,
+                (Some(h), false) => (h + num_rows - step) % num_rows,
+                (None, true) => step - 1,
+                (None, false) => num_rows - step,
+            };
+            if self.is_selectable(index) {
+                self.highlight(Some(index));
+                return;
+            }
+        }
+    }
+
+    /// Open the highlighted item's submenu, if it has one.
+    fn open_submenu(&mut self, highlight_first: bool) {
+        let index = match self.highlighted {
+            Some(index) => index,
+            None => return
+        };
+
+        if self.submenu.is_none() {
+            let entries = match self.entries.get(index) {
+                Some(MenuEntry::Item(item)) if item.enabled && !item.submenu.is_empty() => item.submenu.clone(),
+                _ => return
+            };
+            let mut path = self.path.clone();
+            path.push(index);
+
+            let submenu = MenuPopup::new(path, entries, self.take_focus);
+            let overlay = Overlay {
+                placement: OverlayPlacement::Right,
+                ..Overlay::popup(self.rows[index].widget_tag.widget_id())
+            };
+            if self.widget_tag.show_overlay(submenu.widget_tag.widget_id(), overlay).is_err() {
+                return;
+            }
+            self.submenu = Some(Box::new(submenu));
+            self.widget_tag.request_relayout();
+        }
+
+        if let Some(ref mut submenu) = self.submenu {
+            if highlight_first && submenu.highlighted.is_none() {
+                submenu.step_highlight(true);
+            }
+        }
+    }
+
+    fn close_submenu(&mut self) {
+        if let Some(mut submenu) = self.submenu.take() {
+            submenu.close_submenu();
+            self.widget_tag.hide_overlay(submenu.widget_tag.widget_id());
+            self.widget_tag.request_relayout();
+        }
+    }
+
+    /// Handle a key press, passing it on to the innermost submenu the user is navigating through.
+    fn navigate(&mut self, key: Key) -> MenuNav {
+        // Submenus opened by hovering over an item don't have a highlighted item, and keyboard
+        // navigation stays in this popup until the user moves into them.
+        let submenu_active = self.submenu.as_ref().map(|s| s.highlighted.is_some()).unwrap_or(false);
+        if submenu_active {
+            return match self.submenu.as_mut().unwrap().navigate(key) {
+                MenuNav::Unhandled(Key::LArrow) |
+                MenuNav::Unhandled(Key::Escape) => {
+                    self.close_submenu();
+                    MenuNav::Handled
+                },
+                nav => nav
+            };
+        }
+
+        let highlighted = self.highlighted.and_then(|i| match self.entries.get(i) {
+            Some(MenuEntry::Item(item)) => Some((i, !item.submenu.is_empty())),
+            _ => None
+        });
+        match (key, highlighted) {
+            (Key::UArrow, _) => self.step_highlight(false),
+            (Key::DArrow, _) => self.step_highlight(true),
+            (Key::Home, _) => {
+                self.highlight(None);
+                self.step_highlight(true);
+            },
+            (Key::End, _) => {
+                self.highlight(None);
+                self.step_highlight(false);
+            },
+            (Key::RArrow, Some((_, true))) |
+            (Key::Enter, Some((_, true))) |
+            (Key::Space, Some((_, true))) => self.open_submenu(true),
+            (Key::Enter, Some((index, false))) |
+            (Key::Space, Some((index, false))) => {
+                let mut path = self.path.clone();
+                path.push(index);
+                return MenuNav::Activate(path);
+            },
+            _ => return MenuNav::Unhandled(key)
+        }
+        MenuNav::Handled
+    }
+
+    fn on_row_event(&mut self, event: &RowEvent) {
+        let row_id = match *event {
+            RowEvent::Hover(row_id) |
+            RowEvent::Click(row_id) => row_id
+        };
+        let index = match self.rows.iter().position(|r| r.widget_tag.widget_id() == row_id) {
+            Some(index) => index,
+            None => return
+        };
+        let selectable = self.is_selectable(index);
+
+        match *event {
+            RowEvent::Hover(_) => {
+                self.highlight(Some(index).filter(|_| selectable));
+                self.open_submenu(false);
+            },
+            RowEvent::Click(_) if selectable => {
+                self.highlight(Some(index));
+                match self.entries[index] {
+                    MenuEntry::Item(ref item) if !item.submenu.is_empty() => self.open_submenu(false),
+                    _ => {
+                        let mut path = self.path.clone();
+                        path.push(index);
+                        let popup_id = self.widget_tag.widget_id();
+                        self.widget_tag.send_message_to(MenuEvent::Activate(path), MessageTarget::ParentOf(popup_id));
+                    }
+                }
+            },
+            RowEvent::Click(_) => ()
+        }
+    }
+
+    fn on_menu_event(&mut self, event: &MenuEvent) {
+        let popup_id = self.widget_tag.widget_id();
+        self.widget_tag.send_message_to(event.clone(), MessageTarget::ParentOf(popup_id));
+    }
+
+    fn on_overlay_dismissed(&mut self, dismissed: &OverlayDismissed) {
+        if Some(dismissed.overlay) == self.submenu.as_ref().map(|s| s.widget_tag.widget_id()) {
+            self.submenu = None;
+            self.widget_tag.request_relayout();
+        }
+    }
+}
+
+impl MenuRow {
+    fn new(entry: &MenuEntry) -> MenuRow {
+        let (label, enabled, accelerator) = match entry {
+            MenuEntry::Item(item) => {
+                let check = match item.checked {
+                    Some(true) => "\u{2713} ",
+                    Some(false) => "\u{2003} ",
+                    None => ""
+                };
+                let accelerator = match item.submenu.is_empty() {
+                    true => item.accelerator.clone().unwrap_or_default(),
+                    false => "\u{25B8}".to_string()
+                };
+                (format!("{}{}", check, item.label), item.enabled, accelerator)
+            },
+            MenuEntry::Separator => (String::new(), false, String::new())
+        };
+
+        let mut widget_tag = WidgetTag::new();
+        widget_tag.register_message(Self::on_accelerator_clicked);
+
+        MenuRow {
+            widget_tag,
+            rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+
+            label,
+            separator: *entry == MenuEntry::Separator,
+            enabled,
+            highlighted: false,
+            accelerator: MenuAccelerator {
+                widget_tag: WidgetTag::new(),
+                rect: BoundBox::new2(0, 0, 0, 0),
+                size_bounds: SizeBounds::default(),
+
+                text: accelerator,
+                enabled,
+                highlighted: false,
+            },
+        }
+    }
+
+    fn set_highlighted(&mut self, highlighted: bool) {
+        if highlighted != self.highlighted {
+            self.highlighted = highlighted;
+            self.accelerator.highlighted = highlighted;
+            self.widget_tag.request_redraw();
+            self.accelerator.widget_tag.request_redraw();
+        }
+    }
+
+    fn has_accelerator(&self) -> bool {
+        !self.accelerator.text.is_empty()
+    }
+
+    fn on_accelerator_clicked(&mut self, _: &AcceleratorClicked) {
+        let row_id = self.widget_tag.widget_id();
+        self.widget_tag.send_message_to(RowEvent::Click(row_id), MessageTarget::ParentOf(row_id));
+    }
+}
+
+impl<H> Widget for MenuBar<H>
+    where H: MenuHandler
+{
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        self.widget_tag.request_relayout();
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        use self::WidgetEvent::*;
+        let event = event.unwrap();
+
+        let mut focus = None;
+        let mut bubble = event.default_bubble();
+        let bar_rect = BoundBox::new2(0, 0, self.rect.width(), self.rect.height());
+
+        match event {
+            MouseDown{in_widget: true, ..} => focus = Some(FocusChange::Take),
+            // Clicks on the bar's items, and on the open menu, don't remove focus from the bar.
+            MouseDown{in_widget: false, pos, ..} if self.open.is_none() && !bar_rect.contains(pos) => {
+                focus = Some(FocusChange::Remove);
+            },
+            KeyDown(key, _) => if self.on_key(key) {
+                bubble = false;
+            },
+            LoseFocus => {
+                self.close();
+                self.cursor = None;
+                self.sync_items();
+            },
+            _ => ()
+        }
+
+        EventOps {
+            focus,
+            bubble,
+        }
+    }
+}
+
+impl<H> Widget for ContextMenu<H>
+    where H: MenuHandler
+{
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        SizeBounds::new(DimsBox::new2(0, 0), DimsBox::new2(0, 0))
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        EventOps {
+            focus: None,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
+impl Widget for MenuBarItem {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        let event = event.unwrap();
+        let item_id = self.widget_tag.widget_id();
+        let mut focus = None;
+
+        let bar_event = match event {
+            WidgetEvent::MouseMove{hover_change: Some(ref change), ..} => {
+                let hover = match change {
+                    MouseHoverChange::Enter => true,
+                    MouseHoverChange::Exit => false,
+                    _ => self.hover
+                };
+                if hover != self.hover {
+                    self.hover = hover;
+                    self.widget_tag.request_redraw();
+                }
+                Some(BarEvent::Hover(item_id)).filter(|_| hover)
+            },
+            WidgetEvent::MouseDown{in_widget: true, button: MouseButton::Left, ..} => {
+                focus = Some(FocusChange::Parent);
+                Some(BarEvent::Press(item_id))
+            },
+            _ => None
+        };
+        if let Some(bar_event) = bar_event {
+            self.widget_tag.send_message_to(bar_event, MessageTarget::ParentOf(item_id));
+        }
+
+        EventOps {
+            focus,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
+impl Widget for MenuPopup {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        self.widget_tag.request_relayout();
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        use self::WidgetEvent::*;
+        let event = event.unwrap();
+        let popup_id = self.widget_tag.widget_id();
+
+        let mut focus = None;
+        let mut bubble = event.default_bubble();
+        match event {
+            MouseMove{hover_change: Some(MouseHoverChange::Enter), ..} if self.take_focus && !self.focused => {
+                focus = Some(FocusChange::Take);
+            },
+            GainFocus(..) => self.focused = true,
+            LoseFocus => self.focused = false,
+            KeyDown(key, _) => {
+                bubble = false;
+                self.widget_tag.send_message_to(MenuEvent::Key(key), MessageTarget::ParentOf(popup_id));
+            },
+            _ => ()
+        }
+
+        EventOps {
+            focus,
+            bubble,
+        }
+    }
+}
+
+impl Widget for MenuRow {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        let event = event.unwrap();
+        let row_id = self.widget_tag.widget_id();
+
+        // Releasing the mouse over a row activates it, even if the button was pressed elsewhere.
+        // That lets the user press on a menu bar item, drag down to an item, and release.
+        let row_event = match event {
+            WidgetEvent::MouseMove{hover_change: Some(MouseHoverChange::Enter), ..} => Some(RowEvent::Hover(row_id)),
+            WidgetEvent::MouseUp{in_widget: true, button: MouseButton::Left, ..} => Some(RowEvent::Click(row_id)),
+            _ => None
+        };
+        if let Some(row_event) = row_event {
+            self.widget_tag.send_message_to(row_event, MessageTarget::ParentOf(row_id));
+        }
+
+        EventOps {
+            focus: None,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
+impl Widget for MenuAccelerator {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        let event = event.unwrap();
+        if let WidgetEvent::MouseUp{in_widget: true, button: MouseButton::Left, ..} = event {
+            let accelerator_id = self.widget_tag.widget_id();
+            self.widget_tag.send_message_to(AcceleratorClicked, MessageTarget::ParentOf(accelerator_id));
+        }
+
+        EventOps {
+            focus: None,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
+impl<H> Parent for MenuBar<H>
+    where H: MenuHandler
+{
+    fn num_children(&self) -> usize {
+        self.items.len() + self.open.is_some() as usize
+    }
+
+    fn framed_child<R: Renderer>(&self, widget_ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(index) => self.framed_child_by_index(index as usize),
+            _ => None
+        }
+    }
+    fn framed_child_mut<R: Renderer>(&mut self, widget_ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(index) => self.framed_child_by_index_mut(index as usize),
+            _ => None
+        }
+    }
+
+    fn framed_children<'a, R, G>(&'a self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
+    {
+        for (index, item) in self.items.iter().enumerate() {
+            if let LoopFlow::Break = for_each(WidgetInfo::new(WidgetIdent::Num(index as u32), index, item)) {
+                return;
+            }
+        }
+        if let Some((_, ref popup)) = self.open {
+            let index = self.items.len();
+            let _ = for_each(WidgetInfo::new(WidgetIdent::Num(index as u32), index, popup));
+        }
+    }
+
+    fn framed_children_mut<'a, R, G>(&'a mut self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
+    {
+        for (index, item) in self.items.iter_mut().enumerate() {
+            if let LoopFlow::Break = for_each(WidgetInfoMut::new(WidgetIdent::Num(index as u32), index, item)) {
+                return;
+            }
+        }
+        if let Some((_, ref mut popup)) = self.open {
+            let index = self.items.len();
+            let _ = for_each(WidgetInfoMut::new(WidgetIdent::Num(index as u32), index, popup));
+        }
+    }
+
+    fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
+        let ident = WidgetIdent::Num(index as u32);
+        match (self.items.get(index), &self.open) {
+            (Some(item), _) => Some(WidgetInfo::new(ident, index, item)),
+            (None, Some((_, popup))) if index == self.items.len() => Some(WidgetInfo::new(ident, index, popup)),
+            _ => None
+        }
+    }
+    fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
+        let ident = WidgetIdent::Num(index as u32);
+        let num_items = self.items.len();
+        match (self.items.get_mut(index), &mut self.open) {
+            (Some(item), _) => Some(WidgetInfoMut::new(ident, index, item)),
+            (None, Some((_, popup))) if index == num_items => Some(WidgetInfoMut::new(ident, index, popup)),
+            _ => None
+        }
+    }
+}
+
+impl<H> Parent for ContextMenu<H>
+    where H: MenuHandler
+{
+    fn num_children(&self) -> usize {
+        self.popup.is_some() as usize
+    }
+
+    fn framed_child<R: Renderer>(&self, widget_ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(0) => self.framed_child_by_index(0),
+            _ => None
+        }
+    }
+    fn framed_child_mut<R: Renderer>(&mut self, widget_ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(0) => self.framed_child_by_index_mut(0),
+            _ => None
+        }
+    }
+
+    fn framed_children<'a, R, G>(&'a self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
+    {
+        if let Some(ref popup) = self.popup {
+            let _ = for_each(WidgetInfo::new(WidgetIdent::Num(0), 0, popup));
+        }
+    }
+
+    fn framed_children_mut<'a, R, G>(&'a mut self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
+    {
+        if let Some(ref mut popup) = self.popup {
+            let _ = for_each(WidgetInfoMut::new(WidgetIdent::Num(0), 0, popup));
+        }
+    }
+
+    fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
+        match (index, &self.popup) {
+            (0, Some(popup)) => Some(WidgetInfo::new(WidgetIdent::Num(0), 0, popup)),
+            _ => None
+        }
+    }
+    fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
+        match (index, &mut self.popup) {
+            (0, Some(popup)) => Some(WidgetInfoMut::new(WidgetIdent::Num(0), 0, popup)),
+            _ => None
+        }
+    }
+}
+
+impl Parent for MenuPopup {
+    fn num_children(&self) -> usize {
+        self.rows.len() + self.submenu.is_some() as usize
+    }
+
+    fn framed_child<R: Renderer>(&self, widget_ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(index) => self.framed_child_by_index(index as usize),
+            _ => None
+        }
+    }
+    fn framed_child_mut<R: Renderer>(&mut self, widget_ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(index) => self.framed_child_by_index_mut(index as usize),
+            _ => None
+        }
+    }
+
+    fn framed_children<'a, R, G>(&'a self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
+    {
+        for (index, row) in self.rows.iter().enumerate() {
+            if let LoopFlow::Break = for_each(WidgetInfo::new(WidgetIdent::Num(index as u32), index, row)) {
+                return;
+            }
+        }
+        if let Some(ref submenu) = self.submenu {
+            let index = self.rows.len();
+            let _ = for_each(WidgetInfo::new(WidgetIdent::Num(index as u32), index, &**submenu));
+        }
+    }
+
+    fn framed_children_mut<'a, R, G>(&'a mut self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
+    {
+        for (index, row) in self.rows.iter_mut().enumerate() {
+            if let LoopFlow::Break = for_each(WidgetInfoMut::new(WidgetIdent::Num(index as u32), index, row)) {
+                return;
+            }
+        }
+        if let Some(ref mut submenu) = self.submenu {
+            let index = self.rows.len();
+            let _ = for_each(WidgetInfoMut::new(WidgetIdent::Num(index as u32), index, &mut **submenu));
+        }
+    }
+
+    fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
+        let ident = WidgetIdent::Num(index as u32);
+        match (self.rows.get(index), &self.submenu) {
+            (Some(row), _) => Some(WidgetInfo::new(ident, index, row)),
+            (None, Some(submenu)) if index == self.rows.len() => Some(WidgetInfo::new(ident, index, &**submenu)),
+            _ => None
+        }
+    }
+    fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
+        let ident = WidgetIdent::Num(index as u32);
+        let num_rows = self.rows.len();
+        match (self.rows.get_mut(index), &mut self.submenu) {
+            (Some(row), _) => Some(WidgetInfoMut::new(ident, index, row)),
+            (None, Some(submenu)) if index == num_rows => Some(WidgetInfoMut::new(ident, index, &mut **submenu)),
+            _ => None
+        }
+    }
+}
+
+impl Parent for MenuRow {
+    fn num_children(&self) -> usize {
+        self.has_accelerator() as usize
+    }
+
+    fn framed_child<R: Renderer>(&self, widget_ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(0) => self.framed_child_by_index(0),
+            _ => None
+        }
+    }
+    fn framed_child_mut<R: Renderer>(&mut self, widget_ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(0) => self.framed_child_by_index_mut(0),
+            _ => None
+        }
+    }
+
+    fn framed_children<'a, R, G>(&'a self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
+    {
+        if self.has_accelerator() {
+            let _ = for_each(WidgetInfo::new(WidgetIdent::Num(0), 0, &self.accelerator));
+        }
+    }
+
+    fn framed_children_mut<'a, R, G>(&'a mut self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
+    {
+        if self.has_accelerator() {
+            let _ = for_each(WidgetInfoMut::new(WidgetIdent::Num(0), 0, &mut self.accelerator));
+        }
+    }
+
+    fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
+        match (index, self.has_accelerator()) {
+            (0, true) => Some(WidgetInfo::new(WidgetIdent::Num(0), 0, &self.accelerator)),
+            _ => None
+        }
+    }
+    fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
+        match (index, self.has_accelerator()) {
+            (0, true) => Some(WidgetInfoMut::new(WidgetIdent::Num(0), 0, &mut self.accelerator)),
+            _ => None
+        }
+    }
+}
+
+impl<R, H> WidgetRenderable<R> for MenuBar<H>
+    where R: Renderer,
+          H: MenuHandler
+{
+    type Theme = MenuBarTheme;
+
+    fn theme(&self) -> MenuBarTheme {
+        MenuBarTheme(())
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        self.sync_items();
+
+        let result = layout.finish();
+        let content_rect = result.content_rect;
+        let margin_width = content_rect.min.x + (self.rect.width() - content_rect.max.x);
+        let margin_height = content_rect.min.y + (self.rect.height() - content_rect.max.y);
+
+        let height = self.items.iter().map(|i| i.size_bounds.min.height()).max().unwrap_or(0);
+        let mut x = content_rect.min.x;
+        for item in &mut self.items {
+            let width = item.size_bounds.min.width();
+            let rect = BoundBox::new2(x, content_rect.min.y, x + width, content_rect.min.y + height);
+            if item.rect != rect {
+                item.rect = rect;
+                item.widget_tag.request_relayout();
+            }
+            x += width;
+        }
+
+        let min = DimsBox::new2(
+            (x - content_rect.min.x + margin_width).max(result.size_bounds.min.width()),
+            (height + margin_height).max(result.size_bounds.min.height()),
+        );
+        self.size_bounds = SizeBounds::new(min, DimsBox::new2(result.size_bounds.max.width(), min.height()));
+    }
+}
+
+impl<R, H> WidgetRenderable<R> for ContextMenu<H>
+    where R: Renderer,
+          H: MenuHandler
+{
+    type Theme = ContextMenuTheme;
+
+    fn theme(&self) -> ContextMenuTheme {
+        ContextMenuTheme(())
+    }
+
+    fn render(&mut self, _: &mut R::SubFrame) {}
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        layout.finish();
+    }
+}
+
+impl<R> WidgetRenderable<R> for MenuBarItem
+    where R: Renderer
+{
+    type Theme = MenuBarItemTheme;
+
+    fn theme(&self) -> MenuBarItemTheme {
+        MenuBarItemTheme {
+            hover: self.hover || self.active,
+            open: self.open,
+            enabled: self.enabled,
+        }
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        layout.prepare_string(&self.label);
+
+        let result = layout.finish();
+        self.size_bounds = result.size_bounds;
+    }
+}
+
+impl<R> WidgetRenderable<R> for MenuPopup
+    where R: Renderer
+{
+    type Theme = MenuPopupTheme;
+
+    fn theme(&self) -> MenuPopupTheme {
+        MenuPopupTheme(())
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        let result = layout.finish();
+        let content_rect = result.content_rect;
+        let (margin_left, margin_top) = (content_rect.min.x, content_rect.min.y);
+        let margin_width = margin_left + (self.rect.width() - content_rect.max.x);
+        let margin_height = margin_top + (self.rect.height() - content_rect.max.y);
+
+        let row_width = self.rows.iter().map(|r| r.size_bounds.min.width()).max().unwrap_or(0);
+        let mut y = margin_top;
+        for row in &mut self.rows {
+            // Separators are drawn as a thin line through the middle of their slot.
+            let rect = match row.separator {
+                true => {
+                    let line_y = y + SEPARATOR_HEIGHT / 2;
+                    y += SEPARATOR_HEIGHT;
+                    BoundBox::new2(margin_left, line_y, margin_left + row_width, line_y + 1)
+                },
+                false => {
+                    let height = row.size_bounds.min.height();
+                    y += height;
+                    BoundBox::new2(margin_left, y - height, margin_left + row_width, y)
+                }
+            };
+            if row.rect != rect {
+                row.rect = rect;
+                row.widget_tag.request_relayout();
+            }
+        }
+
+        let dims = DimsBox::new2(
+            (row_width + margin_width).max(result.size_bounds.min.width()),
+            (y - margin_top + margin_height).max(result.size_bounds.min.height()),
+        );
+        self.size_bounds = SizeBounds::new(dims, dims);
+    }
+}
+
+impl<R> WidgetRenderable<R> for MenuRow
+    where R: Renderer
+{
+    type Theme = MenuRowTheme;
+
+    fn theme(&self) -> MenuRowTheme {
+        MenuRowTheme {
+            separator: self.separator,
+            highlighted: self.highlighted,
+            enabled: self.enabled,
+        }
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        if !self.separator {
+            layout.prepare_string(&self.label);
+        }
+
+        let result = layout.finish();
+        let mut min = result.size_bounds.min;
+
+        if self.has_accelerator() {
+            // The accelerator goes on the right edge of the row, and popups make all of their rows
+            // the same width, so the accelerators line up in a column.
+            let accelerator_dims = self.accelerator.size_bounds.min;
+            let rect = BoundBox::new2(
+                self.rect.width() - accelerator_dims.width(),
+                0,
+                self.rect.width(),
+                self.rect.height(),
+            );
+            if self.accelerator.rect != rect {
+                self.accelerator.rect = rect;
+                self.accelerator.widget_tag.request_relayout();
+            }
+
+            min = DimsBox::new2(
+                min.width() + ACCELERATOR_GAP + accelerator_dims.width(),
+                min.height().max(accelerator_dims.height()),
+            );
+        }
+        self.size_bounds = SizeBounds::new_min(min);
+    }
+}
+
+impl<R> WidgetRenderable<R> for MenuAccelerator
+    where R: Renderer
+{
+    type Theme = MenuAcceleratorTheme;
+
+    fn theme(&self) -> MenuAcceleratorTheme {
+        MenuAcceleratorTheme {
+            highlighted: self.highlighted,
+            enabled: self.enabled,
+        }
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        layout.prepare_string(&self.text);
+
+        let result = layout.finish();
+        self.size_bounds = result.size_bounds;
+    }
+}
+
+impl WidgetTheme for MenuBarTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for MenuBarTheme {
+    fn theme_path(&self) -> &'static str {
+        "MenuBar::Background"
+    }
+}
+
+impl WidgetTheme for MenuBarItemTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for MenuBarItemTheme {
+    fn theme_path(&self) -> &'static str {
+        match (self.enabled, self.open, self.hover) {
+            (false, _, _) => "MenuBar::Item::Disabled",
+            (true, true, _) => "MenuBar::Item::Open",
+            (true, false, true) => "MenuBar::Item::Hover",
+            (true, false, false) => "MenuBar::Item::Normal",
+        }
+    }
+}
+
+impl WidgetTheme for ContextMenuTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for ContextMenuTheme {
+    fn theme_path(&self) -> &'static str {
+        "ContextMenu"
+    }
+}
+
+impl WidgetTheme for MenuPopupTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for MenuPopupTheme {
+    fn theme_path(&self) -> &'static str {
+        "Menu::Popup"
+    }
+}
+
+impl WidgetTheme for MenuRowTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for MenuRowTheme {
+    fn theme_path(&self) -> &'static str {
+        match (self.separator, self.enabled, self.highlighted) {
+            (true, _, _) => "Menu::Separator",
+            (false, false, _) => "Menu::Item::Disabled",
+            (false, true, true) => "Menu::Item::Highlighted",
+            (false, true, false) => "Menu::Item::Normal",
+        }
+    }
+}
+
+impl WidgetTheme for MenuAcceleratorTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for MenuAcceleratorTheme {
+    fn theme_path(&self) -> &'static str {
+        match (self.enabled, self.highlighted) {
+            (false, _) => "Menu::Accelerator::Disabled",
+            (true, true) => "Menu::Accelerator::Highlighted",
+            (true, false) => "Menu::Accelerator::Normal",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::WindowEvent;
+    use crate::soft_render::snapshot::Snapshot;
+    use crate::theme::Theme;
+
+    #[derive(Default)]
+    struct ActivateLog(Vec<Vec<usize>>);
+    impl MenuHandler for ActivateLog {
+        type Action = ();
+        fn on_activate(&mut self, path: &[usize], _: &MenuItem) -> Option<()> {
+            self.0.push(path.to_vec());
+            None
+        }
+    }
+
+    fn item(label: &str) -> MenuEntry {
+        MenuEntry::Item(MenuItem::new(label.to_string()))
+    }
+
+    fn press(snapshot: &mut Snapshot<MenuBar<ActivateLog>>, key: Key) {
+        snapshot.process_events(vec![WindowEvent::KeyDown(key), WindowEvent::KeyUp(key)]);
+        snapshot.render();
+    }
+
+    fn highlighted(menu_bar: &MenuBar<ActivateLog>) -> Option<usize> {
+        menu_bar.open.as_ref().and_then(|(_, popup)| popup.highlighted)
+    }
+
+    #[test]
+    fn arrow_and_escape_navigation() {
+        let menus = vec![
+            MenuItem::submenu("File".to_string(), vec![item("New"), item("Open"), MenuEntry::Separator, item("Quit")]),
+            MenuItem::submenu("Edit".to_string(), vec![item("Undo"), item("Redo")]),
+        ];
+        let mut snapshot = Snapshot::new(MenuBar::new(menus, ActivateLog::default()), Theme::default(), DimsBox::new2(200, 100));
+        snapshot.render();
+
+        // Clicking a bar item focuses the bar and opens the item's menu, without highlighting anything.
+        let file_rect = snapshot.root().items[0].rect;
+        snapshot.process_events(vec![
+            WindowEvent::MouseEnter,
+            WindowEvent::MouseMove(file_rect.center()),
+            WindowEvent::MouseDown(MouseButton::Left),
+            WindowEvent::MouseUp(MouseButton::Left),
+        ]);
+        snapshot.render();
+        assert_eq!(Some(0), snapshot.root().open_menu());
+        assert_eq!(None, highlighted(snapshot.root()));
+
+        // Down steps through the items, skipping separators and wrapping around at the end.
+        press(&mut snapshot, Key::DArrow);
+        press(&mut snapshot, Key::DArrow);
+        assert_eq!(Some(1), highlighted(snapshot.root()));
+        press(&mut snapshot, Key::DArrow);
+        assert_eq!(Some(3), highlighted(snapshot.root()));
+        press(&mut snapshot, Key::DArrow);
+        assert_eq!(Some(0), highlighted(snapshot.root()));
+        press(&mut snapshot, Key::UArrow);
+        assert_eq!(Some(3), highlighted(snapshot.root()));
+
+        // Right switches to the next menu, highlighting its first item.
+        press(&mut snapshot, Key::RArrow);
+        assert_eq!(Some(1), snapshot.root().open_menu());
+        assert_eq!(Some(0), highlighted(snapshot.root()));
+
+        // Escape closes the menu, but the bar keeps its cursor so Down re-opens the same menu.
+        press(&mut snapshot, Key::Escape);
+        assert_eq!(None, snapshot.root().open_menu());
+        assert_eq!(Some(1), snapshot.root().cursor);
+        press(&mut snapshot, Key::DArrow);
+        assert_eq!(Some(1), snapshot.root().open_menu());
+
+        press(&mut snapshot, Key::DArrow);
+        press(&mut snapshot, Key::Enter);
+        assert_eq!(None, snapshot.root().open_menu());
+        assert_eq!(vec![vec![1, 1]], snapshot.root().handler.0);
+    }
+}
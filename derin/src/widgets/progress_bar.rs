@@ -7,6 +7,7 @@ use derin_core::{
     widget::{Parent, Widget, WidgetInfo, WidgetInfoMut, WidgetIdent, WidgetTag, WidgetRenderable},
     render::{Renderer, RendererLayout, SubFrame, WidgetTheme},
 };
+use crate::theme::ThemePath;
 use derin_common_types::layout::SizeBounds;
 use crate::{
     event::{EventOps, WidgetEventSourced, InputState},
@@ -238,9 +239,21 @@ impl WidgetTheme for ProgressBarTheme {
     }
 }
 
+impl ThemePath for ProgressBarTheme {
+    fn theme_path(&self) -> &'static str {
+        "ProgressBar::Background"
+    }
+}
+
 impl WidgetTheme for ProgressBarFillTheme {
     type Fallback = !;
     fn fallback(self) -> Option<!> {
         None
     }
 }
+
+impl ThemePath for ProgressBarFillTheme {
+    fn theme_path(&self) -> &'static str {
+        "ProgressBar::Fill"
+    }
+}
@@ -8,12 +8,13 @@ use derin_core::{
     widget::{WidgetIdent, WidgetRenderable, WidgetTag, WidgetInfo, WidgetInfoMut, WidgetId, Widget, Parent},
     render::{Renderer, SubFrame, WidgetTheme},
 };
+use crate::theme::ThemePath;
 use crate::{
     container::WidgetContainer,
     layout::GridLayout,
     widgets::{
         Contents,
-        assistants::toggle_button::{Toggle, ToggleOnClickHandler},
+        assistants::toggle_button::{Toggle, ToggleKind, ToggleOnClickHandler},
     },
 };
 
@@ -79,6 +80,8 @@ impl<C, L> RadioButtonList<C, L>
     pub fn new(buttons: C, layout: L) -> RadioButtonList<C, L> {
         let mut widget_tag = WidgetTag::new();
         widget_tag.register_message(Self::on_child_selected);
+        // The buttons get tabbed into and out of as a single stop.
+        widget_tag.set_focus_group(true);
         RadioButtonList {
             widget_tag,
             rect: BoundBox::new2(0, 0, 0, 0),
@@ -114,7 +117,7 @@ impl RadioButton {
     /// Creates a new radio button, with the given default selected state and contents.
     pub fn new(selected: bool, contents: Contents) -> RadioButton {
         RadioButton {
-            toggle: Toggle::new(selected, contents, RadioButtonToggleHandler, RadioButtonTheme(())),
+            toggle: Toggle::new(ToggleKind::RadioButton, selected, contents, RadioButtonToggleHandler, RadioButtonTheme(())),
         }
     }
 
@@ -162,6 +165,11 @@ impl Widget for RadioButton {
         self.toggle.rect_mut()
     }
 
+    #[inline]
+    fn focus_rect(&self) -> BoundBox<D2, i32> {
+        self.toggle.focus_rect()
+    }
+
     fn size_bounds(&self) -> SizeBounds {
         self.toggle.size_bounds()
     }
@@ -333,7 +341,19 @@ impl WidgetTheme for RadioButtonTheme {
     fn fallback(self) -> Option<!> {None}
 }
 
+impl ThemePath for RadioButtonTheme {
+    fn theme_path(&self) -> &'static str {
+        "RadioButton"
+    }
+}
+
 impl WidgetTheme for RadioButtonListTheme {
     type Fallback = !;
     fn fallback(self) -> Option<!> {None}
 }
+
+impl ThemePath for RadioButtonListTheme {
+    fn theme_path(&self) -> &'static str {
+        "RadioButtonList"
+    }
+}
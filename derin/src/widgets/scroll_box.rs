@@ -2,52 +2,169 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use crate::{
-    core::{
-        LoopFlow,
-        event::{EventOps, WidgetEvent, InputState, WidgetEventSourced},
-        widget::{WidgetIdent, WidgetRenderable, WidgetTag, WidgetInfo, WidgetInfoMut, Widget, Parent},
-        render::Renderer,
-    },
-    gl_render::{ThemedPrim, PrimFrame, RelPoint, Prim},
-    widgets::Clip,
-    widgets::assistants::SliderAssist,
+use derin_core::{
+    LoopFlow,
+    event::{EventOps, WidgetEvent, WidgetEventSourced, InputState, MouseButton, MouseHoverChange},
+    timer::{Timer, TimerId},
+    widget::{MessageTarget, WidgetIdent, WidgetTag, WidgetRenderable, WidgetInfo, WidgetInfoMut, WidgetId, Widget, Parent, Visibility},
+    render::{Renderer, RendererLayout, SubFrame, WidgetTheme},
 };
+use crate::theme::ThemePath;
+use crate::widgets::{Clip, assistants::ButtonState};
 
-use crate::cgmath::{Point2, Vector2};
+use crate::cgmath::{EuclideanSpace, Point2, Vector2};
 use cgmath_geometry::{D2, rect::{BoundBox, DimsBox, GeoBox}};
 use derin_common_types::layout::SizeBounds;
-use derin_common_types::buttons::MouseButton;
-
-use arrayvec::ArrayVec;
-
-use std::f32;
-use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-const SCROLL_BAR_SIZE: i32 = 16;
+/// The thickness of a scroll bar, and the length of its arrow buttons.
+pub(crate) const SCROLL_BAR_SIZE: i32 = 16;
+/// The number of pixels scrolled by a line of mouse wheel input, or by clicking an arrow button.
+const SCROLL_LINE: i32 = 24;
+/// The shortest a scroll bar's head can get.
+const MIN_HEAD_SIZE: i32 = 16;
+/// How long an arrow button has to be held down before it starts repeating.
+const REPEAT_DELAY: Duration = Duration::from_millis(400);
+const REPEAT_INTERVAL: Duration = Duration::from_millis(50);
+/// How long a smooth scroll takes to reach its destination.
+const SMOOTH_SCROLL_DURATION: Duration = Duration::from_millis(150);
+const SMOOTH_SCROLL_FRAME: Duration = Duration::from_millis(16);
 
 /// A widget that is used to apply scroll bars to a contained widget.
 ///
 /// These bars are hidden by default, only appearing when the inner widget's minimum size is
-/// greater than the scroll box's size.
+/// greater than the scroll box's size. The widget can be scrolled by dragging the bars' heads,
+/// clicking the bars or their arrow buttons, or with the mouse wheel. Widgets inside the scroll
+/// box get scrolled into view when they gain keyboard focus, or when they call
+/// [`WidgetTag::request_scroll_into_view`].
+///
+/// [`WidgetTag::request_scroll_into_view`]: ../custom/struct.WidgetTag.html#method.request_scroll_into_view
 #[derive(Debug, Clone)]
 pub struct ScrollBox<W> {
     widget_tag: WidgetTag,
     rect: BoundBox<D2, i32>,
-    slider_x: Option<SliderAssist>,
-    slider_y: Option<SliderAssist>,
-    clip: Clip<W>
+    size_bounds: SizeBounds,
+
+    clip: Clip<W>,
+    bar_x: ScrollBar,
+    bar_y: ScrollBar,
+
+    /// The number of pixels the widget is scrolled by on each axis.
+    offset: Vector2<i32>,
+    /// The furthest the widget can be scrolled on each axis.
+    max_offset: Vector2<i32>,
+    scroll_to: Option<BoundBox<D2, i32>>,
+    smooth_scrolling: bool,
+    animation: Option<ScrollAnimation>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScrollBoxTheme(());
+
+#[derive(Debug, Clone, Default)]
+pub struct ScrollBarTheme(());
+
+#[derive(Debug, Clone, Copy)]
+pub struct ScrollHeadTheme {
+    pub state: ButtonState,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ScrollArrowTheme {
+    pub horizontal: bool,
+    /// Whether the arrow points towards the end of the bar, rather than the start.
+    pub increase: bool,
+    pub state: ButtonState,
+}
+
+/// The track of a scroll bar. Clicking the track scrolls by a page towards the click.
+#[derive(Debug, Clone)]
+struct ScrollBar {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    horizontal: bool,
+    decrease: ScrollArrow,
+    increase: ScrollArrow,
+    head: ScrollHead,
+}
+
+#[derive(Debug, Clone)]
+struct ScrollHead {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    bar_id: WidgetId,
+    horizontal: bool,
+    state: ButtonState,
+    /// Where the head was clicked, along the bar's axis, if it's being dragged.
+    click_pos: Option<i32>,
+}
+
+#[derive(Debug, Clone)]
+struct ScrollArrow {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    bar_id: WidgetId,
+    horizontal: bool,
+    increase: bool,
+    state: ButtonState,
+    repeat_timer: Option<TimerId>,
+}
+
+/// An in-progress smooth scroll.
+#[derive(Debug, Clone, Copy)]
+struct ScrollAnimation {
+    timer_id: TimerId,
+    start_offset: Vector2<i32>,
+    target_offset: Vector2<i32>,
+    start_time: Instant,
 }
 
-impl<W> ScrollBox<W> {
+/// Sent from a scroll bar, or one of its parts, to the scroll box that contains it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScrollEvent {
+    Lines {
+        horizontal: bool,
+        lines: i32,
+    },
+    Pages {
+        horizontal: bool,
+        pages: i32,
+    },
+    /// The head got dragged so that it starts `head_start` pixels from the start of its bar.
+    Drag {
+        horizontal: bool,
+        head_start: i32,
+    },
+}
+
+impl<W> ScrollBox<W>
+    where W: Widget
+{
     /// Creates a `ScrollBox` that scrolls the provided widget.
     pub fn new(widget: W) -> ScrollBox<W> {
+        let mut widget_tag = WidgetTag::new();
+        widget_tag.register_message(Self::on_scroll_event);
+
         ScrollBox {
-            widget_tag: WidgetTag::new(),
+            widget_tag,
             rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+
             clip: Clip::new(widget),
-            slider_x: None,
-            slider_y: None
+            bar_x: ScrollBar::new(true),
+            bar_y: ScrollBar::new(false),
+
+            offset: Vector2::new(0, 0),
+            max_offset: Vector2::new(0, 0),
+            scroll_to: None,
+            smooth_scrolling: false,
+            animation: None,
         }
     }
 
@@ -61,18 +178,293 @@ impl<W> ScrollBox<W> {
         self.clip.widget_mut()
     }
 
-    fn child_summary<R>(&self) -> WidgetInfo<'_, R>
-        where W: Widget,
-              R: Renderer
-    {
-        WidgetInfo::new(CLIP_IDENT.clone(), 0, &self.clip)
+    /// Retrieves the number of pixels the widget is scrolled by on each axis.
+    pub fn offset(&self) -> Vector2<i32> {
+        self.offset
     }
 
-    fn child_summary_mut<R>(&mut self) -> WidgetInfoMut<'_, R>
-        where W: Widget,
-              R: Renderer
-    {
-        WidgetInfoMut::new(CLIP_IDENT.clone(), 0, &mut self.clip)
+    /// Retrieves the number of pixels the widget is scrolled by on each axis, for mutation.
+    pub fn offset_mut(&mut self) -> &mut Vector2<i32> {
+        self.widget_tag.request_redraw().request_relayout();
+        &mut self.offset
+    }
+
+    /// Scroll just far enough to show `rect`, which is relative to the scrollable widget's origin.
+    pub fn scroll_to_rect(&mut self, rect: BoundBox<D2, i32>) {
+        self.stop_animation();
+        self.scroll_to = Some(rect);
+        self.widget_tag.request_redraw().request_relayout();
+    }
+
+    /// Whether scrolling a widget into view animates the scroll, rather than jumping straight to
+    /// the new position. Defaults to `false`.
+    pub fn smooth_scrolling(&self) -> bool {
+        self.smooth_scrolling
+    }
+
+    pub fn set_smooth_scrolling(&mut self, smooth_scrolling: bool) {
+        self.smooth_scrolling = smooth_scrolling;
+        if !smooth_scrolling {
+            self.finish_animation();
+        }
+    }
+
+    fn clamp_offset(&self, offset: Vector2<i32>) -> Vector2<i32> {
+        Vector2::new(
+            offset.x.min(self.max_offset.x).max(0),
+            offset.y.min(self.max_offset.y).max(0),
+        )
+    }
+
+    fn set_offset(&mut self, offset: Vector2<i32>) -> bool {
+        let changed = offset != self.offset;
+        if changed {
+            self.offset = offset;
+            self.widget_tag.request_redraw().request_relayout();
+        }
+        changed
+    }
+
+    /// Scroll by `delta` pixels, returning whether the offset changed.
+    fn scroll_by(&mut self, delta: Vector2<i32>) -> bool {
+        // Direct user input takes over from any smooth scroll.
+        self.stop_animation();
+        let offset = self.clamp_offset(self.offset + delta);
+        self.set_offset(offset)
+    }
+
+    /// Scroll to `offset`, animating the scroll if smooth scrolling is turned on.
+    fn scroll_to_offset(&mut self, offset: Vector2<i32>) {
+        self.stop_animation();
+        if !self.smooth_scrolling || offset == self.offset {
+            self.set_offset(offset);
+            return;
+        }
+
+        let timer_id = TimerId::new();
+        self.widget_tag.timers_mut().insert(timer_id, Timer::new(SMOOTH_SCROLL_FRAME));
+        self.animation = Some(ScrollAnimation {
+            timer_id,
+            start_offset: self.offset,
+            target_offset: offset,
+            start_time: Instant::now(),
+        });
+    }
+
+    fn stop_animation(&mut self) {
+        if let Some(animation) = self.animation.take() {
+            self.widget_tag.timers_mut().remove(&animation.timer_id);
+        }
+    }
+
+    /// Jump to the end of the current smooth scroll, if there is one.
+    fn finish_animation(&mut self) {
+        if let Some(animation) = self.animation {
+            self.stop_animation();
+            self.set_offset(animation.target_offset);
+        }
+    }
+
+    fn step_animation(&mut self, animation: ScrollAnimation) {
+        let elapsed = animation.start_time.elapsed();
+        if elapsed >= SMOOTH_SCROLL_DURATION {
+            self.finish_animation();
+            return;
+        }
+
+        let elapsed_secs = |duration: Duration| duration.as_secs() as f32 + duration.subsec_nanos() as f32 / 1_000_000_000.0;
+        let progress = elapsed_secs(elapsed) / elapsed_secs(SMOOTH_SCROLL_DURATION);
+        // Ease out, so the scroll slows down as it reaches its target.
+        let progress = 1.0 - (1.0 - progress) * (1.0 - progress);
+        let distance = animation.target_offset - animation.start_offset;
+        let offset = animation.start_offset + Vector2::new(
+            (distance.x as f32 * progress).round() as i32,
+            (distance.y as f32 * progress).round() as i32,
+        );
+        self.set_offset(offset);
+    }
+
+    fn on_scroll_event(&mut self, event: &ScrollEvent) {
+        let viewport = self.clip.rect();
+        match *event {
+            ScrollEvent::Lines{horizontal, lines} => {
+                self.scroll_by(along_axis(horizontal, lines * SCROLL_LINE));
+            },
+            ScrollEvent::Pages{horizontal, pages} => {
+                let page = match horizontal {
+                    true => viewport.width(),
+                    false => viewport.height()
+                };
+                self.scroll_by(along_axis(horizontal, pages * page));
+            },
+            ScrollEvent::Drag{horizontal, head_start} => {
+                let (bar, offset, max_offset) = match horizontal {
+                    true => (&self.bar_x, self.offset.x, self.max_offset.x),
+                    false => (&self.bar_y, self.offset.y, self.max_offset.y)
+                };
+                let (track_start, track_len) = bar.track();
+                let travel = track_len - bar.head.len();
+                if travel > 0 {
+                    let new_offset = ((head_start - track_start) as f32 / travel as f32 * max_offset as f32).round() as i32;
+                    self.scroll_by(along_axis(horizontal, new_offset - offset));
+                }
+            }
+        }
+    }
+}
+
+impl ScrollBar {
+    fn new(horizontal: bool) -> ScrollBar {
+        let widget_tag = WidgetTag::new();
+        widget_tag.set_visibility(Visibility::Hidden);
+        let bar_id = widget_tag.widget_id();
+
+        ScrollBar {
+            widget_tag,
+            rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+
+            horizontal,
+            decrease: ScrollArrow::new(bar_id, horizontal, false),
+            increase: ScrollArrow::new(bar_id, horizontal, true),
+            head: ScrollHead {
+                widget_tag: WidgetTag::new(),
+                rect: BoundBox::new2(0, 0, 0, 0),
+                size_bounds: SizeBounds::default(),
+
+                bar_id,
+                horizontal,
+                state: ButtonState::Normal,
+                click_pos: None,
+            },
+        }
+    }
+
+    /// The length of the bar along its axis.
+    fn len(&self) -> i32 {
+        match self.horizontal {
+            true => self.rect.width(),
+            false => self.rect.height()
+        }
+    }
+
+    /// The start and length of the region between the arrow buttons, which the head moves along.
+    fn track(&self) -> (i32, i32) {
+        (SCROLL_BAR_SIZE, (self.len() - SCROLL_BAR_SIZE * 2).max(0))
+    }
+
+    /// A rect that spans the bar's thickness, and goes from `start` to `end` along its axis.
+    fn span(&self, start: i32, end: i32) -> BoundBox<D2, i32> {
+        match self.horizontal {
+            true => BoundBox::new2(start, 0, end, self.rect.height()),
+            false => BoundBox::new2(0, start, self.rect.width(), end)
+        }
+    }
+
+    fn set_visible(&self, visible: bool) {
+        self.widget_tag.set_visibility(match visible {
+            true => Visibility::Visible,
+            false => Visibility::Hidden
+        });
+    }
+
+    /// Position the bar and its parts, for a widget `content_len` pixels long that's scrolled by
+    /// `offset` pixels and shown through a viewport `viewport_len` pixels long.
+    fn update_parts(&mut self, rect: BoundBox<D2, i32>, offset: i32, viewport_len: i32, content_len: i32) {
+        set_rect(&mut self.widget_tag, &mut self.rect, rect);
+
+        let len = self.len();
+        let (track_start, track_len) = self.track();
+        let head_len = (track_len as i64 * viewport_len as i64 / content_len.max(1) as i64) as i32;
+        let head_len = head_len.max(MIN_HEAD_SIZE).min(track_len);
+        let max_offset = content_len - viewport_len;
+        let head_start = match max_offset > 0 {
+            true => track_start + ((track_len - head_len) as f32 * offset as f32 / max_offset as f32).round() as i32,
+            false => track_start
+        };
+
+        let decrease_rect = self.span(0, SCROLL_BAR_SIZE);
+        let increase_rect = self.span(len - SCROLL_BAR_SIZE, len);
+        let head_rect = self.span(head_start, head_start + head_len);
+        set_rect(&mut self.decrease.widget_tag, &mut self.decrease.rect, decrease_rect);
+        set_rect(&mut self.increase.widget_tag, &mut self.increase.rect, increase_rect);
+        set_rect(&mut self.head.widget_tag, &mut self.head.rect, head_rect);
+    }
+}
+
+impl ScrollHead {
+    fn len(&self) -> i32 {
+        match self.horizontal {
+            true => self.rect.width(),
+            false => self.rect.height()
+        }
+    }
+}
+
+impl ScrollArrow {
+    fn new(bar_id: WidgetId, horizontal: bool, increase: bool) -> ScrollArrow {
+        ScrollArrow {
+            widget_tag: WidgetTag::new(),
+            rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+
+            bar_id,
+            horizontal,
+            increase,
+            state: ButtonState::Normal,
+            repeat_timer: None,
+        }
+    }
+
+    fn step(&mut self) {
+        let event = ScrollEvent::Lines {
+            horizontal: self.horizontal,
+            lines: match self.increase {
+                true => 1,
+                false => -1
+            },
+        };
+        self.widget_tag.send_message_to(event, MessageTarget::ParentOf(self.bar_id));
+    }
+
+    fn stop_repeating(&mut self) {
+        if let Some(timer_id) = self.repeat_timer.take() {
+            self.widget_tag.timers_mut().remove(&timer_id);
+        }
+    }
+}
+
+fn set_rect(widget_tag: &mut WidgetTag, rect: &mut BoundBox<D2, i32>, new_rect: BoundBox<D2, i32>) {
+    if *rect != new_rect {
+        *rect = new_rect;
+        widget_tag.request_relayout();
+    }
+}
+
+/// The component of `point` along the horizontal or vertical axis.
+fn axis(horizontal: bool, point: Point2<i32>) -> i32 {
+    match horizontal {
+        true => point.x,
+        false => point.y
+    }
+}
+
+fn along_axis(horizontal: bool, len: i32) -> Vector2<i32> {
+    match horizontal {
+        true => Vector2::new(len, 0),
+        false => Vector2::new(0, len)
+    }
+}
+
+/// Scroll `offset` just far enough to show the region between `start` and `end`, favoring the
+/// start of the region if it doesn't fit.
+fn scroll_to_span(offset: i32, viewport_len: i32, start: i32, end: i32) -> i32 {
+    if start < offset || end - start > viewport_len {
+        start
+    } else if end > offset + viewport_len {
+        end - viewport_len
+    } else {
+        offset
     }
 }
 
@@ -94,105 +486,181 @@ impl<W> Widget for ScrollBox<W>
         self.widget_tag.request_relayout();
         &mut self.rect
     }
+
     fn size_bounds(&self) -> SizeBounds {
-        SizeBounds::default()
+        self.size_bounds
     }
 
-    #[inline]
     fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
-        // TODO: PASS FOCUS TO CHILD
-        let values = |slider_x: &Option<SliderAssist>, slider_y: &Option<SliderAssist>|
-            (slider_x.as_ref().map(|s| s.value), slider_y.as_ref().map(|s| s.value));
-        let start_values = values(&self.slider_x, &self.slider_y);
-        let mut allow_bubble = true;
-
-        match event {
-            WidgetEventSourced::This(ref event) => match event {
-                WidgetEvent::MouseDown{pos, in_widget: true, button: MouseButton::Left} => {
-                    if let Some(ref mut slider_x) = self.slider_x {
-                        slider_x.click_head(*pos);
-                    }
-                    if let Some(ref mut slider_y) = self.slider_y {
-                        slider_y.click_head(*pos);
-                    }
-                    self.widget_tag.request_redraw();
-                },
-                WidgetEvent::MouseMove{new_pos, ..} => {
-                    if let Some(ref mut slider_x) = self.slider_x {
-                        slider_x.move_head(new_pos.x);
-                    }
-                    if let Some(ref mut slider_y) = self.slider_y {
-                        slider_y.move_head(new_pos.y);
-                    }
-                },
-                WidgetEvent::MouseUp{button: MouseButton::Left, ..} => {
-                    if let Some(ref mut slider_x) = self.slider_x {
-                        slider_x.head_click_pos = None;
-                    }
-                    if let Some(ref mut slider_y) = self.slider_y {
-                        slider_y.head_click_pos = None;
-                    }
-                    self.widget_tag.request_redraw();
-                },
-                _ => ()
+        let mut bubble = event.default_bubble();
+        let delta = match event.unwrap() {
+            WidgetEvent::MouseScrollLines{dir, ..} => Some(dir * -SCROLL_LINE),
+            WidgetEvent::MouseScrollPx{dir, ..} => Some(-dir),
+            WidgetEvent::Timer{timer_id, ..} => {
+                match self.animation {
+                    Some(animation) if animation.timer_id == timer_id => self.step_animation(animation),
+                    _ => ()
+                }
+                None
             },
-            WidgetEventSourced::Bubble(ref event, _) => match event {
-                WidgetEvent::MouseScrollLines{dir, in_widget: true} => {
-                    allow_bubble = false;
-                    if let Some(ref mut slider_x) = self.slider_x {
-                        slider_x.value -= (24 * dir.x) as f32;
-                        slider_x.round_to_step();
-                    }
-                    if let Some(ref mut slider_y) = self.slider_y {
-                        slider_y.value -= (24 * dir.y) as f32;
-                        slider_y.round_to_step();
-                    }
-                },
-                WidgetEvent::MouseScrollPx{dir, in_widget: true} => {
-                    allow_bubble = false;
-                    if let Some(ref mut slider_x) = self.slider_x {
-                        slider_x.value -= dir.x as f32;
-                        slider_x.round_to_step();
-                    }
-                    if let Some(ref mut slider_y) = self.slider_y {
-                        slider_y.value -= dir.y as f32;
-                        slider_y.round_to_step();
-                    }
-                },
-                _ => ()
-            }
-        }
+            _ => None
+        };
 
-        if values(&self.slider_x, &self.slider_y) != start_values {
-            self.widget_tag.request_redraw().request_relayout();
+        // Once the widget can't be scrolled any further, let the input through to any scroll boxes
+        // this one is inside of.
+        if let Some(delta) = delta {
+            bubble &= !self.scroll_by(delta);
         }
+
         EventOps {
             focus: None,
-            bubble: allow_bubble && event.default_bubble(),
+            bubble,
         }
     }
-}
 
-lazy_static!{
-    static ref CLIP_IDENT: WidgetIdent = WidgetIdent::Str(Arc::from("clip"));
+    fn scroll_into_view(&mut self, rect: BoundBox<D2, i32>) -> BoundBox<D2, i32> {
+        let viewport = self.clip.rect();
+        let viewport_origin = viewport.min().to_vec();
+
+        // Move `rect` into the scrolled widget's coordinate space.
+        let widget_rect = rect - viewport_origin + self.offset;
+        let current_target = match self.animation {
+            Some(animation) => animation.target_offset,
+            None => self.offset
+        };
+        let target = self.clamp_offset(Vector2::new(
+            scroll_to_span(current_target.x, viewport.width(), widget_rect.min.x, widget_rect.max.x),
+            scroll_to_span(current_target.y, viewport.height(), widget_rect.min.y, widget_rect.max.y),
+        ));
+        if target != current_target {
+            self.scroll_to_offset(target);
+        }
+
+        // Only the part of `rect` inside the viewport can be seen, so that's all the ancestors need
+        // to show.
+        let shown_rect = widget_rect - target + viewport_origin;
+        shown_rect.intersect_rect(viewport).unwrap_or(viewport)
+    }
 }
 
 impl<W> Parent for ScrollBox<W>
     where W: Widget
 {
     fn num_children(&self) -> usize {
-        1
+        3
+    }
+
+    fn framed_child<R: Renderer>(&self, widget_ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(index) => self.framed_child_by_index(index as usize),
+            _ => None
+        }
+    }
+    fn framed_child_mut<R: Renderer>(&mut self, widget_ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(index) => self.framed_child_by_index_mut(index as usize),
+            _ => None
+        }
+    }
+
+    fn framed_children<'a, R, G>(&'a self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
+    {
+        if let LoopFlow::Break = for_each(WidgetInfo::new(WidgetIdent::Num(0), 0, &self.clip)) {
+            return;
+        }
+        if let LoopFlow::Break = for_each(WidgetInfo::new(WidgetIdent::Num(1), 1, &self.bar_x)) {
+            return;
+        }
+        let _ = for_each(WidgetInfo::new(WidgetIdent::Num(2), 2, &self.bar_y));
+    }
+
+    fn framed_children_mut<'a, R, G>(&'a mut self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
+    {
+        if let LoopFlow::Break = for_each(WidgetInfoMut::new(WidgetIdent::Num(0), 0, &mut self.clip)) {
+            return;
+        }
+        if let LoopFlow::Break = for_each(WidgetInfoMut::new(WidgetIdent::Num(1), 1, &mut self.bar_x)) {
+            return;
+        }
+        let _ = for_each(WidgetInfoMut::new(WidgetIdent::Num(2), 2, &mut self.bar_y));
+    }
+
+    fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
+        match index {
+            0 => Some(WidgetInfo::new(WidgetIdent::Num(0), 0, &self.clip)),
+            1 => Some(WidgetInfo::new(WidgetIdent::Num(1), 1, &self.bar_x)),
+            2 => Some(WidgetInfo::new(WidgetIdent::Num(2), 2, &self.bar_y)),
+            _ => None
+        }
+    }
+    fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
+        match index {
+            0 => Some(WidgetInfoMut::new(WidgetIdent::Num(0), 0, &mut self.clip)),
+            1 => Some(WidgetInfoMut::new(WidgetIdent::Num(1), 1, &mut self.bar_x)),
+            2 => Some(WidgetInfoMut::new(WidgetIdent::Num(2), 2, &mut self.bar_y)),
+            _ => None
+        }
+    }
+}
+
+impl Widget for ScrollBar {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        if let WidgetEventSourced::This(WidgetEvent::MouseDown{pos, in_widget: true, button: MouseButton::Left}) = event {
+            let pages = match axis(self.horizontal, pos) < axis(self.horizontal, self.head.rect.min) {
+                true => -1,
+                false => 1
+            };
+            let bar_id = self.widget_tag.widget_id();
+            self.widget_tag.send_message_to(
+                ScrollEvent::Pages{horizontal: self.horizontal, pages},
+                MessageTarget::ParentOf(bar_id)
+            );
+        }
+
+        EventOps {
+            focus: None,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
+impl Parent for ScrollBar {
+    fn num_children(&self) -> usize {
+        3
     }
 
     fn framed_child<R: Renderer>(&self, widget_ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
         match widget_ident {
-            _ if widget_ident == *CLIP_IDENT => Some(self.child_summary()),
+            WidgetIdent::Num(index) => self.framed_child_by_index(index as usize),
             _ => None
         }
     }
     fn framed_child_mut<R: Renderer>(&mut self, widget_ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
         match widget_ident {
-            _ if widget_ident == *CLIP_IDENT => Some(self.child_summary_mut()),
+            WidgetIdent::Num(index) => self.framed_child_by_index_mut(index as usize),
             _ => None
         }
     }
@@ -201,167 +669,394 @@ impl<W> Parent for ScrollBox<W>
         where R: Renderer,
               G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
     {
-        let _ = for_each(self.child_summary());
+        if let LoopFlow::Break = for_each(WidgetInfo::new(WidgetIdent::Num(0), 0, &self.decrease)) {
+            return;
+        }
+        if let LoopFlow::Break = for_each(WidgetInfo::new(WidgetIdent::Num(1), 1, &self.increase)) {
+            return;
+        }
+        let _ = for_each(WidgetInfo::new(WidgetIdent::Num(2), 2, &self.head));
     }
 
     fn framed_children_mut<'a, R, G>(&'a mut self, mut for_each: G)
         where R: Renderer,
               G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
     {
-        let _ = for_each(self.child_summary_mut());
+        if let LoopFlow::Break = for_each(WidgetInfoMut::new(WidgetIdent::Num(0), 0, &mut self.decrease)) {
+            return;
+        }
+        if let LoopFlow::Break = for_each(WidgetInfoMut::new(WidgetIdent::Num(1), 1, &mut self.increase)) {
+            return;
+        }
+        let _ = for_each(WidgetInfoMut::new(WidgetIdent::Num(2), 2, &mut self.head));
     }
 
     fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
         match index {
-            0 => Some(self.child_summary()),
+            0 => Some(WidgetInfo::new(WidgetIdent::Num(0), 0, &self.decrease)),
+            1 => Some(WidgetInfo::new(WidgetIdent::Num(1), 1, &self.increase)),
+            2 => Some(WidgetInfo::new(WidgetIdent::Num(2), 2, &self.head)),
             _ => None
         }
     }
     fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
         match index {
-            0 => Some(self.child_summary_mut()),
+            0 => Some(WidgetInfoMut::new(WidgetIdent::Num(0), 0, &mut self.decrease)),
+            1 => Some(WidgetInfoMut::new(WidgetIdent::Num(1), 1, &mut self.increase)),
+            2 => Some(WidgetInfoMut::new(WidgetIdent::Num(2), 2, &mut self.head)),
             _ => None
         }
     }
 }
 
+impl Widget for ScrollHead {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        use self::WidgetEvent::*;
+        let event = event.unwrap();
+
+        let new_state = match event {
+            MouseMove{new_pos, ref hover_change, ..} => {
+                if let Some(click_pos) = self.click_pos {
+                    // `new_pos` is relative to the head, so offset it by the head's position in
+                    // the bar.
+                    let head_start = axis(self.horizontal, self.rect.min) + axis(self.horizontal, new_pos) - click_pos;
+                    self.widget_tag.send_message_to(
+                        ScrollEvent::Drag{horizontal: self.horizontal, head_start},
+                        MessageTarget::ParentOf(self.bar_id)
+                    );
+                }
+                match (self.click_pos, hover_change) {
+                    (Some(_), _) => ButtonState::Pressed,
+                    (None, Some(MouseHoverChange::Enter)) => ButtonState::Hover,
+                    (None, Some(MouseHoverChange::Exit)) => ButtonState::Normal,
+                    _ => self.state
+                }
+            },
+            MouseDown{pos, in_widget: true, button: MouseButton::Left} => {
+                self.click_pos = Some(axis(self.horizontal, pos));
+                ButtonState::Pressed
+            },
+            MouseUp{in_widget, button: MouseButton::Left, ..} => {
+                self.click_pos = None;
+                match in_widget {
+                    true => ButtonState::Hover,
+                    false => ButtonState::Normal
+                }
+            },
+            _ => self.state
+        };
+
+        if new_state != self.state {
+            self.state = new_state;
+            self.widget_tag.request_redraw();
+        }
+
+        EventOps {
+            focus: None,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
+impl Widget for ScrollArrow {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        use self::WidgetEvent::*;
+        let event = event.unwrap();
+        let pressed = self.repeat_timer.is_some();
+
+        let new_state = match event {
+            MouseMove{hover_change: Some(ref change), ..} => match (pressed, change) {
+                (true, _) => ButtonState::Pressed,
+                (false, MouseHoverChange::Enter) => ButtonState::Hover,
+                (false, MouseHoverChange::Exit) => ButtonState::Normal,
+                _ => self.state
+            },
+            MouseDown{in_widget: true, button: MouseButton::Left, ..} => {
+                self.step();
+                self.stop_repeating();
+                let timer_id = TimerId::new();
+                self.widget_tag.timers_mut().insert(timer_id, Timer::new_delayed(REPEAT_INTERVAL, Instant::now() + REPEAT_DELAY));
+                self.repeat_timer = Some(timer_id);
+                ButtonState::Pressed
+            },
+            MouseUp{in_widget, button: MouseButton::Left, ..} => {
+                self.stop_repeating();
+                match in_widget {
+                    true => ButtonState::Hover,
+                    false => ButtonState::Normal
+                }
+            },
+            WidgetEvent::Timer{timer_id, ..} if Some(timer_id) == self.repeat_timer => {
+                self.step();
+                self.state
+            },
+            _ => self.state
+        };
+
+        if new_state != self.state {
+            self.state = new_state;
+            self.widget_tag.request_redraw();
+        }
+
+        EventOps {
+            focus: None,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
 impl<W, R> WidgetRenderable<R> for ScrollBox<W>
     where W: Widget,
           R: Renderer
 {
+    type Theme = ScrollBoxTheme;
+
+    fn theme(&self) -> ScrollBoxTheme {
+        ScrollBoxTheme(())
+    }
+
     fn render(&mut self, frame: &mut R::SubFrame) {
-        let mut primitives: ArrayVec<[_; 4]> = ArrayVec::new();
-
-        if let Some(slider_x) = self.slider_x.clone() {
-            primitives.push(ThemedPrim {
-                theme_path: "ScrollBackground",
-                min: Point2::new(
-                    RelPoint::new(-1.0, slider_x.bar_rect.min.x),
-                    RelPoint::new(-1.0, slider_x.bar_rect.min.y),
-                ),
-                max: Point2::new(
-                    RelPoint::new(-1.0, slider_x.bar_rect.max.x),
-                    RelPoint::new(-1.0, slider_x.bar_rect.max.y)
-                ),
-                prim: Prim::Image,
-                rect_px_out: None
-            });
-
-            let head_rect = slider_x.head_rect();
-
-            primitives.push(ThemedPrim {
-                theme_path: "ScrollBar",
-                min: Point2::new(
-                    RelPoint::new(-1.0, head_rect.min.x),
-                    RelPoint::new(-1.0, head_rect.min.y),
-                ),
-                max: Point2::new(
-                    RelPoint::new(-1.0, head_rect.max.x),
-                    RelPoint::new(-1.0, head_rect.max.y)
-                ),
-                prim: Prim::Image,
-                rect_px_out: None
-            });
-        }
-        if let Some(slider_y) = self.slider_y.clone() {
-            primitives.push(ThemedPrim {
-                theme_path: "ScrollBackground",
-                min: Point2::new(
-                    RelPoint::new(-1.0, slider_y.bar_rect.min.x),
-                    RelPoint::new(-1.0, slider_y.bar_rect.min.y),
-                ),
-                max: Point2::new(
-                    RelPoint::new(-1.0, slider_y.bar_rect.max.x),
-                    RelPoint::new(-1.0, slider_y.bar_rect.max.y)
-                ),
-                prim: Prim::Image,
-                rect_px_out: None
-            });
-
-            let head_rect = slider_y.head_rect();
-
-            primitives.push(ThemedPrim {
-                theme_path: "ScrollBar",
-                min: Point2::new(
-                    RelPoint::new(-1.0, head_rect.min.x),
-                    RelPoint::new(-1.0, head_rect.min.y),
-                ),
-                max: Point2::new(
-                    RelPoint::new(-1.0, head_rect.max.x),
-                    RelPoint::new(-1.0, head_rect.max.y)
-                ),
-                prim: Prim::Image,
-                rect_px_out: None
-            });
-        }
-
-        frame.upload_primitives(primitives.into_iter());
-    }
-
-    fn update_layout(&mut self, _: &R::Theme) {
-        let child_size_bounds = self.clip.widget().size_bounds();
-        let mut child_dims: DimsBox<D2, _> = self.rect.dims();
-        let mut offset = Vector2 {
-            x: self.slider_x.as_ref().map(|s| s.value as i32).unwrap_or(0),
-            y: self.slider_y.as_ref().map(|s| s.value as i32).unwrap_or(0)
-        };
+        frame.render_laid_out_content();
+    }
 
-        let (mut has_x_scroll, mut has_y_scroll) = (false, false);
-        for _ in 0..2 {
-            let scroll_dims_x = child_dims.dims.x - SCROLL_BAR_SIZE * has_y_scroll as i32;
-            let scroll_dims_y = child_dims.dims.y - SCROLL_BAR_SIZE * has_x_scroll as i32;
-            child_dims.dims.x = scroll_dims_x.max(child_size_bounds.min.width());
-            child_dims.dims.y = scroll_dims_y.max(child_size_bounds.min.height());
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        let result = layout.finish();
+        let content_rect = result.content_rect;
+        self.size_bounds = result.size_bounds;
+
+        let widget_bounds = self.clip.widget().size_bounds();
 
-            has_x_scroll |= child_dims.width() != scroll_dims_x;
-            has_y_scroll |= child_dims.height() != scroll_dims_y;
+        // Showing one of the scroll bars shrinks the viewport, which can make the other one
+        // necessary.
+        let (mut has_x_bar, mut has_y_bar) = (false, false);
+        for _ in 0..2 {
+            let viewport_width = content_rect.width() - SCROLL_BAR_SIZE * has_y_bar as i32;
+            let viewport_height = content_rect.height() - SCROLL_BAR_SIZE * has_x_bar as i32;
+            has_x_bar |= widget_bounds.min.width() > viewport_width;
+            has_y_bar |= widget_bounds.min.height() > viewport_height;
         }
 
-        let clip_dims = DimsBox::new2(
-            self.rect.width() - SCROLL_BAR_SIZE * has_y_scroll as i32,
-            self.rect.height() - SCROLL_BAR_SIZE * has_x_scroll as i32,
+        let viewport = BoundBox::new2(
+            content_rect.min.x,
+            content_rect.min.y,
+            content_rect.max.x - SCROLL_BAR_SIZE * has_y_bar as i32,
+            content_rect.max.y - SCROLL_BAR_SIZE * has_x_bar as i32,
+        );
+        let widget_dims = DimsBox::new2(
+            viewport.width().max(widget_bounds.min.width()),
+            viewport.height().max(widget_bounds.min.height()),
+        );
+
+        self.max_offset = Vector2::new(
+            widget_dims.width() - viewport.width(),
+            widget_dims.height() - viewport.height(),
+        );
+        if let Some(rect) = self.scroll_to.take() {
+            self.offset = Vector2::new(
+                scroll_to_span(self.offset.x, viewport.width(), rect.min.x, rect.max.x),
+                scroll_to_span(self.offset.y, viewport.height(), rect.min.y, rect.max.y),
+            );
+        }
+        self.offset = Vector2::new(
+            self.offset.x.min(self.max_offset.x).max(0),
+            self.offset.y.min(self.max_offset.y).max(0),
         );
 
-        offset.x = offset.x.min((child_dims.width() as u32).saturating_sub(clip_dims.width() as u32) as i32);
-        offset.y = offset.y.min((child_dims.height() as u32).saturating_sub(clip_dims.height() as u32) as i32);
-
-        let self_dims: DimsBox<D2, _> = self.rect.dims();
-        self.slider_x = match has_x_scroll {
-            false => None,
-            true => Some(SliderAssist {
-                value: offset.x as f32,
-                step: f32::EPSILON,
-                min: 0.0,
-                max: (child_dims.width() - clip_dims.width()) as f32,
-
-                head_size: 16.max(clip_dims.width().pow(2) / child_dims.width()), // TODO: PROPER HEIGHT CALCULATION
-                bar_rect: BoundBox::new2(
-                    0, self_dims.height() - SCROLL_BAR_SIZE,
-                    clip_dims.width(), self_dims.height()
-                ),
-                head_click_pos: self.slider_x.as_ref().and_then(|s| s.head_click_pos),
-                horizontal: true
-            })
-        };
-        self.slider_y = match has_y_scroll {
-            false => None,
-            true => Some(SliderAssist {
-                value: offset.y as f32,
-                step: f32::EPSILON,
-                min: 0.0,
-                max: (child_dims.height() - clip_dims.height()) as f32,
-
-                head_size: 16.max(clip_dims.height().pow(2) / child_dims.height()),
-                bar_rect: BoundBox::new2(
-                    self_dims.width() - SCROLL_BAR_SIZE, 0,
-                    self_dims.width(), clip_dims.height()
-                ),
-                head_click_pos: self.slider_y.as_ref().and_then(|s| s.head_click_pos),
-                horizontal: false
-            })
-        };
+        if self.clip.rect() != viewport {
+            *self.clip.rect_mut() = viewport;
+        }
+        let widget_rect = BoundBox::from(widget_dims) - self.offset;
+        if self.clip.widget().rect() != widget_rect {
+            *self.clip.widget_mut().rect_mut() = widget_rect;
+        }
+
+        self.bar_x.set_visible(has_x_bar);
+        self.bar_y.set_visible(has_y_bar);
+        if has_x_bar {
+            self.bar_x.update_parts(
+                BoundBox::new2(viewport.min.x, viewport.max.y, viewport.max.x, content_rect.max.y),
+                self.offset.x,
+                viewport.width(),
+                widget_dims.width()
+            );
+        }
+        if has_y_bar {
+            self.bar_y.update_parts(
+                BoundBox::new2(viewport.max.x, viewport.min.y, content_rect.max.x, viewport.max.y),
+                self.offset.y,
+                viewport.height(),
+                widget_dims.height()
+            );
+        }
+    }
+}
+
+impl<R> WidgetRenderable<R> for ScrollBar
+    where R: Renderer
+{
+    type Theme = ScrollBarTheme;
+
+    fn theme(&self) -> ScrollBarTheme {
+        ScrollBarTheme(())
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        let result = layout.finish();
+        self.size_bounds = result.size_bounds;
+    }
+}
 
-        *self.clip.rect_mut() = BoundBox::from(clip_dims);
-        *self.clip.widget_mut().rect_mut() = BoundBox::from(child_dims) - offset;
+impl<R> WidgetRenderable<R> for ScrollHead
+    where R: Renderer
+{
+    type Theme = ScrollHeadTheme;
+
+    fn theme(&self) -> ScrollHeadTheme {
+        ScrollHeadTheme {
+            state: self.state,
+        }
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        let result = layout.finish();
+        self.size_bounds = result.size_bounds;
+    }
+}
+
+impl<R> WidgetRenderable<R> for ScrollArrow
+    where R: Renderer
+{
+    type Theme = ScrollArrowTheme;
+
+    fn theme(&self) -> ScrollArrowTheme {
+        ScrollArrowTheme {
+            horizontal: self.horizontal,
+            increase: self.increase,
+            state: self.state,
+        }
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        let result = layout.finish();
+        self.size_bounds = result.size_bounds;
+    }
+}
+
+impl WidgetTheme for ScrollBoxTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for ScrollBoxTheme {
+    fn theme_path(&self) -> &'static str {
+        "ScrollBox"
+    }
+}
+
+impl WidgetTheme for ScrollBarTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for ScrollBarTheme {
+    fn theme_path(&self) -> &'static str {
+        "ScrollBackground"
+    }
+}
+
+impl WidgetTheme for ScrollHeadTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for ScrollHeadTheme {
+    fn theme_path(&self) -> &'static str {
+        match self.state {
+            ButtonState::Normal => "ScrollBar",
+            ButtonState::Hover => "ScrollBar::Hover",
+            ButtonState::Pressed => "ScrollBar::Pressed",
+        }
+    }
+}
+
+impl WidgetTheme for ScrollArrowTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for ScrollArrowTheme {
+    fn theme_path(&self) -> &'static str {
+        match (self.horizontal, self.increase, self.state) {
+            (false, false, ButtonState::Normal) => "ScrollArrow::Up",
+            (false, false, ButtonState::Hover) => "ScrollArrow::Up::Hover",
+            (false, false, ButtonState::Pressed) => "ScrollArrow::Up::Pressed",
+            (false, true, ButtonState::Normal) => "ScrollArrow::Down",
+            (false, true, ButtonState::Hover) => "ScrollArrow::Down::Hover",
+            (false, true, ButtonState::Pressed) => "ScrollArrow::Down::Pressed",
+            (true, false, ButtonState::Normal) => "ScrollArrow::Left",
+            (true, false, ButtonState::Hover) => "ScrollArrow::Left::Hover",
+            (true, false, ButtonState::Pressed) => "ScrollArrow::Left::Pressed",
+            (true, true, ButtonState::Normal) => "ScrollArrow::Right",
+            (true, true, ButtonState::Hover) => "ScrollArrow::Right::Hover",
+            (true, true, ButtonState::Pressed) => "ScrollArrow::Right::Pressed",
+        }
     }
 }
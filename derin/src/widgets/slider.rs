@@ -7,6 +7,7 @@ use derin_core::{
     widget::{WidgetTag, WidgetRenderable, Widget},
     render::{Renderer, RendererLayout, SubFrame, WidgetTheme},
 };
+use crate::theme::ThemePath;
 use derin_common_types::layout::SizeBounds;
 use crate::{
     event::{EventOps, WidgetEvent, InputState, MouseButton, WidgetEventSourced},
@@ -296,9 +297,21 @@ impl WidgetTheme for SliderTheme {
     }
 }
 
+impl ThemePath for SliderTheme {
+    fn theme_path(&self) -> &'static str {
+        "Slider::Bar"
+    }
+}
+
 impl WidgetTheme for SliderHandleTheme {
     type Fallback = !;
     fn fallback(self) -> Option<!> {
         None
     }
 }
+
+impl ThemePath for SliderHandleTheme {
+    fn theme_path(&self) -> &'static str {
+        "Slider::Head"
+    }
+}
@@ -0,0 +1,536 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use derin_core::{
+    LoopFlow,
+    event::{EventOps, WidgetEvent, WidgetEventSourced, InputState, MouseButton, MouseHoverChange},
+    widget::{Visibility, WidgetIdent, WidgetRenderable, WidgetTag, WidgetInfo, WidgetInfoMut, Widget, Parent},
+    render::{Renderer, RendererLayout, SubFrame, WidgetTheme},
+};
+use crate::theme::{CursorIcon, ThemePath};
+use crate::container::WidgetContainer;
+
+use crate::cgmath::Point2;
+use cgmath_geometry::{D2, rect::{BoundBox, DimsBox, GeoBox}};
+use derin_common_types::layout::SizeBounds;
+#[cfg(feature = "derin-serde")]
+use serde::{Serialize, Deserialize};
+use std::time::{Duration, Instant};
+
+/// The thickness of the handles between panes.
+const HANDLE_SIZE: i32 = 6;
+/// The longest time that can pass between two clicks on a handle for them to count as a
+/// double-click.
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(500);
+
+/// The direction that a container lays its children out in.
+#[cfg_attr(feature = "derin-serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    /// Children are placed side-by-side, from left to right.
+    Horizontal,
+    /// Children are stacked from top to bottom.
+    Vertical,
+}
+
+/// A container that splits its area between a set of panes, separated by handles that the user can
+/// drag to resize the panes.
+///
+/// Each pane's share of the splitter is stored as a ratio, which can be retrieved with [`ratios`]
+/// so that the layout can be saved and later restored with [`set_ratios`]. Double-clicking a
+/// handle collapses the smaller of the two panes next to it, and double-clicking it again restores
+/// the pane.
+///
+/// Panes are specified by creating structs which implement [`WidgetContainer`], just like with
+/// [`Group`].
+///
+/// [`ratios`]: ./struct.Splitter.html#method.ratios
+/// [`set_ratios`]: ./struct.Splitter.html#method.set_ratios
+/// [`WidgetContainer`]: ../container/trait.WidgetContainer.html
+/// [`Group`]: ./struct.Group.html
+#[derive(Debug, Clone)]
+pub struct Splitter<C> {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    container: C,
+    orientation: Orientation,
+    /// Each pane's share of the space, relative to the other panes.
+    ratios: Vec<f32>,
+    collapsed: Vec<bool>,
+    /// The start of each handle along the splitter's axis, as of the last layout.
+    handles: Vec<i32>,
+    /// The size of each pane along the splitter's axis, as of the last layout.
+    pane_sizes: Vec<i32>,
+    /// The size bounds of each pane along the splitter's axis, as of the last layout.
+    pane_bounds: Vec<(i32, i32)>,
+
+    /// The handle being dragged, and where along the handle it was grabbed.
+    drag: Option<(usize, i32)>,
+    last_click: Option<(usize, Instant)>,
+    over_handle: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SplitterTheme(());
+
+impl<C> Splitter<C>
+    where C: WidgetContainer<dyn Widget>
+{
+    /// Create a new `Splitter` that splits its space evenly between the widgets in `container`.
+    pub fn new(container: C, orientation: Orientation) -> Splitter<C> {
+        Splitter {
+            widget_tag: WidgetTag::new(),
+            rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+
+            container,
+            orientation,
+            ratios: Vec::new(),
+            collapsed: Vec::new(),
+            handles: Vec::new(),
+            pane_sizes: Vec::new(),
+            pane_bounds: Vec::new(),
+
+            drag: None,
+            last_click: None,
+            over_handle: false,
+        }
+    }
+
+    /// Retrieve the panes contained within the splitter.
+    pub fn container(&self) -> &C {
+        &self.container
+    }
+
+    /// Retrieve the panes contained within the splitter, for mutation.
+    pub fn container_mut(&mut self) -> &mut C {
+        self.widget_tag.request_relayout();
+        &mut self.container
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+        self.widget_tag.request_redraw().request_relayout();
+    }
+
+    /// Retrieve each pane's share of the splitter, relative to the other panes.
+    ///
+    /// Collapsed panes keep the ratio they get restored to when they're expanded again.
+    pub fn ratios(&self) -> Vec<f32> {
+        self.sync_pane_count(self.ratios.clone(), 1.0)
+    }
+
+    /// Set each pane's share of the splitter. The ratios don't have to add up to any particular
+    /// value, since they only get compared against each other.
+    pub fn set_ratios(&mut self, ratios: &[f32]) {
+        self.ratios = ratios.iter().map(|r| r.max(0.0)).collect();
+        self.widget_tag.request_redraw().request_relayout();
+    }
+
+    /// Whether the pane at `index` has been collapsed.
+    pub fn collapsed(&self, index: usize) -> bool {
+        self.collapsed.get(index).cloned().unwrap_or(false)
+    }
+
+    pub fn set_collapsed(&mut self, index: usize, collapsed: bool) {
+        self.collapsed = self.sync_pane_count(self.collapsed.clone(), false);
+        if let Some(pane_collapsed) = self.collapsed.get_mut(index) {
+            *pane_collapsed = collapsed;
+            self.widget_tag.request_redraw().request_relayout();
+        }
+    }
+
+    /// Resize `list` to have one entry per pane, filling any new entries with `default`.
+    fn sync_pane_count<T: Clone>(&self, mut list: Vec<T>, default: T) -> Vec<T> {
+        list.resize(self.container.num_children(), default);
+        list
+    }
+
+    fn axis(&self, point: Point2<i32>) -> i32 {
+        match self.orientation {
+            Orientation::Horizontal => point.x,
+            Orientation::Vertical => point.y
+        }
+    }
+
+    fn handle_at(&self, pos: Point2<i32>) -> Option<usize> {
+        let pos = self.axis(pos);
+        self.handles.iter().position(|&start| start <= pos && pos < start + HANDLE_SIZE)
+    }
+
+    /// Move the handle at `index` so that it starts at `handle_start`, resizing the panes on either
+    /// side of it.
+    fn drag_handle(&mut self, index: usize, handle_start: i32) {
+        let (before, after) = (index, index + 1);
+        if after >= self.pane_sizes.len() || self.collapsed.len() != self.pane_sizes.len() {
+            return;
+        }
+        let total = self.pane_sizes[before] + self.pane_sizes[after];
+        let (min_before, max_before) = self.pane_bounds[before];
+        let (min_after, max_after) = self.pane_bounds[after];
+
+        let pane_start = self.handles[index] - self.pane_sizes[before];
+        let size_before = (handle_start - pane_start)
+            .min(max_before).min(total - min_after)
+            .max(min_before).max(total - max_after);
+
+        self.pane_sizes[before] = size_before;
+        self.pane_sizes[after] = total - size_before;
+        // Dragging a collapsed pane open expands it.
+        self.collapsed[before] &= size_before == 0;
+        self.collapsed[after] &= total - size_before == 0;
+
+        // Derive the ratios from the new sizes, so that the panes keep their proportions when the
+        // splitter gets resized.
+        let total_size = self.pane_sizes.iter().sum::<i32>().max(1);
+        for (ratio, (&size, &collapsed)) in self.ratios.iter_mut().zip(self.pane_sizes.iter().zip(&self.collapsed)) {
+            if !collapsed {
+                *ratio = size as f32 / total_size as f32;
+            }
+        }
+        self.widget_tag.request_redraw().request_relayout();
+    }
+
+    /// Collapse the smaller of the panes next to the handle at `index`, or restore the pane if one
+    /// is already collapsed.
+    fn toggle_collapse(&mut self, index: usize) {
+        let (before, after) = (index, index + 1);
+        if after >= self.pane_sizes.len() || self.collapsed.len() != self.pane_sizes.len() {
+            return;
+        }
+        match (self.collapsed[before], self.collapsed[after]) {
+            (true, _) => self.collapsed[before] = false,
+            (_, true) => self.collapsed[after] = false,
+            (false, false) => match self.pane_sizes[before] <= self.pane_sizes[after] {
+                true => self.collapsed[before] = true,
+                false => self.collapsed[after] = true,
+            }
+        }
+        self.widget_tag.request_redraw().request_relayout();
+    }
+}
+
+/// Split `available` pixels between panes, in proportion to `ratios` while respecting each pane's
+/// `(min, max)` bounds. Collapsed panes get no space.
+fn distribute(available: i32, ratios: &[f32], bounds: &[(i32, i32)], collapsed: &[bool]) -> Vec<i32> {
+    let mut sizes = vec![0; ratios.len()];
+    // Panes that got clamped to their bounds, and don't take part in splitting what's left.
+    let mut fixed = collapsed.to_vec();
+
+    loop {
+        let remaining = available - sizes.iter().zip(&fixed).filter(|&(_, &f)| f).map(|(&s, _)| s).sum::<i32>();
+        let unfixed = (0..sizes.len()).filter(|&i| !fixed[i]).collect::<Vec<_>>();
+        if unfixed.is_empty() {
+            break;
+        }
+        let total_ratio = unfixed.iter().map(|&i| ratios[i]).sum::<f32>();
+        let share = |i: usize| match total_ratio > 0.0 {
+            true => remaining as f32 * ratios[i] / total_ratio,
+            false => remaining as f32 / unfixed.len() as f32
+        };
+
+        let mut clamped = false;
+        for &i in &unfixed {
+            let (min, max) = bounds[i];
+            let size = share(i);
+            if size < min as f32 || size > max as f32 {
+                sizes[i] = (size.round() as i32).max(min).min(max);
+                fixed[i] = true;
+                clamped = true;
+            }
+        }
+
+        if !clamped {
+            let mut given = 0;
+            for &i in &unfixed {
+                sizes[i] = share(i) as i32;
+                given += sizes[i];
+            }
+            // Hand the pixels lost to rounding to the last pane.
+            let last = *unfixed.last().unwrap();
+            sizes[last] += remaining - given;
+            break;
+        }
+    }
+
+    sizes
+}
+
+impl<C> Widget for Splitter<C>
+    where C: WidgetContainer<dyn Widget>
+{
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        self.widget_tag.request_relayout();
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        let mut bubble = event.default_bubble();
+        if let WidgetEventSourced::This(event) = event {
+            match event {
+                WidgetEvent::MouseMove{new_pos, in_widget, ref hover_change, ..} => {
+                    if let Some((handle, grab_offset)) = self.drag {
+                        self.drag_handle(handle, self.axis(new_pos) - grab_offset);
+                    }
+
+                    let over_handle = self.drag.is_some() || (
+                        in_widget &&
+                        hover_change != &Some(MouseHoverChange::Exit) &&
+                        self.handle_at(new_pos).is_some()
+                    );
+                    if over_handle != self.over_handle {
+                        self.over_handle = over_handle;
+                        let cursor_icon = match (over_handle, self.orientation) {
+                            (true, Orientation::Horizontal) => CursorIcon::SizeWE,
+                            (true, Orientation::Vertical) => CursorIcon::SizeNS,
+                            (false, _) => CursorIcon::default()
+                        };
+                        self.widget_tag.set_cursor_icon(cursor_icon).ok();
+                    }
+                },
+                WidgetEvent::MouseDown{pos, in_widget: true, button: MouseButton::Left} => {
+                    if let Some(handle) = self.handle_at(pos) {
+                        let now = Instant::now();
+                        match self.last_click {
+                            Some((last_handle, time)) if last_handle == handle && now - time <= DOUBLE_CLICK_TIME => {
+                                self.last_click = None;
+                                self.toggle_collapse(handle);
+                            },
+                            _ => {
+                                self.last_click = Some((handle, now));
+                                self.drag = Some((handle, self.axis(pos) - self.handles[handle]));
+                            }
+                        }
+                        bubble = false;
+                    }
+                },
+                WidgetEvent::MouseUp{button: MouseButton::Left, ..} => {
+                    self.drag = None;
+                },
+                _ => ()
+            }
+        }
+
+        EventOps {
+            focus: None,
+            bubble,
+        }
+    }
+}
+
+impl<C> Parent for Splitter<C>
+    where C: WidgetContainer<dyn Widget>
+{
+    fn num_children(&self) -> usize {
+        self.container.num_children()
+    }
+
+    fn framed_child<R: Renderer>(&self, widget_ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
+        self.container.framed_child(widget_ident).map(WidgetInfo::erase_subtype)
+    }
+    fn framed_child_mut<R: Renderer>(&mut self, widget_ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
+        self.container.framed_child_mut(widget_ident).map(WidgetInfoMut::erase_subtype)
+    }
+
+    fn framed_children<'a, R, G>(&'a self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
+    {
+        self.container.framed_children(|summary| for_each(WidgetInfo::erase_subtype(summary)))
+    }
+
+    fn framed_children_mut<'a, R, G>(&'a mut self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
+    {
+        self.container.framed_children_mut(|summary| for_each(WidgetInfoMut::erase_subtype(summary)))
+    }
+
+    fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
+        self.container.framed_child_by_index(index).map(WidgetInfo::erase_subtype)
+    }
+    fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
+        self.container.framed_child_by_index_mut(index).map(WidgetInfoMut::erase_subtype)
+    }
+}
+
+impl<R, C> WidgetRenderable<R> for Splitter<C>
+    where R: Renderer,
+          C: WidgetContainer<dyn Widget>
+{
+    type Theme = SplitterTheme;
+
+    fn theme(&self) -> SplitterTheme {
+        SplitterTheme(())
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        let result = layout.finish();
+        let content_rect = result.content_rect;
+        let margin_width = content_rect.min.x + (self.rect.width() - content_rect.max.x);
+        let margin_height = content_rect.min.y + (self.rect.height() - content_rect.max.y);
+        let horizontal = self.orientation == Orientation::Horizontal;
+
+        // Panes may have been added or removed through `container_mut`. New panes get the average
+        // share of the existing panes.
+        let num_panes = self.container.num_children();
+        let average_ratio = match self.ratios.len() {
+            0 => 1.0,
+            len => self.ratios.iter().sum::<f32>() / len as f32
+        };
+        self.ratios = self.sync_pane_count(self.ratios.clone(), average_ratio);
+        self.collapsed = self.sync_pane_count(self.collapsed.clone(), false);
+
+        let mut pane_bounds = Vec::with_capacity(num_panes);
+        let (mut cross_min, mut axis_min) = (0, 0);
+        // Panes that take up no space, either because the user collapsed them or because they were
+        // collapsed with `set_visibility`.
+        let mut empty = Vec::with_capacity(num_panes);
+        let collapsed = &self.collapsed;
+        self.container.children::<_>(|summary| {
+            let is_empty = collapsed[summary.index] || summary.widget().widget_tag().visibility() == Visibility::Collapsed;
+            let bounds = summary.widget().size_bounds();
+            let (axis_bounds, cross_bounds) = match horizontal {
+                true => ((bounds.min.width(), bounds.max.width()), bounds.min.height()),
+                false => ((bounds.min.height(), bounds.max.height()), bounds.min.width())
+            };
+            if !is_empty {
+                axis_min += axis_bounds.0;
+                cross_min = cross_min.max(cross_bounds);
+            }
+            pane_bounds.push(axis_bounds);
+            empty.push(is_empty);
+            LoopFlow::Continue
+        });
+
+        let (axis_start, axis_len, cross_start, cross_end) = match horizontal {
+            true => (content_rect.min.x, content_rect.width(), content_rect.min.y, content_rect.max.y),
+            false => (content_rect.min.y, content_rect.height(), content_rect.min.x, content_rect.max.x)
+        };
+        let num_handles = num_panes.saturating_sub(1) as i32;
+        let available = (axis_len - num_handles * HANDLE_SIZE).max(0);
+        let pane_sizes = distribute(available, &self.ratios, &pane_bounds, &empty);
+
+        self.handles.clear();
+        let mut pos = axis_start;
+        let handles = &mut self.handles;
+        self.container.children_mut::<_>(|mut summary| {
+            let index = summary.index;
+            let size = pane_sizes[index];
+            let rect = match horizontal {
+                true => BoundBox::new2(pos, cross_start, pos + size, cross_end),
+                false => BoundBox::new2(cross_start, pos, cross_end, pos + size)
+            };
+
+            // Collapsed panes get an empty rect, which keeps them from being drawn without touching
+            // the visibility the user gave them.
+            let widget = summary.widget_mut();
+            if widget.rect() != rect {
+                *widget.rect_mut() = rect;
+            }
+
+            pos += size;
+            if index + 1 < num_panes {
+                handles.push(pos);
+                pos += HANDLE_SIZE;
+            }
+            LoopFlow::Continue
+        });
+
+        self.pane_sizes = pane_sizes;
+        self.pane_bounds = pane_bounds;
+
+        let axis_min = axis_min + num_handles * HANDLE_SIZE;
+        let (min_width, min_height) = match horizontal {
+            true => (axis_min, cross_min),
+            false => (cross_min, axis_min)
+        };
+        self.size_bounds = SizeBounds {
+            min: DimsBox::new2(
+                (min_width + margin_width).max(result.size_bounds.min.width()),
+                (min_height + margin_height).max(result.size_bounds.min.height()),
+            ),
+            ..result.size_bounds
+        };
+    }
+}
+
+impl WidgetTheme for SplitterTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for SplitterTheme {
+    fn theme_path(&self) -> &'static str {
+        "Splitter"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNBOUNDED: (i32, i32) = (0, i32::max_value());
+
+    #[test]
+    fn distribute_by_ratio() {
+        assert_eq!(vec![25, 75], distribute(100, &[1.0, 3.0], &[UNBOUNDED; 2], &[false; 2]));
+        // Panes with no ratio set split the space evenly.
+        assert_eq!(vec![50, 50], distribute(100, &[0.0, 0.0], &[UNBOUNDED; 2], &[false; 2]));
+    }
+
+    #[test]
+    fn distribute_clamps() {
+        // The first pane can't shrink below its minimum, so the others split what's left.
+        let sizes = distribute(100, &[1.0, 4.0, 4.0], &[(40, i32::max_value()), UNBOUNDED, UNBOUNDED], &[false; 3]);
+        assert_eq!(vec![40, 30, 30], sizes);
+
+        // The first pane can't grow past its maximum, so the rest goes to the second pane.
+        let sizes = distribute(100, &[3.0, 1.0], &[(0, 20), UNBOUNDED], &[false; 2]);
+        assert_eq!(vec![20, 80], sizes);
+    }
+
+    #[test]
+    fn distribute_collapsed() {
+        // Collapsed panes get no space, even if they have a minimum size.
+        let sizes = distribute(90, &[1.0, 1.0, 2.0], &[UNBOUNDED, (30, i32::max_value()), UNBOUNDED], &[false, true, false]);
+        assert_eq!(vec![30, 0, 60], sizes);
+
+        assert_eq!(vec![0, 0], distribute(100, &[1.0, 1.0], &[UNBOUNDED; 2], &[true; 2]));
+    }
+
+    #[test]
+    fn distribute_rounding() {
+        // Pixels lost to rounding go to the last pane, so every pixel gets used.
+        assert_eq!(vec![33, 33, 34], distribute(100, &[1.0; 3], &[UNBOUNDED; 3], &[false; 3]));
+        assert_eq!(vec![14, 14, 73], distribute(101, &[1.0, 1.0, 5.0], &[UNBOUNDED; 3], &[false; 3]));
+    }
+}
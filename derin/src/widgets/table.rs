@@ -0,0 +1,1179 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use derin_core::{
+    LoopFlow,
+    event::{EventOps, WidgetEvent, WidgetEventSourced, InputState, MouseButton, MouseHoverChange, FocusChange, Key, ModifierKeys},
+    timer::{Timer, TimerId},
+    widget::{MessageTarget, WidgetIdent, WidgetTag, WidgetRenderable, WidgetInfo, WidgetInfoMut, WidgetId, Widget, Parent},
+    render::{Renderer, RendererLayout, SubFrame, WidgetTheme, CursorOp},
+};
+use crate::theme::{CursorIcon, ThemePath};
+use crate::widgets::{
+    Contents, SelectionMode,
+    assistants::{
+        ButtonState,
+        text_edit::{TextEditAssist, TextEditOps, CursorFlashOp, LineCharFilter},
+    },
+};
+
+use cgmath_geometry::{D2, rect::{BoundBox, DimsBox, GeoBox}};
+use derin_common_types::layout::{GridSize, SizeBounds, TrackHints, WidgetPos, WidgetSpan};
+use derin_layout_engine::{GridEngine, UpdateHeapCache, SolveError};
+use std::cell::RefCell;
+use std::time::Duration;
+
+/// The width of the region at the right edge of a header that can be dragged to resize its column.
+const RESIZE_GRIP: i32 = 4;
+
+/// Supplies the cells shown in a [`Table`].
+///
+/// [`Table`]: ./struct.Table.html
+pub trait TableSource: 'static {
+    fn num_rows(&self) -> usize;
+
+    /// What should be drawn in the cell at `row` and `column`.
+    fn cell(&self, row: usize, column: usize) -> Contents;
+
+    /// Reorder the rows by the values in `column`. Called when the user clicks a sortable column's
+    /// header.
+    fn sort(&mut self, column: usize, order: SortOrder);
+
+    /// Whether the cell at `row` and `column` can be edited inline. Cells can't be edited by
+    /// default.
+    fn can_edit(&self, _row: usize, _column: usize) -> bool {
+        false
+    }
+
+    /// Set the text of the cell at `row` and `column`, returning whether the new text was accepted.
+    fn edit(&mut self, _row: usize, _column: usize, _text: String) -> bool {
+        false
+    }
+}
+
+/// Determines which action, if any, should be taken in response to the user interacting with a
+/// table.
+pub trait TableHandler: 'static {
+    type Action: 'static;
+
+    fn on_select(&mut self, selection: &[usize]) -> Option<Self::Action>;
+
+    fn on_sort(&mut self, _column: usize, _order: SortOrder) -> Option<Self::Action> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+/// A column in a [`Table`].
+///
+/// [`Table`]: ./struct.Table.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableColumn {
+    pub header: Contents,
+    /// How the column gets sized. Columns with an `fr_size` of `0.0` are rigid, and are resized by
+    /// changing `min_size`. Other columns are resized by changing `fr_size`.
+    pub hints: TrackHints,
+    /// Whether clicking the column's header sorts the table.
+    pub sortable: bool,
+}
+
+/// A grid of cells, with a header row.
+///
+/// Columns can be resized by dragging the right edges of their headers, and clicking the header of
+/// a sortable column sorts the table by that column. Pressing `F2` edits the cell under the
+/// keyboard cursor, if the [`TableSource`] allows it. Whenever the row selection changes, the
+/// provided handler's [`on_select`] function is called.
+///
+/// [`TableSource`]: ./trait.TableSource.html
+/// [`on_select`]: ./trait.TableHandler.html#tymethod.on_select
+#[derive(Debug, Clone)]
+pub struct Table<S: TableSource, H: TableHandler> {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+    layout_engine: GridEngine,
+
+    source: S,
+    source_dirty: bool,
+    columns: Vec<TableColumn>,
+    headers: Vec<TableHeader>,
+    rows: Vec<Vec<TableCell>>,
+    sort: Option<(usize, SortOrder)>,
+
+    selection_mode: SelectionMode,
+    /// The selected rows, sorted by index.
+    selection: Vec<usize>,
+    /// The row and column of the cell keyboard navigation starts from.
+    cursor: Option<(usize, usize)>,
+    anchor: Option<usize>,
+    editing: Option<(usize, usize)>,
+
+    pub handler: H,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TableTheme(());
+
+#[derive(Debug, Clone, Copy)]
+pub struct TableHeaderTheme {
+    pub state: ButtonState,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TableCellTheme {
+    pub selected: bool,
+    pub editing: bool,
+}
+
+#[derive(Debug, Clone)]
+struct TableHeader {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    contents: Contents,
+    sort: Option<SortOrder>,
+    state: ButtonState,
+    over_grip: bool,
+    resizing: bool,
+}
+
+#[derive(Debug, Clone)]
+struct TableCell {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    contents: Contents,
+    selected: bool,
+    edit: Option<TextEditAssist<LineCharFilter>>,
+    flash_timer: Option<TimerId>,
+}
+
+/// Sent from a header or cell to the table that contains it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TableEvent {
+    HeaderClick(WidgetId),
+    Resize(WidgetId, i32),
+    CellPress(WidgetId, ModifierKeys),
+}
+
+impl<S, H> Table<S, H>
+    where S: TableSource,
+          H: TableHandler
+{
+    /// Creates a new table with the given columns, showing the cells in `source`.
+    pub fn new(columns: Vec<TableColumn>, source: S, selection_mode: SelectionMode, handler: H) -> Table<S, H> {
+        let mut widget_tag = WidgetTag::new();
+        widget_tag.register_message(Self::on_table_event);
+        widget_tag.set_focusable(true);
+
+        Table {
+            widget_tag,
+            rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+            layout_engine: GridEngine::new(),
+
+            source,
+            source_dirty: true,
+            columns,
+            headers: Vec::new(),
+            rows: Vec::new(),
+            sort: None,
+
+            selection_mode,
+            selection: Vec::new(),
+            cursor: None,
+            anchor: None,
+            editing: None,
+
+            handler,
+        }
+    }
+
+    /// Retrieves the source supplying the table's cells.
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Retrieves the source supplying the table's cells, for mutation.
+    ///
+    /// Calling this function forces the table to re-query every cell, so you're discouraged from
+    /// calling it unless you're actually changing the source.
+    pub fn source_mut(&mut self) -> &mut S {
+        self.source_dirty = true;
+        self.widget_tag.request_redraw().request_relayout();
+        &mut self.source
+    }
+
+    /// Retrieves the table's columns.
+    pub fn columns(&self) -> &[TableColumn] {
+        &self.columns
+    }
+
+    /// Retrieves the table's columns, for mutation.
+    ///
+    /// Calling this function forces the table to re-query every cell, so you're discouraged from
+    /// calling it unless you're actually changing the columns.
+    pub fn columns_mut(&mut self) -> &mut Vec<TableColumn> {
+        self.source_dirty = true;
+        self.widget_tag.request_redraw().request_relayout();
+        &mut self.columns
+    }
+
+    /// Retrieves the column the table is sorted by, and the order it's sorted in.
+    pub fn sort(&self) -> Option<(usize, SortOrder)> {
+        self.sort
+    }
+
+    /// Sort the table by `column`.
+    pub fn sort_by(&mut self, column: usize, order: SortOrder) {
+        self.finish_edit(true);
+        self.source.sort(column, order);
+        self.sort = Some((column, order));
+        self.source_dirty = true;
+
+        // The source moved the rows around, so the selected indices don't point at the same rows
+        // anymore.
+        self.cursor = None;
+        self.anchor = None;
+        if !self.selection.is_empty() {
+            self.selection.clear();
+            self.notify_select();
+        }
+        self.widget_tag.request_redraw().request_relayout();
+    }
+
+    /// Retrieves the indices of the selected rows, in ascending order.
+    pub fn selection(&self) -> &[usize] {
+        &self.selection
+    }
+
+    /// Replace the selection. Changing the selection through this function doesn't call the
+    /// handler.
+    pub fn set_selection(&mut self, mut selection: Vec<usize>) {
+        selection.sort();
+        selection.dedup();
+        self.selection = selection;
+        self.sync_selection();
+    }
+
+    /// Start editing the cell at `row` and `column` inline, if the source allows it.
+    pub fn edit_cell(&mut self, row: usize, column: usize) {
+        self.refresh_source();
+        self.finish_edit(true);
+        if !self.source.can_edit(row, column) {
+            return;
+        }
+
+        if let Some(cell) = self.rows.get_mut(row).and_then(|r| r.get_mut(column)) {
+            if let Contents::Text(ref text) = cell.contents {
+                let text = text.clone();
+                cell.begin_edit(text);
+                self.editing = Some((row, column));
+            }
+        }
+    }
+
+    fn refresh_source(&mut self) {
+        if !self.source_dirty {
+            return;
+        }
+        self.source_dirty = false;
+
+        let num_rows = self.source.num_rows();
+        let num_cols = self.columns.len();
+
+        self.headers.resize(num_cols, TableHeader::new());
+        for (i, (header, column)) in self.headers.iter_mut().zip(&self.columns).enumerate() {
+            let sort = self.sort.filter(|&(c, _)| c == i).map(|(_, order)| order);
+            header.set_contents(column.header.clone(), sort);
+        }
+
+        self.rows.resize(num_rows, Vec::new());
+        for (r, row) in self.rows.iter_mut().enumerate() {
+            row.resize(num_cols, TableCell::new());
+            for (c, cell) in row.iter_mut().enumerate() {
+                if cell.edit.is_none() {
+                    cell.set_contents(self.source.cell(r, c));
+                }
+            }
+        }
+
+        let num_selected = self.selection.len();
+        self.selection.retain(|&r| r < num_rows);
+        if self.selection.len() != num_selected {
+            self.notify_select();
+        }
+        self.sync_selection();
+        self.cursor = self.cursor.filter(|&(r, c)| r < num_rows && c < num_cols);
+        self.anchor = self.anchor.filter(|&r| r < num_rows);
+        if self.editing.map(|(r, c)| r >= num_rows || c >= num_cols).unwrap_or(false) {
+            self.editing = None;
+        }
+    }
+
+    fn sync_selection(&mut self) {
+        for (r, row) in self.rows.iter_mut().enumerate() {
+            let selected = self.selection.binary_search(&r).is_ok();
+            for cell in row {
+                cell.set_selected(selected);
+            }
+        }
+    }
+
+    fn notify_select(&mut self) {
+        if let Some(action) = self.handler.on_select(&self.selection) {
+            self.widget_tag.broadcast_message(action);
+        }
+    }
+
+    /// Move the cursor to the cell at `row` and `column`, updating the row selection the way
+    /// clicking the cell with `modifiers` held would.
+    fn select_cell(&mut self, row: usize, column: usize, modifiers: ModifierKeys) {
+        if row >= self.rows.len() {
+            return;
+        }
+        self.cursor = Some((row, column));
+
+        let multi = self.selection_mode == SelectionMode::Multi;
+        let old_selection = self.selection.clone();
+        if multi && modifiers.contains(ModifierKeys::SHIFT) {
+            let anchor = self.anchor.unwrap_or(row);
+            let range = anchor.min(row)..anchor.max(row) + 1;
+            match modifiers.contains(ModifierKeys::CTRL) {
+                true => self.selection.extend(range),
+                false => self.selection = range.collect()
+            }
+            self.selection.sort();
+            self.selection.dedup();
+        } else if multi && modifiers.contains(ModifierKeys::CTRL) {
+            self.anchor = Some(row);
+            match self.selection.binary_search(&row) {
+                Ok(i) => {self.selection.remove(i);},
+                Err(i) => self.selection.insert(i, row)
+            }
+        } else {
+            self.anchor = Some(row);
+            self.selection = vec![row];
+        }
+
+        if self.selection != old_selection {
+            self.sync_selection();
+            self.notify_select();
+        }
+    }
+
+    /// Resize `column` to `width` pixels, keeping rigid columns rigid and fractional columns
+    /// fractional.
+    fn resize_column(&mut self, column: usize, width: i32) {
+        let old_width = match self.headers.get(column) {
+            Some(header) => header.rect.width(),
+            None => return
+        };
+        let hints = &mut self.columns[column].hints;
+        let width = width.max(RESIZE_GRIP * 2).min(hints.max_size);
+        match hints.fr_size <= 0.0 {
+            true => hints.min_size = width,
+            false if old_width > 0 => hints.fr_size *= width as f32 / old_width as f32,
+            false => ()
+        }
+        self.widget_tag.request_redraw().request_relayout();
+    }
+
+    fn finish_edit(&mut self, commit: bool) {
+        let (row, column) = match self.editing.take() {
+            Some(cell) => cell,
+            None => return
+        };
+        let text = match self.rows.get_mut(row).and_then(|r| r.get_mut(column)) {
+            Some(cell) => cell.end_edit(),
+            None => return
+        };
+
+        if commit && self.source.edit(row, column, text) {
+            self.source_dirty = true;
+        }
+        self.widget_tag.request_redraw().request_relayout();
+    }
+
+    fn on_table_event(&mut self, event: &TableEvent) {
+        self.refresh_source();
+        match *event {
+            TableEvent::HeaderClick(header_id) => {
+                let column = self.headers.iter().position(|h| h.widget_tag.widget_id() == header_id);
+                if let Some(column) = column.filter(|&c| self.columns[c].sortable) {
+                    let order = match self.sort {
+                        Some((c, SortOrder::Ascending)) if c == column => SortOrder::Descending,
+                        _ => SortOrder::Ascending
+                    };
+                    self.sort_by(column, order);
+                    if let Some(action) = self.handler.on_sort(column, order) {
+                        self.widget_tag.broadcast_message(action);
+                    }
+                }
+            },
+            TableEvent::Resize(header_id, width) => {
+                if let Some(column) = self.headers.iter().position(|h| h.widget_tag.widget_id() == header_id) {
+                    self.resize_column(column, width);
+                }
+            },
+            TableEvent::CellPress(cell_id, modifiers) => {
+                let cell = self.rows.iter().enumerate()
+                    .filter_map(|(r, row)| row.iter().position(|c| c.widget_tag.widget_id() == cell_id).map(|c| (r, c)))
+                    .next();
+                if let Some((row, column)) = cell {
+                    if self.editing != Some((row, column)) {
+                        self.finish_edit(true);
+                        self.select_cell(row, column, modifiers);
+                    }
+                }
+            }
+        }
+    }
+
+    fn on_key(&mut self, key: Key, modifiers: ModifierKeys) -> bool {
+        let (num_rows, num_cols) = (self.rows.len(), self.columns.len());
+        if num_rows == 0 || num_cols == 0 {
+            return false;
+        }
+        let modifiers = modifiers & (ModifierKeys::SHIFT | ModifierKeys::CTRL);
+        let (row, column) = self.cursor.unwrap_or((0, 0));
+
+        match key {
+            Key::UArrow => self.select_cell(row.saturating_sub(1), column, modifiers),
+            Key::DArrow => self.select_cell((row + 1).min(num_rows - 1), column, modifiers),
+            Key::Home => self.select_cell(0, column, modifiers),
+            Key::End => self.select_cell(num_rows - 1, column, modifiers),
+            Key::LArrow => self.cursor = Some((row, column.saturating_sub(1))),
+            Key::RArrow => self.cursor = Some((row, (column + 1).min(num_cols - 1))),
+            Key::Space if self.selection_mode == SelectionMode::Multi => self.select_cell(row, column, ModifierKeys::CTRL),
+            Key::F2 => self.edit_cell(row, column),
+            _ => return false
+        }
+        true
+    }
+}
+
+impl TableHeader {
+    fn new() -> TableHeader {
+        TableHeader {
+            widget_tag: WidgetTag::new(),
+            rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+
+            contents: Contents::Text(String::new()),
+            sort: None,
+            state: ButtonState::Normal,
+            over_grip: false,
+            resizing: false,
+        }
+    }
+
+    fn set_contents(&mut self, contents: Contents, sort: Option<SortOrder>) {
+        if contents != self.contents || sort != self.sort {
+            self.contents = contents;
+            self.sort = sort;
+            self.widget_tag.request_redraw().request_relayout();
+        }
+    }
+}
+
+impl TableCell {
+    fn new() -> TableCell {
+        TableCell {
+            widget_tag: WidgetTag::new(),
+            rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+
+            contents: Contents::Text(String::new()),
+            selected: false,
+            edit: None,
+            flash_timer: None,
+        }
+    }
+
+    fn set_contents(&mut self, contents: Contents) {
+        if contents != self.contents {
+            self.contents = contents;
+            self.widget_tag.request_redraw().request_relayout();
+        }
+    }
+
+    fn set_selected(&mut self, selected: bool) {
+        if selected != self.selected {
+            self.selected = selected;
+            self.widget_tag.request_redraw();
+        }
+    }
+
+    fn begin_edit(&mut self, string: String) {
+        let mut edit = TextEditAssist {
+            string,
+            ..TextEditAssist::default()
+        };
+        edit.cursor_ops.push(CursorOp::SelectAll);
+        self.edit = Some(edit);
+        self.apply_edit_ops(TextEditOps {
+            allow_bubble: false,
+            redraw: true,
+            focus: None,
+            cursor_flash: Some(CursorFlashOp::Start),
+            cursor_icon: None,
+        });
+    }
+
+    fn end_edit(&mut self) -> String {
+        if let Some(timer_id) = self.flash_timer.take() {
+            self.widget_tag.timers_mut().remove(&timer_id);
+        }
+        self.widget_tag.request_redraw().request_relayout();
+        self.edit.take().map(|e| e.string).unwrap_or_default()
+    }
+
+    fn apply_edit_ops(&mut self, ops: TextEditOps) {
+        match (ops.cursor_flash, self.flash_timer) {
+            (Some(CursorFlashOp::Start), None) => {
+                let timer_id = TimerId::new();
+                self.widget_tag.timers_mut().insert(timer_id, Timer::new(Duration::new(1, 0)/2));
+                self.flash_timer = Some(timer_id);
+            },
+            (Some(CursorFlashOp::End), Some(timer_id)) => {
+                self.widget_tag.timers_mut().remove(&timer_id);
+                self.flash_timer = None;
+            },
+            _ => ()
+        }
+        if ops.redraw {
+            self.widget_tag.request_redraw().request_relayout();
+        }
+        if let Some(cursor_icon) = ops.cursor_icon {
+            self.widget_tag.set_cursor_icon(cursor_icon).ok();
+        }
+    }
+}
+
+impl<S, H> Widget for Table<S, H>
+    where S: TableSource,
+          H: TableHandler
+{
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        self.widget_tag.request_relayout();
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, input_state: InputState) -> EventOps {
+        use self::WidgetEvent::*;
+        let event = event.unwrap();
+        self.refresh_source();
+
+        let mut focus = None;
+        let mut bubble = event.default_bubble();
+
+        match (event.clone(), self.editing) {
+            (KeyDown(Key::Enter, _), Some(_)) => {
+                bubble = false;
+                self.finish_edit(true);
+            },
+            (KeyDown(Key::Escape, _), Some(_)) => {
+                bubble = false;
+                self.finish_edit(false);
+            },
+            (KeyDown(..), Some((row, column))) |
+            (KeyUp(..), Some((row, column))) |
+            (Char(_), Some((row, column))) => {
+                let cell = &mut self.rows[row][column];
+                if let Some(ref mut edit) = cell.edit {
+                    let ops = edit.adapt_event(&event, input_state);
+                    bubble = bubble && ops.allow_bubble;
+                    cell.apply_edit_ops(ops);
+                }
+            },
+            (KeyDown(key, modifiers), None) => if self.on_key(key, modifiers) {
+                bubble = false;
+            },
+            (MouseDown{in_widget: true, ..}, _) => focus = Some(FocusChange::Take),
+            (MouseDown{in_widget: false, ..}, _) => {
+                self.finish_edit(true);
+                focus = Some(FocusChange::Remove);
+            },
+            (LoseFocus, _) => self.finish_edit(true),
+            _ => ()
+        }
+
+        EventOps {
+            focus,
+            bubble,
+        }
+    }
+}
+
+impl<S, H> Parent for Table<S, H>
+    where S: TableSource,
+          H: TableHandler
+{
+    fn num_children(&self) -> usize {
+        self.headers.len() * (self.rows.len() + 1)
+    }
+
+    fn framed_child<R: Renderer>(&self, widget_ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
+        match widget_ident {
+            WidgetIdent::NumCollection(column, row) if (column as usize) < self.headers.len() =>
+                self.framed_child_by_index(row as usize * self.headers.len() + column as usize),
+            _ => None
+        }
+    }
+    fn framed_child_mut<R: Renderer>(&mut self, widget_ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
+        match widget_ident {
+            WidgetIdent::NumCollection(column, row) if (column as usize) < self.headers.len() =>
+                self.framed_child_by_index_mut(row as usize * self.headers.len() + column as usize),
+            _ => None
+        }
+    }
+
+    fn framed_children<'a, R, G>(&'a self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
+    {
+        let num_cols = self.headers.len();
+        for (c, header) in self.headers.iter().enumerate() {
+            if let LoopFlow::Break = for_each(WidgetInfo::new(WidgetIdent::NumCollection(c as u32, 0), c, header)) {
+                return;
+            }
+        }
+        for (r, row) in self.rows.iter().enumerate() {
+            for (c, cell) in row.iter().enumerate() {
+                let ident = WidgetIdent::NumCollection(c as u32, r as u32 + 1);
+                if let LoopFlow::Break = for_each(WidgetInfo::new(ident, (r + 1) * num_cols + c, cell)) {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn framed_children_mut<'a, R, G>(&'a mut self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
+    {
+        let num_cols = self.headers.len();
+        for (c, header) in self.headers.iter_mut().enumerate() {
+            if let LoopFlow::Break = for_each(WidgetInfoMut::new(WidgetIdent::NumCollection(c as u32, 0), c, header)) {
+                return;
+            }
+        }
+        for (r, row) in self.rows.iter_mut().enumerate() {
+            for (c, cell) in row.iter_mut().enumerate() {
+                let ident = WidgetIdent::NumCollection(c as u32, r as u32 + 1);
+                if let LoopFlow::Break = for_each(WidgetInfoMut::new(ident, (r + 1) * num_cols + c, cell)) {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
+        let num_cols = self.headers.len();
+        if num_cols == 0 {
+            return None;
+        }
+        let (row, column) = (index / num_cols, index % num_cols);
+        let ident = WidgetIdent::NumCollection(column as u32, row as u32);
+        match row {
+            0 => Some(WidgetInfo::new(ident, index, &self.headers[column])),
+            _ => self.rows.get(row - 1).map(|r| WidgetInfo::new(ident, index, &r[column]))
+        }
+    }
+    fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
+        let num_cols = self.headers.len();
+        if num_cols == 0 {
+            return None;
+        }
+        let (row, column) = (index / num_cols, index % num_cols);
+        let ident = WidgetIdent::NumCollection(column as u32, row as u32);
+        match row {
+            0 => Some(WidgetInfoMut::new(ident, index, &mut self.headers[column])),
+            _ => self.rows.get_mut(row - 1).map(|r| WidgetInfoMut::new(ident, index, &mut r[column]))
+        }
+    }
+}
+
+impl Widget for TableHeader {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        use self::WidgetEvent::*;
+        let event = event.unwrap();
+        let header_id = self.widget_tag.widget_id();
+        let mut table_event = None;
+
+        let new_state = match event {
+            MouseMove{new_pos, in_widget, ref hover_change, ..} => {
+                if self.resizing {
+                    table_event = Some(TableEvent::Resize(header_id, new_pos.x));
+                }
+
+                let over_grip = self.resizing || (in_widget && new_pos.x >= self.rect.width() - RESIZE_GRIP);
+                if over_grip != self.over_grip {
+                    self.over_grip = over_grip;
+                    let cursor_icon = match over_grip {
+                        true => CursorIcon::SizeWE,
+                        false => CursorIcon::default()
+                    };
+                    self.widget_tag.set_cursor_icon(cursor_icon).ok();
+                }
+
+                match hover_change {
+                    Some(MouseHoverChange::Enter) => ButtonState::Hover,
+                    Some(MouseHoverChange::Exit) => ButtonState::Normal,
+                    _ => self.state
+                }
+            },
+            MouseDown{in_widget: true, button: MouseButton::Left, pos} => {
+                match pos.x >= self.rect.width() - RESIZE_GRIP {
+                    true => {
+                        self.resizing = true;
+                        self.state
+                    },
+                    false => ButtonState::Pressed
+                }
+            },
+            MouseUp{button: MouseButton::Left, in_widget, pressed_in_widget, ..} => {
+                match self.resizing {
+                    true => self.resizing = false,
+                    false if in_widget && pressed_in_widget => table_event = Some(TableEvent::HeaderClick(header_id)),
+                    false => ()
+                }
+                match in_widget {
+                    true => ButtonState::Hover,
+                    false => ButtonState::Normal
+                }
+            },
+            _ => self.state
+        };
+
+        if new_state != self.state {
+            self.state = new_state;
+            self.widget_tag.request_redraw();
+        }
+        if let Some(table_event) = table_event {
+            self.widget_tag.send_message_to(table_event, MessageTarget::ParentOf(header_id));
+        }
+
+        EventOps {
+            focus: None,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
+impl Widget for TableCell {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, input_state: InputState) -> EventOps {
+        let event = event.unwrap();
+        let cell_id = self.widget_tag.widget_id();
+        let mut focus = None;
+
+        match event {
+            WidgetEvent::Timer{timer_id, times_triggered, ..} if Some(timer_id) == self.flash_timer => {
+                if let Some(ref mut edit) = self.edit {
+                    edit.cursor_data.draw_cursor = times_triggered % 2 == 0;
+                    self.widget_tag.request_redraw();
+                }
+            },
+            // Keyboard input gets forwarded to the edit by the table, which keeps focus while
+            // editing. Mouse input positions the edit cursor directly.
+            WidgetEvent::MouseDown{in_widget: true, ..} |
+            WidgetEvent::MouseMove{..} |
+            WidgetEvent::MouseUp{..} if self.edit.is_some() => {
+                let ops = self.edit.as_mut().unwrap().adapt_event(&event, input_state);
+                self.apply_edit_ops(ops);
+                if let WidgetEvent::MouseDown{..} = event {
+                    focus = Some(FocusChange::Parent);
+                }
+            },
+            WidgetEvent::MouseDown{in_widget: true, button: MouseButton::Left, ..} => {
+                focus = Some(FocusChange::Parent);
+                let press = TableEvent::CellPress(cell_id, input_state.modifiers);
+                self.widget_tag.send_message_to(press, MessageTarget::ParentOf(cell_id));
+            },
+            _ => ()
+        }
+
+        EventOps {
+            focus,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
+impl<R, S, H> WidgetRenderable<R> for Table<S, H>
+    where R: Renderer,
+          S: TableSource,
+          H: TableHandler
+{
+    type Theme = TableTheme;
+
+    fn theme(&self) -> TableTheme {
+        TableTheme(())
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        #[derive(Default)]
+        struct HeapCache {
+            update_heap_cache: UpdateHeapCache,
+            hints_vec: Vec<WidgetPos>,
+            rects_vec: Vec<Result<BoundBox<D2, i32>, SolveError>>
+        }
+        thread_local! {
+            static HEAP_CACHE: RefCell<HeapCache> = RefCell::new(HeapCache::default());
+        }
+
+        self.refresh_source();
+
+        let result = layout.finish();
+        let content_rect = result.content_rect;
+        let margin_width = content_rect.min.x + (self.rect.width() - content_rect.max.x);
+        let margin_height = content_rect.min.y + (self.rect.height() - content_rect.max.y);
+        let header_height = self.headers.iter().map(|h| h.size_bounds.min.height()).max().unwrap_or(0);
+
+        // Lay out the headers in a single-row grid, which determines the position of each column.
+        HEAP_CACHE.with(|hc| {
+            let mut hc = hc.borrow_mut();
+            let HeapCache {
+                ref mut update_heap_cache,
+                ref mut hints_vec,
+                ref mut rects_vec
+            } = *hc;
+
+            let num_cols = self.headers.len() as u32;
+            self.layout_engine.set_grid_size(GridSize::new(num_cols, 1));
+            for (c, column) in self.columns.iter().enumerate() {
+                self.layout_engine.set_col_hints(c as u32, column.hints);
+            }
+            for (c, header) in self.headers.iter().enumerate() {
+                hints_vec.push(WidgetPos {
+                    size_bounds: SizeBounds::new_min(header.size_bounds.min),
+                    widget_span: WidgetSpan::new(c as u32, 0),
+                    ..WidgetPos::default()
+                });
+                rects_vec.push(Ok(BoundBox::new2(0, 0, 0, 0)));
+            }
+
+            self.layout_engine.desired_size = DimsBox::new2(content_rect.width(), header_height);
+            self.layout_engine.update_engine(hints_vec, rects_vec, update_heap_cache);
+
+            for (header, rect) in self.headers.iter_mut().zip(rects_vec.drain(..)) {
+                let rect = rect.unwrap_or(BoundBox::new2(0, 0, 0, 0));
+                let rect = BoundBox::new2(
+                    content_rect.min.x + rect.min.x,
+                    content_rect.min.y,
+                    content_rect.min.x + rect.max.x,
+                    content_rect.min.y + header_height
+                );
+                if header.rect != rect {
+                    header.rect = rect;
+                    header.widget_tag.request_relayout();
+                }
+            }
+
+            hints_vec.clear();
+        });
+
+        let mut y = content_rect.min.y + header_height;
+        for row in &mut self.rows {
+            let height = row.iter().map(|c| c.size_bounds.min.height()).max().unwrap_or(0);
+            for (cell, header) in row.iter_mut().zip(&self.headers) {
+                let rect = BoundBox::new2(header.rect.min.x, y, header.rect.max.x, y + height);
+                if cell.rect != rect {
+                    cell.rect = rect;
+                    cell.widget_tag.request_relayout();
+                }
+            }
+            y += height;
+        }
+
+        let grid_bounds = self.layout_engine.actual_size_bounds();
+        let min = DimsBox::new2(
+            (grid_bounds.min.width() + margin_width).max(result.size_bounds.min.width()),
+            (y - content_rect.min.y + margin_height).max(result.size_bounds.min.height()),
+        );
+        self.size_bounds = SizeBounds {
+            min,
+            ..result.size_bounds
+        };
+    }
+}
+
+impl<R> WidgetRenderable<R> for TableHeader
+    where R: Renderer
+{
+    type Theme = TableHeaderTheme;
+
+    fn theme(&self) -> TableHeaderTheme {
+        TableHeaderTheme {
+            state: self.state,
+        }
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        let indicator = match self.sort {
+            None => "",
+            Some(SortOrder::Ascending) => " \u{25B2}",
+            Some(SortOrder::Descending) => " \u{25BC}",
+        };
+        match self.contents {
+            Contents::Text(ref s) => layout.prepare_string(&format!("{}{}", s, indicator)),
+            Contents::Icon(ref i) => layout.prepare_icon(i),
+        }
+
+        let result = layout.finish();
+        self.size_bounds = result.size_bounds;
+    }
+}
+
+impl<R> WidgetRenderable<R> for TableCell
+    where R: Renderer
+{
+    type Theme = TableCellTheme;
+
+    fn theme(&self) -> TableCellTheme {
+        TableCellTheme {
+            selected: self.selected,
+            editing: self.edit.is_some(),
+        }
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        match (&mut self.edit, &self.contents) {
+            (Some(edit), _) => layout.prepare_edit_string(
+                &mut edit.string,
+                &mut edit.cursor_data,
+                edit.cursor_ops.drain(..),
+            ),
+            (None, Contents::Text(s)) => layout.prepare_string(s),
+            (None, Contents::Icon(i)) => layout.prepare_icon(i),
+        }
+
+        let result = layout.finish();
+        self.size_bounds = result.size_bounds;
+    }
+}
+
+impl WidgetTheme for TableTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for TableTheme {
+    fn theme_path(&self) -> &'static str {
+        "Table::Background"
+    }
+}
+
+impl WidgetTheme for TableHeaderTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for TableHeaderTheme {
+    fn theme_path(&self) -> &'static str {
+        match self.state {
+            ButtonState::Normal => "Table::Header::Normal",
+            ButtonState::Hover => "Table::Header::Hover",
+            ButtonState::Pressed => "Table::Header::Pressed",
+        }
+    }
+}
+
+impl WidgetTheme for TableCellTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for TableCellTheme {
+    fn theme_path(&self) -> &'static str {
+        match (self.editing, self.selected) {
+            (true, _) => "Table::Cell::Edit",
+            (false, true) => "Table::Cell::Selected",
+            (false, false) => "Table::Cell::Normal",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::WindowEvent;
+    use crate::cgmath::Point2;
+    use crate::soft_render::snapshot::Snapshot;
+    use crate::theme::Theme;
+
+    struct Fruit(Vec<(&'static str, u32)>);
+    impl TableSource for Fruit {
+        fn num_rows(&self) -> usize {
+            self.0.len()
+        }
+
+        fn cell(&self, row: usize, column: usize) -> Contents {
+            let (name, count) = self.0[row];
+            match column {
+                0 => Contents::Text(name.to_string()),
+                _ => Contents::Text(count.to_string())
+            }
+        }
+
+        fn sort(&mut self, column: usize, order: SortOrder) {
+            self.0.sort_by(|a, b| match column {
+                0 => a.0.cmp(b.0),
+                _ => a.1.cmp(&b.1)
+            });
+            if order == SortOrder::Descending {
+                self.0.reverse();
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct SortLog(Vec<(usize, SortOrder)>);
+    impl TableHandler for SortLog {
+        type Action = ();
+        fn on_select(&mut self, _: &[usize]) -> Option<()> {
+            None
+        }
+        fn on_sort(&mut self, column: usize, order: SortOrder) -> Option<()> {
+            self.0.push((column, order));
+            None
+        }
+    }
+
+    fn column(header: &str) -> TableColumn {
+        TableColumn {
+            header: Contents::Text(header.to_string()),
+            hints: TrackHints {
+                min_size: 60,
+                fr_size: 0.0,
+                ..TrackHints::default()
+            },
+            sortable: true,
+        }
+    }
+
+    fn click(snapshot: &mut Snapshot<Table<Fruit, SortLog>>, pos: Point2<i32>) {
+        snapshot.process_events(vec![
+            WindowEvent::MouseMove(pos),
+            WindowEvent::MouseDown(MouseButton::Left),
+            WindowEvent::MouseUp(MouseButton::Left),
+        ]);
+        snapshot.render();
+    }
+
+    #[test]
+    fn sort_and_resize_from_header() {
+        let source = Fruit(vec![("Cherry", 3), ("Apple", 1), ("Banana", 2)]);
+        let table = Table::new(vec![column("Name"), column("Count")], source, SelectionMode::Single, SortLog::default());
+        let mut snapshot = Snapshot::new(table, Theme::default(), DimsBox::new2(200, 100));
+        snapshot.render();
+        snapshot.process_events(vec![WindowEvent::MouseEnter]);
+
+        // Clicking a header sorts by its column, and clicking it again reverses the order.
+        let header = snapshot.root().headers[1].rect;
+        let header_center = Point2::new(header.min.x + 10, header.min.y + header.height() / 2);
+        click(&mut snapshot, header_center);
+        assert_eq!(Some((1, SortOrder::Ascending)), snapshot.root().sort());
+        assert_eq!(vec![("Apple", 1), ("Banana", 2), ("Cherry", 3)], snapshot.root().source().0);
+
+        click(&mut snapshot, header_center);
+        assert_eq!(Some((1, SortOrder::Descending)), snapshot.root().sort());
+        assert_eq!(vec![("Cherry", 3), ("Banana", 2), ("Apple", 1)], snapshot.root().source().0);
+        assert_eq!(vec![(1, SortOrder::Ascending), (1, SortOrder::Descending)], snapshot.root().handler.0);
+
+        // Dragging the separator on a header's right edge resizes the column without sorting.
+        let header = snapshot.root().headers[0].rect;
+        let grip = Point2::new(header.max.x - RESIZE_GRIP / 2, header.min.y + header.height() / 2);
+        snapshot.process_events(vec![
+            WindowEvent::MouseMove(grip),
+            WindowEvent::MouseDown(MouseButton::Left),
+            WindowEvent::MouseMove(Point2::new(grip.x + 30, grip.y)),
+            WindowEvent::MouseUp(MouseButton::Left),
+        ]);
+        snapshot.render();
+        let grip_offset = grip.x - header.min.x;
+        assert_eq!(grip_offset + 30, snapshot.root().columns()[0].hints.min_size);
+        assert_eq!(Some((1, SortOrder::Descending)), snapshot.root().sort());
+        assert_eq!(2, snapshot.root().handler.0.len());
+    }
+}
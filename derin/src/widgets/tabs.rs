@@ -2,27 +2,26 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-
-use crate::{
-    core::{
-        LoopFlow,
-        event::{EventOps, WidgetEvent, WidgetEventSourced, InputState},
-        widget::{WidgetIdent, WidgetRenderable, WidgetTag, WidgetInfo, WidgetInfoMut, Widget, Parent},
-        render::Renderer,
-    },
-    gl_render::{RelPoint, ThemedPrim, Prim, PrimFrame, RenderString},
-    widgets::assistants::ButtonState,
+use derin_core::{
+    LoopFlow,
+    event::{EventOps, WidgetEvent, WidgetEventSourced, InputState, MouseButton, MouseHoverChange, FocusChange, Key, ModifierKeys},
+    widget::{MessageTarget, WidgetIdent, WidgetTag, WidgetRenderable, WidgetInfo, WidgetInfoMut, WidgetId, Widget, Parent, Visibility},
+    render::{Renderer, RendererLayout, SubFrame, WidgetTheme},
+};
+use crate::theme::ThemePath;
+use crate::widgets::{
+    Contents,
+    ScrollArrowTheme,
+    assistants::ButtonState,
+    scroll_box::SCROLL_BAR_SIZE,
 };
 
-use derin_common_types::layout::{SizeBounds, WidgetPos, GridSize, WidgetSpan, TrackHints};
-
-use std::cell::RefCell;
-use crate::cgmath::Point2;
-use cgmath_geometry::{D2, rect::{BoundBox, GeoBox}};
-
-use derin_layout_engine::{GridEngine, UpdateHeapCache, SolveError};
+use crate::cgmath::{EuclideanSpace, Point2};
+use cgmath_geometry::{D2, rect::{BoundBox, DimsBox, GeoBox}};
+use derin_common_types::layout::SizeBounds;
 
-use arrayvec::ArrayVec;
+/// How far the mouse has to move with a tab pressed before the tab starts getting dragged.
+const DRAG_THRESHOLD: i32 = 4;
 
 /// A page within a greater list of tabs.
 ///
@@ -31,61 +30,206 @@ use arrayvec::ArrayVec;
 /// [`TabList`]: ./struct.TabList.html
 #[derive(Debug, Clone)]
 pub struct TabPage<W> {
-    title: RenderString,
+    header: TabHeader,
     /// The widget that's displayed within the tab page.
     pub page: W,
-    open: bool,
-    button_state: ButtonState,
-    rect: BoundBox<D2, i32>
 }
 
 /// A list of tabs.
 ///
 /// This widget lets you display a single widget at a time, from a greater selection of widgets.
-/// Users can switch between these widgets by clicking on a list of tabs at the top of the widget.
+/// Users can switch between these widgets by clicking on a list of tabs at the top of the widget,
+/// or with Ctrl+Tab and Ctrl+Shift+Tab. If the tabs don't fit in the widget, arrow buttons are
+/// shown that scroll through the tabs.
+#[derive(Debug, Clone)]
+pub struct TabList<W, H: TabListHandler> {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    tabs: Vec<TabPage<W>>,
+    open: usize,
+    /// The first tab shown in the tab strip.
+    first_visible: usize,
+    /// Whether the tab strip should be scrolled to show the open tab during the next layout.
+    scroll_to_open: bool,
+    scroll_left: TabScroll,
+    scroll_right: TabScroll,
+    pub handler: H,
+}
+
+/// Determines which action, if any, should be taken when tabs are switched or closed.
+pub trait TabListHandler: 'static {
+    type Action: 'static;
+
+    /// Called when the user opens the tab at `index`.
+    fn on_select(&mut self, index: usize) -> Option<Self::Action>;
+    /// Called after the user closes the tab that was at `index`.
+    fn on_close(&mut self, _index: usize) -> Option<Self::Action> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TabListTheme(());
+
+#[derive(Debug, Clone, Copy)]
+pub struct TabTheme {
+    pub open: bool,
+    pub state: ButtonState,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TabCloseTheme {
+    pub state: ButtonState,
+}
+
+/// The clickable title of a tab.
+#[derive(Debug, Clone)]
+struct TabHeader {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    title: Contents,
+    open: bool,
+    state: ButtonState,
+    close: Option<TabClose>,
+    /// Whether the header has been dragged away from where it was pressed.
+    dragging: bool,
+}
+
+/// The button that closes a tab.
+#[derive(Debug, Clone)]
+struct TabClose {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    header_id: WidgetId,
+    state: ButtonState,
+}
+
+/// The arrow buttons that scroll through the tab strip when the tabs don't fit.
 #[derive(Debug, Clone)]
-pub struct TabList<W> {
+struct TabScroll {
     widget_tag: WidgetTag,
     rect: BoundBox<D2, i32>,
-    layout_engine: GridEngine,
+    size_bounds: SizeBounds,
 
-    tabs: Vec<TabPage<W>>
+    increase: bool,
+    state: ButtonState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TabEvent {
+    /// The tab with the given header got clicked.
+    Select(WidgetId),
+    /// The close button on the tab with the given header got clicked.
+    Close(WidgetId),
+    /// Scroll the tab strip by the given number of tabs.
+    ScrollStrip(i32),
+    /// The tab with the given header got dragged to the given position, in the header's
+    /// coordinate space. The flag is set when the tab got dropped.
+    Drag(WidgetId, Point2<i32>, bool),
+}
+
+/// Sent to every widget when the user drags a tab by its header.
+///
+/// Tab lists don't do anything with dragged tabs themselves. This lets containers, such as
+/// `DockArea`, move tabs between tab lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TabDrag {
+    pub tab_list: WidgetId,
+    /// The index of the dragged tab.
+    pub index: usize,
+    /// The position of the mouse, in the tab list's coordinate space.
+    pub pos: Point2<i32>,
+    /// Whether the tab got dropped, ending the drag.
+    pub dropped: bool,
 }
 
 impl<W> TabPage<W> {
     /// Create a new tab page, with the given title and contained widget.
-    pub fn new(title: String, page: W) -> TabPage<W> {
+    pub fn new(title: Contents, page: W) -> TabPage<W> {
         TabPage {
-            title: RenderString::new(title),
+            header: TabHeader {
+                widget_tag: WidgetTag::new(),
+                rect: BoundBox::new2(0, 0, 0, 0),
+                size_bounds: SizeBounds::default(),
+
+                title,
+                open: false,
+                state: ButtonState::Normal,
+                close: None,
+                dragging: false,
+            },
             page,
-            open: true,
-            button_state: ButtonState::Normal,
-            rect: BoundBox::new2(0, 0, 0, 0)
         }
     }
 
-
-    /// Retrieves a reference to the tab's title.
-    pub fn string(&self) -> &str {
-        self.title.string()
+    /// Retrieves the tab's title.
+    pub fn title(&self) -> &Contents {
+        &self.header.title
     }
 
     /// Retrieves the tab's title, for mutation.
-    pub fn string_mut(&mut self) -> &mut String {
-        self.title.string_mut()
+    pub fn title_mut(&mut self) -> &mut Contents {
+        self.header.widget_tag.request_redraw().request_relayout();
+        &mut self.header.title
+    }
+
+    /// Whether the tab has a button that lets the user close it.
+    pub fn closable(&self) -> bool {
+        self.header.close.is_some()
+    }
+
+    pub fn set_closable(&mut self, closable: bool) {
+        if closable == self.closable() {
+            return;
+        }
+
+        let header_id = self.header.widget_tag.widget_id();
+        self.header.close = match closable {
+            true => Some(TabClose {
+                widget_tag: WidgetTag::new(),
+                rect: BoundBox::new2(0, 0, 0, 0),
+                size_bounds: SizeBounds::default(),
+
+                header_id,
+                state: ButtonState::Normal,
+            }),
+            false => None
+        };
+        self.header.widget_tag.request_redraw().request_relayout();
     }
 }
 
-impl<W> TabList<W> {
-    /// Create a new list of tabs.
-    pub fn new(tabs: Vec<TabPage<W>>) -> TabList<W> {
-        TabList {
-            widget_tag: WidgetTag::new(),
+impl<W, H> TabList<W, H>
+    where W: Widget,
+          H: TabListHandler
+{
+    /// Create a new list of tabs, with the first tab open.
+    pub fn new(tabs: Vec<TabPage<W>>, handler: H) -> TabList<W, H> {
+        let mut widget_tag = WidgetTag::new();
+        widget_tag.set_focusable(true);
+        widget_tag.register_message(Self::on_tab_event);
+
+        let mut tab_list = TabList {
+            widget_tag,
             rect: BoundBox::new2(0, 0, 0, 0),
-            layout_engine: GridEngine::new(),
+            size_bounds: SizeBounds::default(),
 
-            tabs
-        }
+            tabs,
+            open: 0,
+            first_visible: 0,
+            scroll_to_open: true,
+            scroll_left: TabScroll::new(false),
+            scroll_right: TabScroll::new(true),
+            handler,
+        };
+        tab_list.sync_open();
+        tab_list
     }
 
     /// Retrieves a reference to the tab list.
@@ -101,10 +245,152 @@ impl<W> TabList<W> {
         self.widget_tag.request_relayout().request_redraw();
         &mut self.tabs
     }
+
+    /// Retrieves the index of the open tab.
+    pub fn open(&self) -> usize {
+        self.open
+    }
+
+    /// Open the tab at `index`, without calling the handler.
+    pub fn set_open(&mut self, index: usize) {
+        assert!(index < self.tabs.len(), "tab index out of bounds");
+        self.open = index;
+        self.scroll_to_open = true;
+        self.sync_open();
+        self.widget_tag.request_relayout().request_redraw();
+    }
+
+    /// Make sure the headers' open flags agree with `self.open`.
+    fn sync_open(&mut self) {
+        let open = self.open;
+        for (index, tab) in self.tabs.iter_mut().enumerate() {
+            if tab.header.open != (index == open) {
+                tab.header.open = index == open;
+                tab.header.widget_tag.request_redraw();
+            }
+        }
+    }
+
+    fn select(&mut self, index: usize) {
+        if index == self.open {
+            return;
+        }
+        self.set_open(index);
+        if let Some(action) = self.handler.on_select(index) {
+            self.widget_tag.broadcast_message(action);
+        }
+    }
+
+    /// Remove the tab at `index`, keeping the open tab open if it's a different tab. Doesn't call
+    /// the handler.
+    pub(crate) fn remove_tab(&mut self, index: usize) -> TabPage<W> {
+        let tab = self.tabs.remove(index);
+        if index < self.open || (index == self.open && self.open == self.tabs.len() && self.open > 0) {
+            self.open -= 1;
+        }
+        self.scroll_to_open = true;
+        self.sync_open();
+        self.widget_tag.request_relayout().request_redraw();
+        tab
+    }
+
+    fn close(&mut self, index: usize) {
+        let closed_open = index == self.open;
+        self.remove_tab(index);
+
+        if let Some(action) = self.handler.on_close(index) {
+            self.widget_tag.broadcast_message(action);
+        }
+        if closed_open && !self.tabs.is_empty() {
+            if let Some(action) = self.handler.on_select(self.open) {
+                self.widget_tag.broadcast_message(action);
+            }
+        }
+    }
+
+    fn header_index(&self, header_id: WidgetId) -> Option<usize> {
+        self.tabs.iter().position(|tab| tab.header.widget_tag.widget_id() == header_id)
+    }
+
+    fn on_tab_event(&mut self, event: &TabEvent) {
+        match *event {
+            TabEvent::Select(header_id) => if let Some(index) = self.header_index(header_id) {
+                self.select(index);
+            },
+            TabEvent::Close(header_id) => if let Some(index) = self.header_index(header_id) {
+                self.close(index);
+            },
+            TabEvent::ScrollStrip(tabs) => {
+                let max_first = self.tabs.len().saturating_sub(1) as i32;
+                self.first_visible = (self.first_visible as i32 + tabs).min(max_first).max(0) as usize;
+                self.widget_tag.request_relayout().request_redraw();
+            },
+            TabEvent::Drag(header_id, pos, dropped) => if let Some(index) = self.header_index(header_id) {
+                let drag = TabDrag {
+                    tab_list: self.widget_tag.widget_id(),
+                    index,
+                    pos: pos + self.tabs[index].header.rect.min().to_vec(),
+                    dropped,
+                };
+                self.widget_tag.broadcast_message(drag);
+            }
+        }
+    }
+}
+
+impl TabHeader {
+    fn close_width(&self) -> i32 {
+        self.close.as_ref().map(|close| close.size_bounds.min.width()).unwrap_or(0)
+    }
+}
+
+impl TabScroll {
+    fn new(increase: bool) -> TabScroll {
+        TabScroll {
+            widget_tag: WidgetTag::new(),
+            rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+
+            increase,
+            state: ButtonState::Normal,
+        }
+    }
+}
+
+fn set_visible(widget_tag: &WidgetTag, visible: bool) {
+    widget_tag.set_visibility(match visible {
+        true => Visibility::Visible,
+        false => Visibility::Hidden
+    });
+}
+
+fn set_rect(widget_tag: &mut WidgetTag, rect: &mut BoundBox<D2, i32>, new_rect: BoundBox<D2, i32>) {
+    if *rect != new_rect {
+        *rect = new_rect;
+        widget_tag.request_relayout();
+    }
+}
+
+/// Get the new button state for a mouse event. Returns whether the button got clicked alongside the
+/// state.
+fn button_state(state: ButtonState, event: &WidgetEvent) -> (ButtonState, bool) {
+    match *event {
+        WidgetEvent::MouseMove{hover_change: Some(ref change), ..} => match (state, change) {
+            (ButtonState::Pressed, _) => (ButtonState::Pressed, false),
+            (_, MouseHoverChange::Enter) => (ButtonState::Hover, false),
+            (_, MouseHoverChange::Exit) => (ButtonState::Normal, false),
+            _ => (state, false)
+        },
+        WidgetEvent::MouseDown{in_widget: true, button: MouseButton::Left, ..} => (ButtonState::Pressed, false),
+        WidgetEvent::MouseUp{in_widget: true, pressed_in_widget, button: MouseButton::Left, ..} => (ButtonState::Hover, pressed_in_widget),
+        WidgetEvent::MouseUp{in_widget: false, button: MouseButton::Left, ..} => (ButtonState::Normal, false),
+        _ => (state, false)
+    }
 }
 
-impl<W> Widget for TabList<W>
-    where W: Widget
+impl<W, H> Widget for TabList<W, H>
+    where W: Widget,
+          H: TabListHandler
 {
     #[inline]
     fn widget_tag(&self) -> &WidgetTag {
@@ -122,130 +408,87 @@ impl<W> Widget for TabList<W>
         &mut self.rect
     }
 
-    #[inline]
     fn size_bounds(&self) -> SizeBounds {
-        self.layout_engine.actual_size_bounds()
+        self.size_bounds
+    }
+
+    fn focus_rect(&self) -> BoundBox<D2, i32> {
+        match self.tabs.get(self.open) {
+            Some(tab) => tab.header.rect + self.rect.min().to_vec(),
+            None => self.rect
+        }
     }
 
-    #[inline]
     fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
-        // TODO: PASS FOCUS TO CHILD
-        if let WidgetEventSourced::This(ref event) = event {
-            match event {
-                WidgetEvent::MouseMove{new_pos, in_widget: true, ..} => {
-                    let mut state_changed = false;
-                    for tab in self.tabs.iter_mut() {
-                        let new_state = match (tab.button_state, tab.rect.contains(*new_pos)) {
-                            (ButtonState::Pressed, _) => ButtonState::Pressed,
-                            (_, true) => ButtonState::Hover,
-                            (_, false) => ButtonState::Normal,
-                        };
-                        state_changed |= new_state != tab.button_state;
-                        tab.button_state = new_state;
-                    }
-                    if state_changed {
-                        self.widget_tag.request_redraw();
-                    }
-                },
-                // WidgetEvent::MouseEnterChild{..} => {
-                //     let mut state_changed = false;
-                //     for tab in self.tabs.iter_mut() {
-                //         let new_state = match tab.button_state {
-                //             ButtonState::Pressed => ButtonState::Pressed,
-                //             _ => ButtonState::Normal,
-                //         };
-                //         state_changed |= new_state != tab.button_state;
-                //         tab.button_state = new_state;
-                //     }
-                //     if state_changed {
-                //         self.widget_tag.request_redraw();
-                //     }
-                // }
-                WidgetEvent::MouseDown{pos, in_widget: true, ..} => {
-                    let mut state_changed = false;
-                    for tab in self.tabs.iter_mut() {
-                        let new_state = match tab.rect.contains(*pos) {
-                            true => ButtonState::Pressed,
-                            false => tab.button_state
-                        };
-                        state_changed |= new_state != tab.button_state;
-                        tab.button_state = new_state;
-                    }
-                    if state_changed {
-                        self.widget_tag.request_redraw();
-                    }
-                },
-                WidgetEvent::MouseUp{in_widget: true, pressed_in_widget: true, pos, down_pos, ..} => {
-                    // Change tab selection.
-                    let mut state_changed = false;
-                    let (mut old_open, mut new_open) = (None, None);
-                    for (index, tab) in self.tabs.iter_mut().enumerate() {
-                        let tab_contains = tab.rect.contains(*pos);
-                        let is_open = tab_contains && tab.rect.contains(*down_pos);
-                        let new_state = match tab_contains {
-                            true => ButtonState::Hover,
-                            false => ButtonState::Normal
-                        };
-                        state_changed |= is_open != tab.open || new_state != tab.button_state;
-
-                        if tab.open && old_open == None {
-                            old_open = Some(index);
-                        }
-                        if is_open && new_open == None {
-                            new_open = Some(index);
-                        }
+        let mut focus = None;
+        let mut bubble = event.default_bubble();
+        let from_child = match event {
+            WidgetEventSourced::This(_) => false,
+            WidgetEventSourced::Bubble(..) => true,
+        };
 
-                        tab.open = is_open;
-                        tab.button_state = new_state;
-                    }
-                    if state_changed {
-                        self.widget_tag.request_redraw();
-                    }
-                    if !(old_open == new_open || new_open == None) {
-                        self.widget_tag.request_relayout();
-                    }
-                },
-                WidgetEvent::MouseUp{in_widget: false, pressed_in_widget: true, ..} => {
-                    let mut state_changed = false;
-                    for tab in self.tabs.iter_mut() {
-                        let new_state = ButtonState::Normal;
-                        state_changed |= new_state != tab.button_state;
-                        tab.button_state = new_state;
-                    }
-                    if state_changed {
-                        self.widget_tag.request_redraw();
-                    }
-                },
-                _ => ()
+        if let WidgetEvent::KeyDown(key, modifiers) = event.unwrap() {
+            let num_tabs = self.tabs.len();
+            let ctrl = modifiers.contains(ModifierKeys::CTRL);
+            let shift = modifiers.contains(ModifierKeys::SHIFT);
+            let next = (self.open + 1) % num_tabs.max(1);
+            let prev = (self.open + num_tabs.max(1) - 1) % num_tabs.max(1);
+
+            let new_open = match (key, ctrl) {
+                (Key::Tab, true) if shift => Some(prev),
+                (Key::Tab, true) |
+                (Key::PageDown, true) => Some(next),
+                (Key::PageUp, true) => Some(prev),
+                // Arrow keys only switch tabs when the tab strip itself is focused, so they still
+                // reach the page's widgets.
+                (Key::LArrow, false) if !from_child => Some(prev),
+                (Key::RArrow, false) if !from_child => Some(next),
+                (Key::Home, false) if !from_child => Some(0),
+                (Key::End, false) if !from_child => Some(num_tabs.saturating_sub(1)),
+                _ => None
+            };
+
+            if let (Some(index), true) = (new_open, num_tabs > 0) {
+                self.select(index);
+                bubble = false;
+                // The focused widget might be on the page that just got hidden.
+                if from_child {
+                    focus = Some(FocusChange::Take);
+                }
             }
         }
 
         EventOps {
-            focus: None,
-            bubble: event.default_bubble() || event.is_bubble(),
+            focus,
+            bubble,
         }
     }
 }
 
-impl<W> Parent for TabList<W>
-    where W: Widget
+impl<W, H> Parent for TabList<W, H>
+    where W: Widget,
+          H: TabListHandler
 {
     fn num_children(&self) -> usize {
-        self.tabs.len()
+        self.tabs.len() * 2 + 2
     }
 
     fn framed_child<R: Renderer>(&self, widget_ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
-        if let WidgetIdent::Num(index) = widget_ident {
-            self.tabs.get(index as usize).map(|t| WidgetInfo::new(widget_ident, index as usize, &t.page))
-        } else {
-            None
+        let num_tabs = self.tabs.len();
+        match widget_ident {
+            WidgetIdent::NumCollection(tab, part) if part < 2 && (tab as usize) < num_tabs =>
+                self.framed_child_by_index(tab as usize * 2 + part as usize),
+            WidgetIdent::Num(arrow) => self.framed_child_by_index(num_tabs * 2 + arrow as usize),
+            _ => None
         }
     }
     fn framed_child_mut<R: Renderer>(&mut self, widget_ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
-        if let WidgetIdent::Num(index) = widget_ident {
-            self.tabs.get_mut(index as usize).map(|t| WidgetInfoMut::new(widget_ident, index as usize, &mut t.page))
-        } else {
-            None
+        let num_tabs = self.tabs.len();
+        match widget_ident {
+            WidgetIdent::NumCollection(tab, part) if part < 2 && (tab as usize) < num_tabs =>
+                self.framed_child_by_index_mut(tab as usize * 2 + part as usize),
+            WidgetIdent::Num(arrow) => self.framed_child_by_index_mut(num_tabs * 2 + arrow as usize),
+            _ => None
         }
     }
 
@@ -254,177 +497,557 @@ impl<W> Parent for TabList<W>
               G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
     {
         for (index, tab) in self.tabs.iter().enumerate() {
-            match for_each(WidgetInfo::new(WidgetIdent::Num(index as u32), index, &tab.page)) {
-                LoopFlow::Continue => (),
-                LoopFlow::Break => return
+            if let LoopFlow::Break = for_each(WidgetInfo::new(WidgetIdent::NumCollection(index as u32, 0), index * 2, &tab.header)) {
+                return;
+            }
+            if let LoopFlow::Break = for_each(WidgetInfo::new(WidgetIdent::NumCollection(index as u32, 1), index * 2 + 1, &tab.page)) {
+                return;
             }
         }
+        let index = self.tabs.len() * 2;
+        if let LoopFlow::Break = for_each(WidgetInfo::new(WidgetIdent::Num(0), index, &self.scroll_left)) {
+            return;
+        }
+        let _ = for_each(WidgetInfo::new(WidgetIdent::Num(1), index + 1, &self.scroll_right));
     }
 
     fn framed_children_mut<'a, R, G>(&'a mut self, mut for_each: G)
         where R: Renderer,
               G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
     {
+        let arrow_index = self.tabs.len() * 2;
         for (index, tab) in self.tabs.iter_mut().enumerate() {
-            match for_each(WidgetInfoMut::new(WidgetIdent::Num(index as u32), index, &mut tab.page)) {
-                LoopFlow::Continue => (),
-                LoopFlow::Break => return
+            if let LoopFlow::Break = for_each(WidgetInfoMut::new(WidgetIdent::NumCollection(index as u32, 0), index * 2, &mut tab.header)) {
+                return;
             }
+            if let LoopFlow::Break = for_each(WidgetInfoMut::new(WidgetIdent::NumCollection(index as u32, 1), index * 2 + 1, &mut tab.page)) {
+                return;
+            }
+        }
+        if let LoopFlow::Break = for_each(WidgetInfoMut::new(WidgetIdent::Num(0), arrow_index, &mut self.scroll_left)) {
+            return;
         }
+        let _ = for_each(WidgetInfoMut::new(WidgetIdent::Num(1), arrow_index + 1, &mut self.scroll_right));
     }
 
     fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
-        self.tabs.get(index).map(|t| WidgetInfo::new(WidgetIdent::Num(index as u32), index, &t.page))
+        let num_tabs = self.tabs.len();
+        let ident = match index < num_tabs * 2 {
+            true => WidgetIdent::NumCollection((index / 2) as u32, (index % 2) as u32),
+            false => WidgetIdent::Num((index - num_tabs * 2) as u32)
+        };
+        match (self.tabs.get(index / 2), index % 2) {
+            (Some(tab), 0) => Some(WidgetInfo::new(ident, index, &tab.header)),
+            (Some(tab), _) => Some(WidgetInfo::new(ident, index, &tab.page)),
+            (None, _) if index == num_tabs * 2 => Some(WidgetInfo::new(ident, index, &self.scroll_left)),
+            (None, _) if index == num_tabs * 2 + 1 => Some(WidgetInfo::new(ident, index, &self.scroll_right)),
+            _ => None
+        }
     }
     fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
-        self.tabs.get_mut(index).map(|t| WidgetInfoMut::new(WidgetIdent::Num(index as u32), index, &mut t.page))
+        let num_tabs = self.tabs.len();
+        let ident = match index < num_tabs * 2 {
+            true => WidgetIdent::NumCollection((index / 2) as u32, (index % 2) as u32),
+            false => WidgetIdent::Num((index - num_tabs * 2) as u32)
+        };
+        match (self.tabs.get_mut(index / 2), index % 2) {
+            (Some(tab), 0) => Some(WidgetInfoMut::new(ident, index, &mut tab.header)),
+            (Some(tab), _) => Some(WidgetInfoMut::new(ident, index, &mut tab.page)),
+            (None, _) if index == num_tabs * 2 => Some(WidgetInfoMut::new(ident, index, &mut self.scroll_left)),
+            (None, _) if index == num_tabs * 2 + 1 => Some(WidgetInfoMut::new(ident, index, &mut self.scroll_right)),
+            _ => None
+        }
     }
 }
 
-impl<R, W> WidgetRenderable<R> for TabList<W>
-    where R: Renderer,
-          W: Widget
-{
-    fn render(&mut self, frame: &mut R::SubFrame) {
-        for tab in &mut self.tabs {
-            let theme_path = match tab.button_state {
-                ButtonState::Normal => "Tab::Normal",
-                ButtonState::Hover => "Tab::Hover",
-                ButtonState::Pressed => "Tab::Pressed"
-            };
-            frame.upload_primitives(ArrayVec::from([
-                ThemedPrim {
-                    theme_path,
-                    min: Point2::new(
-                        RelPoint::new(-1.0, tab.rect.min.x),
-                        RelPoint::new(-1.0, tab.rect.min.y),
-                    ),
-                    max: Point2::new(
-                        RelPoint::new(-1.0, tab.rect.max.x),
-                        RelPoint::new(-1.0, tab.rect.max.y)
-                    ),
-                    prim: Prim::Image,
-                    rect_px_out: None
+impl Widget for TabHeader {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, input_state: InputState) -> EventOps {
+        let mut focus = None;
+        if let WidgetEventSourced::This(ref event) = event {
+            let (new_state, _) = button_state(self.state, event);
+            let header_id = self.widget_tag.widget_id();
+            match *event {
+                WidgetEvent::MouseDown{in_widget: true, button: MouseButton::Left, ..} => {
+                    // Tabs get selected on press rather than on release, like most platforms do.
+                    self.widget_tag.send_message_to(TabEvent::Select(header_id), MessageTarget::ParentOf(header_id));
+                    focus = Some(FocusChange::Parent);
+                },
+                WidgetEvent::MouseMove{new_pos, ..} if self.state == ButtonState::Pressed => {
+                    let left_down = input_state.mouse_buttons_down.iter().find(|down| down.button == MouseButton::Left);
+                    if let Some(down) = left_down {
+                        let moved = new_pos - down.down_pos;
+                        if self.dragging || moved.x.abs().max(moved.y.abs()) > DRAG_THRESHOLD {
+                            self.dragging = true;
+                            self.widget_tag.send_message_to(TabEvent::Drag(header_id, new_pos, false), MessageTarget::ParentOf(header_id));
+                        }
+                    }
                 },
-                ThemedPrim {
-                    theme_path,
-                    min: Point2::new(
-                        RelPoint::new(-1.0, tab.rect.min.x),
-                        RelPoint::new(-1.0, tab.rect.min.y),
-                    ),
-                    max: Point2::new(
-                        RelPoint::new(-1.0, tab.rect.max.x),
-                        RelPoint::new(-1.0, tab.rect.max.y)
-                    ),
-                    prim: Prim::String(&mut tab.title),
-                    rect_px_out: None
+                WidgetEvent::MouseUp{pos, button: MouseButton::Left, ..} if self.dragging => {
+                    self.dragging = false;
+                    self.widget_tag.send_message_to(TabEvent::Drag(header_id, pos, true), MessageTarget::ParentOf(header_id));
                 },
-            ]));
+                _ => ()
+            }
+            if new_state != self.state {
+                self.state = new_state;
+                self.widget_tag.request_redraw();
+            }
+        }
+
+        EventOps {
+            focus,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
+impl Parent for TabHeader {
+    fn num_children(&self) -> usize {
+        self.close.is_some() as usize
+    }
+
+    fn framed_child<R: Renderer>(&self, widget_ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(0) => self.framed_child_by_index(0),
+            _ => None
+        }
+    }
+    fn framed_child_mut<R: Renderer>(&mut self, widget_ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(0) => self.framed_child_by_index_mut(0),
+            _ => None
         }
     }
 
-    fn update_layout(&mut self, _: &R::Theme) {
-        #[derive(Default)]
-        struct HeapCache {
-            update_heap_cache: UpdateHeapCache,
-            hints_vec: Vec<WidgetPos>,
-            rects_vec: Vec<Result<BoundBox<D2, i32>, SolveError>>
+    fn framed_children<'a, R, G>(&'a self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
+    {
+        if let Some(ref close) = self.close {
+            let _ = for_each(WidgetInfo::new(WidgetIdent::Num(0), 0, close));
         }
-        thread_local! {
-            static HEAP_CACHE: RefCell<HeapCache> = RefCell::new(HeapCache::default());
+    }
+
+    fn framed_children_mut<'a, R, G>(&'a mut self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
+    {
+        if let Some(ref mut close) = self.close {
+            let _ = for_each(WidgetInfoMut::new(WidgetIdent::Num(0), 0, close));
         }
+    }
 
-        HEAP_CACHE.with(|hc| {
-            let mut hc = hc.borrow_mut();
+    fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
+        match (index, &self.close) {
+            (0, Some(close)) => Some(WidgetInfo::new(WidgetIdent::Num(0), 0, close)),
+            _ => None
+        }
+    }
+    fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
+        match (index, &mut self.close) {
+            (0, Some(close)) => Some(WidgetInfoMut::new(WidgetIdent::Num(0), 0, close)),
+            _ => None
+        }
+    }
+}
 
-            let HeapCache {
-                ref mut update_heap_cache,
-                ref mut hints_vec,
-                ref mut rects_vec
-            } = *hc;
+impl Widget for TabClose {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
 
-            let grid_dims = GridSize::new(self.tabs.len() as u32 + 1, 2);
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
 
-            self.layout_engine.desired_size = self.rect.dims();
-            self.layout_engine.set_grid_size(grid_dims);
-            self.layout_engine.set_row_hints(
-                0,
-                TrackHints {
-                    fr_size: 0.0,
-                    ..TrackHints::default()
-                }
-            );
-            self.layout_engine.set_col_hints(
-                grid_dims.x - 1,
-                TrackHints {
-                    fr_size: 1.0,
-                    ..TrackHints::default()
-                }
-            );
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
 
-            let mut active_tab_index_opt = None;
-            for (index, tab) in self.tabs.iter_mut().enumerate() {
-                match (active_tab_index_opt, tab.open) {
-                    (None, true) => active_tab_index_opt = Some(index),
-                    (Some(_), _) => tab.open = false,
-                    _ => ()
-                }
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
 
-                hints_vec.push(WidgetPos {
-                    size_bounds: SizeBounds::new_min(tab.title.min_size()),
-                    widget_span: WidgetSpan::new(index as u32, 0),
-                    ..WidgetPos::default()
-                });
-                rects_vec.push(Ok(BoundBox::new2(0, 0, 0, 0)));
-                self.layout_engine.set_col_hints(
-                    index as u32,
-                    TrackHints {
-                        fr_size: 0.0,
-                        ..TrackHints::default()
-                    }
-                );
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        if let WidgetEventSourced::This(ref event) = event {
+            let (new_state, clicked) = button_state(self.state, event);
+            if clicked {
+                self.widget_tag.send_message_to(TabEvent::Close(self.header_id), MessageTarget::ParentOf(self.header_id));
+            }
+            if new_state != self.state {
+                self.state = new_state;
+                self.widget_tag.request_redraw();
+            }
+        }
+
+        EventOps {
+            focus: None,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
+impl Widget for TabScroll {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        if let WidgetEventSourced::This(ref event) = event {
+            let (new_state, clicked) = button_state(self.state, event);
+            if clicked {
+                let tabs = match self.increase {
+                    true => 1,
+                    false => -1
+                };
+                let scroll_id = self.widget_tag.widget_id();
+                self.widget_tag.send_message_to(TabEvent::ScrollStrip(tabs), MessageTarget::ParentOf(scroll_id));
+            }
+            if new_state != self.state {
+                self.state = new_state;
+                self.widget_tag.request_redraw();
             }
+        }
+
+        EventOps {
+            focus: None,
+            bubble: event.default_bubble(),
+        }
+    }
+}
 
-            let (active_tab, active_tab_index): (&TabPage<W>, usize);
+impl<W, H, R> WidgetRenderable<R> for TabList<W, H>
+    where W: Widget,
+          H: TabListHandler,
+          R: Renderer
+{
+    type Theme = TabListTheme;
 
-            match (active_tab_index_opt, self.tabs.len()) {
-                (Some(i), _) => {
-                    active_tab = &self.tabs[i];
-                    active_tab_index = i;
-                },
-                (None, 0) => {
-                    hints_vec.clear();
-                    rects_vec.clear();
-                    return;
-                },
-                (None, _) => {
-                    let tab = &mut self.tabs[0];
-                    tab.open = true;
-                    active_tab = tab;
-                    active_tab_index = 0;
-                }
+    fn theme(&self) -> TabListTheme {
+        TabListTheme(())
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        let result = layout.finish();
+        let content_rect = result.content_rect;
+        let margin_width = content_rect.min.x + (self.rect.width() - content_rect.max.x);
+        let margin_height = content_rect.min.y + (self.rect.height() - content_rect.max.y);
+
+        // Tabs may have been added or removed through `tabs_mut`.
+        self.open = self.open.min(self.tabs.len().saturating_sub(1));
+        self.sync_open();
+
+        let strip_height = self.tabs.iter()
+            .map(|tab| tab.header.size_bounds.min.height())
+            .max().unwrap_or(0)
+            .max(SCROLL_BAR_SIZE);
+        let header_widths = self.tabs.iter().map(|tab| tab.header.size_bounds.min.width()).collect::<Vec<_>>();
+        let overflow = header_widths.iter().sum::<i32>() > content_rect.width();
+        let strip_width = content_rect.width() - SCROLL_BAR_SIZE * 2 * overflow as i32;
+
+        if !overflow {
+            self.first_visible = 0;
+        } else if self.scroll_to_open {
+            if self.open < self.first_visible {
+                self.first_visible = self.open;
             }
+            while self.first_visible < self.open && header_widths[self.first_visible..=self.open].iter().sum::<i32>() > strip_width {
+                self.first_visible += 1;
+            }
+        }
+        self.first_visible = self.first_visible.min(self.tabs.len().saturating_sub(1));
+        self.scroll_to_open = false;
 
-            hints_vec.push(WidgetPos {
-                widget_span: WidgetSpan::new(.., 1),
-                size_bounds: active_tab.page.size_bounds(),
-                ..WidgetPos::default()
-            });
-            rects_vec.push(Ok(BoundBox::new2(0, 0, 0, 0)));
-            self.layout_engine.update_engine(hints_vec, rects_vec, update_heap_cache);
-
-            let mut rects_iter = rects_vec.drain(..);
-            for (tab, rect) in self.tabs.iter_mut().zip(&mut rects_iter) {
-                tab.rect = rect.unwrap_or(BoundBox::new2(-1, -1, -1, -1));
+        let mut x = content_rect.min.x;
+        for (index, tab) in self.tabs.iter_mut().enumerate() {
+            let width = header_widths[index];
+            // Always show the first visible tab, even if it gets cut off.
+            let visible = index >= self.first_visible &&
+                (index == self.first_visible || x + width <= content_rect.min.x + strip_width);
+            set_visible(&tab.header.widget_tag, visible);
+            if visible {
+                let width = width.min(strip_width);
+                set_rect(
+                    &mut tab.header.widget_tag,
+                    &mut tab.header.rect,
+                    BoundBox::new2(x, content_rect.min.y, x + width, content_rect.min.y + strip_height)
+                );
+                x += width;
             }
-            let active_tab_mut = &mut self.tabs[active_tab_index];
-            *active_tab_mut.page.rect_mut() = rects_iter.next().unwrap().unwrap_or(BoundBox::new2(-1, -1, -1, -1));
+        }
+
+        set_visible(&self.scroll_left.widget_tag, overflow);
+        set_visible(&self.scroll_right.widget_tag, overflow);
+        if overflow {
+            let arrows_start = content_rect.min.x + strip_width;
+            set_rect(
+                &mut self.scroll_left.widget_tag,
+                &mut self.scroll_left.rect,
+                BoundBox::new2(arrows_start, content_rect.min.y, arrows_start + SCROLL_BAR_SIZE, content_rect.min.y + strip_height)
+            );
+            set_rect(
+                &mut self.scroll_right.widget_tag,
+                &mut self.scroll_right.rect,
+                BoundBox::new2(arrows_start + SCROLL_BAR_SIZE, content_rect.min.y, content_rect.max.x, content_rect.min.y + strip_height)
+            );
+        }
 
-            for (_, tab) in self.tabs.iter_mut().enumerate().filter(|&(i, _)| i != active_tab_index) {
-                *tab.page.rect_mut() = BoundBox::new2(-1, -1, -1, -1);
+        let page_rect = BoundBox::new2(content_rect.min.x, content_rect.min.y + strip_height, content_rect.max.x, content_rect.max.y);
+        let mut page_min = DimsBox::new2(0, 0);
+        for (index, tab) in self.tabs.iter_mut().enumerate() {
+            set_visible(tab.page.widget_tag(), index == self.open);
+            if index == self.open {
+                if tab.page.rect() != page_rect {
+                    *tab.page.rect_mut() = page_rect;
+                }
+                page_min = tab.page.size_bounds().min;
             }
+        }
+
+        // The tab strip can scroll, so only make room for the widest tab and the scroll arrows.
+        let strip_min_width = header_widths.iter().cloned().max().unwrap_or(0) + SCROLL_BAR_SIZE * 2;
+        let min = DimsBox::new2(
+            (strip_min_width.max(page_min.width()) + margin_width).max(result.size_bounds.min.width()),
+            (strip_height + page_min.height() + margin_height).max(result.size_bounds.min.height()),
+        );
+        self.size_bounds = SizeBounds {
+            min,
+            ..result.size_bounds
+        };
+    }
+}
+
+impl<R> WidgetRenderable<R> for TabHeader
+    where R: Renderer
+{
+    type Theme = TabTheme;
+
+    fn theme(&self) -> TabTheme {
+        TabTheme {
+            open: self.open,
+            state: self.state,
+        }
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        match self.title {
+            Contents::Text(ref s) => layout.prepare_string(s),
+            Contents::Icon(ref i) => layout.prepare_icon(i),
+        }
+
+        let result = layout.finish();
+        let content_rect = result.content_rect;
+        let close_width = self.close_width();
+        self.size_bounds = SizeBounds {
+            min: DimsBox::new2(result.size_bounds.min.width() + close_width, result.size_bounds.min.height()),
+            ..result.size_bounds
+        };
+
+        if let Some(ref mut close) = self.close {
+            let close_rect = BoundBox::new2(
+                content_rect.max.x - close_width,
+                content_rect.min.y,
+                content_rect.max.x,
+                content_rect.max.y
+            );
+            set_rect(&mut close.widget_tag, &mut close.rect, close_rect);
+        }
+    }
+}
+
+impl<R> WidgetRenderable<R> for TabClose
+    where R: Renderer
+{
+    type Theme = TabCloseTheme;
+
+    fn theme(&self) -> TabCloseTheme {
+        TabCloseTheme {
+            state: self.state,
+        }
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        layout.prepare_string("\u{00D7}");
+        let result = layout.finish();
+        self.size_bounds = result.size_bounds;
+    }
+}
+
+impl<R> WidgetRenderable<R> for TabScroll
+    where R: Renderer
+{
+    type Theme = ScrollArrowTheme;
+
+    fn theme(&self) -> ScrollArrowTheme {
+        ScrollArrowTheme {
+            horizontal: true,
+            increase: self.increase,
+            state: self.state,
+        }
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        let result = layout.finish();
+        self.size_bounds = result.size_bounds;
+    }
+}
+
+impl WidgetTheme for TabListTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for TabListTheme {
+    fn theme_path(&self) -> &'static str {
+        "TabList"
+    }
+}
+
+impl WidgetTheme for TabTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for TabTheme {
+    fn theme_path(&self) -> &'static str {
+        match (self.open, self.state) {
+            (true, _) => "Tab::Open",
+            (false, ButtonState::Normal) => "Tab::Normal",
+            (false, ButtonState::Hover) => "Tab::Hover",
+            (false, ButtonState::Pressed) => "Tab::Pressed",
+        }
+    }
+}
+
+impl WidgetTheme for TabCloseTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for TabCloseTheme {
+    fn theme_path(&self) -> &'static str {
+        match self.state {
+            ButtonState::Normal => "Tab::Close",
+            ButtonState::Hover => "Tab::Close::Hover",
+            ButtonState::Pressed => "Tab::Close::Pressed",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::WindowEvent;
+    use crate::soft_render::snapshot::Snapshot;
+    use crate::theme::Theme;
+    use crate::widgets::Label;
+
+    #[derive(Default)]
+    struct SelectLog(Vec<usize>);
+    impl TabListHandler for SelectLog {
+        type Action = ();
+        fn on_select(&mut self, index: usize) -> Option<()> {
+            self.0.push(index);
+            None
+        }
+    }
+
+    fn page(title: &str) -> TabPage<Label> {
+        TabPage::new(Contents::Text(title.to_string()), Label::new(Contents::Text(title.to_string())))
+    }
+
+    fn press_tab(snapshot: &mut Snapshot<TabList<Label, SelectLog>>) {
+        snapshot.process_events(vec![WindowEvent::KeyDown(Key::Tab), WindowEvent::KeyUp(Key::Tab)]);
+        snapshot.render();
+    }
+
+    #[test]
+    fn ctrl_tab() {
+        let tab_list = TabList::new(vec![page("One"), page("Two"), page("Three")], SelectLog::default());
+        let mut snapshot = Snapshot::new(tab_list, Theme::default(), DimsBox::new2(300, 100));
+        snapshot.render();
+
+        // Clicking the open tab's header focuses the tab list without switching tabs.
+        let header_rect = snapshot.root().tabs[0].header.rect;
+        snapshot.process_events(vec![
+            WindowEvent::MouseEnter,
+            WindowEvent::MouseMove(header_rect.center()),
+            WindowEvent::MouseDown(MouseButton::Left),
+            WindowEvent::MouseUp(MouseButton::Left),
+        ]);
+        assert_eq!(0, snapshot.root().open());
+
+        // Ctrl+Tab cycles forwards through the tabs, wrapping around at the end.
+        snapshot.set_modifiers(ModifierKeys::CTRL);
+        press_tab(&mut snapshot);
+        assert_eq!(1, snapshot.root().open());
+        press_tab(&mut snapshot);
+        press_tab(&mut snapshot);
+        assert_eq!(0, snapshot.root().open());
 
-            hints_vec.clear();
-        })
+        // Adding Shift cycles backwards.
+        snapshot.set_modifiers(ModifierKeys::CTRL | ModifierKeys::SHIFT);
+        press_tab(&mut snapshot);
+        assert_eq!(2, snapshot.root().open());
+        assert_eq!(vec![1, 2, 0, 2], snapshot.root().handler.0);
     }
 }
@@ -0,0 +1,402 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use derin_core::{
+    LoopFlow,
+    event::{EventOps, WidgetEvent, WidgetEventSourced, InputState, MouseHoverChange},
+    overlay::{Overlay, OverlayDismissed},
+    timer::{Timer, TimerId},
+    widget::{WidgetIdent, WidgetRenderable, WidgetTag, WidgetInfo, WidgetInfoMut, Widget, Parent},
+    render::{Renderer, RendererLayout, SubFrame, WidgetTheme},
+};
+use crate::theme::ThemePath;
+use crate::widgets::Contents;
+
+use crate::cgmath::{EuclideanSpace, Vector2};
+use cgmath_geometry::{D2, rect::{BoundBox, GeoBox}};
+use derin_common_types::layout::SizeBounds;
+
+use std::time::{Duration, Instant};
+
+/// The default amount of time the cursor has to rest over a widget before its tooltip appears.
+pub const TOOLTIP_DELAY: Duration = Duration::from_millis(500);
+
+/// Offset of the tooltip's top-left corner from the cursor.
+const CURSOR_OFFSET: Vector2<i32> = Vector2 { x: 0, y: 20 };
+
+/// A wrapper widget that shows help text when the mouse cursor rests over another widget.
+///
+/// The tooltip appears near the cursor once it has hovered over the widget for `delay`, and is
+/// hidden when the cursor leaves the widget or when any mouse button or key gets pressed.
+#[derive(Debug, Clone)]
+pub struct Tooltip<W> {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    widget: W,
+
+    delay: Duration,
+    popup: TooltipPopup,
+    hover_timer: Option<TimerId>,
+    shown: bool,
+}
+
+/// The popup that displays a `Tooltip`'s contents.
+#[derive(Debug, Clone)]
+struct TooltipPopup {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+    contents: Contents,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TooltipTheme(());
+
+#[derive(Debug, Clone, Default)]
+pub struct TooltipPopupTheme(());
+
+impl<W> Tooltip<W> {
+    /// Wraps `widget`, showing `tooltip` when the cursor hovers over it.
+    pub fn new(widget: W, tooltip: Contents) -> Tooltip<W> {
+        let mut widget_tag = WidgetTag::new();
+        widget_tag.register_message(Self::on_overlay_dismissed);
+
+        Tooltip {
+            widget_tag,
+            rect: BoundBox::new2(0, 0, 0, 0),
+            widget,
+
+            delay: TOOLTIP_DELAY,
+            popup: TooltipPopup {
+                widget_tag: WidgetTag::new(),
+                rect: BoundBox::new2(0, 0, 0, 0),
+                size_bounds: SizeBounds::default(),
+                contents: tooltip,
+            },
+            hover_timer: None,
+            shown: false,
+        }
+    }
+
+    /// Retrieves the wrapped widget.
+    pub fn widget(&self) -> &W {
+        &self.widget
+    }
+
+    /// Retrieves the wrapped widget for mutation.
+    pub fn widget_mut(&mut self) -> &mut W {
+        &mut self.widget
+    }
+
+    /// Retrieves the contents of the tooltip.
+    pub fn tooltip(&self) -> &Contents {
+        &self.popup.contents
+    }
+
+    /// Retrieves the contents of the tooltip, for mutation.
+    pub fn tooltip_mut(&mut self) -> &mut Contents {
+        self.popup.widget_tag
+            .request_redraw()
+            .request_relayout();
+
+        &mut self.popup.contents
+    }
+
+    /// Retrieves how long the cursor has to rest over the widget before the tooltip appears.
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Retrieves how long the cursor has to rest over the widget before the tooltip appears,
+    /// for mutation.
+    pub fn delay_mut(&mut self) -> &mut Duration {
+        &mut self.delay
+    }
+
+    fn start_timer(&mut self) {
+        self.stop_timer();
+        let timer_id = TimerId::new();
+        // Timers first trigger at their start time, so start this one once the delay has passed.
+        let timer = Timer::new_delayed(self.delay, Instant::now() + self.delay);
+        self.widget_tag.timers_mut().insert(timer_id, timer);
+        self.hover_timer = Some(timer_id);
+    }
+
+    fn stop_timer(&mut self) {
+        if let Some(timer_id) = self.hover_timer.take() {
+            self.widget_tag.timers_mut().remove(&timer_id);
+        }
+    }
+
+    fn show(&mut self, offset: Vector2<i32>) {
+        let overlay = Overlay::tooltip(self.widget_tag.widget_id(), offset + CURSOR_OFFSET);
+        if self.widget_tag.show_overlay(self.popup.widget_tag.widget_id(), overlay).is_ok() {
+            self.shown = true;
+            self.widget_tag.request_relayout();
+        }
+    }
+
+    fn hide(&mut self) {
+        if self.shown {
+            self.shown = false;
+            self.widget_tag.hide_overlay(self.popup.widget_tag.widget_id());
+            self.widget_tag.request_relayout();
+        }
+    }
+
+    fn on_overlay_dismissed(&mut self, dismissed: &OverlayDismissed) {
+        if self.shown && dismissed.overlay == self.popup.widget_tag.widget_id() {
+            self.shown = false;
+            self.widget_tag.request_relayout();
+        }
+    }
+}
+
+impl<W> Widget for Tooltip<W>
+    where W: Widget
+{
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        self.widget_tag.request_relayout();
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.widget.size_bounds()
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, input_state: InputState) -> EventOps {
+        let event = event.unwrap();
+
+        match event {
+            WidgetEvent::MouseMove{hover_change: Some(MouseHoverChange::Enter), ..} => self.start_timer(),
+            WidgetEvent::MouseMove{hover_change: Some(MouseHoverChange::Exit), ..} => {
+                self.stop_timer();
+                self.hide();
+            },
+            // Pressing a button or key hides the tooltip, so don't bring it back until the cursor
+            // re-enters the widget.
+            WidgetEvent::MouseDown{..} |
+            WidgetEvent::KeyDown(..) => self.stop_timer(),
+            WidgetEvent::Timer{timer_id, ..} if Some(timer_id) == self.hover_timer => {
+                self.stop_timer();
+                if let Some(mouse_pos) = input_state.mouse_pos {
+                    self.show(mouse_pos.to_vec());
+                }
+            },
+            _ => ()
+        }
+
+        EventOps {
+            focus: None,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
+impl Widget for TooltipPopup {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    #[inline]
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        EventOps {
+            focus: None,
+            bubble: event.unwrap().default_bubble(),
+        }
+    }
+}
+
+impl<W> Parent for Tooltip<W>
+    where W: Widget
+{
+    fn num_children(&self) -> usize {
+        1 + self.shown as usize
+    }
+
+    fn framed_child<R: Renderer>(&self, widget_ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(index) => self.framed_child_by_index(index as usize),
+            _ => None
+        }
+    }
+    fn framed_child_mut<R: Renderer>(&mut self, widget_ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(index) => self.framed_child_by_index_mut(index as usize),
+            _ => None
+        }
+    }
+
+    fn framed_children<'a, R, G>(&'a self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
+    {
+        if let LoopFlow::Break = for_each(WidgetInfo::new(WidgetIdent::Num(0), 0, &self.widget)) {
+            return;
+        }
+        if self.shown {
+            let _ = for_each(WidgetInfo::new(WidgetIdent::Num(1), 1, &self.popup));
+        }
+    }
+
+    fn framed_children_mut<'a, R, G>(&'a mut self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
+    {
+        if let LoopFlow::Break = for_each(WidgetInfoMut::new(WidgetIdent::Num(0), 0, &mut self.widget)) {
+            return;
+        }
+        if self.shown {
+            let _ = for_each(WidgetInfoMut::new(WidgetIdent::Num(1), 1, &mut self.popup));
+        }
+    }
+
+    fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
+        match index {
+            0 => Some(WidgetInfo::new(WidgetIdent::Num(0), 0, &self.widget)),
+            1 if self.shown => Some(WidgetInfo::new(WidgetIdent::Num(1), 1, &self.popup)),
+            _ => None
+        }
+    }
+    fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
+        match index {
+            0 => Some(WidgetInfoMut::new(WidgetIdent::Num(0), 0, &mut self.widget)),
+            1 if self.shown => Some(WidgetInfoMut::new(WidgetIdent::Num(1), 1, &mut self.popup)),
+            _ => None
+        }
+    }
+}
+
+impl<W, R> WidgetRenderable<R> for Tooltip<W>
+    where W: Widget,
+          R: Renderer
+{
+    type Theme = TooltipTheme;
+
+    fn theme(&self) -> TooltipTheme {
+        TooltipTheme(())
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, _: &mut R::Layout) {
+        let widget_rect = BoundBox::new2(0, 0, self.rect.width(), self.rect.height());
+        if self.widget.rect() != widget_rect {
+            *self.widget.rect_mut() = widget_rect;
+        }
+    }
+}
+
+impl<R> WidgetRenderable<R> for TooltipPopup
+    where R: Renderer
+{
+    type Theme = TooltipPopupTheme;
+
+    fn theme(&self) -> TooltipPopupTheme {
+        TooltipPopupTheme(())
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        match self.contents {
+            Contents::Text(ref s) => layout.prepare_string(s),
+            Contents::Icon(ref i) => layout.prepare_icon(i),
+        }
+
+        let result = layout.finish();
+        self.size_bounds = result.size_bounds;
+    }
+}
+
+impl WidgetTheme for TooltipTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for TooltipTheme {
+    fn theme_path(&self) -> &'static str {
+        "Tooltip"
+    }
+}
+
+impl WidgetTheme for TooltipPopupTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for TooltipPopupTheme {
+    fn theme_path(&self) -> &'static str {
+        "Tooltip::Popup"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::WindowEvent;
+    use crate::cgmath::Point2;
+    use crate::soft_render::snapshot::Snapshot;
+    use crate::theme::Theme;
+    use crate::widgets::Label;
+    use cgmath_geometry::rect::DimsBox;
+    use std::thread;
+
+    #[test]
+    fn delay_timer() {
+        let mut tooltip = Tooltip::new(Label::new(Contents::Text("Hover".to_string())), Contents::Text("Help".to_string()));
+        *tooltip.delay_mut() = Duration::from_millis(50);
+        let mut snapshot = Snapshot::new(tooltip, Theme::default(), DimsBox::new2(100, 100));
+        snapshot.render();
+
+        // The tooltip doesn't appear until the cursor has rested over the widget for the delay.
+        snapshot.process_events(vec![WindowEvent::MouseEnter, WindowEvent::MouseMove(Point2::new(10, 10))]);
+        snapshot.process_events(vec![WindowEvent::Timer]);
+        assert!(snapshot.root().hover_timer.is_some());
+        assert!(!snapshot.root().shown);
+
+        thread::sleep(Duration::from_millis(60));
+        snapshot.process_events(vec![WindowEvent::Timer]);
+        assert!(snapshot.root().hover_timer.is_none());
+        assert!(snapshot.root().shown);
+
+        // Leaving the widget hides the tooltip again.
+        snapshot.process_events(vec![WindowEvent::MouseExit]);
+        assert!(!snapshot.root().shown);
+    }
+}
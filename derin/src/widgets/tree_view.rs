@@ -0,0 +1,1100 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use derin_core::{
+    LoopFlow,
+    event::{EventOps, WidgetEvent, WidgetEventSourced, InputState, MouseButton, FocusChange, Key, ModifierKeys},
+    timer::{Timer, TimerId},
+    widget::{MessageTarget, WidgetIdent, WidgetTag, WidgetRenderable, WidgetInfo, WidgetInfoMut, WidgetId, Widget, Parent},
+    render::{Renderer, RendererLayout, SubFrame, WidgetTheme, CursorOp},
+};
+use crate::theme::ThemePath;
+use crate::widgets::{
+    Contents,
+    assistants::text_edit::{TextEditAssist, TextEditOps, CursorFlashOp, LineCharFilter},
+};
+
+use cgmath_geometry::{D2, rect::{BoundBox, DimsBox, GeoBox}};
+use derin_common_types::layout::SizeBounds;
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::time::Duration;
+
+/// Supplies the nodes shown in a [`TreeView`].
+///
+/// Nodes are addressed by their path from the root: the identity of each node along the way,
+/// starting with one of the root's children. The root node's path is empty. Identities only have to
+/// be unique among siblings, but they must stay the same when siblings get reordered so that the
+/// tree view's selection and expanded nodes follow the nodes around.
+///
+/// The tree view only asks about nodes whose parents are expanded, so children can be loaded
+/// lazily.
+///
+/// [`TreeView`]: ./struct.TreeView.html
+pub trait TreeModel: 'static {
+    /// The number of children the node at `path` has.
+    fn num_children(&self, path: &[WidgetIdent]) -> usize;
+
+    /// Whether the node at `path` has any children.
+    ///
+    /// This is used to decide whether to draw an expander next to a collapsed node, so models that
+    /// load children lazily can override it to avoid loading anything before the node gets
+    /// expanded.
+    fn has_children(&self, path: &[WidgetIdent]) -> bool {
+        self.num_children(path) > 0
+    }
+
+    /// The identity of the `index`th child of the node at `path`.
+    fn child_ident(&self, path: &[WidgetIdent], index: usize) -> WidgetIdent;
+
+    /// What should be drawn in the node's row.
+    fn contents(&self, path: &[WidgetIdent]) -> Contents;
+
+    /// Whether the node at `path` can be renamed inline. Nodes can't be renamed by default.
+    fn can_rename(&self, _path: &[WidgetIdent]) -> bool {
+        false
+    }
+
+    /// Rename the node at `path`, returning whether the new name was accepted.
+    fn rename(&mut self, _path: &[WidgetIdent], _name: String) -> bool {
+        false
+    }
+}
+
+/// Determines which action, if any, should be taken in response to the user interacting with a
+/// tree view.
+pub trait TreeViewHandler: 'static {
+    type Action: 'static;
+
+    fn on_select(&mut self, selection: &[Vec<WidgetIdent>]) -> Option<Self::Action>;
+
+    fn on_expand(&mut self, _path: &[WidgetIdent], _expanded: bool) -> Option<Self::Action> {
+        None
+    }
+}
+
+/// How many nodes can be selected in a tree view at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SelectionMode {
+    Single,
+    /// Clicking with `Ctrl` held toggles individual nodes, and clicking with `Shift` held selects
+    /// every row between the clicked row and the last row clicked without `Shift`.
+    Multi,
+}
+
+/// A hierarchical list of nodes, which can be expanded and collapsed.
+///
+/// The nodes are supplied on demand by a [`TreeModel`]. Nodes get expanded and collapsed by clicking
+/// their expanders or with the `Left` and `Right` arrow keys, and pressing `F2` renames the node
+/// under the keyboard cursor if the model allows it. Whenever the selection changes, the provided
+/// handler's [`on_select`] function is called.
+///
+/// [`TreeModel`]: ./trait.TreeModel.html
+/// [`on_select`]: ./trait.TreeViewHandler.html#tymethod.on_select
+#[derive(Debug, Clone)]
+pub struct TreeView<M: TreeModel, H: TreeViewHandler> {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    model: M,
+    selection_mode: SelectionMode,
+    selection: Vec<Vec<WidgetIdent>>,
+    expanded: HashSet<Vec<WidgetIdent>>,
+    /// The row keyboard navigation starts from.
+    cursor: Option<Vec<WidgetIdent>>,
+    /// The row `Shift`-selection extends from.
+    anchor: Option<Vec<WidgetIdent>>,
+    renaming: Option<Vec<WidgetIdent>>,
+
+    rows: Vec<TreeRow>,
+    rows_dirty: bool,
+
+    pub handler: H,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TreeViewTheme(());
+
+#[derive(Debug, Clone, Copy)]
+pub struct TreeRowTheme {
+    pub selected: bool,
+    pub renaming: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeExpanderTheme {
+    pub state: ExpanderState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExpanderState {
+    /// The node doesn't have any children.
+    Leaf,
+    Collapsed,
+    Expanded,
+}
+
+#[derive(Debug, Clone)]
+struct TreeRow {
+    path: Vec<WidgetIdent>,
+    label: TreeLabel,
+    expander: TreeExpander,
+}
+
+#[derive(Debug, Clone)]
+struct TreeLabel {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    contents: Contents,
+    selected: bool,
+    edit: Option<TextEditAssist<LineCharFilter>>,
+    flash_timer: Option<TimerId>,
+}
+
+#[derive(Debug, Clone)]
+struct TreeExpander {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    state: ExpanderState,
+}
+
+/// Sent from a row's widgets to the tree view that contains them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RowEvent {
+    Press(WidgetId, ModifierKeys),
+    Toggle(WidgetId),
+}
+
+impl<M, H> TreeView<M, H>
+    where M: TreeModel,
+          H: TreeViewHandler
+{
+    /// Creates a new tree view showing the nodes in `model`, with every node collapsed.
+    pub fn new(model: M, selection_mode: SelectionMode, handler: H) -> TreeView<M, H> {
+        let mut widget_tag = WidgetTag::new();
+        widget_tag.register_message(Self::on_row_event);
+        widget_tag.set_focusable(true);
+
+        TreeView {
+            widget_tag,
+            rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+
+            model,
+            selection_mode,
+            selection: Vec::new(),
+            expanded: HashSet::new(),
+            cursor: None,
+            anchor: None,
+            renaming: None,
+
+            rows: Vec::new(),
+            rows_dirty: true,
+
+            handler,
+        }
+    }
+
+    /// Retrieves the model supplying the tree's nodes.
+    pub fn model(&self) -> &M {
+        &self.model
+    }
+
+    /// Retrieves the model supplying the tree's nodes, for mutation.
+    ///
+    /// Calling this function forces the tree to re-query the model for every visible node, so
+    /// you're discouraged from calling it unless you're actually changing the model.
+    pub fn model_mut(&mut self) -> &mut M {
+        self.rows_dirty = true;
+        self.widget_tag.request_redraw().request_relayout();
+        &mut self.model
+    }
+
+    /// Retrieves the paths of the selected nodes.
+    pub fn selection(&self) -> &[Vec<WidgetIdent>] {
+        &self.selection
+    }
+
+    /// Retrieves the paths of the selected nodes, for mutation.
+    ///
+    /// Changing the selection through this function doesn't call the handler.
+    pub fn selection_mut(&mut self) -> &mut Vec<Vec<WidgetIdent>> {
+        self.rows_dirty = true;
+        self.widget_tag.request_redraw().request_relayout();
+        &mut self.selection
+    }
+
+    /// Retrieves whether the node at `path` is expanded.
+    pub fn is_expanded(&self, path: &[WidgetIdent]) -> bool {
+        self.expanded.contains(path)
+    }
+
+    /// Expand or collapse the node at `path`.
+    ///
+    /// Collapsed nodes remember which of their descendants were expanded.
+    pub fn set_expanded(&mut self, path: &[WidgetIdent], expanded: bool) {
+        let changed = match expanded {
+            true => self.expanded.insert(path.to_vec()),
+            false => self.expanded.remove(path)
+        };
+        if changed {
+            self.rows_dirty = true;
+            self.widget_tag.request_redraw().request_relayout();
+        }
+    }
+
+    /// Start renaming the node at `path` inline, if the model allows it and the node is visible.
+    pub fn rename(&mut self, path: &[WidgetIdent]) {
+        self.refresh_rows();
+        self.finish_rename(true);
+        if !self.model.can_rename(path) {
+            return;
+        }
+
+        if let Some(row) = self.rows.iter_mut().find(|r| r.path == path) {
+            if let Contents::Text(ref text) = row.label.contents {
+                let text = text.clone();
+                row.label.begin_edit(text);
+                self.renaming = Some(path.to_vec());
+            }
+        }
+    }
+
+    fn row_index(&self, path: &[WidgetIdent]) -> Option<usize> {
+        self.rows.iter().position(|r| r.path == path)
+    }
+
+    fn cursor_index(&self) -> Option<usize> {
+        self.cursor.as_ref().and_then(|c| self.row_index(c))
+    }
+
+    /// Whether the node at `path` is still in the model.
+    fn node_exists(&self, path: &[WidgetIdent]) -> bool {
+        (0..path.len()).all(|depth| {
+            let parent = &path[..depth];
+            (0..self.model.num_children(parent)).any(|i| self.model.child_ident(parent, i) == path[depth])
+        })
+    }
+
+    /// Rebuild the rows from the model if the model, the selection, or the set of expanded nodes
+    /// has changed.
+    fn refresh_rows(&mut self) {
+        if !self.rows_dirty {
+            return;
+        }
+        self.rows_dirty = false;
+
+        let num_selected = self.selection.len();
+        let selection = mem::replace(&mut self.selection, Vec::new());
+        let selection = selection.into_iter().filter(|p| self.node_exists(p)).collect();
+        self.selection = selection;
+        if self.selection.len() != num_selected {
+            self.notify_select();
+        }
+
+        // Reuse the widgets of rows that are still visible, so they keep their identity.
+        let mut old_rows = self.rows.drain(..).map(|r| (r.path.clone(), r)).collect::<HashMap<_, _>>();
+        let mut path = Vec::new();
+        self.push_rows(&mut path, &mut old_rows);
+
+        for row in &mut self.rows {
+            row.label.set_selected(self.selection.contains(&row.path));
+        }
+
+        if self.cursor.as_ref().map(|c| !self.rows.iter().any(|r| r.path == *c)).unwrap_or(false) {
+            self.cursor = None;
+        }
+        if self.renaming.as_ref().map(|r| !self.rows.iter().any(|row| row.path == *r)).unwrap_or(false) {
+            self.renaming = None;
+        }
+    }
+
+    fn push_rows(&mut self, path: &mut Vec<WidgetIdent>, old_rows: &mut HashMap<Vec<WidgetIdent>, TreeRow>) {
+        for i in 0..self.model.num_children(path) {
+            let ident = self.model.child_ident(path, i);
+            path.push(ident);
+
+            let expanded = self.expanded.contains(&path[..]);
+            let state = match (self.model.has_children(path), expanded) {
+                (false, _) => ExpanderState::Leaf,
+                (true, false) => ExpanderState::Collapsed,
+                (true, true) => ExpanderState::Expanded,
+            };
+            let contents = self.model.contents(path);
+            let mut row = old_rows.remove(&path[..]).unwrap_or_else(|| TreeRow::new(path.clone()));
+            row.label.set_contents(contents);
+            row.expander.set_state(state);
+            self.rows.push(row);
+
+            if state == ExpanderState::Expanded {
+                self.push_rows(path, old_rows);
+            }
+            path.pop();
+        }
+    }
+
+    fn notify_select(&mut self) {
+        if let Some(action) = self.handler.on_select(&self.selection) {
+            self.widget_tag.broadcast_message(action);
+        }
+    }
+
+    fn set_selection(&mut self, selection: Vec<Vec<WidgetIdent>>) {
+        if selection == self.selection {
+            return;
+        }
+        self.selection = selection;
+        for row in &mut self.rows {
+            row.label.set_selected(self.selection.contains(&row.path));
+        }
+        self.notify_select();
+    }
+
+    /// Move the cursor to the row at `index`, updating the selection the way clicking the row with
+    /// `modifiers` held would.
+    fn select_row(&mut self, index: usize, modifiers: ModifierKeys) {
+        let path = match self.rows.get(index) {
+            Some(row) => row.path.clone(),
+            None => return
+        };
+        self.cursor = Some(path.clone());
+
+        let multi = self.selection_mode == SelectionMode::Multi;
+        if multi && modifiers.contains(ModifierKeys::SHIFT) {
+            let anchor = self.anchor.as_ref().and_then(|a| self.row_index(a)).unwrap_or(index);
+            let (start, end) = (anchor.min(index), anchor.max(index));
+            let mut selection = match modifiers.contains(ModifierKeys::CTRL) {
+                true => self.selection.clone(),
+                false => Vec::new()
+            };
+            for row in &self.rows[start..=end] {
+                if !selection.contains(&row.path) {
+                    selection.push(row.path.clone());
+                }
+            }
+            self.set_selection(selection);
+        } else if multi && modifiers.contains(ModifierKeys::CTRL) {
+            self.anchor = Some(path.clone());
+            let mut selection = self.selection.clone();
+            match selection.iter().position(|p| *p == path) {
+                Some(i) => {selection.remove(i);},
+                None => selection.push(path)
+            }
+            self.set_selection(selection);
+        } else {
+            self.anchor = Some(path.clone());
+            self.set_selection(vec![path]);
+        }
+        self.widget_tag.request_redraw();
+    }
+
+    fn toggle_expanded(&mut self, index: usize) {
+        let (path, state) = match self.rows.get(index) {
+            Some(row) => (row.path.clone(), row.expander.state),
+            None => return
+        };
+        let expand = match state {
+            ExpanderState::Leaf => return,
+            ExpanderState::Collapsed => true,
+            ExpanderState::Expanded => false,
+        };
+
+        self.set_expanded(&path, expand);
+        self.refresh_rows();
+        if let Some(action) = self.handler.on_expand(&path, expand) {
+            self.widget_tag.broadcast_message(action);
+        }
+    }
+
+    fn finish_rename(&mut self, commit: bool) {
+        let path = match self.renaming.take() {
+            Some(path) => path,
+            None => return
+        };
+        let name = match self.rows.iter_mut().find(|r| r.path == path) {
+            Some(row) => row.label.end_edit(),
+            None => return
+        };
+
+        if commit && self.model.rename(&path, name) {
+            self.rows_dirty = true;
+            self.refresh_rows();
+        }
+        self.widget_tag.request_redraw().request_relayout();
+    }
+
+    fn on_row_event(&mut self, event: &RowEvent) {
+        self.refresh_rows();
+        match *event {
+            RowEvent::Press(label_id, modifiers) => {
+                if let Some(index) = self.rows.iter().position(|r| r.label.widget_tag.widget_id() == label_id) {
+                    if self.renaming.as_ref() != Some(&self.rows[index].path) {
+                        self.finish_rename(true);
+                        self.select_row(index, modifiers);
+                    }
+                }
+            },
+            RowEvent::Toggle(expander_id) => {
+                self.finish_rename(true);
+                if let Some(index) = self.rows.iter().position(|r| r.expander.widget_tag.widget_id() == expander_id) {
+                    self.toggle_expanded(index);
+                }
+            }
+        }
+    }
+
+    fn on_key(&mut self, key: Key, modifiers: ModifierKeys) -> bool {
+        let num_rows = self.rows.len();
+        if num_rows == 0 {
+            return false;
+        }
+        let cursor = self.cursor_index();
+        let nav_modifiers = modifiers & (ModifierKeys::SHIFT | ModifierKeys::CTRL);
+
+        match key {
+            Key::UArrow => {
+                let index = cursor.map(|c| c.saturating_sub(1)).unwrap_or(0);
+                self.move_cursor(index, nav_modifiers);
+            },
+            Key::DArrow => {
+                let index = cursor.map(|c| (c + 1).min(num_rows - 1)).unwrap_or(0);
+                self.move_cursor(index, nav_modifiers);
+            },
+            Key::Home => self.move_cursor(0, nav_modifiers),
+            Key::End => self.move_cursor(num_rows - 1, nav_modifiers),
+            Key::LArrow => if let Some(cursor) = cursor {
+                let state = self.rows[cursor].expander.state;
+                match state {
+                    ExpanderState::Expanded => self.toggle_expanded(cursor),
+                    _ => {
+                        let parent_len = self.rows[cursor].path.len() - 1;
+                        let parent = self.rows[..cursor].iter().rposition(|r| r.path.len() == parent_len);
+                        if let Some(parent) = parent {
+                            self.move_cursor(parent, ModifierKeys::empty());
+                        }
+                    }
+                }
+            },
+            Key::RArrow => if let Some(cursor) = cursor {
+                let state = self.rows[cursor].expander.state;
+                match state {
+                    ExpanderState::Collapsed => self.toggle_expanded(cursor),
+                    ExpanderState::Expanded if cursor + 1 < num_rows => self.move_cursor(cursor + 1, ModifierKeys::empty()),
+                    _ => ()
+                }
+            },
+            Key::Space if self.selection_mode == SelectionMode::Multi => if let Some(cursor) = cursor {
+                self.select_row(cursor, ModifierKeys::CTRL);
+            },
+            Key::F2 => if let Some(path) = self.cursor.clone() {
+                self.rename(&path);
+            },
+            _ => return false
+        }
+        true
+    }
+
+    /// Move the cursor with the keyboard. Holding `Ctrl` in multi-selection mode moves the cursor
+    /// without changing the selection.
+    fn move_cursor(&mut self, index: usize, modifiers: ModifierKeys) {
+        let multi = self.selection_mode == SelectionMode::Multi;
+        if multi && modifiers == ModifierKeys::CTRL {
+            self.cursor = self.rows.get(index).map(|r| r.path.clone());
+            self.widget_tag.request_redraw();
+        } else {
+            self.select_row(index, modifiers);
+        }
+    }
+}
+
+impl TreeRow {
+    fn new(path: Vec<WidgetIdent>) -> TreeRow {
+        TreeRow {
+            path,
+            label: TreeLabel {
+                widget_tag: WidgetTag::new(),
+                rect: BoundBox::new2(0, 0, 0, 0),
+                size_bounds: SizeBounds::default(),
+
+                contents: Contents::Text(String::new()),
+                selected: false,
+                edit: None,
+                flash_timer: None,
+            },
+            expander: TreeExpander {
+                widget_tag: WidgetTag::new(),
+                rect: BoundBox::new2(0, 0, 0, 0),
+                size_bounds: SizeBounds::default(),
+
+                state: ExpanderState::Leaf,
+            },
+        }
+    }
+}
+
+impl TreeLabel {
+    fn set_contents(&mut self, contents: Contents) {
+        if contents != self.contents {
+            self.contents = contents;
+            self.widget_tag.request_redraw().request_relayout();
+        }
+    }
+
+    fn set_selected(&mut self, selected: bool) {
+        if selected != self.selected {
+            self.selected = selected;
+            self.widget_tag.request_redraw();
+        }
+    }
+
+    fn begin_edit(&mut self, string: String) {
+        let mut edit = TextEditAssist {
+            string,
+            ..TextEditAssist::default()
+        };
+        edit.cursor_ops.push(CursorOp::SelectAll);
+        self.edit = Some(edit);
+        self.apply_edit_ops(TextEditOps {
+            allow_bubble: false,
+            redraw: true,
+            focus: None,
+            cursor_flash: Some(CursorFlashOp::Start),
+            cursor_icon: None,
+        });
+        self.widget_tag.request_relayout();
+    }
+
+    fn end_edit(&mut self) -> String {
+        if let Some(timer_id) = self.flash_timer.take() {
+            self.widget_tag.timers_mut().remove(&timer_id);
+        }
+        self.widget_tag.request_redraw().request_relayout();
+        self.edit.take().map(|e| e.string).unwrap_or_default()
+    }
+
+    fn apply_edit_ops(&mut self, ops: TextEditOps) {
+        match (ops.cursor_flash, self.flash_timer) {
+            (Some(CursorFlashOp::Start), None) => {
+                let timer_id = TimerId::new();
+                self.widget_tag.timers_mut().insert(timer_id, Timer::new(Duration::new(1, 0)/2));
+                self.flash_timer = Some(timer_id);
+            },
+            (Some(CursorFlashOp::End), Some(timer_id)) => {
+                self.widget_tag.timers_mut().remove(&timer_id);
+                self.flash_timer = None;
+            },
+            _ => ()
+        }
+        if ops.redraw {
+            self.widget_tag.request_redraw().request_relayout();
+        }
+        if let Some(cursor_icon) = ops.cursor_icon {
+            self.widget_tag.set_cursor_icon(cursor_icon).ok();
+        }
+    }
+}
+
+impl TreeExpander {
+    fn set_state(&mut self, state: ExpanderState) {
+        if state != self.state {
+            self.state = state;
+            self.widget_tag.request_redraw().request_relayout();
+        }
+    }
+}
+
+impl<M, H> Widget for TreeView<M, H>
+    where M: TreeModel,
+          H: TreeViewHandler
+{
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        self.widget_tag.request_relayout();
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, input_state: InputState) -> EventOps {
+        use self::WidgetEvent::*;
+        let event = event.unwrap();
+        self.refresh_rows();
+
+        let mut focus = None;
+        let mut bubble = event.default_bubble();
+
+        let renaming_index = self.renaming.as_ref().and_then(|r| self.row_index(r));
+        match (event.clone(), renaming_index) {
+            (KeyDown(Key::Enter, _), Some(_)) => {
+                bubble = false;
+                self.finish_rename(true);
+            },
+            (KeyDown(Key::Escape, _), Some(_)) => {
+                bubble = false;
+                self.finish_rename(false);
+            },
+            (KeyDown(..), Some(index)) |
+            (KeyUp(..), Some(index)) |
+            (Char(_), Some(index)) => {
+                let label = &mut self.rows[index].label;
+                if let Some(ref mut edit) = label.edit {
+                    let ops = edit.adapt_event(&event, input_state);
+                    bubble = bubble && ops.allow_bubble;
+                    label.apply_edit_ops(ops);
+                }
+            },
+            (KeyDown(key, modifiers), None) => if self.on_key(key, modifiers) {
+                bubble = false;
+            },
+            (MouseDown{in_widget: true, ..}, _) => focus = Some(FocusChange::Take),
+            (MouseDown{in_widget: false, ..}, _) => {
+                self.finish_rename(true);
+                focus = Some(FocusChange::Remove);
+            },
+            (LoseFocus, _) => self.finish_rename(true),
+            _ => ()
+        }
+
+        EventOps {
+            focus,
+            bubble,
+        }
+    }
+}
+
+impl<M, H> Parent for TreeView<M, H>
+    where M: TreeModel,
+          H: TreeViewHandler
+{
+    fn num_children(&self) -> usize {
+        self.rows.len() * 2
+    }
+
+    fn framed_child<R: Renderer>(&self, widget_ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
+        match widget_ident {
+            WidgetIdent::NumCollection(row, part) if part < 2 => self.framed_child_by_index(row as usize * 2 + part as usize),
+            _ => None
+        }
+    }
+    fn framed_child_mut<R: Renderer>(&mut self, widget_ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
+        match widget_ident {
+            WidgetIdent::NumCollection(row, part) if part < 2 => self.framed_child_by_index_mut(row as usize * 2 + part as usize),
+            _ => None
+        }
+    }
+
+    fn framed_children<'a, R, G>(&'a self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
+    {
+        for (i, row) in self.rows.iter().enumerate() {
+            let label = WidgetInfo::new(WidgetIdent::NumCollection(i as u32, 0), i * 2, &row.label);
+            if let LoopFlow::Break = for_each(label) {
+                return;
+            }
+            let expander = WidgetInfo::new(WidgetIdent::NumCollection(i as u32, 1), i * 2 + 1, &row.expander);
+            if let LoopFlow::Break = for_each(expander) {
+                return;
+            }
+        }
+    }
+
+    fn framed_children_mut<'a, R, G>(&'a mut self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
+    {
+        for (i, row) in self.rows.iter_mut().enumerate() {
+            let label = WidgetInfoMut::new(WidgetIdent::NumCollection(i as u32, 0), i * 2, &mut row.label);
+            if let LoopFlow::Break = for_each(label) {
+                return;
+            }
+            let expander = WidgetInfoMut::new(WidgetIdent::NumCollection(i as u32, 1), i * 2 + 1, &mut row.expander);
+            if let LoopFlow::Break = for_each(expander) {
+                return;
+            }
+        }
+    }
+
+    fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
+        let row = self.rows.get(index / 2)?;
+        let ident = WidgetIdent::NumCollection((index / 2) as u32, (index % 2) as u32);
+        match index % 2 {
+            0 => Some(WidgetInfo::new(ident, index, &row.label)),
+            _ => Some(WidgetInfo::new(ident, index, &row.expander)),
+        }
+    }
+    fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
+        let row = self.rows.get_mut(index / 2)?;
+        let ident = WidgetIdent::NumCollection((index / 2) as u32, (index % 2) as u32);
+        match index % 2 {
+            0 => Some(WidgetInfoMut::new(ident, index, &mut row.label)),
+            _ => Some(WidgetInfoMut::new(ident, index, &mut row.expander)),
+        }
+    }
+}
+
+impl Widget for TreeLabel {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, input_state: InputState) -> EventOps {
+        let event = event.unwrap();
+        let label_id = self.widget_tag.widget_id();
+        let mut focus = None;
+
+        match event {
+            WidgetEvent::Timer{timer_id, times_triggered, ..} if Some(timer_id) == self.flash_timer => {
+                if let Some(ref mut edit) = self.edit {
+                    edit.cursor_data.draw_cursor = times_triggered % 2 == 0;
+                    self.widget_tag.request_redraw();
+                }
+            },
+            // Keyboard input gets forwarded to the edit by the tree view, which keeps focus while
+            // renaming. Mouse input positions the edit cursor directly.
+            WidgetEvent::MouseDown{in_widget: true, ..} |
+            WidgetEvent::MouseMove{..} |
+            WidgetEvent::MouseUp{..} if self.edit.is_some() => {
+                let ops = self.edit.as_mut().unwrap().adapt_event(&event, input_state);
+                self.apply_edit_ops(ops);
+                if let WidgetEvent::MouseDown{..} = event {
+                    focus = Some(FocusChange::Parent);
+                }
+            },
+            WidgetEvent::MouseDown{in_widget: true, button: MouseButton::Left, ..} => {
+                focus = Some(FocusChange::Parent);
+                let press = RowEvent::Press(label_id, input_state.modifiers);
+                self.widget_tag.send_message_to(press, MessageTarget::ParentOf(label_id));
+            },
+            _ => ()
+        }
+
+        EventOps {
+            focus,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
+impl Widget for TreeExpander {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        let event = event.unwrap();
+        let mut focus = None;
+
+        if let WidgetEvent::MouseDown{in_widget: true, button: MouseButton::Left, ..} = event {
+            focus = Some(FocusChange::Parent);
+            if self.state != ExpanderState::Leaf {
+                let expander_id = self.widget_tag.widget_id();
+                self.widget_tag.send_message_to(RowEvent::Toggle(expander_id), MessageTarget::ParentOf(expander_id));
+            }
+        }
+
+        EventOps {
+            focus,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
+impl<R, M, H> WidgetRenderable<R> for TreeView<M, H>
+    where R: Renderer,
+          M: TreeModel,
+          H: TreeViewHandler
+{
+    type Theme = TreeViewTheme;
+
+    fn theme(&self) -> TreeViewTheme {
+        TreeViewTheme(())
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        self.refresh_rows();
+
+        let result = layout.finish();
+        let content_rect = result.content_rect;
+        let margin_width = content_rect.min.x + (self.rect.width() - content_rect.max.x);
+        let margin_height = content_rect.min.y + (self.rect.height() - content_rect.max.y);
+
+        // Every level gets indented by the width of the widest expander, so that rows line up
+        // whether or not they have children.
+        let indent = self.rows.iter().map(|r| r.expander.size_bounds.min.width()).max().unwrap_or(0);
+
+        let mut y = content_rect.min.y;
+        let mut min_width = 0;
+        for row in &mut self.rows {
+            let depth = row.path.len() as i32 - 1;
+            let height = row.label.size_bounds.min.height().max(row.expander.size_bounds.min.height());
+            let expander_x = content_rect.min.x + depth * indent;
+            let label_x = expander_x + indent;
+            let label_width = row.label.size_bounds.min.width().max(content_rect.max.x - label_x);
+
+            let expander_rect = BoundBox::new2(expander_x, y, label_x, y + height);
+            let label_rect = BoundBox::new2(label_x, y, label_x + label_width, y + height);
+            if row.expander.rect != expander_rect {
+                row.expander.rect = expander_rect;
+                row.expander.widget_tag.request_relayout();
+            }
+            if row.label.rect != label_rect {
+                row.label.rect = label_rect;
+                row.label.widget_tag.request_relayout();
+            }
+
+            min_width = min_width.max(label_x - content_rect.min.x + row.label.size_bounds.min.width());
+            y += height;
+        }
+
+        let min = DimsBox::new2(
+            (min_width + margin_width).max(result.size_bounds.min.width()),
+            (y - content_rect.min.y + margin_height).max(result.size_bounds.min.height()),
+        );
+        self.size_bounds = SizeBounds {
+            min,
+            ..result.size_bounds
+        };
+    }
+}
+
+impl<R> WidgetRenderable<R> for TreeLabel
+    where R: Renderer
+{
+    type Theme = TreeRowTheme;
+
+    fn theme(&self) -> TreeRowTheme {
+        TreeRowTheme {
+            selected: self.selected,
+            renaming: self.edit.is_some(),
+        }
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        match (&mut self.edit, &self.contents) {
+            (Some(edit), _) => layout.prepare_edit_string(
+                &mut edit.string,
+                &mut edit.cursor_data,
+                edit.cursor_ops.drain(..),
+            ),
+            (None, Contents::Text(s)) => layout.prepare_string(s),
+            (None, Contents::Icon(i)) => layout.prepare_icon(i),
+        }
+
+        let result = layout.finish();
+        self.size_bounds = result.size_bounds;
+    }
+}
+
+impl<R> WidgetRenderable<R> for TreeExpander
+    where R: Renderer
+{
+    type Theme = TreeExpanderTheme;
+
+    fn theme(&self) -> TreeExpanderTheme {
+        TreeExpanderTheme {
+            state: self.state,
+        }
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        layout.prepare_string(match self.state {
+            ExpanderState::Leaf => "",
+            ExpanderState::Collapsed => "\u{25B8}",
+            ExpanderState::Expanded => "\u{25BE}",
+        });
+
+        let result = layout.finish();
+        self.size_bounds = result.size_bounds;
+    }
+}
+
+impl WidgetTheme for TreeViewTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for TreeViewTheme {
+    fn theme_path(&self) -> &'static str {
+        "TreeView::Background"
+    }
+}
+
+impl WidgetTheme for TreeRowTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for TreeRowTheme {
+    fn theme_path(&self) -> &'static str {
+        match (self.renaming, self.selected) {
+            (true, _) => "TreeView::Row::Rename",
+            (false, true) => "TreeView::Row::Selected",
+            (false, false) => "TreeView::Row::Normal",
+        }
+    }
+}
+
+impl WidgetTheme for TreeExpanderTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for TreeExpanderTheme {
+    fn theme_path(&self) -> &'static str {
+        match self.state {
+            ExpanderState::Leaf => "TreeView::Expander::Leaf",
+            ExpanderState::Collapsed => "TreeView::Expander::Collapsed",
+            ExpanderState::Expanded => "TreeView::Expander::Expanded",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::WindowEvent;
+    use crate::cgmath::Point2;
+    use crate::soft_render::snapshot::Snapshot;
+    use crate::theme::Theme;
+
+    /// A fruit node with two children, followed by a leaf.
+    struct Produce;
+    impl TreeModel for Produce {
+        fn num_children(&self, path: &[WidgetIdent]) -> usize {
+            match (path.len(), path.first()) {
+                (0, _) => 2,
+                (1, Some(&WidgetIdent::Num(0))) => 2,
+                _ => 0
+            }
+        }
+
+        fn child_ident(&self, _: &[WidgetIdent], index: usize) -> WidgetIdent {
+            WidgetIdent::Num(index as u32)
+        }
+
+        fn contents(&self, path: &[WidgetIdent]) -> Contents {
+            Contents::Text(format!("{:?}", path))
+        }
+    }
+
+    #[derive(Default)]
+    struct ExpandLog(Vec<(Vec<WidgetIdent>, bool)>);
+    impl TreeViewHandler for ExpandLog {
+        type Action = ();
+        fn on_select(&mut self, _: &[Vec<WidgetIdent>]) -> Option<()> {
+            None
+        }
+        fn on_expand(&mut self, path: &[WidgetIdent], expanded: bool) -> Option<()> {
+            self.0.push((path.to_vec(), expanded));
+            None
+        }
+    }
+
+    #[test]
+    fn arrow_key_expand() {
+        let tree_view = TreeView::new(Produce, SelectionMode::Single, ExpandLog::default());
+        let mut snapshot = Snapshot::new(tree_view, Theme::default(), DimsBox::new2(100, 100));
+        snapshot.render();
+        assert_eq!(2, snapshot.root().rows.len());
+
+        // Clicking the first row's label focuses the tree and puts the cursor on the row.
+        snapshot.process_events(vec![
+            WindowEvent::MouseEnter,
+            WindowEvent::MouseMove(Point2::new(80, 5)),
+            WindowEvent::MouseDown(MouseButton::Left),
+            WindowEvent::MouseUp(MouseButton::Left),
+        ]);
+        let fruit = vec![WidgetIdent::Num(0)];
+        assert_eq!(Some(&fruit), snapshot.root().cursor.as_ref());
+
+        snapshot.process_events(vec![WindowEvent::KeyDown(Key::RArrow), WindowEvent::KeyUp(Key::RArrow)]);
+        snapshot.render();
+        assert!(snapshot.root().is_expanded(&fruit));
+        assert_eq!(4, snapshot.root().rows.len());
+
+        // Once expanded, Right moves into the node's children.
+        snapshot.process_events(vec![WindowEvent::KeyDown(Key::RArrow), WindowEvent::KeyUp(Key::RArrow)]);
+        let first_child = vec![WidgetIdent::Num(0), WidgetIdent::Num(0)];
+        assert_eq!(Some(&first_child), snapshot.root().cursor.as_ref());
+
+        // Left on a child goes back to the parent, and Left again collapses it.
+        snapshot.process_events(vec![
+            WindowEvent::KeyDown(Key::LArrow),
+            WindowEvent::KeyUp(Key::LArrow),
+            WindowEvent::KeyDown(Key::LArrow),
+            WindowEvent::KeyUp(Key::LArrow),
+        ]);
+        snapshot.render();
+        assert_eq!(Some(&fruit), snapshot.root().cursor.as_ref());
+        assert!(!snapshot.root().is_expanded(&fruit));
+        assert_eq!(2, snapshot.root().rows.len());
+        assert_eq!(vec![(fruit.clone(), true), (fruit, false)], snapshot.root().handler.0);
+    }
+}
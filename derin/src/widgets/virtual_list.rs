@@ -0,0 +1,752 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use derin_core::{
+    LoopFlow,
+    event::{EventOps, WidgetEvent, WidgetEventSourced, InputState, MouseButton, FocusChange, Key, ModifierKeys},
+    widget::{MessageTarget, WidgetIdent, WidgetTag, WidgetRenderable, WidgetInfo, WidgetInfoMut, WidgetId, Widget, Parent},
+    render::{Renderer, RendererLayout, SubFrame, WidgetTheme},
+};
+use crate::theme::ThemePath;
+use crate::widgets::{Contents, SelectionMode};
+
+use cgmath_geometry::{D2, rect::{BoundBox, GeoBox}};
+use derin_common_types::layout::SizeBounds;
+use std::ops::Range;
+
+/// The number of rows instantiated beyond each edge of the viewport.
+const OVERSCAN: usize = 2;
+
+/// Supplies the items shown in a [`VirtualList`].
+///
+/// Items are only queried while they're in or near the list's viewport.
+///
+/// [`VirtualList`]: ./struct.VirtualList.html
+pub trait ListSource: 'static {
+    fn num_items(&self) -> usize;
+
+    /// The height of the item at `index`, in pixels.
+    fn item_height(&self, index: usize) -> i32;
+
+    /// What should be drawn in the item's row.
+    fn contents(&self, index: usize) -> Contents;
+}
+
+/// Determines which action, if any, should be taken when the selected items change.
+pub trait VirtualListHandler: 'static {
+    type Action: 'static;
+
+    fn on_select(&mut self, selection: &[usize]) -> Option<Self::Action>;
+}
+
+/// A scrollable list that can hold a very large number of items.
+///
+/// Rather than having a widget for every item, the list only creates row widgets for the items
+/// that are visible, and re-uses them for other items as the list scrolls. Scrolling, keyboard
+/// navigation, and selection still work in terms of every item in the [`ListSource`].
+///
+/// [`ListSource`]: ./trait.ListSource.html
+#[derive(Debug, Clone)]
+pub struct VirtualList<S: ListSource, H: VirtualListHandler> {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    source: S,
+    /// The vertical offset of each item, followed by the total height of every item.
+    offsets: Vec<i32>,
+    source_dirty: bool,
+
+    scroll: i32,
+    scroll_to: Option<usize>,
+    /// The height of the region rows get drawn in.
+    viewport_height: i32,
+
+    selection_mode: SelectionMode,
+    /// The selected items, sorted by index.
+    selection: Vec<usize>,
+    cursor: Option<usize>,
+    anchor: Option<usize>,
+
+    rows: Vec<ListRow>,
+
+    pub handler: H,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VirtualListTheme(());
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListRowTheme {
+    pub selected: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ListRow {
+    widget_tag: WidgetTag,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+
+    item: usize,
+    contents: Contents,
+    selected: bool,
+}
+
+/// Sent from a row to the list that contains it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RowEvent {
+    Press(WidgetId, ModifierKeys),
+    ScrollLines(i32),
+    ScrollPx(i32),
+}
+
+impl<S, H> VirtualList<S, H>
+    where S: ListSource,
+          H: VirtualListHandler
+{
+    /// Creates a new list showing the items in `source`.
+    pub fn new(source: S, selection_mode: SelectionMode, handler: H) -> VirtualList<S, H> {
+        let mut widget_tag = WidgetTag::new();
+        widget_tag.register_message(Self::on_row_event);
+        widget_tag.set_focusable(true);
+
+        VirtualList {
+            widget_tag,
+            rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+
+            source,
+            offsets: vec![0],
+            source_dirty: true,
+
+            scroll: 0,
+            scroll_to: None,
+            viewport_height: 0,
+
+            selection_mode,
+            selection: Vec::new(),
+            cursor: None,
+            anchor: None,
+
+            rows: Vec::new(),
+
+            handler,
+        }
+    }
+
+    /// Retrieves the source supplying the list's items.
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Retrieves the source supplying the list's items, for mutation.
+    ///
+    /// Calling this function forces the list to re-query every item's height, and the contents of
+    /// every visible item, so you're discouraged from calling it unless you're actually changing
+    /// the source.
+    pub fn source_mut(&mut self) -> &mut S {
+        self.source_dirty = true;
+        self.widget_tag.request_redraw().request_relayout();
+        &mut self.source
+    }
+
+    /// Retrieves the indices of the selected items, in ascending order.
+    pub fn selection(&self) -> &[usize] {
+        &self.selection
+    }
+
+    /// Replace the selection. Changing the selection through this function doesn't call the
+    /// handler.
+    pub fn set_selection(&mut self, mut selection: Vec<usize>) {
+        selection.sort();
+        selection.dedup();
+        self.selection = selection;
+        self.sync_rows();
+        self.widget_tag.request_redraw();
+    }
+
+    /// Retrieves the number of pixels the list is scrolled down by.
+    pub fn scroll(&self) -> i32 {
+        self.scroll
+    }
+
+    /// Retrieves the number of pixels the list is scrolled down by, for mutation.
+    pub fn scroll_mut(&mut self) -> &mut i32 {
+        self.widget_tag.request_redraw().request_relayout();
+        &mut self.scroll
+    }
+
+    /// Scroll the list just far enough to show the item at `index`.
+    pub fn scroll_to_item(&mut self, index: usize) {
+        self.scroll_to = Some(index);
+        self.widget_tag.request_redraw().request_relayout();
+    }
+
+    fn num_items(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    fn refresh_source(&mut self) {
+        if !self.source_dirty {
+            return;
+        }
+        self.source_dirty = false;
+
+        let num_items = self.source.num_items();
+        self.offsets.clear();
+        self.offsets.reserve(num_items + 1);
+        let mut offset = 0;
+        self.offsets.push(offset);
+        for i in 0..num_items {
+            offset += self.source.item_height(i).max(0);
+            self.offsets.push(offset);
+        }
+
+        let num_selected = self.selection.len();
+        self.selection.retain(|&i| i < num_items);
+        if self.selection.len() != num_selected {
+            self.notify_select();
+        }
+        self.cursor = self.cursor.filter(|&c| c < num_items);
+        self.anchor = self.anchor.filter(|&a| a < num_items);
+
+        // The rows' items may have changed out from under them, so re-query all of them.
+        for row in &mut self.rows {
+            let contents = match row.item < num_items {
+                true => self.source.contents(row.item),
+                false => Contents::Text(String::new())
+            };
+            row.set_contents(contents);
+        }
+    }
+
+    /// The index of the item at vertical offset `y`, clamped to the range of items.
+    fn item_at(&self, y: i32) -> usize {
+        let index = match self.offsets.binary_search(&y) {
+            Ok(i) => i,
+            Err(i) => i.saturating_sub(1)
+        };
+        index.min(self.num_items().saturating_sub(1))
+    }
+
+    /// The items that need rows to cover the viewport.
+    fn visible_items(&self) -> Range<usize> {
+        let num_items = self.num_items();
+        if num_items == 0 {
+            return 0..0;
+        }
+        let first = self.item_at(self.scroll);
+        let last = self.item_at(self.scroll + self.viewport_height);
+        first.saturating_sub(OVERSCAN)..(last + 1 + OVERSCAN).min(num_items)
+    }
+
+    /// Make sure there's exactly one row for every item in `visible`, re-using the rows of items
+    /// that scrolled out of view.
+    fn recycle_rows(&mut self, visible: Range<usize>) {
+        let mut free_rows = Vec::new();
+        let mut covered = vec![false; visible.len()];
+        for (i, row) in self.rows.iter().enumerate() {
+            match visible.contains(&row.item) && !covered[row.item - visible.start] {
+                true => covered[row.item - visible.start] = true,
+                false => free_rows.push(i)
+            }
+        }
+
+        let mut free_rows = free_rows.into_iter();
+        for (offset, _) in covered.iter().enumerate().filter(|&(_, c)| !c) {
+            let item = visible.start + offset;
+            let contents = self.source.contents(item);
+            match free_rows.next() {
+                Some(i) => {
+                    self.rows[i].item = item;
+                    self.rows[i].set_contents(contents);
+                },
+                None => self.rows.push(ListRow::new(item, contents))
+            }
+        }
+
+        // Drop the rows that weren't needed, starting from the back so the indices stay valid.
+        let mut unused = free_rows.collect::<Vec<_>>();
+        unused.sort();
+        for i in unused.into_iter().rev() {
+            self.rows.swap_remove(i);
+        }
+
+        self.sync_rows();
+    }
+
+    fn sync_rows(&mut self) {
+        for row in &mut self.rows {
+            let selected = self.selection.binary_search(&row.item).is_ok();
+            row.set_selected(selected);
+        }
+    }
+
+    fn notify_select(&mut self) {
+        if let Some(action) = self.handler.on_select(&self.selection) {
+            self.widget_tag.broadcast_message(action);
+        }
+    }
+
+    /// Move the cursor to `index`, updating the selection the way clicking the item with
+    /// `modifiers` held would.
+    fn select_item(&mut self, index: usize, modifiers: ModifierKeys) {
+        if index >= self.num_items() {
+            return;
+        }
+        self.cursor = Some(index);
+        self.scroll_to_item(index);
+
+        let multi = self.selection_mode == SelectionMode::Multi;
+        let old_selection = self.selection.clone();
+        if multi && modifiers.contains(ModifierKeys::SHIFT) {
+            let anchor = self.anchor.unwrap_or(index);
+            let range = anchor.min(index)..anchor.max(index) + 1;
+            match modifiers.contains(ModifierKeys::CTRL) {
+                true => self.selection.extend(range),
+                false => self.selection = range.collect()
+            }
+            self.selection.sort();
+            self.selection.dedup();
+        } else if multi && modifiers.contains(ModifierKeys::CTRL) {
+            self.anchor = Some(index);
+            match self.selection.binary_search(&index) {
+                Ok(i) => {self.selection.remove(i);},
+                Err(i) => self.selection.insert(i, index)
+            }
+        } else {
+            self.anchor = Some(index);
+            self.selection = vec![index];
+        }
+
+        if self.selection != old_selection {
+            self.sync_rows();
+            self.notify_select();
+        }
+    }
+
+    fn scroll_by(&mut self, px: i32) {
+        self.scroll -= px;
+        self.widget_tag.request_redraw().request_relayout();
+    }
+
+    fn line_height(&self) -> i32 {
+        match self.num_items() {
+            0 => 24,
+            n => self.offsets[n] / n as i32
+        }
+    }
+
+    fn on_row_event(&mut self, event: &RowEvent) {
+        match *event {
+            RowEvent::Press(row_id, modifiers) => {
+                if let Some(item) = self.rows.iter().find(|r| r.widget_tag.widget_id() == row_id).map(|r| r.item) {
+                    self.select_item(item, modifiers);
+                }
+            },
+            RowEvent::ScrollLines(lines) => {
+                let line_height = self.line_height();
+                self.scroll_by(lines * line_height);
+            },
+            RowEvent::ScrollPx(px) => self.scroll_by(px)
+        }
+    }
+
+    fn on_key(&mut self, key: Key, modifiers: ModifierKeys) -> bool {
+        let num_items = self.num_items();
+        if num_items == 0 {
+            return false;
+        }
+
+        // Holding `Ctrl` in multi-selection mode moves the cursor without changing the selection.
+        let multi = self.selection_mode == SelectionMode::Multi;
+        let move_only = multi && modifiers == ModifierKeys::CTRL;
+        let modifiers = modifiers & (ModifierKeys::SHIFT | ModifierKeys::CTRL);
+
+        let index = match (key, self.cursor) {
+            (Key::UArrow, Some(cursor)) => cursor.saturating_sub(1),
+            (Key::DArrow, Some(cursor)) => (cursor + 1).min(num_items - 1),
+            (Key::PageUp, Some(cursor)) => self.item_at(self.offsets[cursor] - self.viewport_height),
+            (Key::PageDown, Some(cursor)) => self.item_at(self.offsets[cursor] + self.viewport_height),
+            (Key::UArrow, None) |
+            (Key::DArrow, None) |
+            (Key::PageUp, None) |
+            (Key::PageDown, None) |
+            (Key::Home, _) => 0,
+            (Key::End, _) => num_items - 1,
+            (Key::Space, Some(cursor)) if multi => {
+                self.select_item(cursor, ModifierKeys::CTRL);
+                return true;
+            },
+            _ => return false
+        };
+        match move_only {
+            true => {
+                self.cursor = Some(index);
+                self.scroll_to_item(index);
+            },
+            false => self.select_item(index, modifiers)
+        }
+        true
+    }
+}
+
+impl ListRow {
+    fn new(item: usize, contents: Contents) -> ListRow {
+        ListRow {
+            widget_tag: WidgetTag::new(),
+            rect: BoundBox::new2(0, 0, 0, 0),
+            size_bounds: SizeBounds::default(),
+
+            item,
+            contents,
+            selected: false,
+        }
+    }
+
+    fn set_contents(&mut self, contents: Contents) {
+        if contents != self.contents {
+            self.contents = contents;
+            self.widget_tag.request_redraw().request_relayout();
+        }
+    }
+
+    fn set_selected(&mut self, selected: bool) {
+        if selected != self.selected {
+            self.selected = selected;
+            self.widget_tag.request_redraw();
+        }
+    }
+}
+
+impl<S, H> Widget for VirtualList<S, H>
+    where S: ListSource,
+          H: VirtualListHandler
+{
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        self.widget_tag.request_relayout();
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, _: InputState) -> EventOps {
+        use self::WidgetEvent::*;
+        let event = event.unwrap();
+        self.refresh_source();
+
+        let mut focus = None;
+        let mut bubble = event.default_bubble();
+        match event {
+            MouseScrollLines{dir, ..} => {
+                bubble = false;
+                let line_height = self.line_height();
+                self.scroll_by(dir.y * line_height);
+            },
+            MouseScrollPx{dir, ..} => {
+                bubble = false;
+                self.scroll_by(dir.y);
+            },
+            KeyDown(key, modifiers) => if self.on_key(key, modifiers) {
+                bubble = false;
+            },
+            MouseDown{in_widget: true, ..} => focus = Some(FocusChange::Take),
+            MouseDown{in_widget: false, ..} => focus = Some(FocusChange::Remove),
+            _ => ()
+        }
+
+        EventOps {
+            focus,
+            bubble,
+        }
+    }
+}
+
+impl<S, H> Parent for VirtualList<S, H>
+    where S: ListSource,
+          H: VirtualListHandler
+{
+    fn num_children(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn framed_child<R: Renderer>(&self, widget_ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(index) => self.framed_child_by_index(index as usize),
+            _ => None
+        }
+    }
+    fn framed_child_mut<R: Renderer>(&mut self, widget_ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
+        match widget_ident {
+            WidgetIdent::Num(index) => self.framed_child_by_index_mut(index as usize),
+            _ => None
+        }
+    }
+
+    fn framed_children<'a, R, G>(&'a self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
+    {
+        for (index, row) in self.rows.iter().enumerate() {
+            match for_each(WidgetInfo::new(WidgetIdent::Num(index as u32), index, row)) {
+                LoopFlow::Continue => (),
+                LoopFlow::Break => return
+            }
+        }
+    }
+
+    fn framed_children_mut<'a, R, G>(&'a mut self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
+    {
+        for (index, row) in self.rows.iter_mut().enumerate() {
+            match for_each(WidgetInfoMut::new(WidgetIdent::Num(index as u32), index, row)) {
+                LoopFlow::Continue => (),
+                LoopFlow::Break => return
+            }
+        }
+    }
+
+    fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
+        self.rows.get(index).map(|row| WidgetInfo::new(WidgetIdent::Num(index as u32), index, row))
+    }
+    fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
+        self.rows.get_mut(index).map(|row| WidgetInfoMut::new(WidgetIdent::Num(index as u32), index, row))
+    }
+}
+
+impl Widget for ListRow {
+    #[inline]
+    fn widget_tag(&self) -> &WidgetTag {
+        &self.widget_tag
+    }
+
+    #[inline]
+    fn rect(&self) -> BoundBox<D2, i32> {
+        self.rect
+    }
+
+    #[inline]
+    fn rect_mut(&mut self) -> &mut BoundBox<D2, i32> {
+        &mut self.rect
+    }
+
+    fn size_bounds(&self) -> SizeBounds {
+        self.size_bounds
+    }
+
+    fn on_widget_event(&mut self, event: WidgetEventSourced, input_state: InputState) -> EventOps {
+        let event = event.unwrap();
+        let row_id = self.widget_tag.widget_id();
+        let mut focus = None;
+
+        let row_event = match event {
+            WidgetEvent::MouseDown{in_widget: true, button: MouseButton::Left, ..} => {
+                focus = Some(FocusChange::Parent);
+                Some(RowEvent::Press(row_id, input_state.modifiers))
+            },
+            WidgetEvent::MouseScrollLines{dir, ..} => Some(RowEvent::ScrollLines(dir.y)),
+            WidgetEvent::MouseScrollPx{dir, ..} => Some(RowEvent::ScrollPx(dir.y)),
+            _ => None
+        };
+        if let Some(row_event) = row_event {
+            self.widget_tag.send_message_to(row_event, MessageTarget::ParentOf(row_id));
+        }
+
+        EventOps {
+            focus,
+            bubble: event.default_bubble(),
+        }
+    }
+}
+
+impl<R, S, H> WidgetRenderable<R> for VirtualList<S, H>
+    where R: Renderer,
+          S: ListSource,
+          H: VirtualListHandler
+{
+    type Theme = VirtualListTheme;
+
+    fn theme(&self) -> VirtualListTheme {
+        VirtualListTheme(())
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        self.refresh_source();
+
+        let result = layout.finish();
+        let content_rect = result.content_rect;
+        self.size_bounds = result.size_bounds;
+        self.viewport_height = content_rect.height().max(0);
+
+        let total_height = self.offsets[self.num_items()];
+        if let Some(index) = self.scroll_to.take().filter(|&i| i < self.num_items()) {
+            let (item_top, item_bottom) = (self.offsets[index], self.offsets[index + 1]);
+            if item_top < self.scroll {
+                self.scroll = item_top;
+            } else if item_bottom > self.scroll + self.viewport_height {
+                self.scroll = item_bottom - self.viewport_height;
+            }
+        }
+        self.scroll = self.scroll.min(total_height - self.viewport_height).max(0);
+
+        let visible = self.visible_items();
+        self.recycle_rows(visible);
+
+        for row in &mut self.rows {
+            let y = content_rect.min.y + self.offsets[row.item] - self.scroll;
+            let height = self.offsets[row.item + 1] - self.offsets[row.item];
+            let rect = BoundBox::new2(content_rect.min.x, y, content_rect.max.x, y + height);
+            if row.rect != rect {
+                row.rect = rect;
+                row.widget_tag.request_relayout();
+            }
+        }
+    }
+}
+
+impl<R> WidgetRenderable<R> for ListRow
+    where R: Renderer
+{
+    type Theme = ListRowTheme;
+
+    fn theme(&self) -> ListRowTheme {
+        ListRowTheme {
+            selected: self.selected,
+        }
+    }
+
+    fn render(&mut self, frame: &mut R::SubFrame) {
+        frame.render_laid_out_content();
+    }
+
+    fn update_layout(&mut self, layout: &mut R::Layout) {
+        match self.contents {
+            Contents::Text(ref s) => layout.prepare_string(s),
+            Contents::Icon(ref i) => layout.prepare_icon(i),
+        }
+
+        let result = layout.finish();
+        self.size_bounds = result.size_bounds;
+    }
+}
+
+impl WidgetTheme for VirtualListTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for VirtualListTheme {
+    fn theme_path(&self) -> &'static str {
+        "VirtualList::Background"
+    }
+}
+
+impl WidgetTheme for ListRowTheme {
+    type Fallback = !;
+    fn fallback(self) -> Option<!> {
+        None
+    }
+}
+
+impl ThemePath for ListRowTheme {
+    fn theme_path(&self) -> &'static str {
+        match self.selected {
+            true => "VirtualList::Row::Selected",
+            false => "VirtualList::Row::Normal",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::WindowEvent;
+    use crate::cgmath::Point2;
+    use crate::soft_render::snapshot::Snapshot;
+    use crate::theme::Theme;
+    use cgmath_geometry::rect::DimsBox;
+
+    struct Numbers(usize);
+    impl ListSource for Numbers {
+        fn num_items(&self) -> usize {
+            self.0
+        }
+
+        fn item_height(&self, _: usize) -> i32 {
+            20
+        }
+
+        fn contents(&self, index: usize) -> Contents {
+            Contents::Text(index.to_string())
+        }
+    }
+
+    #[derive(Default)]
+    struct SelectLog(Vec<Vec<usize>>);
+    impl VirtualListHandler for SelectLog {
+        type Action = ();
+        fn on_select(&mut self, selection: &[usize]) -> Option<()> {
+            self.0.push(selection.to_vec());
+            None
+        }
+    }
+
+    fn row_ids<S: ListSource, H: VirtualListHandler>(list: &VirtualList<S, H>) -> Vec<WidgetId> {
+        let mut ids = list.rows.iter().map(|r| r.widget_tag.widget_id()).collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn recycle_rows_keeps_focus() {
+        let list = VirtualList::new(Numbers(1000), SelectionMode::Single, SelectLog::default());
+        let mut snapshot = Snapshot::new(list, Theme::default(), DimsBox::new2(100, 100));
+        snapshot.render();
+
+        let rows_before = row_ids(snapshot.root());
+        assert!(rows_before.len() < 20);
+
+        // Clicking a row focuses the list and selects the row's item.
+        snapshot.process_events(vec![
+            WindowEvent::MouseEnter,
+            WindowEvent::MouseMove(Point2::new(50, 30)),
+            WindowEvent::MouseDown(MouseButton::Left),
+            WindowEvent::MouseUp(MouseButton::Left),
+        ]);
+        assert_eq!(&[1], snapshot.root().selection());
+
+        // Jumping to the end scrolls the list, which re-uses the existing rows for the last items.
+        snapshot.process_events(vec![WindowEvent::KeyDown(Key::End), WindowEvent::KeyUp(Key::End)]);
+        snapshot.render();
+        assert_eq!(&[999], snapshot.root().selection());
+        assert_eq!(20 * 1000 - snapshot.root().viewport_height, snapshot.root().scroll());
+        assert!(snapshot.root().rows.iter().all(|r| r.item >= 990));
+        assert!(row_ids(snapshot.root()).iter().all(|id| rows_before.contains(id)));
+
+        // The list is still focused after its rows got recycled, so it keeps handling keys.
+        snapshot.process_events(vec![WindowEvent::KeyDown(Key::UArrow), WindowEvent::KeyUp(Key::UArrow)]);
+        assert_eq!(&[998], snapshot.root().selection());
+        assert_eq!(vec![vec![1], vec![999], vec![998]], snapshot.root().handler.0);
+    }
+}
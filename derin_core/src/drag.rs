@@ -0,0 +1,68 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Dragging data from one widget and dropping it onto another.
+//!
+//! A widget starts a drag with `WidgetTag::start_drag`, handing over a payload of any type. While
+//! the drag is active, the widget under the cursor gets `DragEnter`, `DragOver`, and `DragLeave`
+//! events carrying the payload's type, and accepts the payload by calling `WidgetTag::accept_drag`
+//! while handling `DragEnter` or `DragOver`. If it doesn't accept the payload, the drag gets offered
+//! to its parent, and so on up the tree. The cursor shows `CursorIcon::NotAllowed` while nothing
+//! accepts the payload.
+//!
+//! Releasing a mouse button over an accepting widget sends that widget a `Drop` event, followed by
+//! the payload as a message, which it receives with `WidgetTag::register_message`. Releasing the
+//! button anywhere else or pressing Escape cancels the drag. Either way, the widget that started
+//! the drag gets sent a `DragFinished` message.
+
+use crate::{
+    cgmath::Vector2,
+    widget::WidgetId,
+};
+use std::any::{Any, TypeId};
+
+/// A widget drawn under the cursor while dragging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DragImage {
+    /// The widget to draw. Like an overlay, this must be a descendant of the widget starting the
+    /// drag, and it escapes its parent's clipping rectangle while the drag is active.
+    pub widget: WidgetId,
+    /// The position of the image's top-left corner, relative to the cursor.
+    pub offset: Vector2<i32>,
+}
+
+/// Message sent to the widget that started a drag once the drag ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DragFinished {
+    /// The widget the payload got dropped on, or `None` if the drag was cancelled.
+    pub target: Option<WidgetId>,
+}
+
+#[derive(Debug)]
+pub(crate) struct DragState {
+    pub source: WidgetId,
+    pub payload: Box<Any>,
+    pub payload_type: TypeId,
+    pub image: Option<DragImage>,
+    /// The widgets the drag has entered, from the deepest widget up. The last widget is the one
+    /// that accepted the payload, if any did.
+    pub entered: Vec<WidgetId>,
+    pub target: Option<WidgetId>,
+    /// Whether the widget the drag is being offered to has accepted it.
+    pub accepted: bool,
+}
+
+impl DragState {
+    pub fn new(source: WidgetId, payload: Box<Any>, image: Option<DragImage>) -> DragState {
+        DragState {
+            source,
+            payload_type: (*payload).type_id(),
+            payload,
+            image,
+            entered: Vec::new(),
+            target: None,
+            accepted: false,
+        }
+    }
+}
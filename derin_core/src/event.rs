@@ -9,7 +9,10 @@ use crate::{
     widget::{WidgetIdent},
 };
 
-use std::time::{Instant, Duration};
+use std::{
+    any::TypeId,
+    time::{Instant, Duration},
+};
 
 /// The set of operations to be performed after an event is processed by a widget.
 #[derive(Default)]
@@ -80,6 +83,8 @@ pub enum FocusSource {
         ident: WidgetIdent,
         delta: isize
     },
+    /// Focus was moved with the Tab key, or with Shift+Tab.
+    Traversal,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -112,6 +117,8 @@ pub enum WidgetEventSourced<'a> {
 /// * When the widget has recieved keyboard focus, all user input events are delivered.
 /// * When the given amount of time has passed from a timer registered in `register_timers`, a
 ///  `Timer` event is delivered.
+/// * When a drag is being offered to the widget or one of its children, drag events are
+///   delivered.
 ///
 /// All point coordinates are given relative to the widget's origin.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -194,6 +201,31 @@ pub enum WidgetEvent {
         /// The number of times this timer has been triggered, not including this trigger.
         times_triggered: u32
     },
+    /// A drag started with `WidgetTag::start_drag` has moved over the widget.
+    ///
+    /// Call `WidgetTag::accept_drag` while handling this to accept the payload. Otherwise, the
+    /// drag gets offered to the widget's parent.
+    DragEnter {
+        /// The position of the cursor.
+        pos: Point2<i32>,
+        /// The type of the dragged payload.
+        payload_type: TypeId,
+    },
+    /// An active drag has moved within the widget. Like with `DragEnter`, the widget must call
+    /// `WidgetTag::accept_drag` to keep accepting the payload.
+    DragOver {
+        pos: Point2<i32>,
+        payload_type: TypeId,
+    },
+    /// An active drag has moved out of the widget, been accepted by one of the widget's children,
+    /// or been cancelled.
+    DragLeave,
+    /// The payload of an active drag has been dropped on the widget. The payload itself gets sent
+    /// to the widget as a message immediately afterwards.
+    Drop {
+        pos: Point2<i32>,
+        payload_type: TypeId,
+    },
 }
 
 impl WidgetEventSourced<'_> {
@@ -240,7 +272,11 @@ impl WidgetEvent {
             WidgetEvent::MouseMove{..} |
             WidgetEvent::MouseDown{..} |
             WidgetEvent::MouseUp{..} |
-            WidgetEvent::Timer{..} => false
+            WidgetEvent::Timer{..} |
+            WidgetEvent::DragEnter{..} |
+            WidgetEvent::DragOver{..} |
+            WidgetEvent::DragLeave |
+            WidgetEvent::Drop{..} => false
         }
     }
 
@@ -263,12 +299,19 @@ impl WidgetEvent {
                     down_pos: down_pos + dir,
                     in_widget, pressed_in_widget, button,
                 },
+            WidgetEvent::DragEnter{ pos, payload_type } =>
+                WidgetEvent::DragEnter{ pos: pos + dir, payload_type },
+            WidgetEvent::DragOver{ pos, payload_type } =>
+                WidgetEvent::DragOver{ pos: pos + dir, payload_type },
+            WidgetEvent::Drop{ pos, payload_type } =>
+                WidgetEvent::Drop{ pos: pos + dir, payload_type },
             WidgetEvent::Char(..)              |
             WidgetEvent::LoseFocus             |
             WidgetEvent::GainFocus(..)         |
             WidgetEvent::Timer{..}             |
             WidgetEvent::KeyUp(..)             |
             WidgetEvent::KeyDown(..)           |
+            WidgetEvent::DragLeave             |
             WidgetEvent::MouseScrollPx{..}     |
             WidgetEvent::MouseScrollLines{..} =>
                 self
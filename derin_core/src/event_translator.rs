@@ -3,18 +3,28 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 mod dispatcher;
+mod tab_order;
 
 use crate::{
     WindowEvent, InputState, LoopFlow,
-    cgmath::{Vector2},
+    cgmath::{Point2, Vector2, EuclideanSpace},
+    drag::DragFinished,
     event::{EventOps, FocusChange, FocusSource, MouseHoverChange, WidgetEvent, WidgetEventSourced},
+    message_bus::{MessageTarget, MessageTargeted},
+    overlay::{OverlayDismissed, OverlayLayer},
     render::Renderer,
+    shortcut::{KeyChord, ShortcutMatch, ShortcutRegistry},
+    widget::WidgetId,
     widget_traverser::{Relation, WidgetTraverser, OffsetWidgetScanPath},
     update_state::{UpdateStateCell},
     offset_widget::OffsetWidget,
 };
 use self::dispatcher::{EventDispatcher, EventDestination, DispatchableEvent};
-use cgmath_geometry::rect::{GeoBox, BoundBox};
+use cgmath_geometry::{D2, rect::{GeoBox, BoundBox}};
+use derin_common_types::{
+    buttons::{Key, ModifierKeys},
+    cursor::CursorIcon,
+};
 use std::rc::Rc;
 
 pub(crate) struct EventTranslator
@@ -29,6 +39,7 @@ pub(crate) struct TranslatorActive<'a, 'b, R>
     inner: &'a mut TranslatorInner,
     input_state: &'a mut InputState,
     update_state: Rc<UpdateStateCell>,
+    shortcuts: &'a mut ShortcutRegistry,
 }
 
 struct TranslatorInner {
@@ -50,12 +61,14 @@ impl EventTranslator
         widget_traverser: &'a mut WidgetTraverser<'b, R>,
         input_state: &'a mut InputState,
         update_state: Rc<UpdateStateCell>,
+        shortcuts: &'a mut ShortcutRegistry,
     ) -> TranslatorActive<'a, 'b, R> {
         TranslatorActive {
             widget_traverser,
             inner: &mut self.inner,
             input_state,
             update_state,
+            shortcuts,
         }
     }
 }
@@ -71,6 +84,7 @@ impl<R> TranslatorActive<'_, '_, R>
             ref mut inner,
             input_state,
             ref update_state,
+            ref mut shortcuts,
         } = self;
         let TranslatorInner {
             ref mut event_dispatcher,
@@ -78,30 +92,12 @@ impl<R> TranslatorActive<'_, '_, R>
 
         let root_id = widget_traverser.root_id();
         let mut root_widget_rect = || widget_traverser.get_widget(root_id).unwrap().widget.rect();
-        let mut project_to_outside_root = |point| {
-            let root_rect = root_widget_rect();
-            let border_point = root_rect.nearest_points(point).next().unwrap();
-
-            let mut diff = Vector2::new(0, 0);
-            if border_point != point {
-                diff = (border_point - point).map(|i| i.signum());
-            } else {
-                if border_point.x == root_rect.min.x {
-                    diff.x = -1;
-                }
-                if border_point.x == root_rect.max.x {
-                    diff.x = 1;
-                }
-                if border_point.y == root_rect.min.y {
-                    diff.y = -1;
-                }
-                if border_point.y == root_rect.max.y {
-                    diff.y = 1;
-                }
-            }
+        let mut project_to_outside_root = |point| project_to_outside(root_widget_rect(), point);
 
-            border_point + diff
-        };
+        let overlays = update_state.borrow().overlays.clone();
+        // The drag image follows the cursor around, so it can't be hovered over either.
+        let drag_image = update_state.borrow().drag.as_ref().and_then(|drag| drag.image).map(|image| image.widget);
+        let overlay_ids = overlays.iter().map(|layer| layer.widget_id).chain(drag_image).collect::<Vec<_>>();
 
         let mouse_event_widget_iter =
             input_state.mouse_buttons_down
@@ -116,18 +112,57 @@ impl<R> TranslatorActive<'_, '_, R>
                     .unwrap_or_else(|| project_to_outside_root(new_pos));
                 input_state.mouse_pos = Some(new_pos);
 
-                let hover_widget_id = input_state.mouse_hover_widget
-                    .unwrap_or(widget_traverser.root_id());
+                let old_layer = input_state.mouse_hover_layer;
+                let new_layer = layer_at(widget_traverser, &overlays, new_pos);
+                input_state.mouse_hover_layer = new_layer;
+
+                let hover_widget_id = input_state.mouse_hover_widget.or(old_layer);
+                if old_layer == new_layer {
+                    if let (Some(hover_widget_id), Some(layer)) = (hover_widget_id, new_layer) {
+                        event_dispatcher.queue_event(
+                            EventDestination::Widget(hover_widget_id),
+                            DispatchableEvent::MouseMove {
+                                old_pos, new_pos,
+                                exiting_from_child: None,
+                                layer,
+                            }
+                        );
+                    }
+                } else {
+                    // Move the mouse out of the old layer and into the new one, as if the layers
+                    // weren't overlapping.
+                    if let (Some(old_layer), Some(hover_widget_id)) = (old_layer, hover_widget_id) {
+                        if let Some(layer_rect) = widget_traverser.get_widget(old_layer).map(|path| path.widget.rect()) {
+                            event_dispatcher.queue_event(
+                                EventDestination::Widget(hover_widget_id),
+                                DispatchableEvent::MouseMove {
+                                    old_pos,
+                                    new_pos: move_outside(layer_rect, new_pos),
+                                    exiting_from_child: None,
+                                    layer: old_layer,
+                                }
+                            );
+                        }
+                    }
+                    input_state.mouse_hover_widget = None;
 
-                event_dispatcher.queue_event(
-                    EventDestination::Widget(hover_widget_id),
-                    DispatchableEvent::MouseMove {
-                        old_pos, new_pos,
-                        exiting_from_child: None,
+                    if let Some(new_layer) = new_layer {
+                        if let Some(layer_rect) = widget_traverser.get_widget(new_layer).map(|path| path.widget.rect()) {
+                            event_dispatcher.queue_event(
+                                EventDestination::Widget(new_layer),
+                                DispatchableEvent::MouseMove {
+                                    old_pos: move_outside(layer_rect, old_pos),
+                                    new_pos,
+                                    exiting_from_child: None,
+                                    layer: new_layer,
+                                }
+                            );
+                        }
                     }
-                );
+                }
 
-                for widget_id in mouse_event_widget_iter.filter(|id| *id != hover_widget_id) {
+                let hover_widget_id = hover_widget_id.or(new_layer);
+                for widget_id in mouse_event_widget_iter.filter(|id| Some(*id) != hover_widget_id) {
                     event_dispatcher.queue_direct_event(
                         widget_id,
                         WidgetEvent::MouseMove {
@@ -157,6 +192,38 @@ impl<R> TranslatorActive<'_, '_, R>
             }
             MouseDown(mouse_button) => try {
                 let mouse_pos = input_state.mouse_pos?;
+                dismiss_input_overlays(update_state, &overlays);
+
+                // Dismiss the overlays the mouse was pressed outside of, from the top down.
+                let mut dismissed_overlay = false;
+                for layer in overlays.iter().rev().filter(|l| !l.overlay.dismiss_on_input) {
+                    if overlay_contains(widget_traverser, layer, mouse_pos) {
+                        break;
+                    }
+                    if !layer.overlay.dismiss_on_click_outside {
+                        match layer.overlay.modal {
+                            true => break,
+                            false => continue
+                        }
+                    }
+
+                    dismissed_overlay = true;
+                    let mut update_state = update_state.borrow_mut();
+                    update_state.overlays.retain(|l| l.widget_id != layer.widget_id);
+                    update_state.queue_global_update();
+                    update_state.message_sender.send(MessageTargeted {
+                        message: Box::new(OverlayDismissed{ overlay: layer.widget_id }),
+                        target: Some(MessageTarget::Widget(layer.owner)),
+                    }).ok();
+                }
+                if dismissed_overlay {
+                    // Clicks that dismiss an overlay don't get delivered to anything beneath it.
+                    // The mouse may have been hovering over a dismissed overlay, so find the
+                    // widget it's hovering over now.
+                    self.translate_window_event(WindowEvent::MouseMove(mouse_pos));
+                    return;
+                }
+
                 let hover_widget_id = input_state.mouse_hover_widget?;
 
                 event_dispatcher.queue_direct_event(
@@ -243,35 +310,82 @@ impl<R> TranslatorActive<'_, '_, R>
                 widget_traverser.get_widget(root_id).unwrap().widget.set_rect(BoundBox::new2(0, 0, size.dims.x as i32, size.dims.y as i32));
                 update_state.borrow_mut().queue_global_update();
             },
+            // Escape cancels an active drag instead of getting delivered to the focused widget.
+            KeyDown(Key::Escape) if update_state.borrow().drag.is_some() => try {
+                finish_drag(widget_traverser, update_state, input_state, false);
+            },
             KeyDown(key) => try {
                 if !input_state.keys_down.contains(&key) {
                     input_state.keys_down.push(key);
-                    match input_state.focused_widget {
-                        Some(widget) => event_dispatcher.queue_direct_event(
-                            widget,
-                            WidgetEvent::KeyDown(key, input_state.modifiers),
-                        ),
-                        None => println!("dispatch to universal fallthrough")
+                    dismiss_input_overlays(update_state, &overlays);
+
+                    // Shortcuts take priority over the focused widget. Scoped shortcuts are only
+                    // active if the focused widget is in their subtree.
+                    let mut focus_path = Vec::new();
+                    if let Some(widget) = input_state.focused_widget.filter(|&w| !blocked_by_modal(widget_traverser, &overlays, w)) {
+                        focus_path.push(widget);
+                        while let Some(parent) = widget_traverser.parent_id(*focus_path.last().unwrap()) {
+                            focus_path.push(parent);
+                        }
+                    }
+                    let shortcut_handled = match shortcuts.key_down(KeyChord::new(key, input_state.modifiers), &focus_path) {
+                        ShortcutMatch::None => false,
+                        ShortcutMatch::Pending => true,
+                        ShortcutMatch::Triggered(message) => {
+                            update_state.borrow().message_sender.send(message).ok();
+                            true
+                        }
+                    };
+
+                    let is_tab = key == Key::Tab && (input_state.modifiers - ModifierKeys::SHIFT).is_empty();
+                    if !shortcut_handled && is_tab {
+                        // Tab and Shift+Tab move focus through the tab stops.
+                        let reverse = input_state.modifiers.contains(ModifierKeys::SHIFT);
+                        let target = tab_order::next_tab_stop(widget_traverser, &overlays, input_state.focused_widget, reverse);
+                        if let Some(target) = target {
+                            event_dispatcher.queue_event(
+                                EventDestination::Widget(target),
+                                DispatchableEvent::GainFocus {
+                                    source: FocusSource::Traversal,
+                                    change: match reverse {
+                                        false => FocusChange::Next,
+                                        true => FocusChange::Prev
+                                    }
+                                }
+                            );
+                        }
+                    } else if !shortcut_handled {
+                        match input_state.focused_widget {
+                            Some(widget) if !blocked_by_modal(widget_traverser, &overlays, widget) => event_dispatcher.queue_direct_event(
+                                widget,
+                                WidgetEvent::KeyDown(key, input_state.modifiers),
+                            ),
+                            Some(_) => (),
+                            None => println!("dispatch to universal fallthrough")
+                        }
                     }
                 }
             },
             KeyUp(key) => try {
                 if crate::vec_remove_element(&mut input_state.keys_down, &key).is_some() {
                     match input_state.focused_widget {
-                        Some(widget) => event_dispatcher.queue_direct_event(
+                        Some(widget) if !blocked_by_modal(widget_traverser, &overlays, widget) => event_dispatcher.queue_direct_event(
                             widget,
                             WidgetEvent::KeyUp(key, input_state.modifiers),
                         ),
+                        Some(_) => (),
                         None => println!("dispatch to universal fallthrough")
                     }
                 }
             },
             Char(c) => try {
+                dismiss_input_overlays(update_state, &overlays);
                 match input_state.focused_widget {
-                    Some(widget) => event_dispatcher.queue_direct_event(
+                    Some(widget) if !blocked_by_modal(widget_traverser, &overlays, widget) => event_dispatcher.queue_direct_event(
                         widget,
                         WidgetEvent::Char(c),
                     ),
+                    Some(_) => (),
                     None => println!("dispatch to universal fallthrough")
                 }
             },
@@ -289,9 +403,11 @@ impl<R> TranslatorActive<'_, '_, R>
                 // Helper function that takes the `EventOps` generated by `on_widget_event`, updates
                 // the input state, and queues more events as necessary.
                 let mut perform_event_ops = |ops| {
+                    // Bubbling is handled when dispatching `Direct` events, since that's the only
+                    // place the original event is still around.
                     let EventOps {
                         focus,
-                        bubble,
+                        bubble: _,
                     } = ops;
                     if let Some(focus) = focus {
                         let of = widget_id;
@@ -344,21 +460,24 @@ impl<R> TranslatorActive<'_, '_, R>
                     // We handle `MouseMove` events differently than all other events because
                     // `MouseMove` can trigger other `MouseMove`s if the mouse moves into a child
                     // or parent widget.
-                    DispatchableEvent::MouseMove{old_pos, new_pos, exiting_from_child} => {
+                    DispatchableEvent::MouseMove{old_pos, new_pos, exiting_from_child, layer} => {
                         let widget_rect = match widget.rect_clipped() {
                             Some(rect) => rect,
                             None => return
                         };
-                        let (contains_new, contains_old) = (widget_rect.contains(new_pos), widget_rect.contains(old_pos));
+                        // Hidden widgets can't be hit by the cursor, so moving over one moves the
+                        // cursor out of it.
+                        let (contains_new, contains_old) = (widget.visible() && widget_rect.contains(new_pos), widget_rect.contains(old_pos));
 
                         let mut send_exiting_from_child = |widget: &mut OffsetWidget<'_, R>, in_widget| {
                             if let Some(child_ident) = exiting_from_child.clone() {
-                                perform_event_ops(widget.on_widget_event(
-                                    WidgetEventSourced::This(WidgetEvent::MouseMove {
+                                perform_event_ops(send_event(
+                                    widget,
+                                    WidgetEvent::MouseMove {
                                         old_pos, new_pos,
                                         in_widget,
                                         hover_change: Some(MouseHoverChange::ExitChild(child_ident)),
-                                    }),
+                                    },
                                     input_state,
                                 ));
                             }
@@ -368,7 +487,14 @@ impl<R> TranslatorActive<'_, '_, R>
                             true => {
                                 let mut enter_child_opt = None;
                                 widget.children_mut(|child_summary| {
-                                    if child_summary.widget.rect_clipped().map(|r| r.contains(new_pos)).unwrap_or(false) {
+                                    // Overlays are in their own layers, so the mouse can't move
+                                    // into them from their parents.
+                                    if overlay_ids.contains(&child_summary.widget.widget_id()) {
+                                        return LoopFlow::Continue;
+                                    }
+                                    let hit = child_summary.widget.visible() &&
+                                        child_summary.widget.rect_clipped().map(|r| r.contains(new_pos)).unwrap_or(false);
+                                    if hit {
                                         enter_child_opt = Some((child_summary.widget.widget_id(), child_summary.ident));
                                         LoopFlow::Break
                                     } else {
@@ -379,24 +505,26 @@ impl<R> TranslatorActive<'_, '_, R>
                                 send_exiting_from_child(&mut widget, contains_new && enter_child_opt.is_none());
 
                                 if !contains_old {
-                                    perform_event_ops(widget.on_widget_event(
-                                        WidgetEventSourced::This(WidgetEvent::MouseMove {
+                                    perform_event_ops(send_event(
+                                        &mut widget,
+                                        WidgetEvent::MouseMove {
                                             old_pos, new_pos,
                                             in_widget: enter_child_opt.is_none(),
                                             hover_change: Some(MouseHoverChange::Enter)
-                                        }),
+                                        },
                                         input_state,
                                     ));
                                 }
 
                                 match enter_child_opt {
                                     Some((enter_child_id, enter_child_ident)) => {
-                                        perform_event_ops(widget.on_widget_event(
-                                            WidgetEventSourced::This(WidgetEvent::MouseMove {
+                                        perform_event_ops(send_event(
+                                            &mut widget,
+                                            WidgetEvent::MouseMove {
                                                 old_pos, new_pos,
                                                 in_widget: false,
                                                 hover_change: Some(MouseHoverChange::EnterChild(enter_child_ident))
-                                            }),
+                                            },
                                             input_state,
                                         ));
                                         event_dispatcher.queue_event(
@@ -404,17 +532,19 @@ impl<R> TranslatorActive<'_, '_, R>
                                             DispatchableEvent::MouseMove {
                                                 old_pos, new_pos,
                                                 exiting_from_child: None,
+                                                layer,
                                             }
                                         );
                                     },
                                     None => {
                                         if contains_old && exiting_from_child.is_none() {
-                                            perform_event_ops(widget.on_widget_event(
-                                                WidgetEventSourced::This(WidgetEvent::MouseMove {
+                                            perform_event_ops(send_event(
+                                                &mut widget,
+                                                WidgetEvent::MouseMove {
                                                     old_pos, new_pos,
                                                     in_widget: enter_child_opt.is_none(),
                                                     hover_change: None
-                                                }),
+                                                },
                                                 input_state,
                                             ));
                                         }
@@ -425,25 +555,44 @@ impl<R> TranslatorActive<'_, '_, R>
                             false => {
                                 send_exiting_from_child(&mut widget, contains_new);
 
-                                perform_event_ops(widget.on_widget_event(
-                                    WidgetEventSourced::This(WidgetEvent::MouseMove {
+                                perform_event_ops(send_event(
+                                    &mut widget,
+                                    WidgetEvent::MouseMove {
                                         old_pos, new_pos,
                                         in_widget: false,
                                         hover_change: Some(MouseHoverChange::Exit),
-                                    }),
+                                    },
                                     input_state,
                                 ));
-                                event_dispatcher.queue_event(
-                                    EventDestination::Relation(widget_id, Relation::Parent),
-                                    DispatchableEvent::MouseMove {
-                                        old_pos, new_pos,
-                                        exiting_from_child: Some(path.last().cloned().unwrap()),
-                                    }
-                                );
+                                // Don't move the mouse out of the layer the event started in.
+                                if widget_id != layer {
+                                    event_dispatcher.queue_event(
+                                        EventDestination::Relation(widget_id, Relation::Parent),
+                                        DispatchableEvent::MouseMove {
+                                            old_pos, new_pos,
+                                            exiting_from_child: Some(path.last().cloned().unwrap()),
+                                            layer,
+                                        }
+                                    );
+                                }
                             }
                         }
                     },
-                    DispatchableEvent::GainFocus{source, change} => if input_state.focused_widget != Some(widget_id) {
+                    // Disabled widgets can't take focus. Moving focus to the next or previous
+                    // sibling skips over them instead.
+                    DispatchableEvent::GainFocus{source, change} => if !widget.enabled() {
+                        match (change.clone(), source) {
+                            (FocusChange::Next, FocusSource::Sibling{ident, delta}) |
+                            (FocusChange::Prev, FocusSource::Sibling{ident, delta}) => event_dispatcher.queue_event(
+                                EventDestination::Relation(widget_id, Relation::Sibling(-delta.signum())),
+                                DispatchableEvent::GainFocus {
+                                    source: FocusSource::Sibling{ident, delta: delta + delta.signum()},
+                                    change
+                                }
+                            ),
+                            _ => ()
+                        }
+                    } else if input_state.focused_widget != Some(widget_id) {
                         if let Some(focused_widget_id) = input_state.focused_widget {
                             event_dispatcher.queue_direct_event(
                                 focused_widget_id,
@@ -456,54 +605,311 @@ impl<R> TranslatorActive<'_, '_, R>
                         );
                     },
                     DispatchableEvent::Direct{bubble_source, event} => {
-                        if bubble_source.is_some() {
-                            unimplemented!()
-                        } else {
-                            match event {
-                                WidgetEvent::LoseFocus =>
-                                    input_state.focused_widget = None,
-                                WidgetEvent::GainFocus(..) =>
-                                    input_state.focused_widget= Some(widget_id),
-                                _ => ()
+                        // If the focused widget has been disabled, take focus away from it the next
+                        // time it would've gotten an event.
+                        let event = match widget.enabled() || input_state.focused_widget != Some(widget_id) {
+                            true => event,
+                            false => WidgetEvent::LoseFocus
+                        };
+                        let ops = match bubble_source {
+                            Some(ref source_path) => match widget.enabled() {
+                                true => widget.on_widget_event(
+                                    WidgetEventSourced::Bubble(event.clone(), source_path),
+                                    input_state,
+                                ),
+                                false => EventOps {
+                                    focus: None,
+                                    bubble: false,
+                                }
+                            },
+                            None => {
+                                match event {
+                                    WidgetEvent::LoseFocus => {
+                                        input_state.focused_widget = None;
+                                        if input_state.focus_visible {
+                                            update_state.borrow_mut().redraw.insert(widget_id);
+                                        }
+                                    },
+                                    WidgetEvent::GainFocus(ref source, ref change) => {
+                                        input_state.focused_widget = Some(widget_id);
+                                        // Only show the focus ring if focus was moved with the keyboard.
+                                        input_state.focus_visible = match (source, change) {
+                                            (FocusSource::Traversal, _) |
+                                            (_, FocusChange::Next) |
+                                            (_, FocusChange::Prev) => true,
+                                            _ => false
+                                        };
+                                        if input_state.focus_visible {
+                                            let mut update_state = update_state.borrow_mut();
+                                            update_state.redraw.insert(widget_id);
+                                            // Clicked widgets are already on screen, but keyboard focus
+                                            // can land on widgets that have been scrolled out of view.
+                                            let focus_rect = widget.focus_rect() - widget.rect().min().to_vec();
+                                            update_state.scroll_into_view.push((widget_id, Some(focus_rect)));
+                                        }
+                                    },
+                                    _ => ()
+                                }
+                                send_event(&mut widget, event.clone(), input_state)
                             }
+                        };
+                        let bubble = ops.bubble;
+                        perform_event_ops(ops);
+                        if bubble {
+                            let mut parent_source = vec![widget_ident.clone()];
+                            parent_source.extend(bubble_source.into_iter().flatten());
+                            event_dispatcher.queue_event(
+                                EventDestination::Relation(widget_id, Relation::Parent),
+                                DispatchableEvent::Direct {
+                                    bubble_source: Some(parent_source),
+                                    event,
+                                }
+                            );
                         }
-                        perform_event_ops(widget.on_widget_event(
-                            WidgetEventSourced::This(event),
-                            input_state,
-                        ));
                     }
                 }
             }
         );
+
+        // The drag gets offered to the widgets under the cursor after the mouse events have been
+        // dispatched, so the hovered widget is up-to-date.
+        match window_event {
+            MouseMove(_) => update_drag(widget_traverser, update_state, input_state),
+            MouseUp(_) => finish_drag(widget_traverser, update_state, input_state, true),
+            _ => ()
+        }
+    }
+}
+
+/// Deliver `event` to `widget`. Disabled widgets don't get any events, aside from being told
+/// they've lost focus.
+fn send_event<R: Renderer>(widget: &mut OffsetWidget<'_, R>, event: WidgetEvent, input_state: &InputState) -> EventOps {
+    match widget.enabled() || event == WidgetEvent::LoseFocus {
+        true => widget.on_widget_event(WidgetEventSourced::This(event), input_state),
+        false => EventOps {
+            focus: None,
+            bubble: false,
+        }
+    }
+}
+
+/// Deliver a drag event to the given widget, and return whether the widget accepted the drag.
+fn send_drag_event<R: Renderer>(
+    widget_traverser: &mut WidgetTraverser<'_, R>,
+    update_state: &UpdateStateCell,
+    input_state: &InputState,
+    widget_id: WidgetId,
+    event: WidgetEvent
+) -> bool {
+    if let Some(ref mut drag) = update_state.borrow_mut().drag {
+        drag.accepted = false;
+    }
+    if let Some(OffsetWidgetScanPath{mut widget, ..}) = widget_traverser.get_widget(widget_id) {
+        // Drag events can't move focus, so the `EventOps` get ignored.
+        let _ = send_event(&mut widget, event, input_state);
+    }
+    update_state.borrow().drag.as_ref().map(|drag| drag.accepted).unwrap_or(false)
+}
+
+/// Offer the active drag to the widget under the cursor, then to its ancestors until one of them
+/// accepts it.
+fn update_drag<R: Renderer>(widget_traverser: &mut WidgetTraverser<'_, R>, update_state: &UpdateStateCell, input_state: &InputState) {
+    let (payload_type, entered, was_accepted, image) = match update_state.borrow().drag {
+        Some(ref drag) => (drag.payload_type, drag.entered.clone(), drag.target.is_some(), drag.image),
+        None => return
+    };
+
+    // Reset the cursor before offering the drag, so accepting widgets can set their own.
+    if !was_accepted {
+        update_state.borrow_mut().set_cursor_icon = Some(CursorIcon::Pointer);
+    }
+
+    let mut offered = Vec::new();
+    let mut target = None;
+    if let Some(pos) = input_state.mouse_pos {
+        let mut candidate = input_state.mouse_hover_widget;
+        while let Some(widget_id) = candidate {
+            offered.push(widget_id);
+            let event = match entered.contains(&widget_id) {
+                true => WidgetEvent::DragOver{pos, payload_type},
+                false => WidgetEvent::DragEnter{pos, payload_type}
+            };
+            if send_drag_event(widget_traverser, update_state, input_state, widget_id, event) {
+                target = Some(widget_id);
+                break;
+            }
+            candidate = widget_traverser.parent_id(widget_id);
+        }
+    }
+    for &widget_id in entered.iter().filter(|id| !offered.contains(id)) {
+        send_drag_event(widget_traverser, update_state, input_state, widget_id, WidgetEvent::DragLeave);
+    }
+
+    let mut update_state = update_state.borrow_mut();
+    if target.is_none() {
+        update_state.set_cursor_icon = Some(CursorIcon::NotAllowed);
+    }
+    if let Some(image) = image {
+        update_state.redraw.insert(image.widget);
+    }
+    if let Some(ref mut drag) = update_state.drag {
+        drag.entered = offered;
+        drag.target = target;
+    }
+}
+
+/// End the active drag. If `drop_payload` is `true`, the payload gets dropped on the widget that
+/// accepted it. Otherwise, the drag gets cancelled.
+fn finish_drag<R: Renderer>(widget_traverser: &mut WidgetTraverser<'_, R>, update_state: &UpdateStateCell, input_state: &InputState, drop_payload: bool) {
+    let drag = match update_state.borrow_mut().drag.take() {
+        Some(drag) => drag,
+        None => return
+    };
+    let drop_pos = input_state.mouse_pos.filter(|_| drop_payload);
+    let target = drag.target.filter(|_| drop_pos.is_some());
+
+    for &widget_id in drag.entered.iter().filter(|&&id| Some(id) != target) {
+        send_drag_event(widget_traverser, update_state, input_state, widget_id, WidgetEvent::DragLeave);
+    }
+    if let (Some(target), Some(pos)) = (target, drop_pos) {
+        send_drag_event(widget_traverser, update_state, input_state, target, WidgetEvent::Drop{pos, payload_type: drag.payload_type});
+        update_state.borrow().message_sender.send(MessageTargeted {
+            message: drag.payload,
+            target: Some(MessageTarget::Widget(target)),
+        }).ok();
+    }
+
+    let mut update_state = update_state.borrow_mut();
+    update_state.message_sender.send(MessageTargeted {
+        message: Box::new(DragFinished{ target }),
+        target: Some(MessageTarget::Widget(drag.source)),
+    }).ok();
+    update_state.set_cursor_icon = Some(CursorIcon::Pointer);
+    if drag.image.is_some() {
+        // Move the drag image back to where its parent put it.
+        update_state.queue_global_update();
+    }
+}
+
+/// Move `point` to just outside of `rect`, moving it as little as possible.
+fn project_to_outside(rect: BoundBox<D2, i32>, point: Point2<i32>) -> Point2<i32> {
+    let border_point = rect.nearest_points(point).next().unwrap();
+
+    let mut diff = Vector2::new(0, 0);
+    if border_point != point {
+        diff = (border_point - point).map(|i| i.signum());
+    } else {
+        if border_point.x == rect.min.x {
+            diff.x = -1;
+        }
+        if border_point.x == rect.max.x {
+            diff.x = 1;
+        }
+        if border_point.y == rect.min.y {
+            diff.y = -1;
+        }
+        if border_point.y == rect.max.y {
+            diff.y = 1;
+        }
+    }
+
+    border_point + diff
+}
+
+/// Move `point` outside of `rect` if it's inside of it.
+fn move_outside(rect: BoundBox<D2, i32>, point: Point2<i32>) -> Point2<i32> {
+    match rect.contains(point) {
+        true => project_to_outside(rect, point),
+        false => point
     }
 }
 
+fn overlay_contains<R: Renderer>(widget_traverser: &mut WidgetTraverser<'_, R>, layer: &OverlayLayer, pos: Point2<i32>) -> bool {
+    widget_traverser.get_widget(layer.widget_id)
+        .filter(|path| path.widget.visible())
+        .and_then(|path| path.widget.rect_clipped())
+        .map(|rect| rect.contains(pos))
+        .unwrap_or(false)
+}
+
+/// Find the layer that receives mouse input at `pos`: the root of the topmost overlay containing
+/// `pos`, or the root widget if no overlays contain it. Returns `None` if a modal overlay blocks
+/// input at `pos`.
+fn layer_at<R: Renderer>(widget_traverser: &mut WidgetTraverser<'_, R>, overlays: &[OverlayLayer], pos: Point2<i32>) -> Option<WidgetId> {
+    for layer in overlays.iter().rev() {
+        if overlay_contains(widget_traverser, layer, pos) {
+            return Some(layer.widget_id);
+        }
+        if layer.overlay.modal {
+            return None;
+        }
+    }
+    Some(widget_traverser.root_id())
+}
+
+/// Dismiss the overlays that get dismissed by any mouse button or key press.
+fn dismiss_input_overlays(update_state: &UpdateStateCell, overlays: &[OverlayLayer]) {
+    for layer in overlays.iter().filter(|l| l.overlay.dismiss_on_input) {
+        let mut update_state = update_state.borrow_mut();
+        if update_state.overlays.iter().any(|l| l.widget_id == layer.widget_id) {
+            update_state.overlays.retain(|l| l.widget_id != layer.widget_id);
+            update_state.queue_global_update();
+            update_state.message_sender.send(MessageTargeted {
+                message: Box::new(OverlayDismissed{ overlay: layer.widget_id }),
+                target: Some(MessageTarget::Widget(layer.owner)),
+            }).ok();
+        }
+    }
+}
+
+/// Whether a modal overlay is blocking input to the given widget.
+fn blocked_by_modal<R: Renderer>(widget_traverser: &WidgetTraverser<'_, R>, overlays: &[OverlayLayer], widget_id: WidgetId) -> bool {
+    let modal_index = match overlays.iter().rposition(|layer| layer.overlay.modal) {
+        Some(index) => index,
+        None => return false
+    };
+
+    // Find the layer the widget is in by walking up the tree until we hit an overlay.
+    let mut ancestor = Some(widget_id);
+    while let Some(id) = ancestor {
+        if let Some(index) = overlays.iter().position(|layer| layer.widget_id == id) {
+            return index < modal_index;
+        }
+        ancestor = widget_traverser.parent_id(id);
+    }
+    true
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
         message_bus::MessageBus,
         cgmath::Point2,
-        test_helpers::{TestEvent, TestRenderFrame},
+        drag::DragState,
+        test_helpers::{TestEvent, TestRenderer},
         update_state::UpdateState,
-        widget::WidgetIdent,
+        widget::{Visibility, WidgetIdent},
         widget_traverser::WidgetTraverserBase,
     };
-    use derin_common_types::buttons::{Key, ModifierKeys, MouseButton};
+    use derin_common_types::buttons::MouseButton;
+    use std::any::TypeId;
 
     macro_rules! create_translator {
         ($translator:pat, $tree:expr, $root_id:expr) => {
             let message_bus = MessageBus::new();
-            let mut traverser_base: WidgetTraverserBase<TestRenderFrame> = WidgetTraverserBase::new($root_id);
+            let mut traverser_base: WidgetTraverserBase<TestRenderer> = WidgetTraverserBase::new($root_id);
             let update_state = UpdateState::new(&message_bus);
             let mut traverser = traverser_base.with_root_ref($tree, update_state.clone());
             let mut input_state = InputState::new();
+            let mut shortcuts = ShortcutRegistry::new();
 
             let mut translator = EventTranslator::new();
             let $translator = translator.with_data(
                 &mut traverser,
                 &mut input_state,
-                update_state
+                update_state,
+                &mut shortcuts
             );
         }
     }
@@ -1700,4 +2106,198 @@ mod tests {
         translator.translate_window_event(WindowEvent::KeyDown(Key::Escape));
         translator.translate_window_event(WindowEvent::KeyUp(Key::Escape));
     }
+
+    #[test]
+    fn disabled_widgets() {
+        test_widget_tree!{
+            let event_list = crate::test_helpers::EventList::recording();
+            let mut tree = root {
+                rect: (0, 0, 70, 10);
+                a { rect: (10, 0, 20, 10), focus_controls: true },
+                b { rect: (30, 0, 40, 10), focus_controls: true },
+                c { rect: (50, 0, 60, 10), focus_controls: true }
+            };
+        }
+
+        let a_ident = WidgetIdent::new_str("a");
+        let b_ident = WidgetIdent::new_str("b");
+        tree.descendant(&[b_ident]).unwrap().widget_tag.set_enabled(false);
+
+        create_translator!(mut translator, &mut tree, root);
+
+        // Clicking on `b` shouldn't deliver anything to it, or give it focus.
+        translator.translate_window_event(WindowEvent::MouseEnter);
+        translator.translate_window_event(WindowEvent::MouseMove(Point2::new(35, 5)));
+        translator.translate_window_event(WindowEvent::MouseDown(MouseButton::Left));
+        translator.translate_window_event(WindowEvent::MouseUp(MouseButton::Left));
+        assert_eq!(Vec::<TestEvent>::new(), event_list.received_events_for(b));
+
+        // Focus `a`, then move focus to the next widget. `b` should get skipped over.
+        translator.translate_window_event(WindowEvent::MouseMove(Point2::new(15, 5)));
+        translator.translate_window_event(WindowEvent::MouseDown(MouseButton::Left));
+        translator.translate_window_event(WindowEvent::MouseUp(MouseButton::Left));
+        translator.translate_window_event(WindowEvent::KeyDown(Key::RArrow));
+
+        assert_eq!(Vec::<TestEvent>::new(), event_list.received_events_for(b));
+        assert_eq!(
+            Some(&TestEvent {
+                widget: c,
+                source_child: vec![],
+                event: WidgetEvent::GainFocus(
+                    FocusSource::Sibling {
+                        ident: a_ident,
+                        delta: -2,
+                    },
+                    FocusChange::Next,
+                ),
+            }),
+            event_list.received_events_for(c).last()
+        );
+    }
+
+    #[test]
+    fn hidden_widgets() {
+        test_widget_tree!{
+            let event_list = crate::test_helpers::EventList::recording();
+            let mut tree = root {
+                rect: (0, 0, 50, 10);
+                a { rect: (10, 0, 20, 10) },
+                b { rect: (30, 0, 40, 10) }
+            };
+        }
+
+        tree.descendant(&[WidgetIdent::new_str("a")]).unwrap().widget_tag.set_visibility(Visibility::Hidden);
+
+        create_translator!(mut translator, &mut tree, root);
+
+        // The cursor should pass straight through `a`, but still hit `b`.
+        translator.translate_window_event(WindowEvent::MouseEnter);
+        translator.translate_window_event(WindowEvent::MouseMove(Point2::new(15, 5)));
+        translator.translate_window_event(WindowEvent::MouseDown(MouseButton::Left));
+        translator.translate_window_event(WindowEvent::MouseUp(MouseButton::Left));
+        translator.translate_window_event(WindowEvent::MouseMove(Point2::new(35, 5)));
+
+        assert_eq!(Vec::<TestEvent>::new(), event_list.received_events_for(a));
+        assert_ne!(Vec::<TestEvent>::new(), event_list.received_events_for(b));
+    }
+
+    #[test]
+    fn tab_traversal() {
+        test_widget_tree!{
+            let event_list = crate::test_helpers::EventList::recording();
+            let mut tree = root {
+                rect: (0, 0, 100, 10);
+                a { rect: (0, 0, 10, 10) },
+                g {
+                    rect: (10, 0, 40, 10);
+                    g1 { rect: (0, 0, 10, 10) },
+                    g2 { rect: (10, 0, 20, 10) }
+                },
+                b { rect: (40, 0, 50, 10) },
+                c { rect: (50, 0, 60, 10) }
+            };
+        }
+
+        let (a_ident, c_ident, g_ident) = (WidgetIdent::new_str("a"), WidgetIdent::new_str("c"), WidgetIdent::new_str("g"));
+        tree.descendant(&[a_ident]).unwrap().widget_tag.set_focusable(true);
+        tree.descendant(&[c_ident.clone()]).unwrap().widget_tag.set_focusable(true);
+        tree.descendant(&[c_ident]).unwrap().widget_tag.set_tab_index(Some(0));
+        tree.descendant(&[g_ident.clone()]).unwrap().widget_tag.set_focus_group(true);
+        for ident in &["g1", "g2"] {
+            tree.descendant(&[g_ident.clone(), WidgetIdent::new_str(ident)]).unwrap().widget_tag.set_focusable(true);
+        }
+
+        create_translator!(mut translator, &mut tree, root);
+
+        let gain_focus = |widget, change| TestEvent {
+            widget,
+            source_child: vec![],
+            event: WidgetEvent::GainFocus(FocusSource::Traversal, change),
+        };
+        let mut press_tab = || {
+            translator.translate_window_event(WindowEvent::KeyDown(Key::Tab));
+            translator.translate_window_event(WindowEvent::KeyUp(Key::Tab));
+        };
+
+        // `c` has a tab index, so it comes first. `b` isn't focusable, and `g` only gets one stop.
+        press_tab();
+        assert_eq!(Some(&gain_focus(c, FocusChange::Next)), event_list.received_events_for(c).last());
+        press_tab();
+        assert_eq!(Some(&gain_focus(a, FocusChange::Next)), event_list.received_events_for(a).last());
+        press_tab();
+        assert_eq!(Some(&gain_focus(g1, FocusChange::Next)), event_list.received_events_for(g1).last());
+        press_tab();
+        assert_eq!(2, event_list.received_events_for(c).iter().filter(|e| e.event == WidgetEvent::GainFocus(FocusSource::Traversal, FocusChange::Next)).count());
+
+        assert_eq!(Vec::<TestEvent>::new(), event_list.received_events_for(b));
+        assert_eq!(Vec::<TestEvent>::new(), event_list.received_events_for(g2));
+    }
+
+    #[test]
+    fn drag_and_drop() {
+        test_widget_tree!{
+            let event_list = crate::test_helpers::EventList::recording();
+            let mut tree = root {
+                rect: (0, 0, 50, 10);
+                a { rect: (10, 0, 20, 10) },
+                b { rect: (30, 0, 40, 10), accepts_drags: true }
+            };
+        }
+
+        create_translator!(mut translator, &mut tree, root);
+
+        let drag_events = |widget| event_list.received_events_for(widget).into_iter()
+            .map(|e| e.event)
+            .filter(|event| match event {
+                WidgetEvent::DragEnter{..} |
+                WidgetEvent::DragOver{..} |
+                WidgetEvent::DragLeave |
+                WidgetEvent::Drop{..} => true,
+                _ => false
+            })
+            .collect::<Vec<_>>();
+        let payload_type = TypeId::of::<u32>();
+
+        translator.translate_window_event(WindowEvent::MouseEnter);
+        translator.translate_window_event(WindowEvent::MouseMove(Point2::new(15, 5)));
+        translator.translate_window_event(WindowEvent::MouseDown(MouseButton::Left));
+        translator.update_state.borrow_mut().drag = Some(DragState::new(a, Box::new(0u32), None));
+
+        // Neither `a` nor `root` accept the drag, so it gets rejected.
+        translator.translate_window_event(WindowEvent::MouseMove(Point2::new(16, 5)));
+        assert_eq!(Some(CursorIcon::NotAllowed), translator.update_state.borrow().set_cursor_icon);
+
+        // `b` accepts the drag, so it gets dropped there.
+        translator.translate_window_event(WindowEvent::MouseMove(Point2::new(35, 5)));
+        assert_eq!(Some(CursorIcon::Pointer), translator.update_state.borrow().set_cursor_icon);
+        translator.translate_window_event(WindowEvent::MouseUp(MouseButton::Left));
+        assert!(translator.update_state.borrow().drag.is_none());
+
+        assert_eq!(
+            vec![WidgetEvent::DragEnter{pos: Point2::new(6, 5), payload_type}, WidgetEvent::DragLeave],
+            drag_events(a)
+        );
+        assert_eq!(
+            vec![WidgetEvent::DragEnter{pos: Point2::new(16, 5), payload_type}, WidgetEvent::DragLeave],
+            drag_events(root)
+        );
+        assert_eq!(
+            vec![WidgetEvent::DragEnter{pos: Point2::new(5, 5), payload_type}, WidgetEvent::Drop{pos: Point2::new(5, 5), payload_type}],
+            drag_events(b)
+        );
+
+        // Escape cancels the drag without getting delivered anywhere else.
+        event_list.clear_received();
+        translator.translate_window_event(WindowEvent::MouseDown(MouseButton::Left));
+        translator.update_state.borrow_mut().drag = Some(DragState::new(b, Box::new(0u32), None));
+        translator.translate_window_event(WindowEvent::MouseMove(Point2::new(36, 5)));
+        translator.translate_window_event(WindowEvent::KeyDown(Key::Escape));
+        assert!(translator.update_state.borrow().drag.is_none());
+
+        assert_eq!(
+            vec![WidgetEvent::DragEnter{pos: Point2::new(6, 5), payload_type}, WidgetEvent::DragLeave],
+            drag_events(b)
+        );
+        assert_eq!(Vec::<WidgetEvent>::new(), drag_events(root));
+    }
 }
@@ -27,13 +27,17 @@ pub(crate) enum DispatchableEvent {
         old_pos: Point2<i32>,
         new_pos: Point2<i32>,
         exiting_from_child: Option<WidgetIdent>,
+        /// The root of the layer the event is moving through. The event never bubbles past it.
+        layer: WidgetId,
     },
     GainFocus {
         source: FocusSource,
         change: FocusChange,
     },
     Direct {
-        bubble_source: Option<WidgetId>,
+        /// The path from the receiving widget to the widget the event bubbled up from, or `None`
+        /// if the event is being delivered directly to the receiving widget.
+        bubble_source: Option<Vec<WidgetIdent>>,
         event: WidgetEvent,
     },
 }
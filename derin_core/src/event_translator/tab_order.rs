@@ -0,0 +1,100 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::{
+    overlay::OverlayLayer,
+    render::Renderer,
+    widget::WidgetId,
+    widget_traverser::{OffsetWidgetPath, WidgetTraverser},
+};
+use fnv::FnvHashMap;
+
+/// A widget that can be focused with the Tab key, or a focus group that gets treated as a single
+/// stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TabStop {
+    /// The focus group, or the widget itself if it isn't in a group.
+    key: WidgetId,
+    /// The widget that gets focused when tabbing to the stop.
+    target: WidgetId,
+    tab_index: Option<u32>,
+}
+
+/// Find the widget that pressing Tab (or Shift+Tab, if `reverse` is `true`) should move focus to.
+///
+/// Focus moves through the topmost modal overlay if there is one, and through the main widget tree
+/// otherwise. It wraps around from the last stop to the first.
+pub(super) fn next_tab_stop<R: Renderer>(
+    widget_traverser: &mut WidgetTraverser<'_, R>,
+    overlays: &[OverlayLayer],
+    focused_widget: Option<WidgetId>,
+    reverse: bool
+) -> Option<WidgetId> {
+    let mut focusable = Vec::new();
+    let mut groups = FnvHashMap::default();
+    let mut add_widget = |path: OffsetWidgetPath<'_, R>| {
+        let widget = &path.widget;
+        let widget_tag = widget.widget_tag();
+        if widget_tag.focus_group() {
+            groups.insert(path.widget_id, widget_tag.tab_index());
+        }
+        if widget_tag.focusable() && widget.enabled() && widget.visible() {
+            focusable.push((path.widget_id, widget_tag.tab_index()));
+        }
+    };
+
+    let overlay_ids = overlays.iter().map(|layer| layer.widget_id).collect::<Vec<_>>();
+    match overlays.iter().rposition(|layer| layer.overlay.modal) {
+        Some(modal_index) => widget_traverser.crawl_widget_subtree_except(
+            overlays[modal_index].widget_id,
+            &overlay_ids[modal_index + 1..],
+            &mut add_widget
+        ),
+        None => widget_traverser.crawl_widgets_except(&overlay_ids, &mut add_widget)
+    }
+
+    // The outermost focus group containing `widget_id`, or `widget_id` if it isn't in a group.
+    let stop_key = |widget_id| {
+        let mut key = widget_id;
+        let mut ancestor = Some(widget_id);
+        while let Some(id) = ancestor {
+            if groups.contains_key(&id) {
+                key = id;
+            }
+            ancestor = widget_traverser.parent_id(id);
+        }
+        key
+    };
+
+    let mut stops: Vec<TabStop> = Vec::with_capacity(focusable.len());
+    for &(widget_id, tab_index) in &focusable {
+        let key = stop_key(widget_id);
+        if stops.iter().any(|stop| stop.key == key) {
+            continue;
+        }
+        stops.push(TabStop {
+            key,
+            target: widget_id,
+            tab_index: match key == widget_id {
+                true => tab_index,
+                false => groups[&key],
+            },
+        });
+    }
+    // `sort_by_key` is stable, so stops without a tab index stay in depth-first order.
+    stops.sort_by_key(|stop| (stop.tab_index.is_none(), stop.tab_index));
+
+    if stops.is_empty() {
+        return None;
+    }
+
+    let focused_key = focused_widget.map(stop_key);
+    let next_index = match (stops.iter().position(|stop| Some(stop.key) == focused_key), reverse) {
+        (Some(index), false) => (index + 1) % stops.len(),
+        (Some(index), true) => (index + stops.len() - 1) % stops.len(),
+        (None, false) => 0,
+        (None, true) => stops.len() - 1,
+    };
+    Some(stops[next_index].target)
+}
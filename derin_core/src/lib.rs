@@ -10,14 +10,17 @@ extern crate derin_common_types;
 #[macro_use]
 mod macros;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-helpers"))]
 #[macro_use]
 pub mod test_helpers;
 
 pub mod timer;
+pub mod drag;
 #[macro_use]
 pub mod event;
+pub mod overlay;
 pub mod render;
+pub mod shortcut;
 pub mod widget;
 
 mod mbseq;
@@ -41,8 +44,10 @@ use crate::{
     },
     render::{Renderer},
     mbseq::MouseButtonSequenceTrackPos,
+    overlay::OverlayPlacement,
+    shortcut::{KeyChord, ShortcutId, ShortcutRegistry},
     update_state::{UpdateState, UpdateStateCell},
-    widget_traverser::{Relation, WidgetPath, WidgetTraverser, WidgetTraverserBase},
+    widget_traverser::{Relation, OffsetWidgetPath, WidgetPath, WidgetTraverser, WidgetTraverserBase},
 };
 use derin_common_types::{
     buttons::{MouseButton, Key, ModifierKeys},
@@ -50,6 +55,8 @@ use derin_common_types::{
     layout::SizeBounds,
 };
 use std::{
+    any::TypeId,
+    mem,
     rc::Rc,
     time::Instant,
 };
@@ -64,6 +71,35 @@ fn vec_remove_element<T: PartialEq>(v: &mut Vec<T>, element: &T) -> Option<T> {
     find_index(v, element).map(|i| v.remove(i))
 }
 
+/// Scroll each requested widget into view, starting with the widget's parent and working outwards.
+fn scroll_into_view<R: Renderer>(widget_traverser: &mut WidgetTraverser<'_, R>, requests: Vec<(WidgetId, Option<BoundBox<D2, i32>>)>) {
+    for (widget_id, rect) in requests {
+        // Track the rect in window space, since that's the space `OffsetWidget` rects are in.
+        let mut rect = match widget_traverser.get_widget(widget_id) {
+            Some(WidgetPath{mut widget, ..}) => {
+                widget.cancel_scan();
+                match rect {
+                    Some(rect) => rect + widget.rect().min().to_vec(),
+                    None => widget.rect()
+                }
+            },
+            None => continue
+        };
+
+        let mut ancestor = widget_traverser.parent_id(widget_id);
+        while let Some(ancestor_id) = ancestor {
+            let WidgetPath{mut widget, ..} = match widget_traverser.get_widget(ancestor_id) {
+                Some(path) => path,
+                None => break
+            };
+            let origin = widget.rect().min().to_vec();
+            rect = widget.inner_mut().scroll_into_view(rect - origin) + origin;
+            widget.cancel_scan();
+            ancestor = widget_traverser.parent_id(ancestor_id);
+        }
+    }
+}
+
 pub struct Root<N, R>
     where N: Widget + 'static,
           R: Renderer + 'static
@@ -79,6 +115,7 @@ pub struct Root<N, R>
     timer_tracker: TimerTriggerTracker,
     message_bus: MessageBus,
     update_state: Rc<UpdateStateCell>,
+    shortcuts: ShortcutRegistry,
 
     // User data
     pub root_widget: N,
@@ -92,7 +129,13 @@ struct InputState {
     modifiers: ModifierKeys,
     keys_down: Vec<Key>,
     mouse_hover_widget: Option<WidgetId>,
-    focused_widget: Option<WidgetId>
+    /// The root of the overlay layer the mouse is hovering over, or the root widget if it isn't
+    /// over an overlay. `None` if the mouse is outside of the window or blocked by a modal overlay.
+    mouse_hover_layer: Option<WidgetId>,
+    focused_widget: Option<WidgetId>,
+    /// Whether focus was moved to `focused_widget` with the keyboard, and should be drawn with a
+    /// focus ring.
+    focus_visible: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -131,6 +174,7 @@ pub struct FrameEventProcessor<'a, R>
     timer_tracker: &'a mut TimerTriggerTracker,
     message_bus: &'a mut MessageBus,
     update_state: Rc<UpdateStateCell>,
+    shortcuts: &'a mut ShortcutRegistry,
     widget_traverser: WidgetTraverser<'a, R>,
 }
 
@@ -150,7 +194,9 @@ impl InputState {
             modifiers: ModifierKeys::empty(),
             keys_down: Vec::new(),
             mouse_hover_widget: None,
-            focused_widget: None
+            mouse_hover_layer: None,
+            focused_widget: None,
+            focus_visible: false,
         }
     }
 }
@@ -174,6 +220,7 @@ impl<N, R> Root<N, R>
             timer_tracker: TimerTriggerTracker::new(),
             update_state: UpdateState::new(&message_bus),
             message_bus,
+            shortcuts: ShortcutRegistry::new(),
 
             root_widget, theme, renderer,
         }
@@ -186,10 +233,78 @@ impl<N, R> Root<N, R>
             timer_tracker: &mut self.timer_tracker,
             message_bus: &mut self.message_bus,
             update_state: self.update_state.clone(),
+            shortcuts: &mut self.shortcuts,
             widget_traverser: self.widget_traverser_base.with_root_ref(&mut self.root_widget, self.update_state.clone())
         }
     }
 
+    /// Queue every widget in the tree to be re-laid-out and redrawn.
+    pub fn queue_global_update(&mut self) {
+        self.update_state.borrow_mut().queue_global_update();
+    }
+
+    /// Hand broadcast messages of type `A` to the code driving the root, rather than to widgets.
+    ///
+    /// This lets widgets make requests of the application, such as opening a new window. Messages
+    /// sent to a specific target are still delivered to widgets.
+    pub fn register_root_message_type<A: 'static>(&mut self) {
+        self.message_bus.register_root_message_type(TypeId::of::<A>());
+    }
+
+    /// Retrieve the root messages of type `A` that were broadcast since the last call.
+    pub fn take_root_messages<A: 'static>(&mut self) -> Vec<A> {
+        self.message_bus.take_root_messages(TypeId::of::<A>()).into_iter()
+            .map(|message| *message.downcast::<A>().expect("root message had wrong type"))
+            .collect()
+    }
+
+    /// Broadcast `message` whenever the key chords in `chords` get typed in sequence, regardless
+    /// of which widget has focus.
+    ///
+    /// Register `A` with `register_root_message_type` to handle the shortcut outside of the widget
+    /// tree. Returns an ID that can be passed to `unregister_shortcut`.
+    pub fn register_shortcut<A>(&mut self, chords: &[KeyChord], message: A) -> ShortcutId
+        where A: 'static + Clone
+    {
+        self.shortcuts.register(chords, None, message)
+    }
+
+    /// Send `message` to `scope` whenever the key chords in `chords` get typed in sequence while
+    /// `scope` or one of its descendants has focus.
+    ///
+    /// If several shortcuts share the same chords, the one whose scope is closest to the focused
+    /// widget takes priority.
+    pub fn register_scoped_shortcut<A>(&mut self, scope: WidgetId, chords: &[KeyChord], message: A) -> ShortcutId
+        where A: 'static + Clone
+    {
+        self.shortcuts.register(chords, Some(scope), message)
+    }
+
+    pub fn unregister_shortcut(&mut self, shortcut: ShortcutId) {
+        self.shortcuts.unregister(shortcut);
+    }
+
+    /// The number of physical pixels per logical pixel. Widgets are laid out in logical pixels.
+    #[inline]
+    pub fn scale_factor(&self) -> f32 {
+        self.renderer.scale_factor()
+    }
+
+    /// Change the scale factor, such as when the window moves to a monitor with a different DPI.
+    ///
+    /// This re-lays-out and redraws every widget in the tree, so text and images get re-rasterized
+    /// at the new resolution.
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        if scale_factor == self.renderer.scale_factor() {
+            return;
+        }
+
+        self.renderer.set_scale_factor(scale_factor);
+        let dims = self.renderer.dims();
+        *self.root_widget.rect_mut() = dims.cast().unwrap_or(DimsBox::max_value()).into();
+        self.queue_global_update();
+    }
+
     pub fn relayout(&mut self) -> SizeBounds {
         let mut widget_traverser = self.widget_traverser_base.with_root_ref(&mut self.root_widget, self.update_state.clone());
 
@@ -198,11 +313,23 @@ impl<N, R> Root<N, R>
         let mut iter_num = 0;
         let global_update = self.update_state.borrow().global_update;
 
-        while global_update || self.update_state.borrow().relayout.len() > 0 {
+        while global_update || !self.update_state.borrow().relayout.is_empty() || !self.update_state.borrow().relayout_parent.is_empty() {
             match global_update {
-                false => relayout_widgets.extend(self.update_state.borrow_mut().relayout.drain()),
+                false => {
+                    relayout_widgets.extend(self.update_state.borrow_mut().relayout.drain());
+
+                    let relayout_parent = self.update_state.borrow_mut().relayout_parent.drain().collect::<Vec<_>>();
+                    for widget_id in relayout_parent {
+                        if let Some(WidgetPath{widget_id: parent_id, ..}) = widget_traverser.get_widget_relation(widget_id, Relation::Parent) {
+                            if !relayout_widgets.contains(&parent_id) {
+                                relayout_widgets.push(parent_id);
+                            }
+                        }
+                    }
+                },
                 true => {
                     self.update_state.borrow_mut().relayout.clear();
+                    self.update_state.borrow_mut().relayout_parent.clear();
                     relayout_widgets.extend(widget_traverser.all_widgets());
                 }
             }
@@ -219,6 +346,7 @@ impl<N, R> Root<N, R>
                 };
 
                 let old_widget_rect = widget.rect();
+                let old_size_bounds = widget.size_bounds();
                 self.renderer.layout(widget.widget_id(), |layout| widget.update_layout(layout));
                 let size_bounds = widget.size_bounds();
                 let new_widget_rect = widget.rect();
@@ -229,11 +357,12 @@ impl<N, R> Root<N, R>
 
                 // If we're doing a global update, all widgets are in the relayout list so we don't
                 // need to queue the part for relayout. Otherwise, queue the parent for relayout if
-                // the widget's rect has changed or the widget's dimensions no longer fall in its size
-                // bounds.
+                // the widget's rect or size bounds have changed, or the widget's dimensions no longer
+                // fall in its size bounds.
                 let parent_needs_relayout =
                     dims_bounded != widget_dims ||
-                    old_widget_rect != new_widget_rect;
+                    old_widget_rect != new_widget_rect ||
+                    old_size_bounds != size_bounds;
 
                 if !global_update && parent_needs_relayout {
                     drop(widget);
@@ -267,7 +396,50 @@ impl<N, R> Root<N, R>
             }
         }
 
+        // Position the overlays relative to their anchors. This happens after everything else
+        // gets laid out so the anchors are in their final positions.
         let root_id = widget_traverser.root_id();
+        let window_rect = widget_traverser.get_widget(root_id).unwrap().widget.rect();
+        let overlays = self.update_state.borrow().overlays.clone();
+        for layer in overlays {
+            let anchor_rect = match layer.overlay.placement {
+                OverlayPlacement::WindowCenter => window_rect,
+                _ => match widget_traverser.get_widget(layer.overlay.anchor) {
+                    Some(WidgetPath{widget, ..}) => widget.rect(),
+                    None => continue
+                }
+            };
+
+            let WidgetPath{mut widget, ..} = match widget_traverser.get_widget(layer.widget_id) {
+                Some(widget) => widget,
+                None => continue
+            };
+            let old_rect = widget.rect();
+            let dims = widget.size_bounds().bound_rect(old_rect.dims());
+            let new_rect = layer.overlay.placement.place(anchor_rect, dims, window_rect);
+            if new_rect != old_rect {
+                widget.set_rect(new_rect);
+                self.renderer.layout(widget.widget_id(), |layout| widget.update_layout(layout));
+            }
+            widget.cancel_scan();
+        }
+
+        // Keep the drag image under the cursor.
+        let drag_image = self.update_state.borrow().drag.as_ref().and_then(|drag| drag.image);
+        if let (Some(image), Some(mouse_pos)) = (drag_image, self.input_state.mouse_pos) {
+            if let Some(WidgetPath{mut widget, ..}) = widget_traverser.get_widget(image.widget) {
+                let old_rect = widget.rect();
+                let dims = widget.size_bounds().bound_rect(old_rect.dims());
+                let cursor_rect = BoundBox::new2(mouse_pos.x, mouse_pos.y, mouse_pos.x, mouse_pos.y);
+                let new_rect = OverlayPlacement::Offset(image.offset).place(cursor_rect, dims, window_rect);
+                if new_rect != old_rect {
+                    widget.set_rect(new_rect);
+                    self.renderer.layout(widget.widget_id(), |layout| widget.update_layout(layout));
+                }
+                widget.cancel_scan();
+            }
+        }
+
         let root_widget = widget_traverser.get_widget(root_id).unwrap().widget;
         root_widget.size_bounds()
     }
@@ -281,6 +453,7 @@ impl<N, R> Root<N, R>
 
         let Root {
             ref update_state,
+            ref input_state,
             ref mut widget_traverser_base,
             ref mut root_widget,
             ref theme,
@@ -301,13 +474,17 @@ impl<N, R> Root<N, R>
             let window_rect = BoundBox::new2(0, 0, window_rect.width() as i32, window_rect.height() as i32);
 
             let mut widget_traverser = widget_traverser_base.with_root_ref(root_widget, update_state.clone());
-            widget_traverser.crawl_widgets(|mut path| {
+            let render_widget = |renderer: &mut R, mut path: OffsetWidgetPath<'_, R>| {
+                if !path.widget.visible() {
+                    return;
+                }
                 let render_parameters = RenderParameters {
                     renderer,
                     widget_id: path.widget.widget_id(),
                     theme,
                     transform: path.widget.rect(),
                     clip: path.widget.clip().unwrap_or(window_rect),
+                    enabled: path.widget.enabled(),
                 };
 
                 let result = path.widget.render(render_parameters);
@@ -316,7 +493,51 @@ impl<N, R> Root<N, R>
                     Err(RenderError::ThemeNotSupported) => println!("WARNING: Attempted to render widget but renderer didn't support theme"),
                     Err(RenderError::RendererNotSupported) => println!("WARNING: Attempted to render widget but widget didn't support renderer"),
                 }
+            };
+
+            let overlay_ids = update_state.borrow().overlay_ids().collect::<Vec<_>>();
+            let drag_image = update_state.borrow().drag.as_ref().and_then(|drag| drag.image).map(|image| image.widget);
+
+            // The focus ring gets drawn on top of the layer containing the focused widget.
+            let focus_ring = input_state.focused_widget
+                .filter(|_| input_state.focus_visible)
+                .and_then(|focused_id| {
+                    let WidgetPath{widget, ..} = widget_traverser.get_widget(focused_id)?;
+                    match widget.visible() {
+                        true => Some((widget.focus_rect(), widget.clip().unwrap_or(window_rect))),
+                        false => None
+                    }
+                });
+            let focus_layer = input_state.focused_widget.map(|focused_id| {
+                let mut ancestor = Some(focused_id);
+                while let Some(id) = ancestor {
+                    if overlay_ids.contains(&id) {
+                        return id;
+                    }
+                    ancestor = widget_traverser.parent_id(id);
+                }
+                widget_traverser.root_id()
             });
+            let render_focus_ring = |renderer: &mut R, layer_id| {
+                match focus_ring {
+                    Some((rect, clip)) if focus_layer == Some(layer_id) => renderer.render_focus_ring(theme, rect, clip),
+                    _ => ()
+                }
+            };
+
+            // Draw the overlays after the main tree, from bottom to top, so they show up above
+            // everything beneath them. The drag image goes above everything else.
+            let skip = overlay_ids.iter().cloned().chain(drag_image).collect::<Vec<_>>();
+            widget_traverser.crawl_widgets_except(&skip, |path| render_widget(renderer, path));
+            render_focus_ring(renderer, widget_traverser.root_id());
+            for (i, &overlay_id) in overlay_ids.iter().enumerate() {
+                let skip = &skip[i + 1..];
+                widget_traverser.crawl_widget_subtree_except(overlay_id, skip, |path| render_widget(renderer, path));
+                render_focus_ring(renderer, overlay_id);
+            }
+            if let Some(drag_image) = drag_image {
+                widget_traverser.crawl_widget_subtree_except(drag_image, &[], |path| render_widget(renderer, path));
+            }
             renderer.finish_frame(theme);
         }
     }
@@ -333,6 +554,7 @@ impl<R> FrameEventProcessor<'_, R>
             ref mut input_state,
             ref mut event_translator,
             ref update_state,
+            ref mut shortcuts,
             ref mut widget_traverser,
             timer_tracker: _,
             message_bus: _,
@@ -343,6 +565,7 @@ impl<R> FrameEventProcessor<'_, R>
                 widget_traverser,
                 input_state,
                 update_state.clone(),
+                shortcuts,
             )
             .translate_window_event(event);
     }
@@ -352,6 +575,11 @@ impl<R> FrameEventProcessor<'_, R>
     }
 
     pub fn finish(mut self) -> EventLoopResult {
+        // Scrolling widgets into view can start scroll animation timers, so do that before the
+        // timers get updated.
+        let scroll_requests = mem::replace(&mut self.update_state.borrow_mut().scroll_into_view, Vec::new());
+        scroll_into_view(&mut self.widget_traverser, scroll_requests);
+
         {
             let mut update_state = self.update_state.borrow_mut();
 
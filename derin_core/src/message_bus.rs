@@ -46,6 +46,9 @@ impl WidgetMessageKey {
 pub struct MessageBus {
     /// Maps message types to widget IDs.
     type_map: FnvHashMap<TypeId, FnvHashSet<WidgetId>>,
+    /// Message types that get handed to the code driving the `Root`, instead of to widgets.
+    root_types: FnvHashSet<TypeId>,
+    root_messages: Vec<Message>,
     messages_recv: Receiver<MessageTargeted>,
     messages_send: Sender<MessageTargeted>,
 }
@@ -68,6 +71,8 @@ impl MessageBus {
         let (messages_send, messages_recv) = mpsc::channel();
         MessageBus {
             type_map: FnvHashMap::default(),
+            root_types: FnvHashSet::default(),
+            root_messages: Vec::new(),
             messages_recv, messages_send,
         }
     }
@@ -82,6 +87,11 @@ impl MessageBus {
             // `Box<Any>`, not the inner `Any`.
             let type_id = (*message).type_id();
 
+            if target.is_none() && self.root_types.contains(&type_id) {
+                self.root_messages.push(message);
+                continue;
+            }
+
             let untargeted_widget_ids = self.type_map.get(&type_id)
                 .filter(|wids| wids.len() > 0)
                 .filter(|_| target.is_none());
@@ -105,6 +115,17 @@ impl MessageBus {
         self.type_map.entry(message_type).or_default().insert(widget_id);
     }
 
+    pub fn register_root_message_type(&mut self, message_type: TypeId) {
+        self.root_types.insert(message_type);
+    }
+
+    /// Remove and return the root messages of the given type.
+    pub fn take_root_messages(&mut self, message_type: TypeId) -> Vec<Message> {
+        let (taken, kept) = self.root_messages.drain(..).partition(|m| (**m).type_id() == message_type);
+        self.root_messages = kept;
+        taken
+    }
+
     pub fn remove_widget(&mut self, widget_id: WidgetId) {
         for wid_vec in self.type_map.values_mut() {
             wid_vec.retain(|id| *id != widget_id);
@@ -186,4 +207,40 @@ mod tests {
             MessageTarget::ChildrenOf(a),
         );
     }
+
+    #[test]
+    fn root_messages() {
+        let a = WidgetId::new();
+
+        let mut message_bus = MessageBus::new();
+        message_bus.register_widget_message_type(TypeId::of::<MessageA>(), a);
+        message_bus.register_root_message_type(TypeId::of::<MessageB>());
+
+        message_bus.messages_send.send(MessageTargeted {
+            message: Box::new(MessageB),
+            target: None
+        }).unwrap();
+        message_bus.messages_send.send(MessageTargeted {
+            message: Box::new(MessageA),
+            target: None
+        }).unwrap();
+
+        // The root message gets skipped over, and the widget message gets delivered.
+        let (message, targets) = message_bus.next_message().unwrap();
+        assert_eq!(TypeId::of::<MessageA>(), (*message).type_id());
+        assert_eq!(vec![MessageTarget::Widget(a)], targets.collect::<Vec<_>>());
+        assert!(message_bus.next_message().is_none());
+
+        assert_eq!(0, message_bus.take_root_messages(TypeId::of::<MessageA>()).len());
+        assert_eq!(1, message_bus.take_root_messages(TypeId::of::<MessageB>()).len());
+        assert_eq!(0, message_bus.take_root_messages(TypeId::of::<MessageB>()).len());
+
+        // Targeted messages always go to widgets.
+        message_bus.messages_send.send(MessageTargeted {
+            message: Box::new(MessageB),
+            target: Some(MessageTarget::Widget(a))
+        }).unwrap();
+        let (_, targets) = message_bus.next_message().unwrap();
+        assert_eq!(vec![MessageTarget::Widget(a)], targets.collect::<Vec<_>>());
+    }
 }
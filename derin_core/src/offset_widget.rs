@@ -5,7 +5,7 @@
 use crate::{
     {LoopFlow, InputState},
     widget::{
-        WidgetDyn, WidgetId, WidgetIdent, WidgetTag,
+        Visibility, WidgetDyn, WidgetId, WidgetIdent, WidgetTag,
         dynamic::{RenderParameters, RenderError},
     },
     event::{InputState as EventInputState, WidgetEventSourced, EventOps},
@@ -23,6 +23,10 @@ pub(crate) struct OffsetWidget<'a, R: Renderer> {
     widget: &'a mut WidgetDyn<R>,
     offset: Vector2<i32>,
     clip: Option<BoundBox<D2, i32>>,
+    /// Whether the widget and all of its ancestors are enabled.
+    enabled: bool,
+    /// Whether the widget and all of its ancestors are visible.
+    visible: bool,
 }
 
 pub(crate) struct OffsetWidgetInfo<'a, R: Renderer> {
@@ -33,11 +37,13 @@ pub(crate) struct OffsetWidgetInfo<'a, R: Renderer> {
 
 impl<'a, R: Renderer> OffsetWidget<'a, R> {
     #[inline]
-    pub fn new(widget: &'a mut WidgetDyn<R>, offset: Vector2<i32>, clip: Option<BoundBox<D2, i32>>) -> OffsetWidget<'a, R> {
+    pub fn new(widget: &'a mut WidgetDyn<R>, offset: Vector2<i32>, clip: Option<BoundBox<D2, i32>>, enabled: bool, visible: bool) -> OffsetWidget<'a, R> {
         OffsetWidget {
             widget,
             offset,
             clip,
+            enabled,
+            visible,
         }
     }
 
@@ -55,6 +61,16 @@ impl<'a, R: Renderer> OffsetWidget<'a, R> {
         self.clip
     }
 
+    /// Whether the widget is enabled, taking its ancestors into account.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Whether the widget gets drawn and hit-tested, taking its ancestors into account.
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
     pub fn widget_tag(&self) -> &WidgetTag {
         self.widget.widget_tag()
     }
@@ -64,6 +80,9 @@ impl<'a, R: Renderer> OffsetWidget<'a, R> {
     pub fn rect(&self) -> BoundBox<D2, i32> {
         self.widget.rect() + self.offset
     }
+    pub fn focus_rect(&self) -> BoundBox<D2, i32> {
+        self.widget.focus_rect() + self.offset
+    }
     pub fn rect_clipped(&self) -> Option<BoundBox<D2, i32>> {
         self.clip.and_then(|clip_rect| clip_rect.intersect_rect(self.rect()))
     }
@@ -141,10 +160,14 @@ impl<'a, R: Renderer> OffsetWidget<'a, R> {
     {
         let child_offset = self.rect().min().to_vec();
         let clip_rect = self.rect_clipped();
+        let enabled = self.enabled;
+        let visible = self.visible;
 
         self.widget.children_mut(&mut |widget_slice| {
             for info in widget_slice {
-                let widget: OffsetWidget<'b, _> = OffsetWidget::new(info.widget, child_offset, clip_rect);
+                let child_enabled = enabled && info.widget.widget_tag().enabled();
+                let child_visible = visible && info.widget.widget_tag().visibility() == Visibility::Visible;
+                let widget: OffsetWidget<'b, _> = OffsetWidget::new(info.widget, child_offset, clip_rect, child_enabled, child_visible);
                 let child_offset = OffsetWidgetInfo {
                     ident: info.ident,
                     index: info.index,
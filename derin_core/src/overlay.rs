@@ -0,0 +1,181 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Transient content drawn above the rest of the widget tree, such as menus, tooltips, and modal
+//! dialogs.
+//!
+//! An overlay is a normal widget in the tree, owned by some other widget. The owner shows it with
+//! `WidgetTag::show_overlay`, after which the overlay escapes its parent's clipping rectangle, gets
+//! positioned relative to an anchor widget, gets drawn after everything beneath it, and receives
+//! mouse input before anything beneath it.
+
+use crate::{
+    cgmath::{Point2, Vector2},
+    widget::WidgetId,
+};
+use cgmath_geometry::{D2, rect::{BoundBox, DimsBox, GeoBox}};
+
+/// Configures how an overlay gets positioned and how it treats input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Overlay {
+    /// The widget the overlay gets positioned relative to.
+    pub anchor: WidgetId,
+    pub placement: OverlayPlacement,
+    /// Whether the overlay blocks input to every layer beneath it.
+    pub modal: bool,
+    /// Whether pressing a mouse button outside of the overlay dismisses it.
+    pub dismiss_on_click_outside: bool,
+    /// Whether any mouse button or key press dismisses the overlay. Unlike clicks outside of the
+    /// overlay, presses that dismiss it this way still get delivered to the widgets beneath it.
+    pub dismiss_on_input: bool,
+}
+
+/// Where an overlay gets placed, relative to its anchor.
+///
+/// Overlays are always shifted to fit inside the window if possible.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverlayPlacement {
+    /// Below the anchor, or above it if there isn't enough room below.
+    Below,
+    /// Above the anchor, or below it if there isn't enough room above.
+    Above,
+    /// Right of the anchor, or left of it if there isn't enough room on the right.
+    Right,
+    /// Left of the anchor, or right of it if there isn't enough room on the left.
+    Left,
+    /// Centered over the anchor.
+    Center,
+    /// Centered in the window. The anchor is ignored.
+    WindowCenter,
+    /// At the given offset from the anchor's top-left corner.
+    Offset(Vector2<i32>),
+}
+
+/// Message sent to an overlay's owner when the overlay gets dismissed by a click outside of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OverlayDismissed {
+    pub overlay: WidgetId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct OverlayLayer {
+    pub widget_id: WidgetId,
+    pub owner: WidgetId,
+    pub overlay: Overlay,
+}
+
+impl Overlay {
+    /// A non-modal overlay placed below `anchor`, which gets dismissed when clicking outside of it.
+    pub fn popup(anchor: WidgetId) -> Overlay {
+        Overlay {
+            anchor,
+            placement: OverlayPlacement::Below,
+            modal: false,
+            dismiss_on_click_outside: true,
+            dismiss_on_input: false,
+        }
+    }
+
+    /// A modal overlay centered in the window, which only gets dismissed by its owner.
+    pub fn modal(anchor: WidgetId) -> Overlay {
+        Overlay {
+            anchor,
+            placement: OverlayPlacement::WindowCenter,
+            modal: true,
+            dismiss_on_click_outside: false,
+            dismiss_on_input: false,
+        }
+    }
+
+    /// A non-modal overlay placed at `offset` from `anchor`'s top-left corner, which gets dismissed
+    /// by any mouse button or key press.
+    pub fn tooltip(anchor: WidgetId, offset: Vector2<i32>) -> Overlay {
+        Overlay {
+            anchor,
+            placement: OverlayPlacement::Offset(offset),
+            modal: false,
+            dismiss_on_click_outside: false,
+            dismiss_on_input: true,
+        }
+    }
+}
+
+impl OverlayPlacement {
+    /// Place a rectangle with the dimensions `dims` relative to `anchor`, keeping it inside of
+    /// `window` where possible.
+    pub fn place(self, anchor: BoundBox<D2, i32>, dims: DimsBox<D2, i32>, window: BoundBox<D2, i32>) -> BoundBox<D2, i32> {
+        let (width, height) = (dims.width(), dims.height());
+        let fits_below = anchor.max.y + height <= window.max.y;
+        let fits_above = anchor.min.y - height >= window.min.y;
+        let fits_right = anchor.max.x + width <= window.max.x;
+        let fits_left = anchor.min.x - width >= window.min.x;
+
+        let below = Point2::new(anchor.min.x, anchor.max.y);
+        let above = Point2::new(anchor.min.x, anchor.min.y - height);
+        let right = Point2::new(anchor.max.x, anchor.min.y);
+        let left = Point2::new(anchor.min.x - width, anchor.min.y);
+
+        let origin = match self {
+            OverlayPlacement::Below => match fits_below || !fits_above {
+                true => below,
+                false => above
+            },
+            OverlayPlacement::Above => match fits_above || !fits_below {
+                true => above,
+                false => below
+            },
+            OverlayPlacement::Right => match fits_right || !fits_left {
+                true => right,
+                false => left
+            },
+            OverlayPlacement::Left => match fits_left || !fits_right {
+                true => left,
+                false => right
+            },
+            OverlayPlacement::Center => Point2::new(
+                anchor.min.x + (anchor.width() - width) / 2,
+                anchor.min.y + (anchor.height() - height) / 2,
+            ),
+            OverlayPlacement::WindowCenter => Point2::new(
+                window.min.x + (window.width() - width) / 2,
+                window.min.y + (window.height() - height) / 2,
+            ),
+            OverlayPlacement::Offset(offset) => anchor.min + offset,
+        };
+
+        // Shift the overlay into the window. If it's too big to fit, align it with the top-left
+        // corner.
+        let x = origin.x.min(window.max.x - width).max(window.min.x);
+        let y = origin.y.min(window.max.y - height).max(window.min.y);
+        BoundBox::new2(x, y, x + width, y + height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn place() {
+        let window = BoundBox::new2(0, 0, 100, 100);
+        let dims = DimsBox::new2(20, 30);
+
+        // Enough room in the preferred direction.
+        let anchor = BoundBox::new2(10, 10, 40, 20);
+        assert_eq!(BoundBox::new2(10, 20, 30, 50), OverlayPlacement::Below.place(anchor, dims, window));
+        assert_eq!(BoundBox::new2(40, 10, 60, 40), OverlayPlacement::Right.place(anchor, dims, window));
+        assert_eq!(BoundBox::new2(15, 0, 35, 30), OverlayPlacement::Center.place(anchor, dims, window));
+        assert_eq!(BoundBox::new2(40, 35, 60, 65), OverlayPlacement::WindowCenter.place(anchor, dims, window));
+
+        // Not enough room below, so flip above.
+        let anchor = BoundBox::new2(10, 80, 40, 90);
+        assert_eq!(BoundBox::new2(10, 50, 30, 80), OverlayPlacement::Below.place(anchor, dims, window));
+
+        // Not enough room on either side, so shift into the window.
+        let anchor = BoundBox::new2(90, 40, 100, 60);
+        assert_eq!(BoundBox::new2(80, 60, 100, 90), OverlayPlacement::Below.place(anchor, dims, window));
+        let anchor = BoundBox::new2(0, 0, 100, 100);
+        assert_eq!(BoundBox::new2(0, 70, 20, 100), OverlayPlacement::Below.place(anchor, dims, window));
+    }
+}
@@ -16,8 +16,18 @@ pub trait Renderer: 'static {
     type Theme;
     type Layout: RendererLayout;
 
+    /// Called when the window gets resized. `new_size` is in logical pixels.
     fn resized(&mut self, new_size: DimsBox<D2, u32>);
+    /// The size of the window, in logical pixels.
     fn dims(&self) -> DimsBox<D2, u32>;
+    /// The number of physical pixels per logical pixel.
+    ///
+    /// Widgets get laid out in logical pixels, and the renderer is responsible for scaling them up
+    /// to the display's resolution.
+    fn scale_factor(&self) -> f32;
+    /// Called when the scale factor changes, such as when the window moves to a monitor with a
+    /// different DPI. The window's logical size should stay the same.
+    fn set_scale_factor(&mut self, scale_factor: f32);
     fn widget_removed(&mut self, widget_id: WidgetId);
     fn layout(
         &mut self,
@@ -25,16 +35,24 @@ pub trait Renderer: 'static {
         layout: impl FnOnce(&mut Self::Layout)
     );
     fn start_frame(&mut self, theme: &Self::Theme);
+    /// Draw an outline around `rect` to show which widget has keyboard focus. Only gets called
+    /// when focus was moved with the keyboard.
+    fn render_focus_ring(&mut self, theme: &Self::Theme, rect: BoundBox<D2, i32>, clip: BoundBox<D2, i32>);
     fn finish_frame(&mut self, theme: &Self::Theme);
 }
 
 pub trait WidgetRenderer<T: WidgetTheme>: Renderer {
+    /// Draw a widget with the given theme.
+    ///
+    /// `enabled` is whether the widget and all of its ancestors are enabled. Renderers should draw
+    /// disabled widgets in a way that makes it clear they can't be interacted with.
     fn render_widget(
         &mut self,
         widget_id: WidgetId,
         theme: &Self::Theme,
         transform: BoundBox<D2, i32>,
         clip: BoundBox<D2, i32>,
+        enabled: bool,
         widget_theme: T,
         render_widget: impl FnOnce(&mut Self::SubFrame),
     );
@@ -117,6 +135,8 @@ impl Renderer for ! {
 
     fn resized(&mut self, _: DimsBox<D2, u32>) {unreachable!()}
     fn dims(&self) -> DimsBox<D2, u32> {unreachable!()}
+    fn scale_factor(&self) -> f32 {unreachable!()}
+    fn set_scale_factor(&mut self, _: f32) {unreachable!()}
     fn layout(
         &mut self,
         _: WidgetId,
@@ -124,6 +144,7 @@ impl Renderer for ! {
     ) {unreachable!()}
     fn widget_removed(&mut self, _: WidgetId) {unreachable!()}
     fn start_frame(&mut self, _: &Self::Theme) {unreachable!()}
+    fn render_focus_ring(&mut self, _: &Self::Theme, _: BoundBox<D2, i32>, _: BoundBox<D2, i32>) {unreachable!()}
     fn finish_frame(&mut self, _: &Self::Theme) {unreachable!()}
 }
 
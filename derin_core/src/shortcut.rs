@@ -0,0 +1,242 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Keyboard shortcuts that send messages when a sequence of key chords gets typed.
+
+use crate::{
+    message_bus::{Message, MessageTarget, MessageTargeted},
+    widget::WidgetId,
+};
+use derin_common_types::buttons::{Key, ModifierKeys};
+use std::fmt;
+
+id!(pub ShortcutId);
+
+/// A key pressed while holding down a set of modifier keys, such as Ctrl+S.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub key: Key,
+    pub modifiers: ModifierKeys,
+}
+
+/// The result of feeding a key press into the shortcut registry.
+#[derive(Debug)]
+pub(crate) enum ShortcutMatch {
+    /// The key press isn't part of a shortcut, and should be delivered to the focused widget.
+    None,
+    /// The key press started or continued a multi-stroke shortcut.
+    Pending,
+    /// The key press completed a shortcut, which sent the contained message.
+    Triggered(MessageTargeted),
+}
+
+pub(crate) struct ShortcutRegistry {
+    shortcuts: Vec<Shortcut>,
+    /// The chords typed so far in a multi-stroke shortcut.
+    pending: Vec<KeyChord>,
+}
+
+struct Shortcut {
+    id: ShortcutId,
+    chords: Vec<KeyChord>,
+    /// The widget whose subtree has to have focus for the shortcut to be active, or `None` if the
+    /// shortcut is always active.
+    scope: Option<WidgetId>,
+    message: Box<Fn() -> Message>,
+}
+
+impl KeyChord {
+    #[inline]
+    pub fn new(key: Key, modifiers: ModifierKeys) -> KeyChord {
+        KeyChord{ key, modifiers }
+    }
+}
+
+impl From<Key> for KeyChord {
+    #[inline]
+    fn from(key: Key) -> KeyChord {
+        KeyChord::new(key, ModifierKeys::empty())
+    }
+}
+
+impl ShortcutRegistry {
+    pub fn new() -> ShortcutRegistry {
+        ShortcutRegistry {
+            shortcuts: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn register<A>(&mut self, chords: &[KeyChord], scope: Option<WidgetId>, message: A) -> ShortcutId
+        where A: 'static + Clone
+    {
+        assert_ne!(0, chords.len(), "shortcut must have at least one chord");
+
+        let id = ShortcutId::new();
+        self.shortcuts.push(Shortcut {
+            id,
+            chords: chords.to_vec(),
+            scope,
+            message: Box::new(move || Box::new(message.clone()) as Message),
+        });
+        id
+    }
+
+    pub fn unregister(&mut self, id: ShortcutId) {
+        self.shortcuts.retain(|shortcut| shortcut.id != id);
+        self.pending.clear();
+    }
+
+    /// Feed a key press into the registry. `focus_path` is the focused widget, followed by all of
+    /// its ancestors.
+    ///
+    /// If multiple shortcuts match the typed chords, the one whose scope is closest to the focused
+    /// widget wins. Global shortcuts lose out to every scoped shortcut, and ties go to the shortcut
+    /// that was registered last.
+    pub fn key_down(&mut self, chord: KeyChord, focus_path: &[WidgetId]) -> ShortcutMatch {
+        // Pressing a modifier key on its own shouldn't break up a multi-stroke shortcut.
+        match chord.key {
+            Key::LShift | Key::RShift | Key::Shift |
+            Key::LCtrl | Key::RCtrl | Key::Control |
+            Key::LAlt | Key::RAlt | Key::Menu => return ShortcutMatch::None,
+            _ => ()
+        }
+
+        let was_pending = !self.pending.is_empty();
+        self.pending.push(chord);
+
+        let pending = &self.pending;
+        let best_match = self.shortcuts.iter()
+            .filter(|shortcut| shortcut.chords.starts_with(pending))
+            .filter_map(|shortcut| Some((shortcut, scope_depth(shortcut.scope, focus_path)?)))
+            .max_by_key(|&(_, depth)| depth)
+            .map(|(shortcut, _)| shortcut);
+
+        match best_match {
+            Some(shortcut) if shortcut.chords.len() == self.pending.len() => {
+                self.pending.clear();
+                ShortcutMatch::Triggered(MessageTargeted {
+                    message: (shortcut.message)(),
+                    target: shortcut.scope.map(MessageTarget::Widget),
+                })
+            },
+            Some(_) => ShortcutMatch::Pending,
+            None if was_pending => {
+                // The chord broke off a multi-stroke shortcut, but it may still start another one.
+                self.pending.clear();
+                match self.key_down(chord, focus_path) {
+                    // Swallow keys that break off a multi-stroke shortcut, so the focused widget
+                    // doesn't get a stray key press.
+                    ShortcutMatch::None => ShortcutMatch::Pending,
+                    result => result
+                }
+            },
+            None => {
+                self.pending.clear();
+                ShortcutMatch::None
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ShortcutRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("ShortcutRegistry")
+            .field("num_shortcuts", &self.shortcuts.len())
+            .field("pending", &self.pending)
+            .finish()
+    }
+}
+
+/// How deep `scope` is in `focus_path`, or `None` if `scope` isn't active.
+fn scope_depth(scope: Option<WidgetId>, focus_path: &[WidgetId]) -> Option<usize> {
+    match scope {
+        None => Some(0),
+        Some(scope) => focus_path.iter().position(|&id| id == scope).map(|i| focus_path.len() - i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctrl(key: Key) -> KeyChord {
+        KeyChord::new(key, ModifierKeys::CTRL)
+    }
+
+    fn triggered(result: ShortcutMatch) -> Option<(u32, Option<MessageTarget>)> {
+        match result {
+            ShortcutMatch::Triggered(MessageTargeted{message, target}) => Some((*message.downcast::<u32>().unwrap(), target)),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn single_stroke() {
+        let mut registry = ShortcutRegistry::new();
+        registry.register(&[ctrl(Key::S)], None, 0u32);
+
+        assert_eq!(Some((0, None)), triggered(registry.key_down(ctrl(Key::S), &[])));
+        assert!(triggered(registry.key_down(KeyChord::from(Key::S), &[])).is_none());
+    }
+
+    #[test]
+    fn multi_stroke() {
+        let mut registry = ShortcutRegistry::new();
+        registry.register(&[ctrl(Key::K), ctrl(Key::C)], None, 0u32);
+
+        match registry.key_down(ctrl(Key::K), &[]) {
+            ShortcutMatch::Pending => (),
+            result => panic!("expected pending, got {:?}", result)
+        }
+        // Modifier keys don't interrupt the sequence.
+        match registry.key_down(ctrl(Key::LCtrl), &[]) {
+            ShortcutMatch::None => (),
+            result => panic!("expected none, got {:?}", result)
+        }
+        assert_eq!(Some((0, None)), triggered(registry.key_down(ctrl(Key::C), &[])));
+
+        // A chord that doesn't continue the sequence cancels it.
+        registry.key_down(ctrl(Key::K), &[]);
+        match registry.key_down(ctrl(Key::V), &[]) {
+            ShortcutMatch::Pending => (),
+            result => panic!("expected pending, got {:?}", result)
+        }
+        assert!(triggered(registry.key_down(ctrl(Key::C), &[])).is_none());
+    }
+
+    #[test]
+    fn broken_sequence_restarts() {
+        let mut registry = ShortcutRegistry::new();
+        registry.register(&[ctrl(Key::K), ctrl(Key::C)], None, 0u32);
+        registry.register(&[ctrl(Key::S)], None, 1u32);
+
+        // A chord that breaks off a sequence gets matched on its own.
+        registry.key_down(ctrl(Key::K), &[]);
+        assert_eq!(Some((1, None)), triggered(registry.key_down(ctrl(Key::S), &[])));
+
+        // It can also start a new sequence.
+        registry.key_down(ctrl(Key::K), &[]);
+        registry.key_down(ctrl(Key::V), &[]);
+        registry.key_down(ctrl(Key::K), &[]);
+        match registry.key_down(ctrl(Key::K), &[]) {
+            ShortcutMatch::Pending => (),
+            result => panic!("expected pending, got {:?}", result)
+        }
+        assert_eq!(Some((0, None)), triggered(registry.key_down(ctrl(Key::C), &[])));
+    }
+
+    #[test]
+    fn scope_depth_conflicts() {
+        let (root, parent, child) = (WidgetId::new(), WidgetId::new(), WidgetId::new());
+        let mut registry = ShortcutRegistry::new();
+        registry.register(&[ctrl(Key::Z)], Some(parent), 1u32);
+        registry.register(&[ctrl(Key::Z)], None, 0u32);
+        registry.register(&[ctrl(Key::Z)], Some(child), 2u32);
+
+        assert_eq!(Some((2, Some(MessageTarget::Widget(child)))), triggered(registry.key_down(ctrl(Key::Z), &[child, parent, root])));
+        assert_eq!(Some((1, Some(MessageTarget::Widget(parent)))), triggered(registry.key_down(ctrl(Key::Z), &[parent, root])));
+        assert_eq!(Some((0, None)), triggered(registry.key_down(ctrl(Key::Z), &[root])));
+    }
+}
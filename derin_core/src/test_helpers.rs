@@ -2,18 +2,34 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+//! Utilities for testing widgets against the real event translator.
+//!
+//! This module is only available when the `test-helpers` feature is enabled.
+//!
+//! The [`test_widget_tree!`] macro and [`TestWidgetBuilder`] build trees of synthetic widgets. Each
+//! `TestWidget` logs the events it receives into a shared [`EventList`], which can either check
+//! them against a list of expected events as they arrive, or record them for later inspection.
+//! [`TestDriver`] wraps a widget tree in a `Root` and feeds it `WindowEvent`s, which can target
+//! widgets by their identifier path instead of by pixel coordinates.
+//!
+//! [`test_widget_tree!`]: ../macro.test_widget_tree.html
+//! [`TestWidgetBuilder`]: ./struct.TestWidgetBuilder.html
+//! [`EventList`]: ./struct.EventList.html
+//! [`TestDriver`]: ./struct.TestDriver.html
+
 use crate::{
-    LoopFlow,
+    LoopFlow, Root, WindowEvent,
     event::{EventOps, FocusChange, InputState, WidgetEvent, WidgetEventSourced},
-    render::{RenderFrameClipped, RenderFrame, Theme},
+    render::{Renderer, WidgetRenderer, RendererLayout, SubFrame, WidgetTheme, LayoutResult, CursorData, CursorOp},
     widget::*,
 };
 use cgmath_geometry::{
     D2,
-    rect::BoundBox,
+    cgmath::{Point2, Vector2},
+    rect::{DimsBox, GeoBox},
 };
 use derin_common_types::{
-    buttons::Key,
+    buttons::{Key, MouseButton},
     layout::SizeBounds,
 };
 use indexmap::IndexMap;
@@ -23,7 +39,11 @@ use std::{
     rc::Rc,
 };
 
-pub(crate) struct TestWidget {
+#[doc(hidden)]
+pub use cgmath_geometry::rect::BoundBox;
+
+/// A widget that logs every event it receives into an `EventList`.
+pub struct TestWidget {
     pub widget_tag: WidgetTag,
     pub rect: BoundBox<D2, i32>,
     pub size_bounds: SizeBounds,
@@ -35,46 +55,145 @@ pub(crate) struct TestWidget {
     /// - Right Arrow Key: Focus Next
     /// - Left Arrow Key: Focus Previous
     pub focus_controls: bool,
+    /// Bubbles every received event up to the parent widget.
+    pub bubble_events: bool,
+    /// Whether the widget accepts every drag offered to it.
+    pub accepts_drags: bool,
     pub children: Option<IndexMap<WidgetIdent, TestWidget>>,
 }
 
+/// Builds a tree of `TestWidget`s.
+#[derive(Debug, Clone)]
+pub struct TestWidgetBuilder {
+    widget_id: WidgetId,
+    rect: BoundBox<D2, i32>,
+    size_bounds: SizeBounds,
+    focus_controls: bool,
+    accepts_drags: bool,
+    bubble_events: bool,
+    children: Vec<(WidgetIdent, TestWidgetBuilder)>,
+}
+
+/// The list of events received by a tree of `TestWidget`s.
+///
+/// Lists created with `new` check each event against the list of expected events as it's
+/// received, and panic on the first mismatch or if any expected events haven't been received by
+/// the time the list is dropped. Lists created with `recording` don't check anything, and just
+/// record the events for later inspection.
 #[derive(Clone)]
-pub(crate) struct EventList {
+pub struct EventList {
     events: Rc<RefCell<std::vec::IntoIter<TestEvent>>>,
+    received: Rc<RefCell<Vec<TestEvent>>>,
+    strict: bool,
 }
 
+/// An event received by a `TestWidget`.
 #[derive(Debug, Clone, PartialEq)]
-pub(crate) struct TestEvent {
+pub struct TestEvent {
     pub widget: WidgetId,
     pub event: WidgetEvent,
+    /// The path to the child widget the event bubbled up from, or an empty path if the event was
+    /// delivered directly to this widget.
     pub source_child: Vec<WidgetIdent>,
 }
 
+/// A renderer that doesn't draw anything.
+pub struct TestRenderer {
+    dims: DimsBox<D2, u32>,
+    scale_factor: f32,
+    layout: TestLayout,
+    frame: TestSubFrame,
+}
 #[derive(Default)]
-pub(crate) struct TestRenderFrame {}
+pub struct TestSubFrame {}
 #[derive(Default)]
-pub(crate) struct TestTheme {}
+pub struct TestLayout {}
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TestTheme {}
 
-impl Theme for TestTheme {
-    type Key = ();
-    type ThemeValue = ();
+/// Feeds scripted input into a tree of `TestWidget`s.
+pub struct TestDriver {
+    pub root: Root<TestWidget, TestRenderer>,
+}
 
-    fn widget_theme(&self, key: &()) {}
+/// An input event that can target widgets by their identifier path.
+///
+/// Paths are relative to the root widget, so the empty path refers to the root itself.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptedEvent {
+    Window(WindowEvent),
+    /// Move the mouse to the center of the widget at the given path.
+    MouseMoveTo(Vec<WidgetIdent>),
+    /// Move the mouse to the center of the widget at the given path, then press and release the
+    /// given button.
+    Click(Vec<WidgetIdent>, MouseButton),
+}
+
+impl WidgetTheme for TestTheme {
+    type Fallback = !;
+
+    fn fallback(self) -> Option<!> {
+        None
+    }
 }
 
 impl EventList {
     pub fn new() -> EventList {
         EventList {
-            events: Rc::new(RefCell::new(Vec::new().into_iter()))
+            events: Rc::new(RefCell::new(Vec::new().into_iter())),
+            received: Rc::new(RefCell::new(Vec::new())),
+            strict: true,
+        }
+    }
+
+    /// Create an event list that records received events without checking them.
+    pub fn recording() -> EventList {
+        EventList {
+            strict: false,
+            ..EventList::new()
         }
     }
 
+    /// Set the events the widgets are expected to receive, in order.
     pub fn set_events(&self, events: Vec<TestEvent>) {
         *self.events.borrow_mut() = events.into_iter();
     }
 
-    fn next(&self) -> Option<TestEvent> {
-        self.events.borrow_mut().next()
+    /// Retrieve every event received since the list was created or last cleared.
+    pub fn received_events(&self) -> Vec<TestEvent> {
+        self.received.borrow().clone()
+    }
+
+    /// Retrieve the events the given widget received since the list was created or last cleared,
+    /// including events that bubbled up from its children.
+    pub fn received_events_for(&self, widget: WidgetId) -> Vec<TestEvent> {
+        self.received.borrow().iter().filter(|e| e.widget == widget).cloned().collect()
+    }
+
+    pub fn clear_received(&self) {
+        self.received.borrow_mut().clear();
+    }
+
+    /// Assert that exactly `expected` was received since the list was created or last cleared, then
+    /// clear the list.
+    pub fn assert_received(&self, expected: &[TestEvent]) {
+        let received = std::mem::replace(&mut *self.received.borrow_mut(), Vec::new());
+        assert_eq!(expected, &received[..], "received events mismatched w/ expected events");
+    }
+
+    /// Assert that the given widget received exactly `expected` since the list was created or last
+    /// cleared, without clearing the list.
+    pub fn assert_received_for(&self, widget: WidgetId, expected: &[TestEvent]) {
+        let received = self.received_events_for(widget);
+        assert_eq!(expected, &received[..], "events received by {:?} mismatched w/ expected events", widget);
+    }
+
+    fn receive(&self, event: TestEvent) {
+        if self.strict {
+            let ref_event = self.events.borrow_mut().next();
+            assert_eq!(ref_event.as_ref(), Some(&event), "real event mismatched w/ ref event: {:#?}", ref_event);
+        }
+        self.received.borrow_mut().push(event);
     }
 }
 
@@ -86,19 +205,235 @@ impl Drop for EventList {
     }
 }
 
-impl RenderFrame for TestRenderFrame {
+impl TestWidgetBuilder {
+    /// Start building a widget with a new `WidgetId`.
+    pub fn new(rect: BoundBox<D2, i32>) -> TestWidgetBuilder {
+        TestWidgetBuilder::with_id(WidgetId::new(), rect)
+    }
+
+    pub fn with_id(widget_id: WidgetId, rect: BoundBox<D2, i32>) -> TestWidgetBuilder {
+        TestWidgetBuilder {
+            widget_id,
+            rect,
+            size_bounds: SizeBounds::default(),
+            focus_controls: false,
+            accepts_drags: false,
+            bubble_events: false,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn widget_id(&self) -> WidgetId {
+        self.widget_id
+    }
+
+    pub fn size_bounds(mut self, size_bounds: SizeBounds) -> TestWidgetBuilder {
+        self.size_bounds = size_bounds;
+        self
+    }
+
+    /// Enables/disables keyboard focus controls. See `TestWidget::focus_controls`.
+    pub fn focus_controls(mut self, focus_controls: bool) -> TestWidgetBuilder {
+        self.focus_controls = focus_controls;
+        self
+    }
+
+    /// Makes the widget accept every drag offered to it. See `TestWidget::accepts_drags`.
+    pub fn accepts_drags(mut self, accepts_drags: bool) -> TestWidgetBuilder {
+        self.accepts_drags = accepts_drags;
+        self
+    }
+
+    /// Enables/disables bubbling received events. See `TestWidget::bubble_events`.
+    pub fn bubble_events(mut self, bubble_events: bool) -> TestWidgetBuilder {
+        self.bubble_events = bubble_events;
+        self
+    }
+
+    pub fn child(mut self, ident: WidgetIdent, child: TestWidgetBuilder) -> TestWidgetBuilder {
+        self.children.push((ident, child));
+        self
+    }
+
+    /// Build the widget tree, logging received events into `event_list`.
+    pub fn build(self, event_list: &EventList) -> TestWidget {
+        let mut widget_tag = WidgetTag::new();
+        widget_tag.widget_id = self.widget_id;
+
+        let children: IndexMap<_, _> = self.children.into_iter()
+            .map(|(ident, child)| (ident, child.build(event_list)))
+            .collect();
+
+        TestWidget {
+            widget_tag,
+            rect: self.rect,
+            size_bounds: self.size_bounds,
+            event_list: event_list.clone(),
+            focus_controls: self.focus_controls,
+            bubble_events: self.bubble_events,
+            accepts_drags: self.accepts_drags,
+            children: match children.len() {
+                0 => None,
+                _ => Some(children)
+            }
+        }
+    }
+}
+
+impl TestWidget {
+    /// Retrieve the descendant at the given identifier path.
+    pub fn descendant(&self, path: &[WidgetIdent]) -> Option<&TestWidget> {
+        match path.split_first() {
+            Some((ident, rest)) => self.children.as_ref()?.get(ident)?.descendant(rest),
+            None => Some(self)
+        }
+    }
+
+    /// Retrieve the rectangle of the descendant at the given identifier path, relative to this
+    /// widget's parent.
+    pub fn descendant_rect(&self, path: &[WidgetIdent]) -> Option<BoundBox<D2, i32>> {
+        match path.split_first() {
+            Some((ident, rest)) => {
+                let child_rect = self.children.as_ref()?.get(ident)?.descendant_rect(rest)?;
+                Some(child_rect + Vector2::new(self.rect.min.x, self.rect.min.y))
+            },
+            None => Some(self.rect)
+        }
+    }
+}
+
+impl TestDriver {
+    /// Create a new driver with a window of the given size.
+    ///
+    /// Note that the root widget's rectangle gets resized to fill the window.
+    pub fn new(root_widget: TestWidget, dims: DimsBox<D2, u32>) -> TestDriver {
+        TestDriver {
+            root: Root::new(root_widget, TestTheme {}, TestRenderer::new(dims), dims),
+        }
+    }
+
+    /// Retrieve the `WidgetId` of the widget at the given identifier path.
+    pub fn widget_id(&self, path: &[WidgetIdent]) -> Option<WidgetId> {
+        self.root.root_widget.descendant(path).map(|w| w.widget_id())
+    }
+
+    /// Retrieve the window-space point at the center of the widget at the given identifier path.
+    pub fn widget_center(&self, path: &[WidgetIdent]) -> Option<Point2<i32>> {
+        let rect = self.root.root_widget.descendant_rect(path)?;
+        Some(Point2::new(rect.min.x + rect.width() / 2, rect.min.y + rect.height() / 2))
+    }
+
+    /// Feed a sequence of events to the widget tree, as if they were received in a single frame.
+    ///
+    /// Panics if an event targets a widget path that doesn't exist.
+    pub fn process_events(&mut self, events: impl IntoIterator<Item=ScriptedEvent>) {
+        let events = events.into_iter().flat_map(|event| {
+            let mut window_events = Vec::new();
+            match event {
+                ScriptedEvent::Window(event) => window_events.push(event),
+                ScriptedEvent::MouseMoveTo(path) => window_events.push(WindowEvent::MouseMove(self.expect_center(&path))),
+                ScriptedEvent::Click(path, button) => window_events.extend_from_slice(&[
+                    WindowEvent::MouseMove(self.expect_center(&path)),
+                    WindowEvent::MouseDown(button),
+                    WindowEvent::MouseUp(button),
+                ]),
+            }
+            window_events
+        }).collect::<Vec<_>>();
+
+        let mut frame = self.root.start_frame();
+        for event in events {
+            frame.process_event(event);
+        }
+        let _ = frame.finish();
+    }
+
+    fn expect_center(&self, path: &[WidgetIdent]) -> Point2<i32> {
+        self.widget_center(path).unwrap_or_else(|| panic!("no widget at path {:?}", path))
+    }
+}
+
+impl From<WindowEvent> for ScriptedEvent {
+    fn from(event: WindowEvent) -> ScriptedEvent {
+        ScriptedEvent::Window(event)
+    }
+}
+
+impl TestRenderer {
+    pub fn new(dims: DimsBox<D2, u32>) -> TestRenderer {
+        TestRenderer {
+            dims,
+            scale_factor: 1.0,
+            layout: TestLayout::default(),
+            frame: TestSubFrame::default(),
+        }
+    }
+}
+
+impl Renderer for TestRenderer {
+    type SubFrame = TestSubFrame;
     type Theme = TestTheme;
-    type Primitive = ();
+    type Layout = TestLayout;
+
+    fn resized(&mut self, new_size: DimsBox<D2, u32>) {
+        self.dims = new_size;
+    }
+    fn dims(&self) -> DimsBox<D2, u32> {
+        self.dims
+    }
+    fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+    fn set_scale_factor(&mut self, scale_factor: f32) {
+        self.scale_factor = scale_factor;
+    }
+    fn widget_removed(&mut self, _: WidgetId) {}
+    fn layout(
+        &mut self,
+        _: WidgetId,
+        layout: impl FnOnce(&mut TestLayout)
+    ) {
+        layout(&mut self.layout)
+    }
+    fn start_frame(&mut self, _: &TestTheme) {}
+    fn render_focus_ring(&mut self, _: &TestTheme, _: BoundBox<D2, i32>, _: BoundBox<D2, i32>) {}
+    fn finish_frame(&mut self, _: &TestTheme) {}
+}
+
+impl<T: WidgetTheme> WidgetRenderer<T> for TestRenderer {
+    fn render_widget(
+        &mut self,
+        _: WidgetId,
+        _: &TestTheme,
+        _: BoundBox<D2, i32>,
+        _: BoundBox<D2, i32>,
+        _: bool,
+        _: T,
+        render_widget: impl FnOnce(&mut TestSubFrame),
+    ) {
+        render_widget(&mut self.frame)
+    }
+}
+
+impl SubFrame for TestSubFrame {
+    fn render_laid_out_content(&mut self) {}
+}
 
-    fn upload_primitives<I>(
+impl RendererLayout for TestLayout {
+    fn prepare_string(&mut self, _: &str) {}
+    fn prepare_edit_string(
         &mut self,
-        _theme: &TestTheme,
-        _transform: BoundBox<D2, i32>,
-        _clip: BoundBox<D2, i32>,
-        _prim_iter: I
-    )
-        where I: Iterator<Item=()>
-    {}
+        _: &mut String,
+        _: &mut CursorData,
+        _: impl Iterator<Item=CursorOp>,
+    ) {}
+    fn prepare_icon(&mut self, _: &str) {}
+    fn finish(&mut self) -> LayoutResult {
+        LayoutResult {
+            size_bounds: SizeBounds::default(),
+            content_rect: BoundBox::new2(0, 0, 0, 0),
+        }
+    }
 }
 
 impl Widget for TestWidget {
@@ -117,16 +452,15 @@ impl Widget for TestWidget {
     fn on_widget_event(
         &mut self,
         event: WidgetEventSourced,
-        input_state: InputState,
+        _: InputState,
     ) -> EventOps {
         let (event, source_child) = match event {
             WidgetEventSourced::This(event) => (event, &[][..]),
             WidgetEventSourced::Bubble(event, child) => (event, child)
         };
-        let ref_event = self.event_list.next();
         let mut focus = None;
 
-        if self.focus_controls && source_child.len() == 0 {
+        if self.focus_controls && source_child.is_empty() {
             match event {
                 WidgetEvent::MouseDown{in_widget: true, ..} => focus = Some(FocusChange::Take),
                 WidgetEvent::KeyDown(Key::Escape, _) => focus = Some(FocusChange::Remove),
@@ -136,17 +470,23 @@ impl Widget for TestWidget {
             }
         }
 
-        let real_event = TestEvent {
+        if self.accepts_drags && source_child.is_empty() {
+            match event {
+                WidgetEvent::DragEnter{..} |
+                WidgetEvent::DragOver{..} => self.widget_tag.accept_drag(),
+                _ => ()
+            }
+        }
+
+        self.event_list.receive(TestEvent {
             widget: self.widget_tag.widget_id,
             event,
             source_child: source_child.to_vec()
-        };
-        println!("real event: {:#?}", real_event);
-        assert_eq!(ref_event.as_ref(), Some(&real_event), "real event mismatched w/ ref event: {:#?}", ref_event);
+        });
 
         EventOps {
             focus,
-            ..EventOps::default()
+            bubble: self.bubble_events,
         }
     }
 
@@ -155,8 +495,14 @@ impl Widget for TestWidget {
     }
 }
 
-impl<F: RenderFrame> WidgetRenderable<F> for TestWidget {
-    fn render(&mut self, _frame: &mut RenderFrameClipped<F>) {}
+impl<R: Renderer> WidgetRenderable<R> for TestWidget {
+    type Theme = TestTheme;
+
+    fn theme(&self) -> TestTheme {
+        TestTheme {}
+    }
+
+    fn render(&mut self, _frame: &mut R::SubFrame) {}
 }
 
 impl Parent for TestWidget {
@@ -164,31 +510,31 @@ impl Parent for TestWidget {
         self.children.as_ref().map(|c| c.len()).unwrap_or(0)
     }
 
-    fn framed_child<F: RenderFrame>(&self, ident: WidgetIdent) -> Option<WidgetInfo<'_, F>> {
+    fn framed_child<R: Renderer>(&self, ident: WidgetIdent) -> Option<WidgetInfo<'_, R>> {
         self.children.as_ref()
             .and_then(|c| c.get_full(&ident))
             .map(|(index, _, widget)| WidgetInfo::new(ident, index, widget))
     }
-    fn framed_child_mut<F: RenderFrame>(&mut self, ident: WidgetIdent) -> Option<WidgetInfoMut<'_, F>> {
+    fn framed_child_mut<R: Renderer>(&mut self, ident: WidgetIdent) -> Option<WidgetInfoMut<'_, R>> {
         self.children.as_mut()
             .and_then(|c| c.get_full_mut(&ident))
             .map(|(index, _, widget)| WidgetInfoMut::new(ident, index, widget))
     }
 
-    fn framed_child_by_index<F: RenderFrame>(&self, index: usize) -> Option<WidgetInfo<'_, F>> {
+    fn framed_child_by_index<R: Renderer>(&self, index: usize) -> Option<WidgetInfo<'_, R>> {
         self.children.as_ref()
             .and_then(|c| c.get_index(index))
             .map(|(ident, widget)| WidgetInfo::new(ident.clone(), index, widget))
     }
-    fn framed_child_by_index_mut<F: RenderFrame>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, F>> {
+    fn framed_child_by_index_mut<R: Renderer>(&mut self, index: usize) -> Option<WidgetInfoMut<'_, R>> {
         self.children.as_mut()
             .and_then(|c| c.get_index_mut(index))
             .map(|(ident, widget)| WidgetInfoMut::new(ident.clone(), index, widget))
     }
 
-    fn framed_children<'a, F, G>(&'a self, mut for_each: G)
-        where F: RenderFrame,
-              G: FnMut(WidgetInfo<'a, F>) -> LoopFlow
+    fn framed_children<'a, R, G>(&'a self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfo<'a, R>) -> LoopFlow
     {
         for (index, (ident, widget)) in self.children.as_ref().into_iter().flat_map(|c| c.iter().enumerate()) {
             let flow = for_each(WidgetInfo::new(ident.clone(), index, widget));
@@ -197,9 +543,9 @@ impl Parent for TestWidget {
             }
         }
     }
-    fn framed_children_mut<'a, F, G>(&'a mut self, mut for_each: G)
-        where F: RenderFrame,
-              G: FnMut(WidgetInfoMut<'a, F>) -> LoopFlow
+    fn framed_children_mut<'a, R, G>(&'a mut self, mut for_each: G)
+        where R: Renderer,
+              G: FnMut(WidgetInfoMut<'a, R>) -> LoopFlow
     {
         for (index, (ident, widget)) in self.children.as_mut().into_iter().flat_map(|c| c.iter_mut().enumerate()) {
             let flow = for_each(WidgetInfoMut::new(ident.clone(), index, widget));
@@ -210,98 +556,73 @@ impl Parent for TestWidget {
     }
 }
 
-macro_rules! extract_widget_tree_idents {
-    ($($widget_ident:ident {
-        rect: ($x:expr, $y:expr, $w:expr, $h:expr)
-        $(, focus_controls: $focus_controls:expr)?
-        $(;$($children:tt)*)?
-    }),*) => {$(
-        let $widget_ident = crate::widget::WidgetId::new();
-        println!("widget {} = {:?}", stringify!($widget_ident), $widget_ident);
-
-        extract_widget_tree_idents!{$($($children)*)*}
-    )*};
-}
-
+/// Build a tree of `TestWidget`s, binding each widget's `WidgetId` to a variable with the same name
+/// as the widget.
+///
+/// ```ignore
+/// test_widget_tree!{
+///     let event_list = EventList::new();
+///     let tree = root {
+///         rect: (0, 0, 500, 500);
+///         left {
+///             rect: (10, 10, 240, 490), focus_controls: true;
+///             tl {rect: (10, 10, 220, 230)},
+///             bl {rect: (10, 250, 220, 470)}
+///         },
+///         right {rect: (260, 10, 490, 490)}
+///     };
+/// }
+/// ```
+#[macro_export]
 macro_rules! test_widget_tree {
     (
         let $event_list:ident = $event_list_expr:expr;
         let $root_pat:pat = $root:ident {
-            rect: ($x:expr, $y:expr, $w:expr, $h:expr)
-            $(, focus_controls: $focus_controls:expr)?
-            $(;$($rest:tt)*)?
+            $($root_body:tt)*
         };
     ) => {
-        extract_widget_tree_idents!{
-            $root {
-                rect: ($x, $y, $w, $h)
-                $(;$($rest)*)?
-            }
-        }
-        let $event_list: crate::test_helpers::EventList = $event_list_expr;
-        let $root_pat = {
-            #[allow(unused_mut)]
-            {
-                use std::sync::Arc;
-                let mut children = indexmap::IndexMap::new();
-                test_widget_tree!(
-                    @insert
-                    $event_list,
-                    children,
-                    $($($rest)*)*
-                );
-
-                let mut widget_tag = crate::widget::WidgetTag::new();
-                widget_tag.widget_id = $root;
-
-                let root = crate::test_helpers::TestWidget {
-                    widget_tag,
-                    rect: cgmath_geometry::rect::BoundBox::new2($x, $y, $w, $h),
-                    size_bounds: derin_common_types::layout::SizeBounds::default(),
-                    event_list: $event_list.clone(),
-                    focus_controls: $($focus_controls ||)? false,
-                    children: match children.len() {
-                        0 => None,
-                        _ => Some(children)
-                    }
-                };
-                root
-            }
-        };
+        $crate::test_widget_tree!{@idents $root { $($root_body)* }}
+        let $event_list: $crate::test_helpers::EventList = $event_list_expr;
+        let $root_pat = $crate::test_widget_tree!(@builder $root { $($root_body)* }).build(&$event_list);
     };
     (
-        @insert $event_list:expr, $widget_map:ident,
-        $($child:ident {
+        @idents
+        $($widget_ident:ident {
             rect: ($x:expr, $y:expr, $w:expr, $h:expr)
             $(, focus_controls: $focus_controls:expr)?
+            $(, accepts_drags: $accepts_drags:expr)?
             $(;$($children:tt)*)?
         }),*
-    ) => {$({
-        let mut children = indexmap::IndexMap::new();
-        test_widget_tree!(
-            @insert
-            $event_list,
-            children,
-            $($($children)*)*
-        );
-
-        let mut widget_tag = crate::widget::WidgetTag::new();
-        widget_tag.widget_id = $child;
-
-        let widget = crate::test_helpers::TestWidget {
-            widget_tag,
-            rect: cgmath_geometry::rect::BoundBox::new2($x, $y, $w, $h),
-            size_bounds: derin_common_types::layout::SizeBounds::default(),
-            event_list: $event_list.clone(),
-            focus_controls: $($focus_controls ||)? false,
-            children: match children.len() {
-                0 => None,
-                _ => Some(children)
-            }
-        };
+    ) => {$(
+        let $widget_ident = $crate::widget::WidgetId::new();
 
-        $widget_map.insert(crate::widget::WidgetIdent::Str(Arc::from(stringify!($child))), widget);
-    })*};
+        $crate::test_widget_tree!{@idents $($($children)*)*}
+    )*};
+    (
+        @builder
+        $widget_ident:ident {
+            rect: ($x:expr, $y:expr, $w:expr, $h:expr)
+            $(, focus_controls: $focus_controls:expr)?
+            $(, accepts_drags: $accepts_drags:expr)?
+            $(;$($children:tt)*)?
+        }
+    ) => {{
+        let builder = $crate::test_helpers::TestWidgetBuilder::with_id(
+            $widget_ident,
+            $crate::test_helpers::BoundBox::new2($x, $y, $w, $h),
+        ).focus_controls($($focus_controls ||)? false)
+            .accepts_drags($($accepts_drags ||)? false);
+        $crate::test_widget_tree!(@children builder, $($($children)*)*)
+    }};
+    (
+        @children $builder:ident,
+        $($child:ident { $($child_body:tt)* }),*
+    ) => {
+        $builder$(.child(
+            $crate::widget::WidgetIdent::new_str(stringify!($child)),
+            $crate::test_widget_tree!(@builder $child { $($child_body)* })
+        ))*
+    };
 }
 
 #[cfg(test)]
@@ -310,12 +631,12 @@ mod tests {
     use crate::widget::WidgetDyn;
 
     fn check_child_widget(
-        parent: &dyn WidgetDyn<TestRenderFrame>,
+        parent: &dyn WidgetDyn<TestRenderer>,
         index: usize,
         ident: WidgetIdent,
         id: WidgetId,
         rect: BoundBox<D2, i32>,
-    ) -> &dyn WidgetDyn<TestRenderFrame> {
+    ) -> &dyn WidgetDyn<TestRenderer> {
         let summary_by_ident = parent.child(ident.clone()).expect(&format!("Could not find child by ident: {} {:?}", index, ident));
         let summary_by_index = parent.child_by_index(index).expect(&format!("Could not find child by index: {} {:?}", index, ident));
 
@@ -359,6 +680,88 @@ mod tests {
         check_child_widget(left_widget, 1, WidgetIdent::new_str("bl"), bl, BoundBox::new2(10, 250, 220, 470));
     }
 
+    #[test]
+    fn driver_target_by_path() {
+        let event_list = EventList::recording();
+        let child = TestWidgetBuilder::new(BoundBox::new2(10, 10, 30, 30))
+            .child(WidgetIdent::new_str("inner"), TestWidgetBuilder::new(BoundBox::new2(0, 0, 4, 4)))
+            .focus_controls(true);
+        let child_id = child.widget_id();
+        let tree = TestWidgetBuilder::new(BoundBox::new2(0, 0, 100, 100))
+            .child(WidgetIdent::new_str("child"), child)
+            .build(&event_list);
+
+        let mut driver = TestDriver::new(tree, DimsBox::new2(100, 100));
+        let child_path = vec![WidgetIdent::new_str("child")];
+        let inner_path = vec![WidgetIdent::new_str("child"), WidgetIdent::new_str("inner")];
+        assert_eq!(driver.widget_id(&child_path), Some(child_id));
+        assert_eq!(driver.widget_center(&child_path), Some(Point2::new(20, 20)));
+        assert_eq!(driver.widget_center(&inner_path), Some(Point2::new(12, 12)));
+        assert_eq!(driver.widget_center(&[WidgetIdent::new_str("missing")]), None);
+
+        driver.process_events(vec![
+            WindowEvent::MouseEnter.into(),
+            ScriptedEvent::Click(vec![WidgetIdent::new_str("child")], MouseButton::Left),
+        ]);
+
+        let child_events = event_list.received_events_for(child_id);
+        assert!(child_events.iter().any(|e| match e.event {
+            WidgetEvent::MouseDown{in_widget: true, button: MouseButton::Left, ..} => true,
+            _ => false
+        }));
+        assert!(child_events.iter().any(|e| match e.event {
+            WidgetEvent::GainFocus(..) => true,
+            _ => false
+        }));
+        assert!(event_list.received_events().iter().all(|e| e.source_child.is_empty()));
+
+        event_list.clear_received();
+        event_list.assert_received(&[]);
+    }
+
+    #[test]
+    fn driver_bubble_source_path() {
+        let event_list = EventList::recording();
+        let inner = TestWidgetBuilder::new(BoundBox::new2(0, 0, 4, 4)).bubble_events(true);
+        let inner_id = inner.widget_id();
+        let child = TestWidgetBuilder::new(BoundBox::new2(10, 10, 30, 30))
+            .child(WidgetIdent::new_str("inner"), inner)
+            .bubble_events(true);
+        let child_id = child.widget_id();
+        let root = TestWidgetBuilder::new(BoundBox::new2(0, 0, 100, 100))
+            .child(WidgetIdent::new_str("child"), child);
+        let root_id = root.widget_id();
+
+        let mut driver = TestDriver::new(root.build(&event_list), DimsBox::new2(100, 100));
+        let inner_path = vec![WidgetIdent::new_str("child"), WidgetIdent::new_str("inner")];
+        driver.process_events(vec![WindowEvent::MouseEnter.into()]);
+        driver.process_events(vec![ScriptedEvent::MouseMoveTo(inner_path.clone())]);
+        event_list.clear_received();
+
+        driver.process_events(vec![WindowEvent::MouseDown(MouseButton::Left).into()]);
+
+        let mouse_down = |pos| WidgetEvent::MouseDown {
+            pos,
+            in_widget: true,
+            button: MouseButton::Left,
+        };
+        event_list.assert_received_for(inner_id, &[TestEvent {
+            widget: inner_id,
+            event: mouse_down(Point2::new(2, 2)),
+            source_child: vec![],
+        }]);
+        event_list.assert_received_for(child_id, &[TestEvent {
+            widget: child_id,
+            event: mouse_down(Point2::new(2, 2)),
+            source_child: vec![WidgetIdent::new_str("inner")],
+        }]);
+        event_list.assert_received_for(root_id, &[TestEvent {
+            widget: root_id,
+            event: mouse_down(Point2::new(12, 12)),
+            source_child: inner_path,
+        }]);
+    }
+
     #[test]
     #[should_panic]
     fn event_list_force_clear() {
@@ -3,13 +3,17 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use crate::{
+    drag::{DragImage, DragState},
     message_bus::{Message, MessageTarget, MessageTargeted, MessageBus},
     cgmath::Point2,
+    overlay::{Overlay, OverlayLayer},
     widget::WidgetId,
 };
 use derin_common_types::cursor::CursorIcon;
+use cgmath_geometry::{D2, rect::BoundBox};
 use fnv::FnvHashSet;
 use std::{
+    any::Any,
     mem,
     rc::{Rc, Weak},
     sync::mpsc::Sender,
@@ -35,11 +39,20 @@ pub(crate) type UpdateStateCell = RefCell<UpdateState>;
 pub(crate) struct UpdateState {
     pub redraw: FnvHashSet<WidgetId>,
     pub relayout: FnvHashSet<WidgetId>,
+    /// Widgets whose parents need to be re-laid-out.
+    pub relayout_parent: FnvHashSet<WidgetId>,
     pub update_timers: FnvHashSet<WidgetId>,
     pub update_messages: FnvHashSet<WidgetId>,
     pub remove_from_tree: FnvHashSet<WidgetId>,
     pub set_cursor_icon: Option<CursorIcon>,
     pub set_cursor_pos: Option<(WidgetId, Point2<i32>)>,
+    /// Widgets that should be scrolled into view, alongside the part of the widget to show. `None`
+    /// shows the whole widget.
+    pub scroll_into_view: Vec<(WidgetId, Option<BoundBox<D2, i32>>)>,
+    /// The overlays being shown, ordered from bottom to top.
+    pub overlays: Vec<OverlayLayer>,
+    /// The drag-and-drop operation in progress, if any.
+    pub drag: Option<DragState>,
     pub message_sender: Sender<MessageTargeted>,
     pub global_update: bool,
 }
@@ -47,6 +60,8 @@ pub(crate) struct UpdateState {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UpdateError {
     NoRootWidget,
+    /// Another drag-and-drop operation is already in progress.
+    DragInProgress,
 }
 
 impl UpdateState {
@@ -55,11 +70,15 @@ impl UpdateState {
             RefCell::new(UpdateState {
                 redraw: FnvHashSet::default(),
                 relayout: FnvHashSet::default(),
+                relayout_parent: FnvHashSet::default(),
                 update_timers: FnvHashSet::default(),
                 update_messages: FnvHashSet::default(),
                 remove_from_tree: FnvHashSet::default(),
                 set_cursor_icon: None,
                 set_cursor_pos: None,
+                scroll_into_view: Vec::new(),
+                overlays: Vec::new(),
+                drag: None,
                 message_sender: message_bus.sender(),
                 global_update: true,
             })
@@ -80,6 +99,10 @@ impl UpdateState {
     pub fn reset_global_update(&mut self) {
         self.global_update = false;
     }
+
+    pub fn overlay_ids(&self) -> impl '_ + Iterator<Item=WidgetId> {
+        self.overlays.iter().map(|layer| layer.widget_id)
+    }
 }
 
 impl UpdateStateShared {
@@ -138,6 +161,7 @@ impl UpdateStateShared {
                         let mut old_state = old_state.borrow_mut();
                         old_state.redraw.remove(&id);
                         old_state.relayout.remove(&id);
+                        old_state.relayout_parent.remove(&id);
                         old_state.remove_from_tree.insert(id);
                     }
 
@@ -171,6 +195,26 @@ impl UpdateStateShared {
         });
     }
 
+    pub fn request_relayout_parent(&mut self, id: WidgetId) {
+        self.upgrade(|this| match this {
+            UpdateStateShared::Occupied(update_state) => {
+                update_state.borrow_mut().relayout_parent.insert(id);
+            },
+            // Ditto.
+            UpdateStateShared::Vacant(_) => ()
+        });
+    }
+
+    pub fn request_scroll_into_view(&mut self, id: WidgetId, rect: Option<BoundBox<D2, i32>>) {
+        self.upgrade(|this| match this {
+            UpdateStateShared::Occupied(update_state) => {
+                update_state.borrow_mut().scroll_into_view.push((id, rect));
+            },
+            // The widget hasn't been added to a tree yet, so there's nothing to scroll.
+            UpdateStateShared::Vacant(_) => ()
+        });
+    }
+
     pub fn request_update_timers(&mut self, id: WidgetId) {
         self.upgrade(|this| match this {
             UpdateStateShared::Occupied(update_state) => {
@@ -231,12 +275,73 @@ impl UpdateStateShared {
         })
     }
 
+    pub fn request_show_overlay(&mut self, owner: WidgetId, id: WidgetId, overlay: Overlay) -> Result<(), UpdateError> {
+        self.upgrade(|this| match this {
+            UpdateStateShared::Occupied(update_state) => {
+                let mut update_state = update_state.borrow_mut();
+                // Showing an overlay that's already visible moves it to the top.
+                update_state.overlays.retain(|layer| layer.widget_id != id);
+                update_state.overlays.push(OverlayLayer{ widget_id: id, owner, overlay });
+                update_state.relayout.insert(id);
+                update_state.redraw.insert(id);
+                Ok(())
+            },
+            UpdateStateShared::Vacant(_) => Err(UpdateError::NoRootWidget)
+        })
+    }
+
+    pub fn request_hide_overlay(&mut self, id: WidgetId) {
+        self.upgrade(|this| match this {
+            UpdateStateShared::Occupied(update_state) => {
+                let mut update_state = update_state.borrow_mut();
+                let num_overlays = update_state.overlays.len();
+                update_state.overlays.retain(|layer| layer.widget_id != id);
+                if update_state.overlays.len() != num_overlays {
+                    update_state.queue_global_update();
+                }
+            },
+            UpdateStateShared::Vacant(_) => ()
+        });
+    }
+
+    pub fn request_start_drag(&mut self, source: WidgetId, payload: Box<Any>, image: Option<DragImage>) -> Result<(), UpdateError> {
+        self.upgrade(|this| match this {
+            UpdateStateShared::Occupied(update_state) => {
+                let mut update_state = update_state.borrow_mut();
+                if update_state.drag.is_some() {
+                    return Err(UpdateError::DragInProgress);
+                }
+                if let Some(image) = image {
+                    update_state.redraw.insert(image.widget);
+                }
+                update_state.drag = Some(DragState::new(source, payload, image));
+                Ok(())
+            },
+            UpdateStateShared::Vacant(_) => Err(UpdateError::NoRootWidget)
+        })
+    }
+
+    pub fn accept_drag(&mut self) {
+        self.upgrade(|this| match this {
+            UpdateStateShared::Occupied(update_state) => {
+                let mut update_state = update_state.borrow_mut();
+                if let Some(ref mut drag) = update_state.drag {
+                    drag.accepted = true;
+                }
+            },
+            // Widgets outside of the tree can't be dragged over.
+            UpdateStateShared::Vacant(_) => ()
+        });
+    }
+
     pub fn remove_from_tree(&mut self, id: WidgetId) {
         self.upgrade(|this| match this {
             UpdateStateShared::Occupied(update_state) => {
                 let mut update_state = update_state.borrow_mut();
+                update_state.overlays.retain(|layer| layer.widget_id != id && layer.owner != id);
                 update_state.redraw.remove(&id);
                 update_state.relayout.remove(&id);
+                update_state.relayout_parent.remove(&id);
                 update_state.update_timers.remove(&id);
                 update_state.update_messages.remove(&id);
                 update_state.remove_from_tree.insert(id);
@@ -11,8 +11,10 @@ pub use crate::{
 
 use crate::{
     LoopFlow,
+    drag::DragImage,
     event::{WidgetEventSourced, EventOps, InputState},
     message_bus::{WidgetMessageKey, WidgetMessageFn},
+    overlay::Overlay,
     render::{Renderer, WidgetTheme},
     timer::{TimerId, Timer},
     update_state::{UpdateStateShared, UpdateStateCell},
@@ -52,6 +54,11 @@ pub struct WidgetTag {
     registered_messages: FnvHashMap<WidgetMessageKey, Cell<SmallVec<[WidgetMessageFn; 1]>>>,
     pub(crate) widget_id: WidgetId,
     pub(crate) timers: FnvHashMap<TimerId, Timer>,
+    enabled: Cell<bool>,
+    visibility: Cell<Visibility>,
+    focusable: Cell<bool>,
+    tab_index: Cell<Option<u32>>,
+    focus_group: Cell<bool>,
 }
 
 impl fmt::Debug for WidgetTag {
@@ -72,6 +79,26 @@ impl Clone for WidgetTag {
 
 id!(pub WidgetId);
 
+/// Whether a widget gets drawn, and whether it takes up space in its parent's layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Visibility {
+    /// The widget gets drawn and hit-tested as normal.
+    Visible,
+    /// The widget keeps its place in its parent's layout, but isn't drawn and doesn't receive
+    /// mouse input.
+    Hidden,
+    /// The widget isn't drawn, doesn't receive mouse input, and takes up no space in its parent's
+    /// layout.
+    Collapsed,
+}
+
+impl Default for Visibility {
+    #[inline]
+    fn default() -> Visibility {
+        Visibility::Visible
+    }
+}
+
 
 /// The base widget trait.
 ///
@@ -95,6 +122,23 @@ pub trait Widget: 'static {
         SizeBounds::default()
     }
 
+    /// The rectangle to draw the focus ring around, in the same coordinate space as `rect`.
+    ///
+    /// Defaults to `rect`. Widgets can override this to put the ring around part of themselves,
+    /// such as the box of a check box.
+    fn focus_rect(&self) -> BoundBox<D2, i32> {
+        self.rect()
+    }
+
+    /// Scroll so that `rect` is visible, if this widget scrolls its children. `rect` is in the same
+    /// coordinate space as the rects of this widget's children.
+    ///
+    /// Returns where `rect` ended up after scrolling, which gets passed on to this widget's parent
+    /// so that it can scroll `rect` into view as well. The default implementation doesn't scroll.
+    fn scroll_into_view(&mut self, rect: BoundBox<D2, i32>) -> BoundBox<D2, i32> {
+        rect
+    }
+
     #[doc(hidden)]
     fn dispatch_message(&mut self, message: &Any) {
         let message_key = WidgetMessageKey::from_dyn_message::<Self>(message);
@@ -460,6 +504,11 @@ impl WidgetTag {
             widget_id: WidgetId::new(),
             registered_messages: FnvHashMap::default(),
             timers: FnvHashMap::default(),
+            enabled: Cell::new(true),
+            visibility: Cell::new(Visibility::Visible),
+            focusable: Cell::new(false),
+            tab_index: Cell::new(None),
+            focus_group: Cell::new(false),
         }
     }
 
@@ -480,6 +529,29 @@ impl WidgetTag {
         self
     }
 
+    /// Ask the widget's ancestors to scroll so that the widget is visible.
+    #[inline]
+    pub fn request_scroll_into_view(&mut self) -> &mut WidgetTag {
+        self.update_state.get_mut().request_scroll_into_view(self.widget_id, None);
+        self
+    }
+
+    /// Ask the widget's ancestors to scroll so that `rect` is visible. `rect` is relative to the
+    /// widget's origin, and can be used to show part of the widget, such as a text caret.
+    #[inline]
+    pub fn request_scroll_rect_into_view(&mut self, rect: BoundBox<D2, i32>) -> &mut WidgetTag {
+        self.update_state.get_mut().request_scroll_into_view(self.widget_id, Some(rect));
+        self
+    }
+
+    /// Re-lay-out the widget's parent, even if the widget's own rect and size bounds haven't
+    /// changed.
+    #[inline]
+    pub fn request_relayout_parent(&mut self) -> &mut WidgetTag {
+        self.update_state.get_mut().request_relayout_parent(self.widget_id);
+        self
+    }
+
     pub fn timers(&self) -> &FnvHashMap<TimerId, Timer> {
         &self.timers
     }
@@ -527,6 +599,123 @@ impl WidgetTag {
         self.update_state.get_mut().request_set_cursor_icon(cursor_icon)
     }
 
+    /// Show the widget `overlay` above the rest of the widget tree. `overlay` must be a
+    /// descendant of this widget.
+    ///
+    /// If the overlay gets dismissed by a click outside of it, this widget gets sent an
+    /// `OverlayDismissed` message.
+    pub fn show_overlay(&mut self, overlay: WidgetId, config: Overlay) -> Result<(), UpdateError> {
+        self.update_state.get_mut().request_show_overlay(self.widget_id, overlay, config)
+    }
+
+    /// Stop showing `overlay` above the rest of the widget tree.
+    pub fn hide_overlay(&mut self, overlay: WidgetId) {
+        self.update_state.get_mut().request_hide_overlay(overlay)
+    }
+
+    /// Start dragging `payload`, drawing `image` under the cursor until the drag ends.
+    ///
+    /// This widget gets sent a `DragFinished` message once the payload gets dropped or the drag
+    /// gets cancelled. See the `drag` module for details.
+    pub fn start_drag(&mut self, payload: Box<Any>, image: Option<DragImage>) -> Result<(), UpdateError> {
+        self.update_state.get_mut().request_start_drag(self.widget_id, payload, image)
+    }
+
+    /// Accept the payload of the drag being offered to this widget.
+    ///
+    /// This only has an effect while handling `DragEnter` or `DragOver`, and needs to be called
+    /// every time one of those gets delivered to keep accepting the payload.
+    pub fn accept_drag(&mut self) {
+        self.update_state.get_mut().accept_drag()
+    }
+
+    /// Whether or not this widget has been enabled with `set_enabled`.
+    ///
+    /// Note that this doesn't account for the widget's ancestors: a widget with a disabled
+    /// ancestor is treated as disabled even if this returns `true`.
+    #[inline]
+    pub fn enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    /// Enable or disable the widget, along with all of its descendants.
+    ///
+    /// Disabled widgets don't get any input events, get skipped over when moving focus between
+    /// siblings, and get drawn with their theme's `::Disabled` variant. This takes `&self` so that
+    /// any widget can be disabled through `Widget::widget_tag`.
+    pub fn set_enabled(&self, enabled: bool) {
+        if enabled != self.enabled.get() {
+            self.enabled.set(enabled);
+            // Enabling or disabling a widget doesn't change its layout, so it only needs to be
+            // redrawn.
+            self.update_state.borrow_mut().request_redraw(self.widget_id);
+        }
+    }
+
+    /// Retrieve the widget's visibility, as set with `set_visibility`.
+    ///
+    /// Like `enabled`, this doesn't account for the widget's ancestors.
+    #[inline]
+    pub fn visibility(&self) -> Visibility {
+        self.visibility.get()
+    }
+
+    /// Show, hide, or collapse the widget, along with all of its descendants.
+    ///
+    /// Collapsing or un-collapsing the widget re-lays-out its parent, along with any ancestors
+    /// whose size changes as a result.
+    pub fn set_visibility(&self, visibility: Visibility) {
+        let old_visibility = self.visibility.replace(visibility);
+        if old_visibility != visibility {
+            let mut update_state = self.update_state.borrow_mut();
+            update_state.request_redraw(self.widget_id);
+            if old_visibility == Visibility::Collapsed || visibility == Visibility::Collapsed {
+                update_state.request_relayout_parent(self.widget_id);
+            }
+        }
+    }
+
+    /// Whether the widget can receive focus from the Tab key.
+    #[inline]
+    pub fn focusable(&self) -> bool {
+        self.focusable.get()
+    }
+
+    /// Set whether the widget can receive focus from the Tab key. Widgets aren't focusable by
+    /// default.
+    ///
+    /// Disabled and invisible widgets get skipped over regardless of this setting.
+    pub fn set_focusable(&self, focusable: bool) {
+        self.focusable.set(focusable);
+    }
+
+    #[inline]
+    pub fn tab_index(&self) -> Option<u32> {
+        self.tab_index.get()
+    }
+
+    /// Set the widget's position in the tab order.
+    ///
+    /// Widgets with a tab index get focused before widgets without one, in ascending order. Widgets
+    /// with the same tab index, or without one, get focused in depth-first tree order.
+    pub fn set_tab_index(&self, tab_index: Option<u32>) {
+        self.tab_index.set(tab_index);
+    }
+
+    #[inline]
+    pub fn focus_group(&self) -> bool {
+        self.focus_group.get()
+    }
+
+    /// Set whether the widget's subtree should be treated as a single tab stop.
+    ///
+    /// Tabbing into a focus group focuses its first focusable widget, and tabbing away from any
+    /// widget in the group moves focus past the rest of the group. The group takes its place in
+    /// the tab order from the group widget's tab index.
+    pub fn set_focus_group(&self, focus_group: bool) {
+        self.focus_group.set(focus_group);
+    }
+
     #[inline]
     pub fn has_keyboard_focus(&self) -> bool {
         unimplemented!()
@@ -38,6 +38,8 @@ pub(crate) trait WidgetDyn<R: Renderer>: 'static {
     ) -> EventOps;
 
     fn size_bounds(&self) -> SizeBounds;
+    fn focus_rect(&self) -> BoundBox<D2, i32>;
+    fn scroll_into_view(&mut self, rect: BoundBox<D2, i32>) -> BoundBox<D2, i32>;
     fn dispatch_message(&mut self, message: &Any);
 
     // Parent methods
@@ -128,6 +130,12 @@ impl<W, R> WidgetDyn<R> for W
     fn size_bounds(&self) -> SizeBounds {
         <Self as Widget>::size_bounds(self)
     }
+    fn focus_rect(&self) -> BoundBox<D2, i32> {
+        <Self as Widget>::focus_rect(self)
+    }
+    fn scroll_into_view(&mut self, rect: BoundBox<D2, i32>) -> BoundBox<D2, i32> {
+        <Self as Widget>::scroll_into_view(self, rect)
+    }
     fn dispatch_message(&mut self, message: &Any) {
         <Self as Widget>::dispatch_message(self, message)
     }
@@ -318,6 +326,7 @@ pub struct RenderParameters<'a, R: Renderer> {
     pub theme: &'a R::Theme,
     pub transform: BoundBox<D2, i32>,
     pub clip: BoundBox<D2, i32>,
+    pub enabled: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -373,6 +382,7 @@ fn render_with_theme_or_fallback<W, R>(widget: &mut W, render_parameters: Render
                 theme,
                 transform,
                 clip,
+                enabled,
             } = render_parameters;
 
             renderer.render_widget(
@@ -380,6 +390,7 @@ fn render_with_theme_or_fallback<W, R>(widget: &mut W, render_parameters: Render
                 theme,
                 transform,
                 clip,
+                enabled,
                 widget_theme_parameters,
                 |frame| widget.render(frame)
             );
@@ -58,8 +58,10 @@ impl<R> WidgetTraverserBase<R>
         // holds.
         assert_eq!(root.widget_id(), self.virtual_widget_tree.root_id());
 
+        // Overlays get drawn above their ancestors, so they shouldn't get clipped by them.
+        let overlay_ids = update_state.borrow().overlay_ids().collect::<Vec<_>>();
         WidgetTraverser {
-            stack: self.stack_cache.use_cache(root),
+            stack: self.stack_cache.use_cache(root, overlay_ids),
             virtual_widget_tree: &mut self.virtual_widget_tree,
             update_state
         }
@@ -148,32 +150,32 @@ impl<R> WidgetTraverser<'_, R>
 
     /// Crawl over all widgets in the tree. Any operations performed on the widget *should not*
     /// modify the structure of the child widgets.
-    pub fn crawl_widgets(&mut self, mut for_each: impl FnMut(OffsetWidgetPath<'_, R>)) {
-        let stack = &mut self.stack;
-
-        stack.truncate(1);
-        for_each(stack.top_mut());
-
-        let mut child_index = 0;
-        loop {
-            let child_opt = stack.try_push(|top_widget| {
-                top_widget.child_by_index_mut(child_index)
-            });
+    pub fn crawl_widgets(&mut self, for_each: impl FnMut(OffsetWidgetPath<'_, R>)) {
+        self.crawl_widgets_except(&[], for_each)
+    }
 
+    /// Crawl over all widgets in the tree, skipping over the widgets in `skip` along with all
+    /// their descendants. Any operations performed on the widget *should not* modify the
+    /// structure of the child widgets.
+    pub fn crawl_widgets_except(&mut self, skip: &[WidgetId], mut for_each: impl FnMut(OffsetWidgetPath<'_, R>)) {
+        self.stack.truncate(1);
+        for_each(self.stack.top_mut());
+        crawl_stack_top_children(&mut self.stack, skip, &mut for_each);
+    }
 
-            match child_opt {
-                Some(child) => {
-                    for_each(child);
-                    child_index = 0;
-                },
-                None => {
-                    child_index = stack.top_index() + 1;
-                    if stack.pop().is_none() {
-                        break
-                    }
-                }
-            }
+    /// Crawl over the widget `id` and all its descendants, skipping over the widgets in `skip`
+    /// along with all their descendants. Any operations performed on the widget *should not*
+    /// modify the structure of the child widgets.
+    pub fn crawl_widget_subtree_except(&mut self, id: WidgetId, skip: &[WidgetId], mut for_each: impl FnMut(OffsetWidgetPath<'_, R>)) {
+        // Make sure the widget is in the virtual widget tree.
+        if self.get_widget(id).is_none() {
+            return;
         }
+        match self.get_widget_with_tree(id) {
+            Some(path) => for_each(path),
+            None => return
+        }
+        crawl_stack_top_children(&mut self.stack, skip, &mut for_each);
     }
 
     pub fn crawl_widget_children(&mut self, parent: WidgetId, mut for_each: impl FnMut(OffsetWidgetPath<'_, R>)) {
@@ -217,11 +219,53 @@ impl<R> WidgetTraverser<'_, R>
         self.virtual_widget_tree.root_id()
     }
 
+    /// Retrieve the ID of the widget's parent, without moving to the widget. Only works for
+    /// widgets that have already been found by the traverser.
+    pub fn parent_id(&self, id: WidgetId) -> Option<WidgetId> {
+        self.virtual_widget_tree.parent(id).ok()
+    }
+
     pub fn all_widgets(&self) -> impl '_ + Iterator<Item=WidgetId> {
         self.virtual_widget_tree.all_nodes().map(|(id, _)| id)
     }
 }
 
+/// Crawl over all descendants of the top of the stack, skipping over the widgets in `skip` along
+/// with all their descendants. Leaves the widget the crawl started on at the top of the stack.
+fn crawl_stack_top_children<R: Renderer>(
+    stack: &mut WidgetStack<'_, R>,
+    skip: &[WidgetId],
+    for_each: &mut impl FnMut(OffsetWidgetPath<'_, R>)
+) {
+    let base_id = stack.top_id();
+    let mut child_index = 0;
+    loop {
+        let child_opt = stack.try_push(|top_widget| {
+            top_widget.child_by_index_mut(child_index)
+        });
+
+        let pushed_skipped = match child_opt {
+            Some(child) => match skip.contains(&child.widget_id) {
+                false => {
+                    for_each(child);
+                    child_index = 0;
+                    continue;
+                },
+                true => true
+            },
+            None => false
+        };
+
+        // Either the top widget is out of children or we just pushed a skipped widget, so move on
+        // to the next sibling of the top widget.
+        if !pushed_skipped && stack.top_id() == base_id {
+            break;
+        }
+        child_index = stack.top_index() + 1;
+        stack.pop();
+    }
+}
+
 impl<R> WidgetTraverser<'_, R>
     where R: Renderer
 {
@@ -290,7 +334,7 @@ mod tests {
     use super::*;
     use crate::{
         message_bus::MessageBus,
-        test_helpers::TestRenderFrame,
+        test_helpers::TestRenderer,
         update_state::UpdateState,
     };
     use cgmath_geometry::rect::BoundBox;
@@ -311,7 +355,7 @@ mod tests {
             };
         }
 
-        let mut traverser_base: WidgetTraverserBase<TestRenderFrame> = WidgetTraverserBase::new(root);
+        let mut traverser_base: WidgetTraverserBase<TestRenderer> = WidgetTraverserBase::new(root);
         let message_bus = MessageBus::new();
         let update_state = UpdateState::new(&message_bus);
         let mut traverser = traverser_base.with_root_ref(&mut tree, update_state.clone());
@@ -331,6 +375,42 @@ mod tests {
         assert_eq!(None, expected_id_iter.next());
     }
 
+    #[test]
+    fn crawl_widgets_except() {
+        test_widget_tree!{
+            let event_list = crate::test_helpers::EventList::new();
+            let mut tree = root {
+                rect: (0, 0, 0, 0);
+                a {
+                    rect: (0, 0, 0, 0);
+                    aa {
+                        rect: (0, 0, 0, 0);
+                        aaa { rect: (0, 0, 0, 0) }
+                    },
+                    ab { rect: (0, 0, 0, 0) }
+                },
+                b { rect: (0, 0, 0, 0) }
+            };
+        }
+
+        let mut traverser_base: WidgetTraverserBase<TestRenderer> = WidgetTraverserBase::new(root);
+        let message_bus = MessageBus::new();
+        let update_state = UpdateState::new(&message_bus);
+        let mut traverser = traverser_base.with_root_ref(&mut tree, update_state.clone());
+
+        let mut crawled = Vec::new();
+        traverser.crawl_widgets_except(&[aa], |path| crawled.push(path.widget_id));
+        assert_eq!(vec![root, a, ab, b], crawled);
+
+        crawled.clear();
+        traverser.crawl_widget_subtree_except(a, &[ab], |path| crawled.push(path.widget_id));
+        assert_eq!(vec![a, aa, aaa], crawled);
+
+        crawled.clear();
+        traverser.crawl_widget_subtree_except(aa, &[], |path| crawled.push(path.widget_id));
+        assert_eq!(vec![aa, aaa], crawled);
+    }
+
     #[test]
     fn get_widget() {
         test_widget_tree!{
@@ -353,7 +433,7 @@ mod tests {
         }
 
         for _ in 0..1000 {
-            let mut traverser_base: WidgetTraverserBase<TestRenderFrame> = WidgetTraverserBase::new(root);
+            let mut traverser_base: WidgetTraverserBase<TestRenderer> = WidgetTraverserBase::new(root);
             let message_bus = MessageBus::new();
             let update_state = UpdateState::new(&message_bus);
             let mut traverser = traverser_base.with_root_ref(&mut tree, update_state.clone());
@@ -410,7 +490,7 @@ mod tests {
             };
         }
 
-        let mut traverser_base: WidgetTraverserBase<TestRenderFrame> = WidgetTraverserBase::new(root);
+        let mut traverser_base: WidgetTraverserBase<TestRenderer> = WidgetTraverserBase::new(root);
         let message_bus = MessageBus::new();
         let update_state = UpdateState::new(&message_bus);
         let mut traverser = traverser_base.with_root_ref(&mut tree, update_state.clone());
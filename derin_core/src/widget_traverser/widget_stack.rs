@@ -7,7 +7,7 @@ use std::mem;
 use crate::{
     offset_widget::OffsetWidget,
     render::Renderer,
-    widget::{WidgetDyn, WidgetId, WidgetIdent, WidgetInfoMut, ROOT_IDENT},
+    widget::{Visibility, WidgetDyn, WidgetId, WidgetIdent, WidgetInfoMut, ROOT_IDENT},
     widget_traverser::virtual_widget_tree::PathRevItem,
 };
 
@@ -41,12 +41,15 @@ struct ElementRects {
 
 pub(crate) struct WidgetStackCache<R: Renderer> {
     vec: Vec<StackElement<R>>,
-    ident_vec: Vec<WidgetIdent>
+    ident_vec: Vec<WidgetIdent>,
+    unclipped: Vec<WidgetId>
 }
 
 pub(crate) struct WidgetStack<'a, R: 'a + Renderer> {
     vec: &'a mut Vec<StackElement<R>>,
     ident_vec: &'a mut Vec<WidgetIdent>,
+    /// Widgets that ignore their ancestors' clipping rectangles, like overlays.
+    unclipped: &'a [WidgetId],
     clip_rect: Option<BoundBox<D2, i32>>,
     top_parent_offset: Vector2<i32>,
 }
@@ -65,15 +68,18 @@ impl<R: Renderer> WidgetStackCache<R> {
         WidgetStackCache {
             vec: Vec::new(),
             ident_vec: Vec::new(),
+            unclipped: Vec::new(),
         }
     }
 
-    pub fn use_cache<'a>(&'a mut self, widget: &mut WidgetDyn<R>) -> WidgetStack<'a, R> {
+    pub fn use_cache<'a>(&'a mut self, widget: &mut WidgetDyn<R>, unclipped: impl IntoIterator<Item=WidgetId>) -> WidgetStack<'a, R> {
         let mut cache_swap = Vec::new();
         mem::swap(&mut cache_swap, &mut self.vec);
 
         self.vec.clear();
         self.ident_vec.clear();
+        self.unclipped.clear();
+        self.unclipped.extend(unclipped);
 
         self.vec.push(StackElement {
             widget_id: widget.widget_id(),
@@ -86,6 +92,7 @@ impl<R: Renderer> WidgetStackCache<R> {
         WidgetStack {
             vec: &mut self.vec,
             ident_vec: &mut self.ident_vec,
+            unclipped: &self.unclipped,
             clip_rect: Some(BoundBox::new(Point2::new(0, 0), Point2::max_value())),
             top_parent_offset: Vector2::new(0, 0),
         }
@@ -107,8 +114,11 @@ impl<'a, R: Renderer> WidgetStack<'a, R> {
     #[inline]
     pub fn top_mut(&mut self) -> OffsetWidgetPath<R> {
         let (widget, widget_id) = self.vec.last_mut().map(|n| unsafe{ (&mut *n.widget, n.widget_id) }).unwrap();
+        let clip_rect = self.clip_rect_for(widget_id);
+        let enabled = self.top_enabled();
+        let visible = self.top_visible();
         OffsetWidgetPath {
-            widget: OffsetWidget::new(widget, self.top_parent_offset, self.clip_rect),
+            widget: OffsetWidget::new(widget, self.top_parent_offset, clip_rect, enabled, visible),
             path: &self.ident_vec,
             index: self.top_index(),
             widget_id
@@ -143,6 +153,24 @@ impl<'a, R: Renderer> WidgetStack<'a, R> {
     //     self.vec.len()
     // }
 
+    /// The clipping rectangle the given widget is drawn with, if it's the top of the stack.
+    fn clip_rect_for(&self, widget_id: WidgetId) -> Option<BoundBox<D2, i32>> {
+        match self.unclipped.contains(&widget_id) {
+            true => Some(BoundBox::new(Point2::new(0, 0), Point2::max_value())),
+            false => self.clip_rect
+        }
+    }
+
+    /// Whether the top widget and all of its ancestors are enabled.
+    fn top_enabled(&self) -> bool {
+        self.vec.iter().all(|e| unsafe{ &*e.widget }.widget_tag().enabled())
+    }
+
+    /// Whether the top widget and all of its ancestors are visible.
+    fn top_visible(&self) -> bool {
+        self.vec.iter().all(|e| unsafe{ &*e.widget }.widget_tag().visibility() == Visibility::Visible)
+    }
+
     #[inline]
     pub fn truncate(&mut self, len: usize) {
         assert_ne!(0, len);
@@ -189,6 +217,7 @@ impl<'a, R: Renderer> WidgetStack<'a, R> {
     {
         let mut old_top = self.top_mut();
         let top_rect = old_top.widget.rect();
+        let top_id = old_top.widget_id;
 
         let new_top_opt = with_top(old_top.widget.inner_mut());
 
@@ -200,8 +229,8 @@ impl<'a, R: Renderer> WidgetStack<'a, R> {
 
             assert_ne!(new_top_widget, self.top_mut().widget.inner_mut() as *mut WidgetDyn<R>);
             {
+                let top_clip = self.clip_rect_for(top_id).and_then(|r| r.intersect_rect(top_rect));
                 let old_top = self.vec.last_mut().unwrap();
-                let top_clip = self.clip_rect.and_then(|r| r.intersect_rect(top_rect));
 
                 old_top.rectangles = Some(ElementRects {
                     bounds: top_rect,
@@ -248,12 +277,13 @@ impl<'a, R: Renderer> WidgetStack<'a, R> {
         let new_widget = try {
             while self.vec.len() < self.ident_vec.len() {
                 let i = self.vec.len() - 1;
+                let top_clip_rect = self.clip_rect_for(self.vec[i].widget_id);
                 let top = &mut self.vec[i];
                 let top_widget = unsafe{ &mut *top.widget };
 
                 {
                     let top_rect = top_widget.rect() + self.top_parent_offset;
-                    let top_clip = self.clip_rect.and_then(|r| r.intersect_rect(top_rect));
+                    let top_clip = top_clip_rect.and_then(|r| r.intersect_rect(top_rect));
 
                     top.rectangles = Some(ElementRects {
                         bounds: top_rect,